@@ -0,0 +1,26 @@
+use criterion::{BenchmarkId, Criterion, criterion_group, criterion_main};
+
+use bact_sim::bench::{self, POPULATIONS, SEED, TICKS};
+
+fn headless_run(c: &mut Criterion) {
+    let mut group = c.benchmark_group(format!("headless_{}_ticks", TICKS));
+    group.sample_size(10);
+    for population in POPULATIONS {
+        group.bench_with_input(BenchmarkId::from_parameter(population), &population, |b, &population| {
+            b.iter_batched(
+                || bench::world(population, 1, SEED),
+                |(mut world, params)| {
+                    for _ in 0..TICKS {
+                        world.step(&params);
+                    }
+                    world
+                },
+                criterion::BatchSize::LargeInput,
+            );
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, headless_run);
+criterion_main!(benches);
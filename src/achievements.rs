@@ -0,0 +1,173 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+use crate::components::{Age, Genome, Lineage};
+use crate::dna::{SENSE_RADIUS, SPEED};
+use crate::world::World;
+
+// How often the world is checked, and how long bacteria have to outlast
+// their predators
+const CHECK_EVERY: u64 = 50;
+const SURVIVAL_TICKS: u64 = 1000;
+
+// Milestones of a long run, unlocked once and kept across sessions
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Achievement {
+    Generation100,
+    Generation1000,
+    Population1000,
+    Population5000,
+    SenseRadius100,
+    Speed5,
+    Elder,
+    PredatorsOutlasted,
+}
+
+impl Achievement {
+    pub const ALL: [Achievement; 8] = [
+        Achievement::Generation100,
+        Achievement::Generation1000,
+        Achievement::Population1000,
+        Achievement::Population5000,
+        Achievement::SenseRadius100,
+        Achievement::Speed5,
+        Achievement::Elder,
+        Achievement::PredatorsOutlasted,
+    ];
+
+    // Key in the saved file
+    pub fn name(self) -> &'static str {
+        match self {
+            Achievement::Generation100 => "generation_100",
+            Achievement::Generation1000 => "generation_1000",
+            Achievement::Population1000 => "population_1000",
+            Achievement::Population5000 => "population_5000",
+            Achievement::SenseRadius100 => "sense_radius_100",
+            Achievement::Speed5 => "speed_5",
+            Achievement::Elder => "elder",
+            Achievement::PredatorsOutlasted => "predators_outlasted",
+        }
+    }
+
+    pub fn title(self) -> &'static str {
+        match self {
+            Achievement::Generation100 => "Centennial",
+            Achievement::Generation1000 => "Deep Lineage",
+            Achievement::Population1000 => "Colony",
+            Achievement::Population5000 => "Bloom",
+            Achievement::SenseRadius100 => "Eagle Eye",
+            Achievement::Speed5 => "Speed Demon",
+            Achievement::Elder => "Elder",
+            Achievement::PredatorsOutlasted => "Last Ones Standing",
+        }
+    }
+
+    pub fn description(self) -> &'static str {
+        match self {
+            Achievement::Generation100 => "A bacterium of generation 100",
+            Achievement::Generation1000 => "A bacterium of generation 1,000",
+            Achievement::Population1000 => "Over 1,000 bacteria at once",
+            Achievement::Population5000 => "Over 5,000 bacteria at once",
+            Achievement::SenseRadius100 => "A sense radius of 100 evolved",
+            Achievement::Speed5 => "A speed of 5 evolved",
+            Achievement::Elder => "A bacterium 5,000 ticks old",
+            Achievement::PredatorsOutlasted => "Bacteria alive 1,000 ticks after predators died out",
+        }
+    }
+}
+
+// When an achievement was first unlocked: the world tick, and seconds since
+// the Unix epoch
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct Unlock {
+    pub tick: u64,
+    pub time: u64,
+}
+
+// Unlocked achievements by name, kept in a JSON file across runs
+pub struct Achievements {
+    path: String,
+    unlocked: BTreeMap<String, Unlock>,
+    last_check: Option<u64>,
+    // Whether this world has had predators, and the tick they died out at
+    // while they stay extinct
+    predators_seen: bool,
+    predators_gone: Option<u64>,
+}
+
+impl Achievements {
+    // None unlocked if the file doesn't exist yet
+    pub fn load(path: &str) -> Result<Self, String> {
+        let unlocked = match std::fs::read_to_string(path) {
+            Ok(text) => serde_json::from_str(&text).map_err(|e| e.to_string())?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => BTreeMap::new(),
+            Err(e) => return Err(e.to_string()),
+        };
+        Ok(Self { path: path.to_string(), unlocked, last_check: None, predators_seen: false, predators_gone: None })
+    }
+
+    pub fn unlocked(&self, achievement: Achievement) -> Option<&Unlock> {
+        self.unlocked.get(achievement.name())
+    }
+
+    pub fn count(&self) -> usize {
+        Achievement::ALL.iter().filter(|a| self.unlocked(**a).is_some()).count()
+    }
+
+    // The world was replaced
+    pub fn new_world(&mut self) {
+        self.last_check = None;
+        self.predators_seen = false;
+        self.predators_gone = None;
+    }
+
+    // Every `CHECK_EVERY` ticks, unlock whatever the world has achieved;
+    // returns the new ones, saved by then
+    pub fn check(&mut self, world: &World) -> Result<Vec<Achievement>, String> {
+        if self.last_check.is_some_and(|t| t + CHECK_EVERY > world.tick) {
+            return Ok(Vec::new());
+        }
+        self.last_check = Some(world.tick);
+
+        let bacteria = world.bacteria_count();
+        if world.predator_count() > 0 {
+            self.predators_seen = true;
+            self.predators_gone = None;
+        } else if self.predators_seen && self.predators_gone.is_none() {
+            self.predators_gone = Some(world.tick);
+        }
+        let (mut generation, mut sense, mut speed, mut age) = (0, 0.0f32, 0.0f32, 0.0f32);
+        for (genome, lineage, a) in world.ecs.query::<(&Genome, &Lineage, &Age)>().iter() {
+            generation = generation.max(lineage.generation);
+            sense = sense.max(genome.0.gene(SENSE_RADIUS));
+            speed = speed.max(genome.0.gene(SPEED));
+            age = age.max(a.0);
+        }
+
+        let mut new = Vec::new();
+        for achievement in Achievement::ALL {
+            let reached = match achievement {
+                Achievement::Generation100 => generation >= 100,
+                Achievement::Generation1000 => generation >= 1000,
+                Achievement::Population1000 => bacteria > 1000,
+                Achievement::Population5000 => bacteria > 5000,
+                Achievement::SenseRadius100 => sense >= 100.0,
+                Achievement::Speed5 => speed >= 5.0,
+                Achievement::Elder => age >= 5000.0,
+                Achievement::PredatorsOutlasted => bacteria > 0 && self.predators_gone.is_some_and(|t| t + SURVIVAL_TICKS <= world.tick),
+            };
+            if reached && self.unlocked(achievement).is_none() {
+                let time = std::time::SystemTime::now()
+                    .duration_since(std::time::UNIX_EPOCH)
+                    .map_or(0, |d| d.as_secs());
+                self.unlocked.insert(achievement.name().to_string(), Unlock { tick: world.tick, time });
+                new.push(achievement);
+            }
+        }
+        if !new.is_empty() {
+            let text = serde_json::to_string_pretty(&self.unlocked).map_err(|e| e.to_string())?;
+            std::fs::write(&self.path, text).map_err(|e| e.to_string())?;
+        }
+        Ok(new)
+    }
+}
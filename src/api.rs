@@ -0,0 +1,109 @@
+use serde::Serialize;
+use std::sync::mpsc::{channel, Receiver};
+use tiny_http::{Header, Method, Request, Response, Server};
+use tracing::warn;
+
+use crate::params::SimulationParams;
+use crate::telemetry::Frame;
+use crate::world::World;
+
+// How many organisms or food items a spawn request adds without `?count=`
+const DEFAULT_SPAWN_COUNT: usize = 10;
+
+// Small REST API for steering a run from outside:
+//
+//   GET  /stats              counts and averages for the current tick
+//   GET  /snapshot           every organism, as in telemetry snapshots
+//   GET  /params             the simulation parameters
+//   POST /params             merge a JSON object of parameters into them
+//   POST /pause, /resume     stop or restart the clock
+//   POST /spawn/bacteria     add organisms or food (`?count=N`, default 10)
+//   POST /spawn/predators
+//   POST /spawn/food
+//
+// Requests are accepted on a background thread and answered by the sim loop
+// through `handle_pending`, so they always see a whole tick.
+pub struct ControlApi {
+    requests: Receiver<Request>,
+}
+
+impl ControlApi {
+    pub fn bind(addr: &str) -> Result<Self, String> {
+        let server = Server::http(addr).map_err(|e| e.to_string())?;
+        let (tx, requests) = channel();
+        std::thread::spawn(move || {
+            for request in server.incoming_requests() {
+                if tx.send(request).is_err() {
+                    return;
+                }
+            }
+        });
+        Ok(Self { requests })
+    }
+
+    // Answer the requests waiting, calling `settle` first if there are any,
+    // e.g. to get the world back from the sim thread
+    pub fn handle_pending(&self, world: &mut World, params: &mut SimulationParams, paused: &mut bool, settle: impl FnOnce(&mut World)) {
+        let mut requests = self.requests.try_iter().peekable();
+        if requests.peek().is_some() {
+            settle(world);
+        }
+        for mut request in requests {
+            let (status, body) = route(&mut request, world, params, paused);
+            let header = Header::from_bytes("Content-Type", "application/json").unwrap();
+            let response = Response::from_string(body).with_status_code(status).with_header(header);
+            if let Err(e) = request.respond(response) {
+                warn!("Couldn't answer API request: {}", e);
+            }
+        }
+    }
+}
+
+fn route(request: &mut Request, world: &mut World, params: &mut SimulationParams, paused: &mut bool) -> (u16, String) {
+    let url = request.url().to_owned();
+    let (path, query) = url.split_once('?').unwrap_or((&url, ""));
+    let count = query.split('&')
+        .find_map(|kv| kv.strip_prefix("count="))
+        .map_or(Ok(DEFAULT_SPAWN_COUNT), |v| v.parse().map_err(|_| format!("bad count '{}'", v)));
+
+    let result = match (request.method(), path) {
+        (Method::Get, "/stats") => json(&Frame::stats(world)),
+        (Method::Get, "/snapshot") => json(&Frame::snapshot(world)),
+        (Method::Get, "/params") => json(params),
+        (Method::Post, "/params") => {
+            let mut body = String::new();
+            request.as_reader().read_to_string(&mut body).map_err(|e| e.to_string())
+                .and_then(|_| merge_params(params, &body))
+                .and_then(|_| json(params))
+        }
+        (Method::Post, "/pause") | (Method::Post, "/resume") => {
+            *paused = path == "/pause";
+            json(&serde_json::json!({ "paused": *paused }))
+        }
+        (Method::Post, "/spawn/bacteria") => count.map(|n| world.spawn_bacteria(n, params.initial_energy)).and_then(|_| json(&Frame::stats(world))),
+        (Method::Post, "/spawn/predators") => count.map(|n| world.spawn_predators(n)).and_then(|_| json(&Frame::stats(world))),
+        (Method::Post, "/spawn/food") => count.map(|n| world.spawn_food(n)).and_then(|_| json(&Frame::stats(world))),
+        _ => return (404, error_body(&format!("no route for {} {}", request.method(), path))),
+    };
+    match result {
+        Ok(body) => (200, body),
+        Err(e) => (400, error_body(&e)),
+    }
+}
+
+// Fields missing from `body` keep their current values
+fn merge_params(params: &mut SimulationParams, body: &str) -> Result<(), String> {
+    let changes: serde_json::Value = serde_json::from_str(body).map_err(|e| e.to_string())?;
+    let serde_json::Value::Object(changes) = changes else {
+        return Err("expected a JSON object of parameters".to_owned());
+    };
+    params.merge(changes)
+}
+
+fn json(value: &impl Serialize) -> Result<String, String> {
+    serde_json::to_string(value).map_err(|e| e.to_string())
+}
+
+fn error_body(message: &str) -> String {
+    serde_json::json!({ "error": message }).to_string()
+}
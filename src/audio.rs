@@ -0,0 +1,168 @@
+use macroquad::audio::{load_sound_from_bytes, play_sound, set_sound_volume, PlaySoundParams, Sound};
+use macroquad::prelude::get_time;
+use std::f32::consts::TAU;
+
+use crate::events::{Event, Meal, Organism};
+use crate::ui::SoundSettings;
+
+// Sounds are synthesized at startup, so there are no asset files to ship
+const SAMPLE_RATE: u32 = 22_050;
+// Seconds the ambient drone takes before it loops; every tone in it fits a
+// whole number of cycles so the loop is seamless
+const AMBIENT_SECONDS: f32 = 8.0;
+
+// Something worth a sound during a tick
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Effect {
+    Eat,
+    Kill,
+    Birth,
+    Extinction,
+}
+
+impl Effect {
+    pub const ALL: [Effect; 4] = [Effect::Eat, Effect::Kill, Effect::Birth, Effect::Extinction];
+
+    fn index(self) -> usize {
+        Effect::ALL.iter().position(|&e| e == self).unwrap()
+    }
+
+    // Seconds before the same effect plays again, so a busy tick doesn't
+    // turn into noise
+    fn cooldown(self) -> f64 {
+        match self {
+            Effect::Eat => 0.08,
+            Effect::Kill => 0.2,
+            Effect::Birth => 0.25,
+            Effect::Extinction => 3.0,
+        }
+    }
+
+    fn samples(self) -> Vec<f32> {
+        match self {
+            // Short rising blip
+            Effect::Eat => synth(0.06, |t, _| sine(t, 900.0 + 3000.0 * t) * decay(t, 40.0) * 0.5),
+            // Falling thud with a little grit
+            Effect::Kill => synth(0.2, |t, i| {
+                let grit = ((i as f32 * 12.9898).sin() * 43758.547).fract() - 0.5;
+                (sine(t, 160.0 - 300.0 * t) + grit * 0.3) * decay(t, 14.0) * 0.7
+            }),
+            // Two bell notes a fifth apart
+            Effect::Birth => synth(0.45, |t, _| {
+                let first = sine(t, 660.0) * decay(t, 9.0);
+                let second = if t > 0.1 { sine(t, 990.0) * decay(t - 0.1, 8.0) } else { 0.0 };
+                (first + second) * 0.35
+            }),
+            // Slow falling minor triad
+            Effect::Extinction => synth(1.5, |t, _| {
+                let note = [440.0, 349.2, 261.6][((t / 0.4) as usize).min(2)];
+                (sine(t, note) + 0.3 * sine(t, note * 2.0)) * decay(t % 0.4, 2.0) * (1.5 - t).min(0.3) / 0.3 * 0.4
+            }),
+        }
+    }
+}
+
+fn sine(t: f32, freq: f32) -> f32 {
+    (TAU * freq * t).sin()
+}
+
+fn decay(t: f32, rate: f32) -> f32 {
+    (-t * rate).exp()
+}
+
+// `seconds` of audio from `sample(time, index)`
+fn synth(seconds: f32, sample: impl Fn(f32, usize) -> f32) -> Vec<f32> {
+    let count = (seconds * SAMPLE_RATE as f32) as usize;
+    (0..count).map(|i| sample(i as f32 / SAMPLE_RATE as f32, i)).collect()
+}
+
+// Low drone with a slow swell, quiet enough to sit under the effects
+fn ambient() -> Vec<f32> {
+    synth(AMBIENT_SECONDS, |t, _| {
+        let swell = 0.6 + 0.4 * (TAU * t / AMBIENT_SECONDS).sin();
+        let shimmer = 0.5 + 0.5 * (TAU * 2.0 * t / AMBIENT_SECONDS).cos();
+        (sine(t, 55.0) * 0.5 + sine(t, 82.5) * 0.3 * swell + sine(t, 220.0) * 0.08 * shimmer) * 0.5
+    })
+}
+
+// 16-bit mono PCM WAV
+fn wav(samples: &[f32]) -> Vec<u8> {
+    let data_len = samples.len() as u32 * 2;
+    let mut bytes = Vec::with_capacity(44 + data_len as usize);
+    bytes.extend_from_slice(b"RIFF");
+    bytes.extend_from_slice(&(36 + data_len).to_le_bytes());
+    bytes.extend_from_slice(b"WAVEfmt ");
+    bytes.extend_from_slice(&16u32.to_le_bytes());
+    bytes.extend_from_slice(&1u16.to_le_bytes());
+    bytes.extend_from_slice(&1u16.to_le_bytes());
+    bytes.extend_from_slice(&SAMPLE_RATE.to_le_bytes());
+    bytes.extend_from_slice(&(SAMPLE_RATE * 2).to_le_bytes());
+    bytes.extend_from_slice(&2u16.to_le_bytes());
+    bytes.extend_from_slice(&16u16.to_le_bytes());
+    bytes.extend_from_slice(b"data");
+    bytes.extend_from_slice(&data_len.to_le_bytes());
+    for s in samples {
+        bytes.extend_from_slice(&((s.clamp(-1.0, 1.0) * i16::MAX as f32) as i16).to_le_bytes());
+    }
+    bytes
+}
+
+// Ambient loop and event sounds. Without the `audio` feature macroquad has no
+// audio backend and these stay silent, so `main` doesn't create them.
+pub struct Audio {
+    effects: Vec<Sound>,
+    ambient: Sound,
+    // When each effect last played, indexed like `Effect::ALL`
+    last_played: [f64; Effect::ALL.len()],
+    // Ambient volume currently set, to only touch it on a change
+    ambient_volume: f32,
+    predators: usize,
+}
+
+impl Audio {
+    pub async fn new(settings: &SoundSettings) -> Result<Self, String> {
+        let mut effects = Vec::new();
+        for effect in Effect::ALL {
+            effects.push(load_sound_from_bytes(&wav(&effect.samples())).await.map_err(|e| e.to_string())?);
+        }
+        let ambient = load_sound_from_bytes(&wav(&ambient())).await.map_err(|e| e.to_string())?;
+        let ambient_volume = settings.ambient_volume();
+        play_sound(&ambient, PlaySoundParams { looped: true, volume: ambient_volume });
+        Ok(Self { effects, ambient, last_played: [f64::MIN; Effect::ALL.len()], ambient_volume, predators: 0 })
+    }
+
+    // Play the sounds for a tick's events; `predators` is the count after it
+    pub fn update<V>(&mut self, settings: &SoundSettings, events: &[Event<V>], predators: usize) {
+        let volume = settings.ambient_volume();
+        if volume != self.ambient_volume {
+            set_sound_volume(&self.ambient, volume);
+            self.ambient_volume = volume;
+        }
+
+        let mut heard = [false; Effect::ALL.len()];
+        for event in events {
+            let effect = match event {
+                Event::Ate { meal: Meal::Food(_), .. } => Effect::Eat,
+                Event::Ate { meal: Meal::Prey(_), .. } => Effect::Kill,
+                Event::Reproduced { offspring: Organism::Bacterium(_), .. } => Effect::Birth,
+                // Bacteria are only ever respawned after dying out
+                Event::Spawned { organism: Organism::Bacterium(_) } => Effect::Extinction,
+                _ => continue,
+            };
+            heard[effect.index()] = true;
+        }
+        if self.predators > 0 && predators == 0 {
+            heard[Effect::Extinction.index()] = true;
+        }
+        self.predators = predators;
+
+        let volume = settings.effects_volume();
+        let now = get_time();
+        for (i, effect) in Effect::ALL.into_iter().enumerate() {
+            if heard[i] && volume > 0.0 && now - self.last_played[i] >= effect.cooldown() {
+                play_sound(&self.effects[i], PlaySoundParams { looped: false, volume });
+                self.last_played[i] = now;
+            }
+        }
+    }
+}
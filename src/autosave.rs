@@ -0,0 +1,308 @@
+use hecs::Entity;
+use macroquad::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+use crate::cadence::Cadence;
+use crate::components::{Age, Aversion, Body, Camouflage, Digesting, Energy, Expression, Food, Imprint, Genome, Lineage, Metabolism, Plasmids, Position, PredatorTag, Refractory, Species, Velocity};
+use crate::dna::{Plasmid, DNA, SIZE};
+use crate::field::Field;
+use crate::habitat::Habitat;
+use crate::organism::{Bacterium, Predator};
+use crate::params::SimulationParams;
+use crate::terrain::Terrain;
+use crate::world::World;
+
+// Present while a run with autosaves is going; left behind by a crash
+const RUNNING_MARKER: &str = "running";
+
+// Lineage links by entity handle, which a restored world keeps
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+struct SavedLineage {
+    parent: Option<u64>,
+    generation: u32,
+    founder: Option<u64>,
+    offspring: u32,
+    origin: Option<usize>,
+}
+
+impl SavedLineage {
+    fn of(lineage: &Lineage) -> Self {
+        Self {
+            parent: lineage.parent.map(|e| e.to_bits().get()),
+            generation: lineage.generation,
+            founder: lineage.founder.map(|e| e.to_bits().get()),
+            offspring: lineage.offspring,
+            origin: lineage.origin,
+        }
+    }
+
+    fn lineage(&self) -> Lineage {
+        Lineage {
+            parent: self.parent.and_then(Entity::from_bits),
+            generation: self.generation,
+            founder: self.founder.and_then(Entity::from_bits),
+            offspring: self.offspring,
+            origin: self.origin,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+struct SavedBacterium {
+    id: u64,
+    pos: [f32; 2],
+    vel: [f32; 2],
+    // Its gene list and markers, or in older saves a field per core locus
+    #[serde(flatten)]
+    dna: DNA,
+    // Body size of one still growing
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    body_size: Option<f32>,
+    // Speed, size and sense radius factors of one that came out off its genes
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    expression: Option<[f32; 3]>,
+    #[serde(default)]
+    imprint: f32,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    plasmids: Vec<Plasmid>,
+    energy: f32,
+    age: f32,
+    lineage: SavedLineage,
+    // Ticks left before dividing again and digesting the last meal
+    #[serde(default)]
+    refractory: u32,
+    #[serde(default)]
+    digesting: u32,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+struct SavedPredator {
+    id: u64,
+    pos: [f32; 2],
+    vel: [f32; 2],
+    speed: f32,
+    size: f32,
+    sense_radius: f32,
+    energy: f32,
+    age: f32,
+    lineage: SavedLineage,
+    // Index into the parameters' species, for a configured consumer
+    #[serde(default)]
+    species: Option<usize>,
+    #[serde(default)]
+    refractory: u32,
+    #[serde(default)]
+    digesting: u32,
+    // Ticks kept still, and whether that hid it
+    #[serde(default)]
+    still: u32,
+    #[serde(default)]
+    hidden: bool,
+    // Colours of prey it learned to shun, each with the tick it forgets them
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    aversions: Vec<([f32; 4], u64)>,
+}
+
+// Everything needed to carry on a run: the parameters, the world's layout,
+// terrain and fields, and every organism and food item. The random number
+// generator isn't saved, so a resumed run draws fresh numbers.
+#[derive(Serialize, Deserialize)]
+pub struct WorldSave {
+    pub params: SimulationParams,
+    pub tick: u64,
+    width: f32,
+    height: f32,
+    // Per habitat region, in order
+    food_multipliers: Vec<f32>,
+    // `Terrain::to_text`
+    terrain: String,
+    waste: Field,
+    oxygen: Field,
+    ph: Field,
+    bacteria: Vec<SavedBacterium>,
+    predators: Vec<SavedPredator>,
+    food: Vec<[f32; 2]>,
+}
+
+impl WorldSave {
+    pub fn of(world: &World, params: &SimulationParams) -> Self {
+        let bacteria = world.ecs.query::<(Entity, &Position, &Velocity, &Body, &Expression, &Imprint, &Plasmids, &Genome, &Energy, &Age, &Lineage, (&Refractory, &Digesting))>().iter()
+            .map(|(id, pos, vel, body, expression, imprint, plasmids, genome, energy, age, lineage, (refractory, digesting))| {
+                let dna = &genome.0;
+                SavedBacterium {
+                    id: id.to_bits().get(),
+                    pos: pos.0.into(),
+                    vel: vel.0.into(),
+                    dna: dna.clone(),
+                    body_size: (body.size < dna.gene(SIZE) * expression.size).then_some(body.size),
+                    expression: (*expression != Expression::default()).then_some([expression.speed, expression.size, expression.sense_radius]),
+                    imprint: imprint.0,
+                    plasmids: plasmids.0.clone(),
+                    energy: energy.0,
+                    age: age.0,
+                    lineage: SavedLineage::of(lineage),
+                    refractory: refractory.0,
+                    digesting: digesting.0,
+                }
+            })
+            .collect();
+        let predators = world.ecs.query::<(Entity, &Position, &Velocity, &Body, &Energy, &Age, &Lineage, Option<&Species>, (&Refractory, &Digesting, &Camouflage, &Aversion))>().with::<&PredatorTag>().iter()
+            .map(|(id, pos, vel, body, energy, age, lineage, species, (refractory, digesting, camouflage, aversion))| SavedPredator {
+                id: id.to_bits().get(),
+                pos: pos.0.into(),
+                vel: vel.0.into(),
+                speed: body.speed,
+                size: body.size,
+                sense_radius: body.sense_radius,
+                energy: energy.0,
+                age: age.0,
+                lineage: SavedLineage::of(lineage),
+                species: species.map(|s| s.0),
+                refractory: refractory.0,
+                digesting: digesting.0,
+                still: camouflage.still,
+                hidden: camouflage.hidden,
+                aversions: aversion.0.iter().map(|&(color, until)| (color.into(), until)).collect(),
+            })
+            .collect();
+        let food = world.ecs.query::<&Position>().with::<&Food>().iter().map(|p| p.0.into()).collect();
+        Self {
+            params: params.clone(),
+            tick: world.tick,
+            width: world.width,
+            height: world.height,
+            food_multipliers: world.habitat.regions.iter().map(|r| r.food_multiplier).collect(),
+            terrain: world.terrain.to_text(),
+            waste: world.waste.clone(),
+            oxygen: world.oxygen.clone(),
+            ph: world.ph.clone(),
+            bacteria,
+            predators,
+            food,
+        }
+    }
+
+    // The saved world, drawing random numbers from `seed` from here on
+    pub fn restore(&self, seed: u64) -> Result<World, String> {
+        let mut world = World::empty(self.width, self.height, seed);
+        world.habitat = Habitat::new(self.params.world_layout, self.width, self.height);
+        for (region, &multiplier) in world.habitat.regions.iter_mut().zip(&self.food_multipliers) {
+            region.food_multiplier = multiplier;
+        }
+        world.terrain = Terrain::from_text(&self.terrain)?;
+        world.waste = self.waste.clone();
+        world.oxygen = self.oxygen.clone();
+        world.ph = self.ph.clone();
+        world.tick = self.tick;
+
+        for b in &self.bacteria {
+            let id = Entity::from_bits(b.id).ok_or("bad bacterium id")?;
+            let expression = b.expression.map_or_else(Expression::default, |[speed, size, sense_radius]| Expression { speed, size, sense_radius });
+            Bacterium { pos: Vec2::from(b.pos), vel: Vec2::from(b.vel), dna: b.dna.clone(), energy: b.energy, age: b.age, size: b.body_size, expression, imprint: b.imprint, plasmids: b.plasmids.clone(), lineage: b.lineage.lineage() }
+                .spawn_at(&mut world.ecs, id);
+            if let Ok((refractory, digesting)) = world.ecs.query_one_mut::<(&mut Refractory, &mut Digesting)>(id) {
+                *refractory = Refractory(b.refractory);
+                *digesting = Digesting(b.digesting);
+            }
+        }
+        for p in &self.predators {
+            let id = Entity::from_bits(p.id).ok_or("bad predator id")?;
+            // A species' metabolism comes from its configuration
+            let metabolism = p.species.and_then(|i| self.params.species.get(i)).map_or(Metabolism::for_predator().0, |s| s.metabolism);
+            Predator {
+                pos: Vec2::from(p.pos),
+                vel: p.vel.into(),
+                energy: p.energy,
+                speed: p.speed,
+                size: p.size,
+                sense_radius: p.sense_radius,
+                metabolism,
+                species: p.species,
+                lineage: p.lineage.lineage(),
+            }
+            .spawn_at(&mut world.ecs, id);
+            if let Ok((age, refractory, digesting, camouflage, aversion)) = world.ecs.query_one_mut::<(&mut Age, &mut Refractory, &mut Digesting, &mut Camouflage, &mut Aversion)>(id) {
+                age.0 = p.age;
+                *refractory = Refractory(p.refractory);
+                *digesting = Digesting(p.digesting);
+                *camouflage = Camouflage { still: p.still, hidden: p.hidden };
+                *aversion = Aversion(p.aversions.iter().map(|&([r, g, b, a], until)| (Color::new(r, g, b, a), until)).collect());
+            }
+        }
+        for &pos in &self.food {
+            world.ecs.spawn((Position(Vec2::from(pos)), Food));
+        }
+        Ok(world)
+    }
+
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let text = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
+        serde_json::from_str(&text).map_err(|e| e.to_string())
+    }
+}
+
+// Saves the world every `every` ticks into `keep` files in `dir`
+// (`autosave_0.json`, `autosave_1.json`, ...), overwriting the oldest. A
+// marker file in the folder is there while the run goes on, so the next
+// launch can tell whether it ended in a crash.
+pub struct Autosave {
+    dir: PathBuf,
+    every: Cadence,
+    keep: usize,
+    next_slot: usize,
+}
+
+impl Autosave {
+    pub fn start(dir: &str, every: u64, keep: usize) -> Result<Self, String> {
+        let dir = PathBuf::from(dir);
+        std::fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+        std::fs::write(dir.join(RUNNING_MARKER), "").map_err(|e| e.to_string())?;
+        // Carry on after the newest save rather than overwrite it first
+        let next_slot = newest(&dir).and_then(|(_, slot)| slot).map_or(0, |slot| (slot + 1) % keep.max(1));
+        Ok(Self { dir, every: Cadence::new(every.max(1)), keep: keep.max(1), next_slot })
+    }
+
+    // Save if a multiple of `every` ticks was reached since the last call
+    pub fn tick(&mut self, world: &World, params: &SimulationParams) -> Result<(), String> {
+        if world.tick == 0 || !self.every.due(world.tick) {
+            return Ok(());
+        }
+        let text = serde_json::to_string(&WorldSave::of(world, params)).map_err(|e| e.to_string())?;
+        // Write aside and rename, so a crash mid-save can't ruin the file
+        let path = self.dir.join(format!("autosave_{}.json", self.next_slot));
+        let partial = path.with_extension("json.partial");
+        std::fs::write(&partial, text).map_err(|e| e.to_string())?;
+        std::fs::rename(&partial, &path).map_err(|e| e.to_string())?;
+        self.next_slot = (self.next_slot + 1) % self.keep;
+        Ok(())
+    }
+
+    // The run ended normally
+    pub fn finish(&self) {
+        let _ = std::fs::remove_file(self.dir.join(RUNNING_MARKER));
+    }
+}
+
+// Most recently written autosave in `dir`, with its slot number
+fn newest(dir: &Path) -> Option<(PathBuf, Option<usize>)> {
+    std::fs::read_dir(dir).ok()?
+        .filter_map(|e| e.ok())
+        .filter_map(|e| {
+            let name = e.file_name().to_string_lossy().into_owned();
+            let slot = name.strip_prefix("autosave_")?.strip_suffix(".json")?.parse().ok();
+            Some((e.metadata().ok()?.modified().ok()?, e.path(), slot))
+        })
+        .max_by_key(|(modified, _, _)| *modified)
+        .map(|(_, path, slot)| (path, slot))
+}
+
+// The newest autosave in `dir`, if any
+pub fn latest(dir: &str) -> Option<PathBuf> {
+    newest(Path::new(dir)).map(|(path, _)| path)
+}
+
+// Whether the last run autosaving to `dir` ended without finishing
+pub fn crashed(dir: &str) -> bool {
+    Path::new(dir).join(RUNNING_MARKER).exists() && latest(dir).is_some()
+}
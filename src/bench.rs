@@ -0,0 +1,30 @@
+use crate::params::{SimulationParams, DEFAULT_WORLD_HEIGHT, DEFAULT_WORLD_WIDTH};
+use crate::world::World;
+
+// The standard benchmark, run by both `cargo bench` and the `bench`
+// subcommand so their numbers compare: this many ticks at each population,
+// every world from the same seed
+pub const TICKS: u64 = 1_000;
+pub const POPULATIONS: [usize; 3] = [1_000, 10_000, 50_000];
+pub const SEED: u64 = 42;
+
+// Bacteria in a default-sized world, as in the window
+const DENSITY: f32 = 50.0;
+
+// A world of `bacteria` at the default density, a tenth as many predators and
+// four food items each. The world grows with the population, and the food
+// supply with its area.
+pub fn world(bacteria: usize, sense_stride: usize, seed: u64) -> (World, SimulationParams) {
+    let side = (bacteria as f32 / DENSITY).sqrt();
+    let params = SimulationParams {
+        world_width: DEFAULT_WORLD_WIDTH * side,
+        world_height: DEFAULT_WORLD_HEIGHT * side,
+        sense_stride,
+        ..SimulationParams::default()
+    };
+    let mut world = World::empty(params.world_width, params.world_height, seed);
+    world.spawn_bacteria(bacteria, params.initial_energy);
+    world.spawn_predators(bacteria / 10);
+    world.spawn_food(bacteria * 4);
+    (world, params)
+}
@@ -0,0 +1,28 @@
+// Something done every `every` ticks (0 for never), such as a save or an
+// export. Ticks can go by between looks, several to a frame or a whole batch
+// on the worker thread, so it's due whenever a multiple of `every` was
+// reached since the last look rather than only on one, and once per multiple.
+#[derive(Clone, Copy, Debug)]
+pub struct Cadence {
+    pub every: u64,
+    last: Option<u64>,
+}
+
+impl Cadence {
+    pub fn new(every: u64) -> Self {
+        Self { every, last: None }
+    }
+
+    // Whether it's due at `tick`. A tick before the last one looked at (a new
+    // world) starts over.
+    pub fn due(&mut self, tick: u64) -> bool {
+        let last = self.last.replace(tick);
+        if self.every == 0 {
+            return false;
+        }
+        match last {
+            Some(last) if last <= tick => tick / self.every > last / self.every,
+            _ => tick.is_multiple_of(self.every),
+        }
+    }
+}
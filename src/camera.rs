@@ -0,0 +1,173 @@
+use macroquad::prelude::*;
+
+const MAX_SCALE: f32 = 8.0;
+const ZOOM_STEP: f32 = 1.15;
+const PAN_SPEED: f32 = 600.0;
+// Radians of orbit per pixel dragged
+const ORBIT_SPEED: f32 = 0.01;
+
+// View onto the world: `center` (world units) is drawn at the middle of the
+// `view` rectangle (screen pixels), magnified by `scale` pixels per world unit.
+pub struct Camera {
+    pub center: Vec2,
+    pub scale: f32,
+    pub view: Rect,
+    drag_anchor: Option<Vec2>,
+}
+
+impl Camera {
+    // Start at 1:1, centred on the world
+    pub fn new(view: Rect, world_w: f32, world_h: f32) -> Self {
+        Self {
+            center: vec2(world_w / 2.0, world_h / 2.0),
+            scale: 1.0,
+            view,
+            drag_anchor: None,
+        }
+    }
+
+    pub fn world_to_screen(&self, p: Vec2) -> Vec2 {
+        self.view.center() + (p - self.center) * self.scale
+    }
+
+    pub fn screen_to_world(&self, p: Vec2) -> Vec2 {
+        self.center + (p - self.view.center()) / self.scale
+    }
+
+    // World-space rectangle currently inside the view
+    pub fn visible_rect(&self) -> Rect {
+        let size = self.view.size() / self.scale;
+        let top_left = self.center - size / 2.0;
+        Rect::new(top_left.x, top_left.y, size.x, size.y)
+    }
+
+    pub fn shows_whole_world(&self, world_w: f32, world_h: f32) -> bool {
+        let r = self.visible_rect();
+        r.x <= 0.0 && r.y <= 0.0 && r.right() >= world_w && r.bottom() >= world_h
+    }
+
+    // Mouse wheel zooms around the cursor, right/middle drag and WASD/arrows pan
+    pub fn handle_input(&mut self, world_w: f32, world_h: f32) {
+        let mouse = Vec2::from(mouse_position());
+        let over_view = self.view.contains(mouse);
+
+        let (_, wheel) = mouse_wheel();
+        if over_view && wheel != 0.0 {
+            let anchor = self.screen_to_world(mouse);
+            let factor = if wheel > 0.0 { ZOOM_STEP } else { 1.0 / ZOOM_STEP };
+            self.scale = (self.scale * factor).clamp(self.min_scale(world_w, world_h), MAX_SCALE);
+            // Keep the point under the cursor fixed
+            self.center = anchor - (mouse - self.view.center()) / self.scale;
+        }
+
+        let dragging = is_mouse_button_down(MouseButton::Right) || is_mouse_button_down(MouseButton::Middle);
+        match (dragging, self.drag_anchor) {
+            (true, Some(anchor)) => {
+                self.center -= (mouse - anchor) / self.scale;
+                self.drag_anchor = Some(mouse);
+            }
+            (true, None) if over_view => self.drag_anchor = Some(mouse),
+            (false, _) => self.drag_anchor = None,
+            _ => {}
+        }
+
+        let mut pan = vec2(0.0, 0.0);
+        if is_key_down(KeyCode::A) || is_key_down(KeyCode::Left) {
+            pan.x -= 1.0;
+        }
+        if is_key_down(KeyCode::D) || is_key_down(KeyCode::Right) {
+            pan.x += 1.0;
+        }
+        if is_key_down(KeyCode::W) || is_key_down(KeyCode::Up) {
+            pan.y -= 1.0;
+        }
+        if is_key_down(KeyCode::S) || is_key_down(KeyCode::Down) {
+            pan.y += 1.0;
+        }
+        self.center += pan * PAN_SPEED * get_frame_time() / self.scale;
+
+        self.center = self.center.clamp(vec2(0.0, 0.0), vec2(world_w, world_h));
+    }
+
+    // Zoomed out far enough to see the whole world, but never past 1:1 for
+    // worlds that already fit
+    fn min_scale(&self, world_w: f32, world_h: f32) -> f32 {
+        (self.view.w / world_w).min(self.view.h / world_h).min(1.0)
+    }
+
+    // macroquad camera that maps world units onto the view
+    pub fn to_camera2d(&self) -> Camera2D {
+        let (sw, sh) = (screen_width(), screen_height());
+        let origin = self.view.center();
+        Camera2D {
+            target: self.center,
+            zoom: vec2(2.0 * self.scale / sw, 2.0 * self.scale / sh),
+            offset: vec2(2.0 * origin.x / sw - 1.0, 1.0 - 2.0 * origin.y / sh),
+            ..Default::default()
+        }
+    }
+}
+
+// 3D camera circling `target`: left drag orbits, the wheel moves in and out
+pub struct OrbitCamera {
+    pub target: Vec3,
+    pub yaw: f32,
+    pub pitch: f32,
+    pub distance: f32,
+    pub view: Rect,
+    drag_anchor: Option<Vec2>,
+}
+
+impl OrbitCamera {
+    // Looking at the middle of a box from in front, far enough to see all of it
+    pub fn new(view: Rect, size: Vec3) -> Self {
+        Self {
+            target: size / 2.0,
+            yaw: 0.0,
+            pitch: 0.3,
+            distance: size.max_element() * 1.4,
+            view,
+            drag_anchor: None,
+        }
+    }
+
+    pub fn handle_input(&mut self) {
+        let mouse = Vec2::from(mouse_position());
+        let (_, wheel) = mouse_wheel();
+        if self.view.contains(mouse) && wheel != 0.0 {
+            let factor = if wheel > 0.0 { 1.0 / ZOOM_STEP } else { ZOOM_STEP };
+            self.distance = (self.distance * factor).max(50.0);
+        }
+
+        match (is_mouse_button_down(MouseButton::Left), self.drag_anchor) {
+            (true, Some(anchor)) => {
+                let delta = mouse - anchor;
+                self.yaw -= delta.x * ORBIT_SPEED;
+                self.pitch = (self.pitch + delta.y * ORBIT_SPEED).clamp(-1.5, 1.5);
+                self.drag_anchor = Some(mouse);
+            }
+            (true, None) if self.view.contains(mouse) => self.drag_anchor = Some(mouse),
+            (false, _) => self.drag_anchor = None,
+            _ => {}
+        }
+    }
+
+    pub fn position(&self) -> Vec3 {
+        let offset = vec3(self.yaw.sin() * self.pitch.cos(), self.pitch.sin(), self.yaw.cos() * self.pitch.cos());
+        self.target + offset * self.distance
+    }
+
+    // Renders into the view rectangle only; macroquad viewports count from the
+    // bottom of the window
+    pub fn to_camera3d(&self) -> Camera3D {
+        let bottom = screen_height() - self.view.bottom();
+        Camera3D {
+            position: self.position(),
+            target: self.target,
+            up: vec3(0.0, 1.0, 0.0),
+            aspect: Some(self.view.w / self.view.h.max(1.0)),
+            viewport: Some((self.view.x as i32, bottom as i32, self.view.w as i32, self.view.h as i32)),
+            ..Default::default()
+        }
+    }
+}
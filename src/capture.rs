@@ -0,0 +1,211 @@
+use macroquad::prelude::*;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{sync_channel, Receiver, SyncSender};
+use std::thread::JoinHandle;
+use tracing::warn;
+
+use crate::cadence::Cadence;
+
+// Folder the screenshot key saves into
+pub const SCREENSHOT_DIR: &str = "screenshots";
+
+// Save what has been drawn so far this frame as a PNG
+pub fn save_frame(path: &Path) -> std::io::Result<()> {
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    get_screen_data().export_png(&path.to_string_lossy());
+    Ok(())
+}
+
+// `screenshots/tick_<tick>.png`, for the screenshot key
+pub fn screenshot_path(tick: u64) -> PathBuf {
+    Path::new(SCREENSHOT_DIR).join(format!("tick_{:08}.png", tick))
+}
+
+// Saves a frame every `every` ticks into `dir`, named by tick so the files sort
+// into a video-ready sequence
+pub struct Timelapse {
+    pub dir: PathBuf,
+    pub every: Cadence,
+}
+
+impl Timelapse {
+    pub fn new(dir: impl Into<PathBuf>, every: u64) -> Self {
+        Self { dir: dir.into(), every: Cadence::new(every.max(1)) }
+    }
+
+    // Capture the frame if one came due since the last, once (the same tick
+    // is drawn many times while paused)
+    pub fn capture(&mut self, tick: u64) {
+        if !self.every.due(tick) {
+            return;
+        }
+        let path = self.dir.join(format!("frame_{:08}.png", tick));
+        if let Err(e) = save_frame(&path) {
+            warn!("Couldn't save timelapse frame {}: {}", path.display(), e);
+        }
+    }
+}
+
+// Container a recorded clip is encoded to
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ClipFormat {
+    // Built-in encoder (the `gif` feature)
+    Gif,
+    // Piped through `ffmpeg`, which has to be on the PATH
+    Mp4,
+}
+
+impl ClipFormat {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "gif" => Some(ClipFormat::Gif),
+            "mp4" => Some(ClipFormat::Mp4),
+            _ => None,
+        }
+    }
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            ClipFormat::Gif => "gif",
+            ClipFormat::Mp4 => "mp4",
+        }
+    }
+}
+
+// Frames queued for the encoder before capturing waits for it
+const CLIP_BACKLOG: usize = 8;
+
+// Background thread turning frames into a clip file
+type Encoder = JoinHandle<Result<(), String>>;
+
+// Records the sim area for a fixed duration at `fps` frames per second. Frames
+// are encoded on a background thread as they come in.
+pub struct Recorder {
+    pub path: PathBuf,
+    area: Rect,
+    fps: f32,
+    remaining: f32,
+    since_frame: f32,
+    frames: Option<SyncSender<Vec<u8>>>,
+    encoder: Option<Encoder>,
+}
+
+impl Recorder {
+    // Encoders want even dimensions, so the area is trimmed to them
+    pub fn start(path: PathBuf, format: ClipFormat, area: Rect, fps: f32, seconds: f32) -> Result<Self, String> {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+        }
+        let area = Rect::new(area.x.floor(), area.y.floor(), (area.w as u32 & !1) as f32, (area.h as u32 & !1) as f32);
+        let (width, height) = (area.w as u16, area.h as u16);
+        let (tx, rx) = sync_channel(CLIP_BACKLOG);
+        let encoder = match format {
+            ClipFormat::Gif => encode_gif(&path, width, height, fps, rx)?,
+            ClipFormat::Mp4 => encode_mp4(&path, width, height, fps, rx)?,
+        };
+        Ok(Self {
+            path,
+            area,
+            fps,
+            remaining: seconds,
+            since_frame: f32::INFINITY,
+            frames: Some(tx),
+            encoder: Some(encoder),
+        })
+    }
+
+    // Grab the frame drawn so far if one is due; false once the clip is long
+    // enough
+    pub fn capture(&mut self, dt: f32) -> bool {
+        self.since_frame += dt;
+        self.remaining -= dt;
+        if self.since_frame >= 1.0 / self.fps {
+            self.since_frame = 0.0;
+            let frame = crop(&get_screen_data(), self.area);
+            if let Some(frames) = &self.frames
+                && frames.send(frame).is_err()
+            {
+                return false;
+            }
+        }
+        self.remaining > 0.0
+    }
+
+    // Stop capturing and wait for the encoder to write the file
+    pub fn finish(mut self) -> Result<PathBuf, String> {
+        self.frames = None;
+        match self.encoder.take().map(|e| e.join()) {
+            Some(Ok(result)) => result.map(|_| self.path.clone()),
+            _ => Err("the encoder crashed".to_owned()),
+        }
+    }
+}
+
+// RGBA rows of `area` (screen coordinates) from a screen grab, top row first.
+// Screen grabs are stored bottom row first; parts of `area` off screen are black.
+fn crop(screen: &Image, area: Rect) -> Vec<u8> {
+    let (w, h) = (area.w as usize, area.h as usize);
+    let (screen_w, screen_h) = (screen.width as usize, screen.height as usize);
+    let mut rgba = vec![0; w * h * 4];
+    for row in 0..h {
+        let y = area.y as usize + row;
+        if y >= screen_h {
+            break;
+        }
+        let src_row = screen_h - 1 - y;
+        let x0 = (area.x as usize).min(screen_w);
+        let x1 = (area.x as usize + w).min(screen_w);
+        let src = &screen.bytes[(src_row * screen_w + x0) * 4..(src_row * screen_w + x1) * 4];
+        rgba[row * w * 4..row * w * 4 + src.len()].copy_from_slice(src);
+    }
+    rgba
+}
+
+#[cfg(feature = "gif")]
+fn encode_gif(path: &Path, width: u16, height: u16, fps: f32, frames: Receiver<Vec<u8>>) -> Result<Encoder, String> {
+    let file = std::fs::File::create(path).map_err(|e| e.to_string())?;
+    let mut encoder = gif::Encoder::new(file, width, height, &[]).map_err(|e| e.to_string())?;
+    encoder.set_repeat(gif::Repeat::Infinite).map_err(|e| e.to_string())?;
+    let delay = (100.0 / fps).round() as u16;
+    Ok(std::thread::spawn(move || {
+        for mut rgba in frames {
+            let mut frame = gif::Frame::from_rgba_speed(width, height, &mut rgba, 10);
+            frame.delay = delay;
+            encoder.write_frame(&frame).map_err(|e| e.to_string())?;
+        }
+        Ok(())
+    }))
+}
+
+#[cfg(not(feature = "gif"))]
+fn encode_gif(_: &Path, _: u16, _: u16, _: f32, _: Receiver<Vec<u8>>) -> Result<Encoder, String> {
+    Err("GIF recording needs the `gif` feature; record MP4 instead".to_owned())
+}
+
+fn encode_mp4(path: &Path, width: u16, height: u16, fps: f32, frames: Receiver<Vec<u8>>) -> Result<Encoder, String> {
+    use std::io::Write;
+    use std::process::{Command, Stdio};
+
+    let mut ffmpeg = Command::new("ffmpeg")
+        .args(["-loglevel", "error", "-y", "-f", "rawvideo", "-pix_fmt", "rgba"])
+        .args(["-s", &format!("{}x{}", width, height), "-r", &fps.to_string(), "-i", "-"])
+        .args(["-pix_fmt", "yuv420p"])
+        .arg(path)
+        .stdin(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("couldn't run ffmpeg: {}", e))?;
+    let mut stdin = ffmpeg.stdin.take().ok_or("couldn't write to ffmpeg")?;
+    Ok(std::thread::spawn(move || {
+        for rgba in frames {
+            stdin.write_all(&rgba).map_err(|e| e.to_string())?;
+        }
+        drop(stdin);
+        match ffmpeg.wait() {
+            Ok(status) if status.success() => Ok(()),
+            Ok(status) => Err(format!("ffmpeg exited with {}", status)),
+            Err(e) => Err(e.to_string()),
+        }
+    }))
+}
@@ -0,0 +1,154 @@
+use macroquad::prelude::*;
+use std::path::{Path, PathBuf};
+
+use crate::habitat::Region;
+use crate::stats::{Marker, Metric, Stats};
+
+// Pixel size of an exported chart
+const CHART_WIDTH: f32 = 1600.0;
+const CHART_HEIGHT: f32 = 900.0;
+// Room left around the plot for the title, axis labels and legend
+const MARGIN_LEFT: f32 = 120.0;
+const MARGIN_RIGHT: f32 = 60.0;
+const MARGIN_TOP: f32 = 90.0;
+const MARGIN_BOTTOM: f32 = 110.0;
+
+const BACKGROUND: Color = Color::new(0.06, 0.06, 0.08, 1.0);
+const AXIS_COLOR: Color = Color::new(0.75, 0.75, 0.78, 1.0);
+const GRID_COLOR: Color = Color::new(0.25, 0.25, 0.3, 1.0);
+
+// One line on a chart
+pub struct Series<'a> {
+    pub label: &'a str,
+    pub data: &'a [f32],
+    pub color: Color,
+}
+
+// Render the graphs of `metrics` (with axes, gridline values and a legend) to
+// `dir/<name>_<tick>.png`. Must be called while drawing a frame.
+pub fn export_charts(stats: &Stats, regions: &[Region], metrics: &[Metric], dir: &Path) -> Result<Vec<PathBuf>, String> {
+    std::fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+    let tick = stats.tick_history.last().copied().unwrap_or(0);
+    let markers = stats.marker_series();
+    let charts: Vec<(&str, &str, Vec<Series>)> = metrics.iter()
+        .map(|&metric| {
+            let series = match metric.history(stats) {
+                Some(data) => vec![Series { label: metric.title(), data, color: metric.color() }],
+                None if metric == Metric::Markers => markers.iter()
+                    .map(|(label, data, color)| Series { label, data, color: *color })
+                    .collect(),
+                None => stats.region_history.iter().zip(regions)
+                    .map(|(data, region)| Series { label: region.name, data, color: region.color })
+                    .collect(),
+            };
+            (metric.file_name(), metric.title(), series)
+        })
+        .filter(|(_, _, series)| !series.is_empty())
+        .collect();
+
+    let target = render_target(CHART_WIDTH as u32, CHART_HEIGHT as u32);
+    let mut camera = Camera2D::from_display_rect(Rect::new(0.0, 0.0, CHART_WIDTH, CHART_HEIGHT));
+    camera.render_target = Some(target.clone());
+
+    let mut paths = Vec::new();
+    for (name, title, series) in charts {
+        set_camera(&camera);
+        clear_background(BACKGROUND);
+        draw_chart(title, &series, &stats.tick_history, &stats.markers);
+        set_default_camera();
+        let path = dir.join(format!("{}_{:08}.png", name, tick));
+        target.texture.get_texture_data().export_png(&path.to_string_lossy());
+        paths.push(path);
+    }
+    Ok(paths)
+}
+
+// Title, axes with tick and value labels, gridlines, event markers, the lines
+// themselves and a legend. Entries line up with the end of `ticks`.
+fn draw_chart(title: &str, series: &[Series], ticks: &[u64], markers: &[Marker]) {
+    let plot = Rect::new(MARGIN_LEFT, MARGIN_TOP, CHART_WIDTH - MARGIN_LEFT - MARGIN_RIGHT, CHART_HEIGHT - MARGIN_TOP - MARGIN_BOTTOM);
+    draw_text(title, MARGIN_LEFT, 55.0, 44.0, WHITE);
+
+    let max_value = series.iter().flat_map(|s| s.data.iter().copied()).fold(0.0, f32::max);
+    let step = nice_step(max_value.max(1e-3), 5);
+    let top = (max_value / step).ceil().max(1.0) * step;
+
+    // Value gridlines and labels
+    let mut value = 0.0;
+    while value <= top + step * 0.5 {
+        let y = plot.bottom() - value / top * plot.h;
+        draw_line(plot.x, y, plot.right(), y, 1.0, GRID_COLOR);
+        let label = format_value(value, step);
+        let dims = measure_text(&label, None, 24, 1.0);
+        draw_text(&label, plot.x - dims.width - 12.0, y + 8.0, 24.0, AXIS_COLOR);
+        value += step;
+    }
+
+    // Tick labels along the bottom
+    let (first, last) = (ticks.first().copied().unwrap_or(0), ticks.last().copied().unwrap_or(0));
+    let span = last.saturating_sub(first).max(1);
+    let tick_step = (nice_step(span as f32, 6) as u64).max(1);
+    let mut tick = first.div_ceil(tick_step) * tick_step;
+    while tick <= last {
+        let x = plot.x + (tick - first) as f32 / span as f32 * plot.w;
+        draw_line(x, plot.bottom(), x, plot.bottom() + 8.0, 2.0, AXIS_COLOR);
+        let label = tick.to_string();
+        let dims = measure_text(&label, None, 24, 1.0);
+        draw_text(&label, x - dims.width / 2.0, plot.bottom() + 34.0, 24.0, AXIS_COLOR);
+        tick += tick_step;
+    }
+    draw_text("Tick", plot.x + plot.w / 2.0 - 20.0, plot.bottom() + 80.0, 28.0, AXIS_COLOR);
+
+    for marker in markers.iter().filter(|m| (first..=last).contains(&m.tick)) {
+        let x = plot.x + (marker.tick - first) as f32 / span as f32 * plot.w;
+        draw_line(x, plot.y, x, plot.bottom(), 2.0, Color::new(marker.color.r, marker.color.g, marker.color.b, 0.6));
+        draw_text(&marker.label, x + 6.0, plot.y + 24.0, 22.0, marker.color);
+    }
+
+    // The lines, placed by tick so gaps (pauses, resets) show as they happened
+    for s in series {
+        let offset = ticks.len().saturating_sub(s.data.len());
+        let points: Vec<Vec2> = s.data.iter().enumerate()
+            .map(|(i, v)| {
+                let t = ticks.get(offset + i).copied().unwrap_or(last);
+                vec2(plot.x + t.saturating_sub(first) as f32 / span as f32 * plot.w, plot.bottom() - v / top * plot.h)
+            })
+            .collect();
+        for pair in points.windows(2) {
+            draw_line(pair[0].x, pair[0].y, pair[1].x, pair[1].y, 3.0, s.color);
+        }
+    }
+
+    draw_line(plot.x, plot.y, plot.x, plot.bottom(), 2.0, AXIS_COLOR);
+    draw_line(plot.x, plot.bottom(), plot.right(), plot.bottom(), 2.0, AXIS_COLOR);
+
+    // Legend in the top-right corner, with each line's latest value
+    let mut y = plot.y + 30.0;
+    for s in series {
+        let label = match s.data.last() {
+            Some(v) => format!("{}: {}", s.label, format_value(*v, step / 10.0)),
+            None => s.label.to_owned(),
+        };
+        let dims = measure_text(&label, None, 24, 1.0);
+        let x = plot.right() - dims.width - 20.0;
+        draw_rectangle(x - 30.0, y - 14.0, 20.0, 6.0, s.color);
+        draw_text(&label, x, y, 24.0, WHITE);
+        y += 32.0;
+    }
+}
+
+// Round step (1, 2 or 5 times a power of ten) splitting `range` into about
+// `count` parts, for axis labels
+pub fn nice_step(range: f32, count: u32) -> f32 {
+    let raw = range / count as f32;
+    let magnitude = 10f32.powf(raw.log10().floor());
+    let norm = raw / magnitude;
+    let nice = if norm < 1.5 { 1.0 } else if norm < 3.5 { 2.0 } else if norm < 7.5 { 5.0 } else { 10.0 };
+    nice * magnitude
+}
+
+// As many decimals as `step` needs
+pub fn format_value(value: f32, step: f32) -> String {
+    let decimals = (-step.log10().floor()).max(0.0) as usize;
+    format!("{:.*}", decimals, value)
+}
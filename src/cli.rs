@@ -0,0 +1,265 @@
+use clap::{Args, Parser, Subcommand};
+
+use crate::bench;
+use crate::capture::ClipFormat;
+use crate::params::{PhZone, MAX_SENSE_STRIDE, MAX_WORLD_SIZE, MIN_WORLD_SIZE};
+
+// Command line: a subcommand per way of running the simulation, or `run`'s
+// flags on their own
+#[derive(Parser)]
+#[command(name = "bact-sim", version, about = "Bacterial ecosystem simulation", args_conflicts_with_subcommands = true)]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+    #[command(flatten)]
+    pub run: RunArgs,
+    #[arg(long, global = true, value_name = "FILTER", help = "Log level (debug) or per-target levels (warn,sim=debug)")]
+    pub log_level: Option<String>,
+    #[arg(long, global = true, value_name = "PATH", help = "Write the log to this file as well as stderr")]
+    pub log_file: Option<String>,
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    #[command(about = "Open the simulation in a window (the default)")]
+    Run(RunArgs),
+    #[command(about = "Run without a window, as fast as possible")]
+    Headless(HeadlessArgs),
+    #[command(about = "Carry on from a saved world (a console `save` or an autosave) in a window")]
+    Replay(ReplayArgs),
+    #[command(about = "Run every combination of parameter values and write a CSV of the outcomes")]
+    Sweep(SweepArgs),
+    #[command(about = "Measure ticks per second at a few population sizes")]
+    Bench(BenchArgs),
+    #[command(about = "Report how two sets of `--stats-csv` runs differ")]
+    Compare(CompareArgs),
+    #[command(about = "Report what changed between two saved worlds")]
+    Diff(DiffArgs),
+    #[command(about = "Pit population files against each other")]
+    Tournament(TournamentArgs),
+}
+
+// How the world is set up, shared by everything that builds one
+#[derive(Args)]
+pub struct WorldArgs {
+    #[arg(long, value_name = "PATH", help = "Parameters and key bindings from a JSON file, watched for edits")]
+    pub config: Option<String>,
+    #[arg(long, value_name = "WxH", value_parser = parse_world_size, help = "World size, e.g. 10000x10000")]
+    pub world: Option<(f32, f32)>,
+    #[arg(long, conflicts_with = "petri", help = "Islands of differing richness")]
+    pub islands: bool,
+    #[arg(long, help = "A circular petri dish arena")]
+    pub petri: bool,
+    #[arg(long, requires = "petri", help = "Put half the dish's food in its middle")]
+    pub rich_center: bool,
+    #[arg(long, value_name = "X,Y,RADIUS,PH", value_parser = parse_ph_zone, help = "A pH zone as x,y,radius,ph; repeat for more, replacing the defaults")]
+    pub ph_zone: Vec<PhZone>,
+    #[arg(long, value_name = "K", value_parser = parse_sense_stride, help = "Have each organism look for food or prey only every K ticks, staggered, for speed")]
+    pub sense_stride: Option<usize>,
+    #[arg(long, value_name = "PATH", conflicts_with = "mask", help = "Terrain saved by the terrain editor")]
+    pub terrain: Option<String>,
+    #[arg(long, value_name = "PATH", help = "Terrain drawn as an image, stretched over the world")]
+    pub mask: Option<String>,
+    #[arg(long, value_name = "PATH", help = "Start every world with the bacteria in this file")]
+    pub population: Option<String>,
+    #[arg(long, help = "Seed for the world's random numbers (default: the clock)")]
+    pub seed: Option<u64>,
+}
+
+// The challenge a run plays and where it reports to, shared by the windowed
+// and headless modes
+#[derive(Args)]
+pub struct OutputArgs {
+    #[arg(long, value_name = "ADDR", help = "Stream stats over WebSocket on this address")]
+    pub telemetry: Option<String>,
+    #[arg(long, value_name = "N", help = "Ticks between telemetry stats messages")]
+    pub telemetry_every: Option<u64>,
+    #[arg(long, value_name = "N", help = "Ticks between telemetry organism snapshots")]
+    pub snapshot_every: Option<u64>,
+    #[cfg(feature = "http-api")]
+    #[arg(long, value_name = "ADDR", help = "Serve the REST control API on this address")]
+    pub api: Option<String>,
+    #[arg(long, value_name = "ADDR", help = "Serve Prometheus metrics on this address")]
+    pub metrics: Option<String>,
+    #[cfg(feature = "sqlite")]
+    #[arg(long, value_name = "PATH", help = "Log every tick to this SQLite file")]
+    pub log_db: Option<String>,
+    #[cfg(feature = "sqlite")]
+    #[arg(long, value_name = "N", help = "Ticks between organism snapshots in the SQLite log")]
+    pub log_snapshot_every: Option<u64>,
+    #[cfg(feature = "parquet")]
+    #[arg(long, value_name = "DIR", help = "Export the population as Parquet into this folder")]
+    pub parquet_dir: Option<String>,
+    #[cfg(feature = "parquet")]
+    #[arg(long, value_name = "N", help = "Ticks between Parquet exports")]
+    pub parquet_every: Option<u64>,
+    #[arg(long, value_name = "PATH", help = "Write every tick's stats to this CSV file")]
+    pub stats_csv: Option<String>,
+    #[arg(long, value_name = "PATH", help = "Write a summary report (.html or .md) when the run ends")]
+    pub report: Option<String>,
+    #[arg(long, value_name = "PATH", help = "Archive notable genomes in this file")]
+    pub hall_of_fame: Option<String>,
+    #[arg(long, value_name = "PATH", help = "Track achievements in this file")]
+    pub achievements: Option<String>,
+    #[arg(long, value_name = "PATH", help = "Write the survivors' phylogeny (.nwk Newick, or .dot GraphViz) when the run ends")]
+    pub export_tree: Option<String>,
+    #[arg(long, value_name = "N", default_value_t = 100, help = "Survivors sampled into the exported phylogeny, 0 for all")]
+    pub tree_sample: usize,
+    #[arg(long, value_name = "PATH", help = "Play the challenge in this scenario file")]
+    pub scenario: Option<String>,
+}
+
+#[derive(Args)]
+pub struct AutosaveArgs {
+    #[arg(long, value_name = "DIR", help = "Folder autosaves rotate in")]
+    pub autosave_dir: Option<String>,
+    #[arg(long, value_name = "N", help = "Ticks between autosaves, 0 for none")]
+    pub autosave_every: Option<u64>,
+    #[arg(long, value_name = "N", help = "Autosaves kept")]
+    pub autosave_keep: Option<usize>,
+    #[arg(long, help = "Carry on from the newest autosave")]
+    pub resume: bool,
+}
+
+// What only a window can do
+#[derive(Args)]
+pub struct WindowArgs {
+    #[arg(long, help = "Start with sound muted")]
+    pub mute: bool,
+    #[arg(long, value_name = "FORMAT", value_parser = parse_clip_format, help = "Clip format: gif or mp4")]
+    pub record_format: Option<ClipFormat>,
+    #[arg(long, value_name = "SECONDS", help = "Clip length")]
+    pub record_seconds: Option<f32>,
+    #[arg(long, value_name = "FPS", help = "Clip frame rate")]
+    pub record_fps: Option<f32>,
+    #[arg(long, value_name = "DIR", help = "Save timelapse frames into this folder")]
+    pub timelapse: Option<String>,
+    #[arg(long, value_name = "N", help = "Ticks between timelapse frames")]
+    pub timelapse_every: Option<u64>,
+    #[arg(long, value_name = "PATH", help = "Draw an earlier run's `--stats-csv` behind the graphs")]
+    pub baseline: Option<String>,
+    #[arg(long, value_name = "N", help = "Ticks run per frame (default 1)")]
+    pub ticks_per_frame: Option<u32>,
+    #[arg(long, value_name = "MS", help = "Run fewer ticks per frame when frames would take longer than this")]
+    pub frame_budget: Option<f32>,
+    #[arg(long, help = "Step the simulation on a worker thread, so slow ticks don't hold up the window")]
+    pub sim_thread: bool,
+}
+
+// Everything a windowed session takes
+#[derive(Args)]
+pub struct SessionArgs {
+    #[command(flatten)]
+    pub world: WorldArgs,
+    #[command(flatten)]
+    pub output: OutputArgs,
+    #[command(flatten)]
+    pub autosave: AutosaveArgs,
+    #[command(flatten)]
+    pub window: WindowArgs,
+}
+
+#[derive(Args)]
+pub struct RunArgs {
+    #[arg(long = "3d", help = "The 3D box instead of the 2D dish")]
+    pub three_d: bool,
+    #[command(flatten)]
+    pub session: SessionArgs,
+}
+
+#[derive(Args)]
+pub struct HeadlessArgs {
+    #[arg(long, value_name = "N", help = "Stop after this many ticks (default: run until stopped)")]
+    pub ticks: Option<u64>,
+    #[command(flatten)]
+    pub world: WorldArgs,
+    #[command(flatten)]
+    pub output: OutputArgs,
+    #[command(flatten)]
+    pub autosave: AutosaveArgs,
+}
+
+#[derive(Args)]
+pub struct ReplayArgs {
+    #[arg(help = "Saved world to open")]
+    pub file: String,
+    #[command(flatten)]
+    pub session: SessionArgs,
+}
+
+#[derive(Args)]
+pub struct SweepArgs {
+    #[arg(help = "JSON file of the values to try per parameter (see `sweep::Sweep`)")]
+    pub plan: String,
+    #[arg(long, value_name = "PATH", help = "CSV file for the outcomes (default: stdout)")]
+    pub out: Option<String>,
+    #[arg(long, value_name = "N", help = "Runs at once (default: one per CPU)")]
+    pub jobs: Option<usize>,
+    #[command(flatten)]
+    pub world: WorldArgs,
+}
+
+#[derive(Args)]
+pub struct BenchArgs {
+    #[arg(long, value_name = "N", default_value_t = bench::TICKS, help = "Ticks timed per population")]
+    pub ticks: u64,
+    #[arg(long, value_name = "N,N,...", value_delimiter = ',', default_values_t = bench::POPULATIONS, help = "Bacteria to start with, comma-separated")]
+    pub populations: Vec<usize>,
+    #[arg(long, value_name = "K", default_value_t = 1, value_parser = parse_sense_stride, help = "Ticks between an organism's looks for food or prey")]
+    pub sense_stride: usize,
+    #[arg(long, default_value_t = bench::SEED, help = "Seed for every population's world")]
+    pub seed: u64,
+}
+
+#[derive(Args)]
+pub struct CompareArgs {
+    #[arg(value_name = "RUNS_A", help = "One side's runs, comma-separated")]
+    pub a: String,
+    #[arg(value_name = "RUNS_B", help = "The other side's runs, comma-separated")]
+    pub b: String,
+}
+
+#[derive(Args)]
+pub struct DiffArgs {
+    #[arg(value_name = "SAVE_A", help = "The earlier saved world (a console `save` or an autosave)")]
+    pub a: String,
+    #[arg(value_name = "SAVE_B", help = "The later saved world")]
+    pub b: String,
+}
+
+#[derive(Args)]
+pub struct TournamentArgs {
+    #[arg(num_args = 2.., required = true, value_name = "POPULATION", help = "Population files competing, at least two")]
+    pub entrants: Vec<String>,
+    #[arg(long, value_name = "N", default_value_t = 5000, help = "Ticks per trial")]
+    pub ticks: u64,
+    #[arg(long, value_name = "N", default_value_t = 5, help = "Trials run, seeded one after another")]
+    pub trials: u64,
+    #[command(flatten)]
+    pub world: WorldArgs,
+}
+
+fn parse_world_size(s: &str) -> Result<(f32, f32), String> {
+    let size: Option<(f32, f32)> = s.split_once('x').and_then(|(w, h)| Some((w.parse().ok()?, h.parse().ok()?)));
+    let (w, h) = size.ok_or_else(|| format!("expected WIDTHxHEIGHT, got '{}'", s))?;
+    let range = MIN_WORLD_SIZE..=MAX_WORLD_SIZE;
+    if !range.contains(&w) || !range.contains(&h) {
+        return Err(format!("width and height must be between {} and {}, got '{}'", MIN_WORLD_SIZE, MAX_WORLD_SIZE, s));
+    }
+    Ok((w, h))
+}
+
+fn parse_sense_stride(s: &str) -> Result<usize, String> {
+    match s.parse() {
+        Ok(k) if (1..=MAX_SENSE_STRIDE).contains(&k) => Ok(k),
+        _ => Err(format!("expected a number of ticks from 1 to {}, got '{}'", MAX_SENSE_STRIDE, s)),
+    }
+}
+
+fn parse_ph_zone(s: &str) -> Result<PhZone, String> {
+    PhZone::parse(s).ok_or_else(|| format!("expected x,y,radius,ph, got '{}'", s))
+}
+
+fn parse_clip_format(s: &str) -> Result<ClipFormat, String> {
+    ClipFormat::parse(s).ok_or_else(|| format!("expected gif or mp4, got '{}'", s))
+}
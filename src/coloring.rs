@@ -0,0 +1,379 @@
+use hecs::Entity;
+use macroquad::prelude::*;
+use std::collections::HashMap;
+
+use crate::components::{Age, Energy, Genome, Lineage};
+use crate::dna::{DNA, SIZE, SPEED};
+use crate::stats::CORRELATED_GENES;
+use crate::world::World;
+
+const GENES: usize = CORRELATED_GENES.len();
+
+// How many species the genomes are split into, and how often that's redone
+const SPECIES_COUNT: usize = 6;
+const SPECIES_REFRESH: u64 = 100;
+const KMEANS_ROUNDS: usize = 10;
+
+// Lineages listed in the legend, biggest first
+const LEGEND_LINEAGES: usize = 5;
+
+// One colour per species, biggest first
+const SPECIES_COLORS: [Color; SPECIES_COUNT] = [
+    Color::new(0.95, 0.35, 0.3, 1.0),
+    Color::new(0.3, 0.6, 1.0, 1.0),
+    Color::new(0.4, 0.9, 0.35, 1.0),
+    Color::new(1.0, 0.8, 0.2, 1.0),
+    Color::new(0.8, 0.4, 1.0, 1.0),
+    Color::new(0.3, 0.9, 0.9, 1.0),
+];
+
+// Colour stops for continuous values, low to high (viridis)
+const RAMP: [Color; 5] = [
+    Color::new(0.27, 0.0, 0.33, 1.0),
+    Color::new(0.23, 0.32, 0.55, 1.0),
+    Color::new(0.13, 0.57, 0.55, 1.0),
+    Color::new(0.37, 0.79, 0.38, 1.0),
+    Color::new(0.99, 0.91, 0.14, 1.0),
+];
+
+// Okabe-Ito colours, told apart with any common colour blindness
+const OKABE_ITO: [Color; SPECIES_COUNT] = [
+    Color::new(0.9, 0.62, 0.0, 1.0),
+    Color::new(0.34, 0.71, 0.91, 1.0),
+    Color::new(0.0, 0.62, 0.45, 1.0),
+    Color::new(0.94, 0.89, 0.26, 1.0),
+    Color::new(0.0, 0.45, 0.7, 1.0),
+    Color::new(0.8, 0.47, 0.65, 1.0),
+];
+
+// Cividis: blue to yellow, changing in lightness all the way, so it reads
+// the same with red-green colour blindness
+const CIVIDIS: [Color; 5] = [
+    Color::new(0.0, 0.13, 0.3, 1.0),
+    Color::new(0.26, 0.3, 0.42, 1.0),
+    Color::new(0.49, 0.49, 0.47, 1.0),
+    Color::new(0.74, 0.68, 0.42, 1.0),
+    Color::new(1.0, 0.91, 0.27, 1.0),
+];
+
+// Colours every view of the world is drawn with
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Palette {
+    #[default]
+    Standard,
+    // Okabe-Ito and cividis; DNA colours are spread along cividis by hue and
+    // predators are white
+    ColorblindSafe,
+}
+
+impl Palette {
+    pub fn label(self) -> &'static str {
+        match self {
+            Palette::Standard => "Standard",
+            Palette::ColorblindSafe => "Colourblind safe",
+        }
+    }
+
+    pub fn next(self) -> Self {
+        match self {
+            Palette::Standard => Palette::ColorblindSafe,
+            Palette::ColorblindSafe => Palette::Standard,
+        }
+    }
+
+    // Position of `t` (0 to 1) along the palette's colour ramp
+    pub fn ramp(self, t: f32) -> Color {
+        let stops = match self {
+            Palette::Standard => &RAMP,
+            Palette::ColorblindSafe => &CIVIDIS,
+        };
+        let t = t.clamp(0.0, 1.0) * (stops.len() - 1) as f32;
+        let i = (t as usize).min(stops.len() - 2);
+        let f = t - i as f32;
+        let (a, b) = (stops[i], stops[i + 1]);
+        Color::new(a.r + (b.r - a.r) * f, a.g + (b.g - a.g) * f, a.b + (b.b - a.b) * f, 1.0)
+    }
+
+    fn species(self, species: usize) -> Color {
+        let colors = match self {
+            Palette::Standard => &SPECIES_COLORS,
+            Palette::ColorblindSafe => &OKABE_ITO,
+        };
+        colors[species.min(SPECIES_COUNT - 1)]
+    }
+
+    // A steady, well-spread colour per founding ancestor. The colourblind
+    // palette only has a few, so distant lineages may share one.
+    pub fn lineage(self, founder: Entity) -> Color {
+        let hash = founder.id().wrapping_mul(2_654_435_761) >> 8;
+        match self {
+            Palette::Standard => macroquad::color::hsl_to_rgb(hash as f32 / (1u32 << 24) as f32, 0.75, 0.55),
+            Palette::ColorblindSafe => OKABE_ITO[hash as usize % OKABE_ITO.len()],
+        }
+    }
+
+    pub fn dna(self, color: Color) -> Color {
+        match self {
+            Palette::Standard => color,
+            Palette::ColorblindSafe => self.ramp(macroquad::color::rgb_to_hsl(color).0),
+        }
+    }
+
+    pub fn predator(self) -> Color {
+        match self {
+            Palette::Standard => Color::new(0.95, 0.25, 0.15, 0.95),
+            Palette::ColorblindSafe => Color::new(0.95, 0.95, 0.95, 0.95),
+        }
+    }
+}
+
+// What bacteria are coloured by in the main view
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ColorMode {
+    #[default]
+    Dna,
+    Speed,
+    Size,
+    Energy,
+    Age,
+    Generation,
+    Species,
+    Lineage,
+}
+
+impl ColorMode {
+    pub const ALL: [ColorMode; 8] = [
+        ColorMode::Dna,
+        ColorMode::Speed,
+        ColorMode::Size,
+        ColorMode::Energy,
+        ColorMode::Age,
+        ColorMode::Generation,
+        ColorMode::Species,
+        ColorMode::Lineage,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            ColorMode::Dna => "DNA colour",
+            ColorMode::Speed => "Speed",
+            ColorMode::Size => "Size",
+            ColorMode::Energy => "Energy",
+            ColorMode::Age => "Age",
+            ColorMode::Generation => "Generation",
+            ColorMode::Species => "Species",
+            ColorMode::Lineage => "Lineage",
+        }
+    }
+
+    pub fn next(self) -> Self {
+        let i = ColorMode::ALL.iter().position(|&m| m == self).unwrap();
+        ColorMode::ALL[(i + 1) % ColorMode::ALL.len()]
+    }
+
+    // The value a continuous mode colours by
+    fn value(self, dna: &DNA, energy: f32, age: f32, lineage: &Lineage) -> Option<f32> {
+        match self {
+            ColorMode::Speed => Some(dna.gene(SPEED)),
+            ColorMode::Size => Some(dna.gene(SIZE)),
+            ColorMode::Energy => Some(energy),
+            ColorMode::Age => Some(age),
+            ColorMode::Generation => Some(lineage.generation as f32),
+            ColorMode::Dna | ColorMode::Species | ColorMode::Lineage => None,
+        }
+    }
+}
+
+// Genome clusters found by k-means over the standardized `CORRELATED_GENES`,
+// redone every `SPECIES_REFRESH` ticks so colours don't flicker
+#[derive(Clone, Debug, Default)]
+pub struct Species {
+    tick: Option<u64>,
+    mean: [f32; GENES],
+    std: [f32; GENES],
+    // Biggest cluster first
+    centroids: Vec<[f32; GENES]>,
+}
+
+impl Species {
+    fn standardize(&self, dna: &DNA) -> [f32; GENES] {
+        let mut z = CORRELATED_GENES.map(|(_, tag)| dna.gene(tag));
+        for (i, v) in z.iter_mut().enumerate() {
+            *v = (*v - self.mean[i]) / self.std[i];
+        }
+        z
+    }
+
+    // Index of the species `dna` is closest to
+    pub fn of(&self, dna: &DNA) -> usize {
+        nearest(&self.centroids, &self.standardize(dna))
+    }
+
+    // Genes that set a species apart, e.g. "Spd+ Size-"
+    pub fn describe(&self, species: usize) -> String {
+        let Some(centroid) = self.centroids.get(species) else { return String::new() };
+        let mut genes: Vec<(usize, f32)> = centroid.iter().copied().enumerate().filter(|(_, z)| z.abs() > 0.5).collect();
+        genes.sort_by(|a, b| b.1.abs().total_cmp(&a.1.abs()));
+        genes.iter()
+            .take(2)
+            .map(|&(i, z)| format!("{}{}", CORRELATED_GENES[i].0, if z > 0.0 { "+" } else { "-" }))
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    pub fn refresh(&mut self, world: &World) {
+        let due = match self.tick {
+            Some(tick) => self.centroids.is_empty() || world.tick < tick || world.tick >= tick + SPECIES_REFRESH,
+            None => true,
+        };
+        if !due {
+            return;
+        }
+        self.tick = Some(world.tick);
+
+        let genes: Vec<[f32; GENES]> = world.ecs.query::<&Genome>().iter()
+            .map(|g| CORRELATED_GENES.map(|(_, tag)| g.0.gene(tag)))
+            .collect();
+        let count = genes.len().max(1) as f32;
+        self.mean = [0.0; GENES];
+        self.std = [0.0; GENES];
+        for values in &genes {
+            for (m, v) in self.mean.iter_mut().zip(values) {
+                *m += v / count;
+            }
+        }
+        for values in &genes {
+            for ((s, m), v) in self.std.iter_mut().zip(&self.mean).zip(values) {
+                *s += (v - m).powi(2) / count;
+            }
+        }
+        // Genes nobody varies in don't separate anything
+        self.std = self.std.map(|var| if var > 0.0 { var.sqrt() } else { 1.0 });
+        let points: Vec<[f32; GENES]> = genes.iter()
+            .map(|values| std::array::from_fn(|i| (values[i] - self.mean[i]) / self.std[i]))
+            .collect();
+        self.centroids = kmeans(&points, SPECIES_COUNT);
+    }
+}
+
+fn distance2(a: &[f32; GENES], b: &[f32; GENES]) -> f32 {
+    a.iter().zip(b).map(|(x, y)| (x - y).powi(2)).sum()
+}
+
+fn nearest(centroids: &[[f32; GENES]], point: &[f32; GENES]) -> usize {
+    (0..centroids.len())
+        .min_by(|&a, &b| distance2(&centroids[a], point).total_cmp(&distance2(&centroids[b], point)))
+        .unwrap_or(0)
+}
+
+// Up to `k` centroids, biggest cluster first. Seeded farthest-first from the
+// first point so the result doesn't depend on a random draw.
+fn kmeans(points: &[[f32; GENES]], k: usize) -> Vec<[f32; GENES]> {
+    let Some(&first) = points.first() else { return Vec::new() };
+    let mut centroids = vec![first];
+    while centroids.len() < k {
+        let farthest = points.iter()
+            .max_by(|a, b| {
+                let da = distance2(&centroids[nearest(&centroids, a)], a);
+                let db = distance2(&centroids[nearest(&centroids, b)], b);
+                da.total_cmp(&db)
+            })
+            .copied()
+            .unwrap();
+        if centroids.contains(&farthest) {
+            break;
+        }
+        centroids.push(farthest);
+    }
+
+    let mut counts = vec![0usize; centroids.len()];
+    for _ in 0..KMEANS_ROUNDS {
+        let mut sums = vec![[0.0f32; GENES]; centroids.len()];
+        counts = vec![0; centroids.len()];
+        for p in points {
+            let c = nearest(&centroids, p);
+            counts[c] += 1;
+            for (s, v) in sums[c].iter_mut().zip(p) {
+                *s += v;
+            }
+        }
+        for (c, sum) in sums.iter().enumerate() {
+            if counts[c] > 0 {
+                centroids[c] = sum.map(|s| s / counts[c] as f32);
+            }
+        }
+    }
+
+    let mut order: Vec<usize> = (0..centroids.len()).filter(|&c| counts[c] > 0).collect();
+    order.sort_by_key(|&c| std::cmp::Reverse(counts[c]));
+    order.into_iter().map(|c| centroids[c]).collect()
+}
+
+// How to colour each bacterium this frame, and the legend explaining it
+pub struct Coloring {
+    pub mode: ColorMode,
+    pub palette: Palette,
+    // Range of a continuous mode's value over the living bacteria
+    pub range: (f32, f32),
+    // Swatches for the species or biggest lineages, with a label each
+    pub legend: Vec<(Color, String)>,
+    species: Species,
+}
+
+impl Coloring {
+    pub fn new(mode: ColorMode, palette: Palette, world: &World, species: &mut Species) -> Self {
+        let mut coloring = Self { mode, palette, range: (0.0, 1.0), legend: Vec::new(), species: Species::default() };
+        match mode {
+            ColorMode::Dna => {}
+            ColorMode::Species => {
+                species.refresh(world);
+                let mut counts = vec![0usize; species.centroids.len()];
+                for genome in world.ecs.query::<&Genome>().iter() {
+                    counts[species.of(&genome.0)] += 1;
+                }
+                coloring.legend = counts.iter().enumerate()
+                    .map(|(i, n)| (palette.species(i), format!("{}: {} {}", i + 1, n, species.describe(i))))
+                    .collect();
+                coloring.species = species.clone();
+            }
+            ColorMode::Lineage => {
+                let mut counts: HashMap<Entity, usize> = HashMap::new();
+                for (id, lineage) in world.ecs.query::<(Entity, &Lineage)>().with::<&Genome>().iter() {
+                    *counts.entry(lineage.founder.unwrap_or(id)).or_default() += 1;
+                }
+                let mut biggest: Vec<(Entity, usize)> = counts.iter().map(|(&f, &n)| (f, n)).collect();
+                biggest.sort_by_key(|&(f, n)| (std::cmp::Reverse(n), f));
+                coloring.legend = biggest.iter()
+                    .take(LEGEND_LINEAGES)
+                    .map(|&(f, n)| (palette.lineage(f), format!("#{}: {}", f.id(), n)))
+                    .collect();
+                coloring.legend.push((GRAY, format!("{} lineages alive", counts.len())));
+            }
+            _ => {
+                let mut range = (f32::MAX, f32::MIN);
+                for (genome, energy, age, lineage) in world.ecs.query::<(&Genome, &Energy, &Age, &Lineage)>().iter() {
+                    if let Some(v) = mode.value(&genome.0, energy.0, age.0, lineage) {
+                        range = (range.0.min(v), range.1.max(v));
+                    }
+                }
+                if range.0 <= range.1 {
+                    coloring.range = range;
+                }
+            }
+        }
+        coloring
+    }
+
+    pub fn color(&self, id: Entity, genome: &Genome, energy: f32, age: f32, lineage: &Lineage) -> Color {
+        match self.mode {
+            ColorMode::Dna => self.palette.dna(genome.0.color()),
+            ColorMode::Species => self.palette.species(self.species.of(&genome.0)),
+            ColorMode::Lineage => self.palette.lineage(lineage.founder.unwrap_or(id)),
+            mode => {
+                let v = mode.value(&genome.0, energy, age, lineage).unwrap_or(0.0);
+                let (lo, hi) = self.range;
+                self.palette.ramp(if hi > lo { (v - lo) / (hi - lo) } else { 0.5 })
+            }
+        }
+    }
+}
@@ -0,0 +1,175 @@
+use crate::stats::Metric;
+use crate::stats_csv::{columns, Baseline};
+
+// Share of each run's rows, from the end, that counts as its final state
+const FINAL_FRACTION: f32 = 0.1;
+
+// Values of `metric` over the final stretch of a run
+fn final_values(run: &Baseline, metric: Metric) -> &[f32] {
+    let Some(series) = run.series(metric) else { return &[] };
+    let count = ((series.len() as f32 * FINAL_FRACTION).ceil() as usize).max(1);
+    &series[series.len().saturating_sub(count)..]
+}
+
+fn mean(values: &[f32]) -> f32 {
+    values.iter().sum::<f32>() / values.len().max(1) as f32
+}
+
+fn variance(values: &[f32]) -> f32 {
+    if values.len() < 2 {
+        return 0.0;
+    }
+    let m = mean(values);
+    values.iter().map(|v| (v - m) * (v - m)).sum::<f32>() / (values.len() - 1) as f32
+}
+
+// Standardized mean difference, over the pooled standard deviation
+pub fn cohens_d(a: &[f32], b: &[f32]) -> f32 {
+    let (na, nb) = (a.len() as f32, b.len() as f32);
+    let pooled = (((na - 1.0) * variance(a) + (nb - 1.0) * variance(b)) / (na + nb - 2.0).max(1.0)).sqrt();
+    if pooled > 0.0 { (mean(b) - mean(a)) / pooled } else { 0.0 }
+}
+
+// Chance a value from `b` beats one from `a`, minus the reverse: -1 to 1
+pub fn cliffs_delta(a: &[f32], b: &[f32]) -> f32 {
+    let mut score = 0i64;
+    for x in a {
+        for y in b {
+            score += (y > x) as i64 - (y < x) as i64;
+        }
+    }
+    score as f32 / (a.len() * b.len()).max(1) as f32
+}
+
+// Standard normal cumulative distribution (Abramowitz and Stegun 7.1.26)
+fn normal_cdf(z: f64) -> f64 {
+    let x = z.abs() / std::f64::consts::SQRT_2;
+    let t = 1.0 / (1.0 + 0.3275911 * x);
+    let poly = t * (0.254829592 + t * (-0.284496736 + t * (1.421413741 + t * (-1.453152027 + t * 1.061405429))));
+    let erf = 1.0 - poly * (-x * x).exp();
+    if z >= 0.0 { 0.5 * (1.0 + erf) } else { 0.5 * (1.0 - erf) }
+}
+
+// Mann-Whitney U for `a`, and its two-sided p-value from the normal
+// approximation with tie and continuity corrections
+pub fn mann_whitney(a: &[f32], b: &[f32]) -> (f64, f64) {
+    let mut all: Vec<(f32, bool)> = a.iter().map(|&v| (v, true)).chain(b.iter().map(|&v| (v, false))).collect();
+    all.sort_by(|x, y| x.0.total_cmp(&y.0));
+    let n = all.len() as f64;
+    let (na, nb) = (a.len() as f64, b.len() as f64);
+
+    // Tied values share their average rank
+    let (mut rank_sum_a, mut ties) = (0.0, 0.0);
+    let mut i = 0;
+    while i < all.len() {
+        let j = i + all[i..].iter().take_while(|v| v.0 == all[i].0).count();
+        let rank = (i + j + 1) as f64 / 2.0;
+        rank_sum_a += rank * all[i..j].iter().filter(|v| v.1).count() as f64;
+        let t = (j - i) as f64;
+        ties += t * t * t - t;
+        i = j;
+    }
+    let u = rank_sum_a - na * (na + 1.0) / 2.0;
+
+    let sd = (na * nb / 12.0 * ((n + 1.0) - ties / (n * (n - 1.0)).max(1.0))).sqrt();
+    if sd == 0.0 {
+        return (u, 1.0);
+    }
+    let z = ((u - na * nb / 2.0).abs() - 0.5).max(0.0) / sd;
+    (u, (2.0 * (1.0 - normal_cdf(z))).min(1.0))
+}
+
+// Runs `a` against runs `b`, one line per metric. With several runs on both
+// sides each contributes the mean of its final stretch, so replicates are
+// the samples; otherwise every final row is one.
+pub fn report(a: &[Baseline], b: &[Baseline], names: (&str, &str)) -> String {
+    let replicates = a.len() > 1 && b.len() > 1;
+    let samples = |runs: &[Baseline], metric: Metric| -> Vec<f32> {
+        if replicates {
+            runs.iter().map(|run| final_values(run, metric)).filter(|v| !v.is_empty()).map(mean).collect()
+        } else {
+            runs.iter().flat_map(|run| final_values(run, metric).iter().copied()).collect()
+        }
+    };
+
+    let runs = |n: usize| if n == 1 { "1 run".to_string() } else { format!("{} runs", n) };
+    let mut text = format!("A: {} ({})\nB: {} ({})\n", names.0, runs(a.len()), names.1, runs(b.len()));
+    text += &if replicates {
+        format!("Samples: each run's mean over its last {:.0}% of rows\n\n", FINAL_FRACTION * 100.0)
+    } else {
+        format!("Samples: every row in the last {:.0}% of each run (not independent, so read p loosely)\n\n", FINAL_FRACTION * 100.0)
+    };
+    let width = columns().map(|metric| metric.file_name().len()).fold("metric".len(), usize::max);
+    text += &format!("{:<width$} {:>10} {:>10} {:>9} {:>9} {:>9} {:>9} {:>8}\n", "metric", "A mean", "B mean", "change", "d", "delta", "U", "p");
+    for metric in columns() {
+        let (xs, ys) = (samples(a, metric), samples(b, metric));
+        if xs.is_empty() || ys.is_empty() {
+            continue;
+        }
+        let (ma, mb) = (mean(&xs), mean(&ys));
+        let change = if ma != 0.0 { format!("{:+.1}%", (mb - ma) / ma.abs() * 100.0) } else { "-".to_string() };
+        let (u, p) = mann_whitney(&xs, &ys);
+        let stars = if p < 0.01 { " **" } else if p < 0.05 { " *" } else { "" };
+        text += &format!(
+            "{:<width$} {:>10.3} {:>10.3} {:>9} {:>9.2} {:>9.2} {:>9.1} {:>8.4}{}\n",
+            metric.file_name(), ma, mb, change, cohens_d(&xs, &ys), cliffs_delta(&xs, &ys), u, p, stars,
+        );
+    }
+    text += "\nd: Cohen's d (B - A over the pooled SD), delta: Cliff's delta (B over A, -1 to 1)\n";
+    text += "U and p: two-sided Mann-Whitney test; * p < 0.05, ** p < 0.01";
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn close(a: f64, b: f64) -> bool {
+        (a - b).abs() < 1e-5
+    }
+
+    #[test]
+    fn cohens_d_is_the_gap_over_the_pooled_sd() {
+        assert_eq!(cohens_d(&[1.0, 2.0, 3.0], &[4.0, 5.0, 6.0]), 3.0);
+        // Variances 1 and 4 pool to 2.5
+        assert!(close(cohens_d(&[1.0, 2.0, 3.0], &[3.0, 5.0, 7.0]) as f64, 3.0 / 2.5f64.sqrt()));
+        assert_eq!(cohens_d(&[2.0, 2.0], &[5.0, 5.0]), 0.0);
+    }
+
+    #[test]
+    fn cliffs_delta_counts_ties_as_neither() {
+        assert_eq!(cliffs_delta(&[1.0, 2.0], &[3.0, 4.0]), 1.0);
+        assert_eq!(cliffs_delta(&[3.0, 4.0], &[1.0, 2.0]), -1.0);
+        // 6 pairs where b is higher, 1 where it's lower, 2 ties
+        assert!(close(cliffs_delta(&[1.0, 2.0, 3.0], &[2.0, 3.0, 4.0]) as f64, 5.0 / 9.0));
+        assert_eq!(cliffs_delta(&[], &[1.0]), 0.0);
+    }
+
+    #[test]
+    fn mann_whitney_without_ties() {
+        let (u, p) = mann_whitney(&[1.0, 2.0, 3.0], &[4.0, 5.0, 6.0]);
+        assert_eq!(u, 0.0);
+        assert!(close(p, 0.0808556), "p = {}", p);
+        let (u, p) = mann_whitney(&[4.0, 5.0, 6.0], &[1.0, 2.0, 3.0]);
+        assert_eq!(u, 9.0);
+        assert!(close(p, 0.0808556), "p = {}", p);
+    }
+
+    #[test]
+    fn mann_whitney_with_ties() {
+        // Ranks 1, 3, 3, 6 for `a`: U = 3, with two runs of three ties. Without
+        // the tie correction p would be 0.1939, without continuity 0.1292.
+        let (u, p) = mann_whitney(&[1.0, 2.0, 2.0, 3.0], &[2.0, 3.0, 3.0, 4.0]);
+        assert_eq!(u, 3.0);
+        assert!(close(p, 0.1720337), "p = {}", p);
+    }
+
+    #[test]
+    fn mann_whitney_continuity_correction_stops_at_no_difference() {
+        // U is 2, its mean; the correction can't push |U - mean| below 0
+        let (u, p) = mann_whitney(&[1.0, 4.0], &[2.0, 3.0]);
+        assert!(u == 2.0 && close(p, 1.0), "U = {}, p = {}", u, p);
+        // Every value tied: no spread at all
+        assert_eq!(mann_whitney(&[1.0, 1.0], &[1.0, 1.0]), (2.0, 1.0));
+    }
+}
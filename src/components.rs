@@ -0,0 +1,314 @@
+use macroquad::prelude::*;
+
+use crate::dna::{ARMOR, BURST, DNA, PH_OPTIMUM, PH_TOLERANCE, Plasmid, RESISTANCE, SENSE_RADIUS, SIZE, SPEED, TOXIN, VIGILANCE};
+
+// Components stored in the ECS. Bacteria carry a `Genome`, consumers (the
+// built-in predators and any configured species) a `PredatorTag`, and food is
+// an entity with just a `Position` and `Food`.
+// hecs keeps every component type in its own column per archetype, so the hot
+// passes (movement, metabolism) walk dense arrays and never touch the genome.
+
+// Where things are and where they're heading: on the plane unless the world
+// is the 3D box (see `Space`)
+#[derive(Clone, Copy, Debug)]
+pub struct Position<V = Vec2>(pub V);
+
+#[derive(Clone, Copy, Debug)]
+pub struct Velocity<V = Vec2>(pub V);
+
+// What the organism last steered towards (food for bacteria, prey for
+// predators); none while fleeing or wandering
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Target<V = Vec2>(pub Option<V>);
+
+#[derive(Clone, Copy, Debug)]
+pub struct Energy(pub f32);
+
+#[derive(Clone, Copy, Debug)]
+pub struct Age(pub f32);
+
+// Physical traits used by sensing, movement and collisions. For bacteria this is
+// expressed from the genome at birth.
+#[derive(Clone, Copy, Debug)]
+pub struct Body {
+    pub speed: f32,
+    pub size: f32,
+    pub sense_radius: f32,
+}
+
+// Share of its speed a fully armoured bacterium loses
+const ARMOR_SLOWDOWN: f32 = 0.3;
+
+impl Body {
+    pub fn from_dna(dna: &DNA) -> Self {
+        Self::expressed(dna, &Expression::default())
+    }
+
+    // As developed by one individual, off its genes by `expression`
+    pub fn expressed(dna: &DNA, expression: &Expression) -> Self {
+        Self {
+            speed: dna.gene(SPEED) * expression.speed * (1.0 - ARMOR_SLOWDOWN * dna.gene(ARMOR)),
+            size: dna.gene(SIZE) * expression.size,
+            sense_radius: dna.gene(SENSE_RADIUS) * expression.sense_radius,
+        }
+    }
+}
+
+// How far an individual's speed, size and sense radius came out from what its
+// genes say, as factors. Developmental noise, so it isn't inherited.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Expression {
+    pub speed: f32,
+    pub size: f32,
+    pub sense_radius: f32,
+}
+
+impl Expression {
+    // Each trait off by up to `noise` (a share) either way
+    pub fn random(noise: f32, rng: &mut impl ::rand::Rng) -> Self {
+        let mut factor = || (1.0 + noise * rng.gen_range(-1.0f32..1.0)).max(0.1);
+        Self { speed: factor(), size: factor(), sense_radius: factor() }
+    }
+}
+
+impl Default for Expression {
+    fn default() -> Self {
+        Self { speed: 1.0, size: 1.0, sense_radius: 1.0 }
+    }
+}
+
+// Upkeep per tick for each pH unit of tolerance, and for full vigilance,
+// armour and toxin
+const PH_TOLERANCE_COST: f32 = 0.02;
+const VIGILANCE_COST: f32 = 0.05;
+const ARMOR_COST: f32 = 0.1;
+const TOXIN_COST: f32 = 0.05;
+
+// Energy burned per tick at 1x speed, fixed at birth
+#[derive(Clone, Copy, Debug)]
+pub struct Metabolism(pub f32);
+
+impl Metabolism {
+    pub fn for_bacterium(body: &Body, dna: &DNA) -> Self {
+        Self((body.speed * body.speed * body.size * 0.005) + 0.1 + dna.gene(PH_TOLERANCE) * PH_TOLERANCE_COST + dna.gene(VIGILANCE) * VIGILANCE_COST + dna.gene(ARMOR) * ARMOR_COST + dna.gene(TOXIN) * TOXIN_COST)
+    }
+
+    // Predators consume more energy
+    pub fn for_predator() -> Self {
+        Self(0.2)
+    }
+}
+
+// Share of energy an aerobic metabolism saves where oxygen is plentiful
+const AEROBIC_SAVING: f32 = 0.3;
+// Extra cost for an aerobe with no oxygen at all
+const HYPOXIA_PENALTY: f32 = 1.0;
+// Extra cost for an anaerobe in full oxygen
+const OXYGEN_TOXICITY: f32 = 0.5;
+
+// How aerobic a bacterium's metabolism is (0..1), from its genome. Aerobes burn
+// less energy but suffer without oxygen; anaerobes are less efficient but don't
+// care, and are poisoned by too much of it.
+#[derive(Clone, Copy, Debug)]
+pub struct Respiration(pub f32);
+
+impl Respiration {
+    // Multiplier on the metabolic cost at a given oxygen level (0..1)
+    pub fn cost_factor(&self, oxygen: f32) -> f32 {
+        let a = self.0;
+        1.0 - AEROBIC_SAVING * a + HYPOXIA_PENALTY * a * (1.0 - oxygen) + OXYGEN_TOXICITY * (1.0 - a) * oxygen
+    }
+}
+
+// pH range a bacterium lives in unharmed: `optimum` plus or minus `tolerance`
+#[derive(Clone, Copy, Debug)]
+pub struct PhTolerance {
+    pub optimum: f32,
+    pub tolerance: f32,
+}
+
+impl PhTolerance {
+    pub fn from_dna(dna: &DNA) -> Self {
+        Self { optimum: dna.gene(PH_OPTIMUM), tolerance: dna.gene(PH_TOLERANCE) }
+    }
+
+    // pH units beyond the tolerated range
+    pub fn excess(&self, ph: f32) -> f32 {
+        ((ph - self.optimum).abs() - self.tolerance).max(0.0)
+    }
+}
+
+// Ticks an escape burst lasts, and ticks after it before the next can start
+const BURST_TICKS: u32 = 8;
+const BURST_COOLDOWN: u32 = 60;
+// Speed-up and up-front energy cost of a burst at full strength
+const BURST_BOOST: f32 = 1.5;
+const BURST_COST: f32 = 20.0;
+
+// A bacterium's startle response: a short burst of speed, paid for in energy
+// up front, when a predator gets very close. `strength` is the `burst` gene.
+#[derive(Clone, Copy, Debug)]
+pub struct Startle {
+    pub strength: f32,
+    // Ticks of the current burst left
+    pub bursting: u32,
+    // Ticks until the next burst can start
+    pub cooldown: u32,
+}
+
+impl Startle {
+    pub fn from_dna(dna: &DNA) -> Self {
+        Self { strength: dna.gene(BURST), bursting: 0, cooldown: 0 }
+    }
+
+    pub fn ready(&self) -> bool {
+        self.strength > 0.0 && self.cooldown == 0
+    }
+
+    pub fn cost(&self) -> f32 {
+        BURST_COST * self.strength
+    }
+
+    pub fn start(&mut self) {
+        self.bursting = BURST_TICKS;
+        self.cooldown = BURST_TICKS + BURST_COOLDOWN;
+    }
+
+    // Multiplier on movement speed
+    pub fn speed_factor(&self) -> f32 {
+        if self.bursting > 0 { 1.0 + BURST_BOOST * self.strength } else { 1.0 }
+    }
+
+    pub fn tick(&mut self) {
+        self.bursting = self.bursting.saturating_sub(1);
+        self.cooldown = self.cooldown.saturating_sub(1);
+    }
+}
+
+// Share of its speed an organism keeps while digesting
+const DIGESTING_SPEED: f32 = 0.5;
+
+// Ticks left digesting the last meal, during which an organism moves slowly
+// and eats nothing more
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Digesting(pub u32);
+
+impl Digesting {
+    pub fn busy(&self) -> bool {
+        self.0 > 0
+    }
+
+    pub fn speed_factor(&self) -> f32 {
+        if self.busy() { DIGESTING_SPEED } else { 1.0 }
+    }
+}
+
+// Epigenetic stress mark, 0..1: left by hard times (hunger, polluted water)
+// and partly passed on to offspring, it makes a bacterium thriftier but slower
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Imprint(pub f32);
+
+// Share of its metabolism a fully marked bacterium saves, and of its speed it
+// loses
+const IMPRINT_SAVING: f32 = 0.3;
+const IMPRINT_SLOWDOWN: f32 = 0.2;
+
+impl Imprint {
+    pub fn cost_factor(&self) -> f32 {
+        1.0 - IMPRINT_SAVING * self.0
+    }
+
+    pub fn speed_factor(&self) -> f32 {
+        1.0 - IMPRINT_SLOWDOWN * self.0
+    }
+}
+
+// Plasmids a bacterium carries, usually none. Each of their genes costs
+// upkeep every tick.
+#[derive(Clone, Debug, Default)]
+pub struct Plasmids(pub Vec<Plasmid>);
+
+impl Plasmids {
+    pub fn gene_count(&self) -> usize {
+        self.0.iter().map(|p| p.genes.len()).sum()
+    }
+
+    // Share of the antibiotic's harm they block
+    pub fn resistance(&self) -> f32 {
+        self.0.iter().flat_map(|p| &p.genes)
+            .filter(|g| g.tag == RESISTANCE)
+            .fold(0.0f32, |best, g| best.max(g.value))
+            .clamp(0.0, 1.0)
+    }
+}
+
+// Ticks until an organism can divide again
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Refractory(pub u32);
+
+#[derive(Clone, Debug)]
+pub struct Genome(pub DNA);
+
+// Who an organism descends from, and how many offspring it has had. The parent handle stays safe to hold after the
+// parent dies: hecs bumps the generation when a slot is reused, so a stale
+// handle never resolves to an unrelated organism.
+#[derive(Clone, Copy, Debug)]
+pub struct Lineage {
+    pub parent: Option<hecs::Entity>,
+    pub generation: u32,
+    // First ancestor; none for a founder itself
+    pub founder: Option<hecs::Entity>,
+    pub offspring: u32,
+    // Tournament entrant it descends from, by index
+    pub origin: Option<usize>,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct PredatorTag;
+
+// Colours a consumer shuns, and how far apart (in RGB) two colours can be and
+// still look alike to it
+const AVERSION_MEMORY: usize = 3;
+const AVERSION_SIMILARITY: f32 = 0.2;
+
+// Colours of prey that poisoned a consumer, each shunned until a tick
+#[derive(Clone, Debug, Default)]
+pub struct Aversion(pub Vec<(Color, u64)>);
+
+impl Aversion {
+    pub fn active(&self, tick: u64) -> bool {
+        self.0.iter().any(|(_, until)| *until > tick)
+    }
+
+    pub fn avoids(&self, color: Color, tick: u64) -> bool {
+        self.0.iter().any(|(c, until)| {
+            *until > tick && vec3(c.r - color.r, c.g - color.g, c.b - color.b).length() < AVERSION_SIMILARITY
+        })
+    }
+
+    // Remember `color` until `until`, forgetting the oldest past the memory's
+    // size
+    pub fn learn(&mut self, color: Color, until: u64, tick: u64) {
+        self.0.retain(|(_, u)| *u > tick);
+        self.0.push((color, until));
+        if self.0.len() > AVERSION_MEMORY {
+            self.0.remove(0);
+        }
+    }
+}
+
+// How long a consumer has kept still, and whether that has hidden it: an
+// ambusher that settles is only spotted from close by
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Camouflage {
+    pub still: u32,
+    pub hidden: bool,
+}
+
+// A consumer of a configured species, by index into `SimulationParams::species`;
+// built-in predators have none
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Species(pub usize);
+
+#[derive(Clone, Copy, Debug)]
+pub struct Food;
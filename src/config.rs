@@ -0,0 +1,76 @@
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::time::{Duration, Instant, SystemTime};
+
+// How often `ConfigWatch` looks at the file
+const CHECK_INTERVAL: Duration = Duration::from_millis(500);
+
+// Settings file given with `--config`, in JSON:
+//
+//     {
+//         "params": { "mutation_rate": 0.05 },
+//         "keybindings": { "pause": "P", "pollution": "F6" }
+//     }
+//
+// Both sections are optional; `params` holds any subset of the simulation
+// parameters, and command-line flags override them.
+#[derive(Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub params: serde_json::Map<String, serde_json::Value>,
+    // Action name to key name, see `keymap::Action::name` and `keymap::key_name`
+    pub keybindings: HashMap<String, String>,
+}
+
+impl Config {
+    pub fn load(path: &str) -> Result<Self, String> {
+        let text = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
+        serde_json::from_str(&text).map_err(|e| e.to_string())
+    }
+}
+
+// Watches the config file for edits while the simulation runs. Only the
+// parameters whose values changed in the file are handed back, so settings
+// tuned from the panel or flags since launch are kept otherwise.
+pub struct ConfigWatch {
+    path: String,
+    modified: Option<SystemTime>,
+    params: serde_json::Map<String, serde_json::Value>,
+    last_check: Instant,
+}
+
+fn modified(path: &str) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+impl ConfigWatch {
+    pub fn new(path: &str) -> Self {
+        Self {
+            path: path.to_string(),
+            modified: modified(path),
+            params: Config::load(path).map(|c| c.params).unwrap_or_default(),
+            last_check: Instant::now(),
+        }
+    }
+
+    // The parameters changed since the file was last read, empty until it's
+    // saved again; an error (once per save) if it no longer loads
+    pub fn poll(&mut self) -> Result<serde_json::Map<String, serde_json::Value>, String> {
+        if self.last_check.elapsed() < CHECK_INTERVAL {
+            return Ok(serde_json::Map::new());
+        }
+        self.last_check = Instant::now();
+        let modified = modified(&self.path);
+        if modified == self.modified {
+            return Ok(serde_json::Map::new());
+        }
+        self.modified = modified;
+        let params = Config::load(&self.path)?.params;
+        let changes = params.iter()
+            .filter(|(name, value)| self.params.get(*name) != Some(value))
+            .map(|(name, value)| (name.clone(), value.clone()))
+            .collect();
+        self.params = params;
+        Ok(changes)
+    }
+}
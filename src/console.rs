@@ -0,0 +1,274 @@
+use hecs::Entity;
+use tracing::info;
+
+use crate::autosave::WorldSave;
+use crate::logging::UI;
+use crate::dna;
+use crate::components::{Food, Genome, PredatorTag};
+use crate::organism::{Bacterium, Predator};
+use crate::params::SimulationParams;
+use crate::world::World;
+
+// Lines of output and commands kept
+const OUTPUT_LIMIT: usize = 200;
+const HISTORY_LIMIT: usize = 100;
+
+// Each command and how it's used
+const COMMANDS: [(&str, &str); 8] = [
+    ("spawn", "spawn bacterium|predator|food [count] [gene=value ...]"),
+    ("kill", "kill bacteria|predators|food|all"),
+    ("set", "set <param> <value>"),
+    ("get", "get <param>"),
+    ("save", "save <file>"),
+    ("load", "load <file>"),
+    ("clear", "clear"),
+    ("help", "help"),
+];
+const KINDS: [&str; 3] = ["bacterium", "predator", "food"];
+const BACTERIUM_GENES: [&str; 19] = ["speed", "size", "sense_radius", "aerobic", "ph_optimum", "ph_tolerance", "burst", "vigilance", "aggression", "armor", "toxin", "investment", "maturation", "cooldown", "dispersal", "flee_energy", "crowding", "mutation_rate", "energy"];
+const PREDATOR_GENES: [&str; 4] = ["speed", "size", "sense_radius", "energy"];
+
+// What a command asks of the caller beyond what it did to the world
+pub enum Outcome {
+    Done(String),
+    // Replace the world with this one
+    Load(Box<WorldSave>),
+}
+
+// Drop-down command console: the line being typed, what earlier commands
+// printed, and the commands entered so far for the arrow keys to recall
+#[derive(Default)]
+pub struct Console {
+    pub open: bool,
+    pub input: String,
+    // Text and whether it's an error
+    output: Vec<(String, bool)>,
+    history: Vec<String>,
+    // Position in `history` while recalling with the arrow keys
+    recalled: Option<usize>,
+}
+
+impl Console {
+    pub fn output(&self) -> &[(String, bool)] {
+        &self.output
+    }
+
+    fn print(&mut self, text: &str, error: bool) {
+        self.output.extend(text.lines().map(|line| (line.to_string(), error)));
+        let excess = self.output.len().saturating_sub(OUTPUT_LIMIT);
+        self.output.drain(..excess);
+    }
+
+    // Run the typed line; a world to load is handed back
+    pub fn submit(&mut self, world: &mut World, params: &mut SimulationParams) -> Option<WorldSave> {
+        let line = std::mem::take(&mut self.input).trim().to_string();
+        self.recalled = None;
+        if line.is_empty() {
+            return None;
+        }
+        if self.history.last() != Some(&line) {
+            self.history.push(line.clone());
+            let excess = self.history.len().saturating_sub(HISTORY_LIMIT);
+            self.history.drain(..excess);
+        }
+        info!(target: UI, "Console: {}", line);
+        self.print(&format!("> {}", line), false);
+        if line == "clear" {
+            self.output.clear();
+            return None;
+        }
+        match execute(&line, world, params) {
+            Ok(Outcome::Done(text)) => self.print(&text, false),
+            Ok(Outcome::Load(save)) => {
+                self.print(&format!("Loading tick {}", save.tick), false);
+                return Some(*save);
+            }
+            Err(e) => self.print(&e, true),
+        }
+        None
+    }
+
+    // Step back (`older`) or forward through the history
+    pub fn recall(&mut self, older: bool) {
+        let last = self.history.len().checked_sub(1);
+        self.recalled = match (self.recalled, older) {
+            (None, true) => last,
+            (Some(i), true) => Some(i.saturating_sub(1)),
+            (Some(i), false) if Some(i) < last => Some(i + 1),
+            _ => None,
+        };
+        self.input = self.recalled.map_or_else(String::new, |i| self.history[i].clone());
+    }
+
+    // Complete the word being typed; with several candidates, extend it as
+    // far as they agree and list them
+    pub fn complete(&mut self, params: &SimulationParams) {
+        let words: Vec<&str> = self.input.split_whitespace().collect();
+        let typing = if self.input.ends_with(' ') || self.input.is_empty() { "" } else { words.last().copied().unwrap_or("") };
+        let before = if typing.is_empty() { &words[..] } else { &words[..words.len() - 1] };
+        let param_names = param_names(params);
+        let candidates: Vec<String> = match before {
+            [] => COMMANDS.iter().map(|(c, _)| format!("{} ", c)).collect(),
+            ["spawn"] => KINDS.iter().map(|k| format!("{} ", k)).collect(),
+            ["spawn", kind, ..] => match kind_of(kind) {
+                Some("bacterium") => BACTERIUM_GENES.iter().map(|g| format!("{}=", g)).collect(),
+                Some("predator") => PREDATOR_GENES.iter().map(|g| format!("{}=", g)).collect(),
+                _ => Vec::new(),
+            },
+            ["kill"] => ["bacteria ", "predators ", "food ", "all "].map(String::from).to_vec(),
+            ["set" | "get"] => param_names.iter().map(|p| format!("{} ", p)).collect(),
+            _ => Vec::new(),
+        };
+        let matches: Vec<&String> = candidates.iter().filter(|c| c.starts_with(typing)).collect();
+        let Some(first) = matches.first() else { return };
+        let common = matches.iter().fold(first.len(), |len, m| {
+            first.chars().zip(m.chars()).take(len).take_while(|(a, b)| a == b).count()
+        });
+        self.input.truncate(self.input.len() - typing.len());
+        self.input.push_str(&first[..common]);
+        if matches.len() > 1 {
+            let options: Vec<&str> = matches.iter().map(|m| m.trim_end()).collect();
+            self.print(&options.join("  "), false);
+        }
+    }
+}
+
+fn param_names(params: &SimulationParams) -> Vec<String> {
+    match serde_json::to_value(params) {
+        Ok(serde_json::Value::Object(map)) => map.keys().cloned().collect(),
+        _ => Vec::new(),
+    }
+}
+
+// Parameters are f32, so drop the digits widening them to JSON adds
+fn show(value: &serde_json::Value) -> String {
+    match value.as_f64() {
+        Some(v) if !value.is_u64() && !value.is_i64() => (v as f32).to_string(),
+        _ => value.to_string(),
+    }
+}
+
+// The kind named, singular or plural
+fn kind_of(word: &str) -> Option<&'static str> {
+    match word {
+        "bacterium" | "bacteria" => Some("bacterium"),
+        "predator" | "predators" => Some("predator"),
+        "food" => Some("food"),
+        _ => None,
+    }
+}
+
+// Run one command line against the world and parameters
+pub fn execute(line: &str, world: &mut World, params: &mut SimulationParams) -> Result<Outcome, String> {
+    let words: Vec<&str> = line.split_whitespace().collect();
+    let text = match words[..] {
+        ["spawn", kind, ref rest @ ..] => spawn(world, params, kind, rest)?,
+        ["kill", what] => kill(world, what)?,
+        ["set", name, ref value @ ..] if !value.is_empty() => {
+            let text = value.join(" ");
+            // Bare words are taken as strings, e.g. enum variants
+            let value = serde_json::from_str(&text).unwrap_or(serde_json::Value::String(text));
+            let mut change = serde_json::Map::new();
+            change.insert(name.to_string(), value.clone());
+            params.merge(change)?;
+            format!("{} = {}", name, show(&value))
+        }
+        ["get", name] => {
+            let all = serde_json::to_value(&*params).map_err(|e| e.to_string())?;
+            let value = all.get(name).ok_or_else(|| format!("unknown parameter '{}'", name))?;
+            format!("{} = {}", name, show(value))
+        }
+        ["save", path] => {
+            let text = serde_json::to_string(&WorldSave::of(world, params)).map_err(|e| e.to_string())?;
+            std::fs::write(path, text).map_err(|e| e.to_string())?;
+            format!("Saved tick {} to {}", world.tick, path)
+        }
+        ["load", path] => return WorldSave::load(std::path::Path::new(path)).map(|save| Outcome::Load(Box::new(save))),
+        ["help"] => COMMANDS.map(|(_, usage)| usage).join("\n"),
+        [] => String::new(),
+        [command, ..] => {
+            return Err(match COMMANDS.iter().find(|(c, _)| *c == command) {
+                Some((_, usage)) => format!("usage: {}", usage),
+                None => format!("unknown command '{}', try help", command),
+            });
+        }
+    };
+    Ok(Outcome::Done(text))
+}
+
+// `spawn bacterium 100 speed=3`: the count defaults to 1, and genes left
+// out are random (bacteria) or the usual (predators)
+fn spawn(world: &mut World, params: &SimulationParams, kind: &str, rest: &[&str]) -> Result<String, String> {
+    let kind = kind_of(kind).ok_or_else(|| format!("can't spawn '{}'", kind))?;
+    let (count, settings) = match rest {
+        [first, settings @ ..] if !first.contains('=') => (first.parse().map_err(|_| format!("bad count '{}'", first))?, settings),
+        _ => (1, rest),
+    };
+    let genes = match kind {
+        "bacterium" => &BACTERIUM_GENES[..],
+        "predator" => &PREDATOR_GENES[..],
+        _ => &[],
+    };
+    let mut values = Vec::new();
+    for setting in settings {
+        let (gene, value) = setting.split_once('=').ok_or_else(|| format!("expected gene=value, got '{}'", setting))?;
+        if !genes.contains(&gene) {
+            return Err(format!("{} has no '{}'", kind, gene));
+        }
+        let value: f32 = value.parse().map_err(|_| format!("bad {} '{}'", gene, value))?;
+        let positive = ["speed", "size", "energy"].contains(&gene);
+        if !value.is_finite() || value < 0.0 || (positive && value == 0.0) {
+            return Err(format!("{} can't be {}", gene, value));
+        }
+        values.push((gene, value));
+    }
+
+    let value = |gene: &str, default: f32| values.iter().rev().find(|(g, _)| *g == gene).map_or(default, |(_, v)| *v);
+    let positions = match kind {
+        "food" => {
+            world.spawn_food(count);
+            Vec::new()
+        }
+        _ => world.spawn_positions(count),
+    };
+    for pos in positions {
+        if kind == "bacterium" {
+            let mut bacterium = Bacterium::new(pos, value("energy", params.initial_energy), &mut *world.rng());
+            let dna = &mut bacterium.dna;
+            for gene in BACTERIUM_GENES.into_iter().filter(|&gene| gene != "energy") {
+                dna.set(gene, dna::clamp(gene, value(gene, dna.gene(gene))));
+            }
+            bacterium.spawn(&mut world.ecs);
+        } else {
+            let mut predator = Predator::new(pos, &mut *world.rng());
+            predator.speed = value("speed", predator.speed);
+            predator.size = value("size", predator.size);
+            predator.sense_radius = value("sense_radius", predator.sense_radius);
+            predator.energy = value("energy", predator.energy);
+            predator.spawn(&mut world.ecs);
+        }
+    }
+    Ok(format!("Spawned {} {}", count, if count == 1 { kind.to_string() } else { plural(kind) }))
+}
+
+fn plural(kind: &str) -> String {
+    match kind {
+        "bacterium" => "bacteria".to_string(),
+        "food" => "food".to_string(),
+        _ => format!("{}s", kind),
+    }
+}
+
+fn kill(world: &mut World, what: &str) -> Result<String, String> {
+    let doomed: Vec<Entity> = match what {
+        "bacteria" | "bacterium" => world.ecs.query::<Entity>().with::<&Genome>().iter().collect(),
+        "predators" | "predator" => world.ecs.query::<Entity>().with::<&PredatorTag>().iter().collect(),
+        "food" => world.ecs.query::<Entity>().with::<&Food>().iter().collect(),
+        "all" => world.ecs.query::<Entity>().iter().collect(),
+        _ => return Err(format!("can't kill '{}'", what)),
+    };
+    for &id in &doomed {
+        let _ = world.ecs.despawn(id);
+    }
+    Ok(format!("Removed {}", doomed.len()))
+}
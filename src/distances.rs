@@ -0,0 +1,131 @@
+use macroquad::prelude::*;
+use std::marker::PhantomData;
+use std::ops::Range;
+
+use crate::space::Space;
+
+// Points taken at a time: with their coordinates in plain arrays of this
+// size the compiler works out the distances with SIMD instructions (one
+// AVX or two SSE registers' worth)
+const LANES: usize = 8;
+
+// Positions laid out for scanning many at a time: all x coordinates, then all
+// y (then all z, in 3D), padded to whole chunks with points too far away to
+// ever count
+pub struct Points<V = Vec2> {
+    axes: [Vec<f32>; 3],
+    space: PhantomData<V>,
+}
+
+impl<V> Default for Points<V> {
+    fn default() -> Self {
+        Self { axes: Default::default(), space: PhantomData }
+    }
+}
+
+impl<V: Space> Points<V> {
+    pub fn new(points: &[V]) -> Self {
+        let mut laid_out = Self::default();
+        laid_out.set(points);
+        laid_out
+    }
+
+    // Lay out `points` in place of those held, reusing the storage
+    pub fn set(&mut self, points: &[V]) {
+        let padded = points.len().div_ceil(LANES) * LANES;
+        for (axis, coords) in self.axes.iter_mut().enumerate().take(V::AXES) {
+            coords.clear();
+            coords.extend(points.iter().map(|p| p[axis]));
+            coords.resize(padded, f32::INFINITY);
+        }
+    }
+
+    fn get(&self, i: usize) -> V {
+        let mut point = V::ZERO;
+        for axis in 0..V::AXES {
+            point[axis] = self.axes[axis][i];
+        }
+        point
+    }
+
+    // Distances from `from` to each chunk of points, each computed as
+    // `Vec2::distance` (or `Vec3::distance`) does so the results match it to
+    // the bit. Padding comes out infinitely far.
+    fn chunks(&self, from: V) -> impl Iterator<Item = (usize, [f32; LANES])> + '_ {
+        let [xs, ys, zs] = self.axes.each_ref().map(|coords| coords.as_chunks::<LANES>().0);
+        let from = [from[0], from[1], from.depth()];
+        xs.iter().zip(ys).enumerate().map(move |(c, (xs, ys))| {
+            let zs = (V::AXES > 2).then(|| &zs[c]);
+            (c * LANES, distances(from, xs, ys, zs))
+        })
+    }
+
+    // The points closer to `from` than `limit`, handed to `f` in order with
+    // their index and distance. Chunks without any are passed over after a
+    // single vector compare.
+    pub fn each_within(&self, from: V, limit: f32, mut f: impl FnMut(usize, f32)) {
+        for (start, dist) in self.chunks(from) {
+            if !any_below(&dist, limit) {
+                continue;
+            }
+            for (i, d) in dist.into_iter().enumerate() {
+                if d < limit {
+                    f(start + i, d);
+                }
+            }
+        }
+    }
+
+    // Nearest point within `radius`, not counting one right at `from` (such
+    // as a cannibal's own position)
+    pub fn nearest_within(&self, from: V, radius: f32) -> Option<V> {
+        let mut nearest_dist = f32::MAX;
+        let mut nearest = None;
+        for (start, dist) in self.chunks(from) {
+            if !any_below(&dist, radius.min(nearest_dist)) {
+                continue;
+            }
+            for (i, d) in dist.into_iter().enumerate() {
+                if d > 0.0 && d < radius && d < nearest_dist {
+                    nearest_dist = d;
+                    nearest = Some(start + i);
+                }
+            }
+        }
+        nearest.map(|i| self.get(i))
+    }
+
+    // How many points lie at a distance in `range` from `from`
+    pub fn count_within(&self, from: V, range: Range<f32>) -> usize {
+        // A count per lane, added up at the end
+        let mut counts = [0u32; LANES];
+        for (_, dist) in self.chunks(from) {
+            for i in 0..LANES {
+                counts[i] += ((range.start <= dist[i]) & (dist[i] < range.end)) as u32;
+            }
+        }
+        counts.iter().sum::<u32>() as usize
+    }
+}
+
+#[inline(always)]
+fn distances(from: [f32; 3], xs: &[f32; LANES], ys: &[f32; LANES], zs: Option<&[f32; LANES]>) -> [f32; LANES] {
+    let mut dist = [0.0f32; LANES];
+    for i in 0..LANES {
+        let (dx, dy) = (from[0] - xs[i], from[1] - ys[i]);
+        dist[i] = dx * dx + dy * dy;
+    }
+    if let Some(zs) = zs {
+        for i in 0..LANES {
+            let dz = from[2] - zs[i];
+            dist[i] += dz * dz;
+        }
+    }
+    dist.map(f32::sqrt)
+}
+
+// Whether any distance of a chunk is under `limit`, without branching on
+// each
+fn any_below(dist: &[f32; LANES], limit: f32) -> bool {
+    dist.iter().fold(false, |any, &d| any | (d < limit))
+}
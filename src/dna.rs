@@ -0,0 +1,384 @@
+use macroquad::prelude::*;
+use ::rand::Rng;
+use std::borrow::Cow;
+use std::ops::Range;
+
+use serde::{Deserialize, Deserializer, Serialize};
+
+use crate::params::SimulationParams;
+
+// A gene: a tag naming what it does, and its value. A genome is a list of
+// these that the mechanics read by tag (see `DNA::gene`), so duplication
+// and deletion change what a bacterium carries. Plasmids carry genes of the
+// same form.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Gene {
+    #[serde(deserialize_with = "known_tag")]
+    pub tag: Cow<'static, str>,
+    pub value: f32,
+}
+
+impl Gene {
+    pub fn new(tag: &'static str, value: f32) -> Self {
+        Self { tag: Cow::Borrowed(tag), value }
+    }
+}
+
+// Most genes a genome holds; duplication stops there
+pub const MAX_GENES: usize = 64;
+
+// Tags of the core loci, which every founder carries once
+pub const SPEED: &str = "speed";
+pub const SIZE: &str = "size";
+pub const SENSE_RADIUS: &str = "sense_radius";
+pub const COLOR_R: &str = "color_r";
+pub const COLOR_G: &str = "color_g";
+pub const COLOR_B: &str = "color_b";
+// 1 is strictly aerobic, 0 strictly anaerobic
+pub const AEROBIC: &str = "aerobic";
+// Preferred pH and how far from it the bacterium copes without harm
+pub const PH_OPTIMUM: &str = "ph_optimum";
+pub const PH_TOLERANCE: &str = "ph_tolerance";
+// Strength of the escape burst when a predator gets very close, 0 for none
+pub const BURST: &str = "burst";
+// How well the bacterium spots a predator lying in ambush, 0..1
+pub const VIGILANCE: &str = "vigilance";
+// How readily it picks fights with unrelated bacteria, 0..1
+pub const AGGRESSION: &str = "aggression";
+// Spikes and plating that fend off attacks, 0..1, at the cost of speed and
+// upkeep
+pub const ARMOR: &str = "armor";
+// Poison that hurts whatever eats the bacterium, 0..1
+pub const TOXIN: &str = "toxin";
+// Share of its energy a parent hands its offspring, when that evolves (see
+// `evolve_offspring_share`)
+pub const INVESTMENT: &str = "investment";
+// Ticks before first dividing and between divisions, when those evolve (see
+// `evolve_reproduction_timing`)
+pub const MATURATION: &str = "maturation";
+pub const COOLDOWN: &str = "cooldown";
+// How far from the parent offspring land, 0..1 of the longest throw
+pub const DISPERSAL: &str = "dispersal";
+// Behaviour switches: energy below which hunger wins over fear and the
+// bacterium forages rather than flees, and how many neighbours within its
+// sense radius it puts up with before leaving the crowd instead
+pub const FLEE_ENERGY: &str = "flee_energy";
+pub const CROWDING: &str = "crowding";
+// Chance each of its offspring's genes mutates, when that evolves (see
+// `evolve_mutation_rate`)
+pub const MUTATION_RATE: &str = "mutation_rate";
+
+// How a locus mutates: by up to the mutation strength as a share of its
+// value, for genes that matter by proportion; by up to the strength times
+// a step on the gene's own scale; or, for colour, by a drift of up to the
+// given amount every generation, whatever the mutation rate
+#[derive(Clone, Copy, Debug)]
+enum Step {
+    Relative,
+    Additive(f32),
+    Drift(f32),
+}
+
+// A core locus: the range founders draw it from, the bounds mutation keeps
+// it in, how it mutates, and the value read once a genome has lost every
+// copy of it
+struct Locus {
+    tag: &'static str,
+    founders: Range<f32>,
+    bounds: (f32, f32),
+    step: Step,
+    knockout: f32,
+}
+
+// The core loci, in the order founders draw them. Investment is kept off
+// the ends so neither parent nor offspring is left with nothing, and the
+// mutation rate so no lineage copies itself perfectly or scrambles every
+// gene.
+static LOCI: [Locus; 21] = [
+    Locus { tag: SPEED, founders: 1.0..3.0, bounds: (0.1, 100.0), step: Step::Relative, knockout: 0.1 },
+    Locus { tag: SIZE, founders: 3.0..8.0, bounds: (0.1, 100.0), step: Step::Relative, knockout: 0.1 },
+    Locus { tag: SENSE_RADIUS, founders: 20.0..60.0, bounds: (0.1, 100.0), step: Step::Relative, knockout: 0.1 },
+    Locus { tag: COLOR_R, founders: 0.2..1.0, bounds: (0.2, 1.0), step: Step::Drift(0.05), knockout: 0.2 },
+    Locus { tag: COLOR_G, founders: 0.2..1.0, bounds: (0.2, 1.0), step: Step::Drift(0.05), knockout: 0.2 },
+    Locus { tag: COLOR_B, founders: 0.2..1.0, bounds: (0.2, 1.0), step: Step::Drift(0.05), knockout: 0.2 },
+    Locus { tag: AEROBIC, founders: 0.0..1.0, bounds: (0.0, 1.0), step: Step::Additive(1.0), knockout: 0.0 },
+    Locus { tag: PH_OPTIMUM, founders: 6.0..8.0, bounds: (0.0, 14.0), step: Step::Additive(7.0), knockout: 7.0 },
+    Locus { tag: PH_TOLERANCE, founders: 0.5..2.0, bounds: (0.1, 7.0), step: Step::Relative, knockout: 0.1 },
+    Locus { tag: BURST, founders: 0.0..1.0, bounds: (0.0, 1.0), step: Step::Additive(1.0), knockout: 0.0 },
+    Locus { tag: VIGILANCE, founders: 0.0..1.0, bounds: (0.0, 1.0), step: Step::Additive(1.0), knockout: 0.0 },
+    Locus { tag: AGGRESSION, founders: 0.0..1.0, bounds: (0.0, 1.0), step: Step::Additive(1.0), knockout: 0.0 },
+    Locus { tag: ARMOR, founders: 0.0..0.5, bounds: (0.0, 1.0), step: Step::Additive(1.0), knockout: 0.0 },
+    Locus { tag: TOXIN, founders: 0.0..0.3, bounds: (0.0, 1.0), step: Step::Additive(1.0), knockout: 0.0 },
+    Locus { tag: INVESTMENT, founders: 0.3..0.7, bounds: (0.05, 0.95), step: Step::Additive(1.0), knockout: 0.5 },
+    Locus { tag: MATURATION, founders: 0.0..100.0, bounds: (0.0, 1000.0), step: Step::Additive(100.0), knockout: 0.0 },
+    Locus { tag: COOLDOWN, founders: 0.0..100.0, bounds: (0.0, 1000.0), step: Step::Additive(100.0), knockout: 0.0 },
+    Locus { tag: DISPERSAL, founders: 0.0..0.5, bounds: (0.0, 1.0), step: Step::Additive(1.0), knockout: 0.0 },
+    Locus { tag: FLEE_ENERGY, founders: 0.0..40.0, bounds: (0.0, 500.0), step: Step::Additive(50.0), knockout: 0.0 },
+    Locus { tag: CROWDING, founders: 5.0..20.0, bounds: (0.0, 100.0), step: Step::Additive(10.0), knockout: 50.0 },
+    Locus { tag: MUTATION_RATE, founders: 0.05..0.15, bounds: (MIN_MUTATION_RATE, MAX_MUTATION_RATE), step: Step::Relative, knockout: 0.1 },
+];
+
+fn locus(tag: &str) -> Option<&'static Locus> {
+    LOCI.iter().find(|locus| locus.tag == tag)
+}
+
+// `value` kept within the bounds of `tag`'s locus; unchanged for genes
+// outside the core loci
+pub fn clamp(tag: &str, value: f32) -> f32 {
+    locus(tag).map_or(value, |locus| value.clamp(locus.bounds.0, locus.bounds.1))
+}
+
+// Tags the simulation knows are shared by every gene carrying them, so
+// copying a gene at birth doesn't copy its tag
+fn known_tag<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Cow<'static, str>, D::Error> {
+    let tag = String::deserialize(deserializer)?;
+    Ok(match LOCI.iter().map(|locus| locus.tag).chain([RESISTANCE]).find(|known| *known == tag) {
+        Some(known) => Cow::Borrowed(known),
+        None => Cow::Owned(tag),
+    })
+}
+
+// Tag of the plasmid gene that shields a bacterium from the antibiotic, its
+// value the share of the harm it blocks
+pub const RESISTANCE: &str = "resistance";
+
+// A small packet of genes a bacterium carries outside its genome, copied to
+// offspring and to neighbours by conjugation (see `Plasmids`)
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Plasmid {
+    pub genes: Vec<Gene>,
+}
+
+impl Plasmid {
+    // The canonical one, carrying full antibiotic resistance
+    pub fn resistance() -> Self {
+        Self { genes: vec![Gene { tag: Cow::Borrowed(RESISTANCE), value: 1.0 }] }
+    }
+}
+
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(from = "SavedDna")]
+pub struct DNA {
+    pub genes: Vec<Gene>,
+    // Allele at each neutral marker locus, empty unless `neutral_markers` is
+    // set. Nothing reads them, so their frequencies only drift.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub markers: Vec<u8>,
+}
+
+// A genome as saved: its gene list, or, from before genomes were lists, a
+// field per core locus and the extra genes after them
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum SavedDna {
+    Genes {
+        genes: Vec<Gene>,
+        #[serde(default)]
+        markers: Vec<u8>,
+    },
+    Loci(Box<SavedLoci>),
+}
+
+#[derive(Deserialize)]
+struct SavedLoci {
+    speed: f32,
+    size: f32,
+    sense_radius: f32,
+    // Three channels in the hall of fame, four in autosaves
+    color: Vec<f32>,
+    aerobic: f32,
+    ph_optimum: f32,
+    ph_tolerance: f32,
+    #[serde(default)]
+    burst: f32,
+    #[serde(default)]
+    vigilance: f32,
+    #[serde(default)]
+    aggression: f32,
+    #[serde(default)]
+    armor: f32,
+    #[serde(default)]
+    toxin: f32,
+    #[serde(default = "default_investment")]
+    investment: f32,
+    #[serde(default)]
+    maturation: f32,
+    #[serde(default)]
+    cooldown: f32,
+    #[serde(default)]
+    dispersal: f32,
+    #[serde(default)]
+    flee_energy: f32,
+    #[serde(default = "default_crowding")]
+    crowding: f32,
+    #[serde(default = "default_mutation_rate")]
+    mutation_rate: f32,
+    #[serde(default)]
+    extra_genes: Vec<Gene>,
+    #[serde(default)]
+    markers: Vec<u8>,
+}
+
+impl From<SavedDna> for DNA {
+    fn from(saved: SavedDna) -> Self {
+        let loci = match saved {
+            SavedDna::Genes { genes, markers } => return Self { genes, markers },
+            SavedDna::Loci(loci) => *loci,
+        };
+        let channel = |i: usize| loci.color.get(i).copied().unwrap_or(1.0);
+        let core = [
+            (SPEED, loci.speed),
+            (SIZE, loci.size),
+            (SENSE_RADIUS, loci.sense_radius),
+            (COLOR_R, channel(0)),
+            (COLOR_G, channel(1)),
+            (COLOR_B, channel(2)),
+            (AEROBIC, loci.aerobic),
+            (PH_OPTIMUM, loci.ph_optimum),
+            (PH_TOLERANCE, loci.ph_tolerance),
+            (BURST, loci.burst),
+            (VIGILANCE, loci.vigilance),
+            (AGGRESSION, loci.aggression),
+            (ARMOR, loci.armor),
+            (TOXIN, loci.toxin),
+            (INVESTMENT, loci.investment),
+            (MATURATION, loci.maturation),
+            (COOLDOWN, loci.cooldown),
+            (DISPERSAL, loci.dispersal),
+            (FLEE_ENERGY, loci.flee_energy),
+            (CROWDING, loci.crowding),
+            (MUTATION_RATE, loci.mutation_rate),
+        ];
+        let genes = core.into_iter().map(|(tag, value)| Gene::new(tag, value)).chain(loci.extra_genes).collect();
+        Self { genes, markers: loci.markers }
+    }
+}
+
+// Most alleles a marker locus can have
+pub const MAX_MARKER_ALLELES: usize = 8;
+
+// A founder's marker alleles, one at random per locus
+pub fn random_markers(params: &SimulationParams, rng: &mut impl Rng) -> Vec<u8> {
+    let alleles = params.marker_alleles.clamp(1, MAX_MARKER_ALLELES);
+    (0..params.neutral_markers).map(|_| rng.gen_range(0..alleles) as u8).collect()
+}
+
+// Investment for genomes saved before the gene existed: an even split
+fn default_investment() -> f32 {
+    0.5
+}
+
+// Crowding for genomes saved before the gene existed, tolerant enough to rarely
+// matter
+fn default_crowding() -> f32 {
+    50.0
+}
+
+// Mutation rate for genomes saved before the gene existed: the default
+// setting
+fn default_mutation_rate() -> f32 {
+    0.1
+}
+
+// Bounds of the mutation rate gene
+pub const MIN_MUTATION_RATE: f32 = 0.001;
+pub const MAX_MUTATION_RATE: f32 = 0.5;
+
+// A mutation's change to a gene, up to `mutation_strength` either way; none
+// at all at strength 0
+fn nudge(params: &SimulationParams, rng: &mut impl Rng) -> f32 {
+    let strength = params.mutation_strength;
+    if strength > 0.0 { rng.gen_range(-strength..strength) } else { 0.0 }
+}
+
+impl DNA {
+    // One copy of each core locus
+    pub fn random(rng: &mut impl Rng) -> Self {
+        Self {
+            genes: LOCI.iter().map(|locus| Gene::new(locus.tag, rng.gen_range(locus.founders.clone()))).collect(),
+            markers: Vec::new(),
+        }
+    }
+
+    // The value the mechanics read for `tag`: that of its first copy. Later
+    // copies are paralogs, free to drift, one of which takes over if the
+    // first is deleted. With no copy left, a core locus reads as knocked out.
+    pub fn gene(&self, tag: &str) -> f32 {
+        match self.genes.iter().find(|gene| gene.tag == tag) {
+            Some(gene) => gene.value,
+            None => locus(tag).map_or(0.0, |locus| locus.knockout),
+        }
+    }
+
+    // Set the first copy of `tag`, adding one if there's none
+    pub fn set(&mut self, tag: &'static str, value: f32) {
+        match self.genes.iter_mut().find(|gene| gene.tag == tag) {
+            Some(gene) => gene.value = value,
+            None => self.genes.push(Gene::new(tag, value)),
+        }
+    }
+
+    pub fn color(&self) -> Color {
+        Color::new(self.gene(COLOR_R), self.gene(COLOR_G), self.gene(COLOR_B), 0.9)
+    }
+
+    // Marker alleles after mutation, each switching to another allele
+    fn mutate_markers(&self, params: &SimulationParams, rng: &mut impl Rng) -> Vec<u8> {
+        let alleles = params.marker_alleles.clamp(1, MAX_MARKER_ALLELES) as u8;
+        if params.marker_mutation_rate <= 0.0 || alleles < 2 {
+            return self.markers.clone();
+        }
+        let rate = params.marker_mutation_rate.min(1.0) as f64;
+        self.markers.iter()
+            .map(|&allele| if rng.gen_bool(rate) { (allele % alleles + rng.gen_range(1..alleles)) % alleles } else { allele })
+            .collect()
+    }
+
+    // Moved `share` of the way towards the speed, size and sense radius an
+    // individual actually has, for Lamarckian inheritance
+    pub fn acquire(&self, speed: f32, size: f32, sense_radius: f32, share: f32) -> Self {
+        let mut dna = self.clone();
+        for (tag, actual) in [(SPEED, speed), (SIZE, size), (SENSE_RADIUS, sense_radius)] {
+            let gene = self.gene(tag);
+            dna.set(tag, gene + (actual - gene) * share);
+        }
+        dna
+    }
+
+    // Each gene mutates with the bacterium's own mutation rate when that
+    // evolves, otherwise the setting's, by its locus's step and within its
+    // bounds. Genes of unknown tags take steps scaled to their value,
+    // whatever its units, and able to leave 0. Colour drifts after the
+    // rest. Then a gene may be duplicated or deleted.
+    pub fn mutate(&self, params: &SimulationParams, rng: &mut impl Rng) -> Self {
+        let rate = (if params.evolve_mutation_rate { self.gene(MUTATION_RATE) } else { params.mutation_rate }) as f64;
+        let mut genes = self.genes.clone();
+        for gene in &mut genes {
+            let locus = locus(&gene.tag);
+            if matches!(locus, Some(Locus { step: Step::Drift(_), .. })) || !rng.gen_bool(rate) {
+                continue;
+            }
+            let change = nudge(params, rng);
+            gene.value = match locus {
+                Some(Locus { step: Step::Relative, bounds: (min, max), .. }) => (gene.value * (1.0 + change)).clamp(*min, *max),
+                Some(Locus { step: Step::Additive(step), bounds: (min, max), .. }) => (gene.value + change * step).clamp(*min, *max),
+                _ => gene.value + change * gene.value.abs().max(1.0),
+            };
+        }
+        for gene in &mut genes {
+            if let Some(Locus { step: Step::Drift(drift), bounds: (min, max), .. }) = locus(&gene.tag) {
+                gene.value = (gene.value + rng.gen_range(-drift..*drift)).clamp(*min, *max);
+            }
+        }
+
+        if genes.len() < MAX_GENES && params.gene_duplication_rate > 0.0 && rng.gen_bool(params.gene_duplication_rate.min(1.0) as f64) {
+            let copy = genes[rng.gen_range(0..genes.len())].clone();
+            genes.push(copy);
+        }
+        if params.gene_deletion_rate > 0.0 && !genes.is_empty() && rng.gen_bool(params.gene_deletion_rate.min(1.0) as f64) {
+            genes.remove(rng.gen_range(0..genes.len()));
+        }
+        Self { genes, markers: self.mutate_markers(params, rng) }
+    }
+}
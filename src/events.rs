@@ -0,0 +1,54 @@
+use hecs::Entity;
+use macroquad::prelude::*;
+
+use crate::dna::Plasmid;
+use crate::organism::{Bacterium, Predator};
+
+#[derive(Clone, Debug)]
+pub enum Organism<V = Vec2> {
+    Bacterium(Bacterium<V>),
+    Predator(Predator<V>),
+}
+
+// What got eaten in an `Event::Ate`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Meal {
+    Food(Entity),
+    Prey(Entity),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DeathCause {
+    Eaten,
+    // Lost a fight to another bacterium
+    Killed,
+    Starved,
+}
+
+// Everything that can change the world during a tick. Systems emit these and the
+// world applies them, so a recorded tick can be replayed with `World::apply_tick`.
+#[derive(Clone, Debug)]
+pub enum Event<V = Vec2> {
+    FoodSpawned { pos: V },
+    Steered { id: Entity, vel: V, target: Option<V> },
+    Startled { id: Entity, cost: f32 },
+    Moved { id: Entity, pos: V, vel: V, energy_cost: f32, waste: f32 },
+    Migrated { id: Entity, pos: V },
+    Camouflaged { id: Entity, hidden: bool },
+    Ate { eater: Entity, meal: Meal, energy: f32 },
+    Digesting { id: Entity, ticks: u32 },
+    // A young bacterium grew to `size`, paying `cost` energy for it
+    Grew { id: Entity, size: f32, cost: f32 },
+    // A bacterium's epigenetic mark deepened to `imprint`
+    Imprinted { id: Entity, imprint: f32 },
+    // A bacterium picked up a copy of a neighbour's plasmid
+    Conjugated { id: Entity, plasmid: Plasmid },
+    // The parent can't divide again for `cooldown` ticks
+    Reproduced { parent: Entity, parent_energy: f32, cooldown: u32, offspring: Organism<V> },
+    Damaged { id: Entity, amount: f32 },
+    // A consumer learns to shun prey coloured like `color` until tick `until`
+    Learned { id: Entity, color: Color, until: u64 },
+    Died { id: Entity, cause: DeathCause },
+    Spawned { organism: Organism<V> },
+    WasteDecayed { factor: f32 },
+}
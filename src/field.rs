@@ -0,0 +1,57 @@
+use macroquad::prelude::*;
+use serde::{Deserialize, Serialize};
+
+// A scalar quantity stored per square grid cell over the world. Points outside
+// the grid read as zero and writes there are dropped.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Field {
+    pub cols: usize,
+    pub rows: usize,
+    pub cell: f32,
+    values: Vec<f32>,
+}
+
+impl Field {
+    pub fn new(width: f32, height: f32, cell: f32) -> Self {
+        let cols = (width / cell).ceil().max(1.0) as usize;
+        let rows = (height / cell).ceil().max(1.0) as usize;
+        Self { cols, rows, cell, values: vec![0.0; cols * rows] }
+    }
+
+    fn index(&self, p: Vec2) -> Option<usize> {
+        if p.x < 0.0 || p.y < 0.0 {
+            return None;
+        }
+        let (cx, cy) = ((p.x / self.cell) as usize, (p.y / self.cell) as usize);
+        (cx < self.cols && cy < self.rows).then(|| cy * self.cols + cx)
+    }
+
+    pub fn at(&self, p: Vec2) -> f32 {
+        self.index(p).map_or(0.0, |i| self.values[i])
+    }
+
+    pub fn get(&self, col: usize, row: usize) -> f32 {
+        self.values[row * self.cols + col]
+    }
+
+    pub fn add(&mut self, p: Vec2, amount: f32) {
+        if let Some(i) = self.index(p) {
+            self.values[i] += amount;
+        }
+    }
+
+    pub fn scale(&mut self, factor: f32) {
+        for v in &mut self.values {
+            *v *= factor;
+        }
+    }
+
+    // Range of grid cells overlapping `rect` (world units), for drawing
+    pub fn cells_in(&self, rect: Rect) -> (std::ops::Range<usize>, std::ops::Range<usize>) {
+        let first_col = (rect.x / self.cell).max(0.0) as usize;
+        let first_row = (rect.y / self.cell).max(0.0) as usize;
+        let last_col = ((rect.right() / self.cell).ceil().max(0.0) as usize).min(self.cols);
+        let last_row = ((rect.bottom() / self.cell).ceil().max(0.0) as usize).min(self.rows);
+        (first_col..last_col, first_row..last_row)
+    }
+}
@@ -0,0 +1,128 @@
+use hecs::Entity;
+use std::collections::{HashMap, VecDeque};
+
+use crate::components::{Genome, Lineage};
+use crate::stats::CORRELATED_GENES;
+use crate::world::World;
+
+// Finished lifetimes kept, the oldest dropped first; ticks between estimates
+const MAX_LIFETIMES: usize = 2000;
+const ESTIMATE_EVERY: u64 = 100;
+
+// Bins along each gene of the landscape
+pub const LANDSCAPE_BINS: usize = 12;
+
+// Every gene of `CORRELATED_GENES`, in its order
+type Genes = [f32; CORRELATED_GENES.len()];
+
+// A bacterium's genes and its offspring so far, or over its whole life once
+// it has died
+#[derive(Clone, Copy)]
+struct Lifetime {
+    genes: Genes,
+    offspring: u32,
+}
+
+// Realized fitness over two genes, by index into `CORRELATED_GENES`
+pub struct Estimate {
+    pub genes: (usize, usize),
+    pub tick: u64,
+    pub x_range: (f32, f32),
+    pub y_range: (f32, f32),
+    // Mean offspring per lifetime by bin, rows from the lowest y up; none
+    // where no bacterium has lived out its life
+    pub fitness: [[Option<f32>; LANDSCAPE_BINS]; LANDSCAPE_BINS],
+    pub lifetimes: usize,
+}
+
+// Offspring per lifetime of the bacteria that died recently, binned by two
+// genes and re-estimated every `ESTIMATE_EVERY` ticks, to show which way
+// selection pushes the population
+pub struct FitnessLandscape {
+    living: HashMap<Entity, Lifetime>,
+    finished: VecDeque<Lifetime>,
+    last_tick: Option<u64>,
+    pub estimate: Option<Estimate>,
+}
+
+impl FitnessLandscape {
+    pub fn new() -> Self {
+        Self { living: HashMap::new(), finished: VecDeque::new(), last_tick: None, estimate: None }
+    }
+
+    // The world was replaced, so handles from the old one mean nothing
+    pub fn new_world(&mut self) {
+        *self = Self::new();
+    }
+
+    // Once a tick, count the bacteria gone since the last one as finished,
+    // with the offspring they had when last seen. Re-estimates when due or
+    // when other genes are asked for.
+    pub fn record(&mut self, world: &World, genes: (usize, usize)) {
+        if self.last_tick != Some(world.tick) {
+            self.last_tick = Some(world.tick);
+            let mut living = HashMap::with_capacity(self.living.len());
+            for (id, genome, lineage) in world.ecs.query::<(Entity, &Genome, &Lineage)>().iter() {
+                let genes = self.living.get(&id).map_or_else(|| CORRELATED_GENES.map(|(_, tag)| genome.0.gene(tag)), |l| l.genes);
+                living.insert(id, Lifetime { genes, offspring: lineage.offspring });
+            }
+            for (id, lifetime) in self.living.drain() {
+                if !living.contains_key(&id) {
+                    self.finished.push_back(lifetime);
+                }
+            }
+            while self.finished.len() > MAX_LIFETIMES {
+                self.finished.pop_front();
+            }
+            self.living = living;
+        }
+        let due = self.estimate.as_ref().is_none_or(|e| e.genes != genes || e.tick + ESTIMATE_EVERY <= world.tick);
+        if due {
+            self.estimate = Some(self.estimate(genes, world.tick));
+        }
+    }
+
+    // The living bacteria's values of the two genes
+    pub fn population(&self, (x, y): (usize, usize)) -> impl Iterator<Item = (f32, f32)> + '_ {
+        self.living.values().map(move |l| (l.genes[x], l.genes[y]))
+    }
+
+    fn estimate(&self, genes: (usize, usize), tick: u64) -> Estimate {
+        let everyone = || self.finished.iter().chain(self.living.values());
+        let range = |gene: usize| {
+            let (lo, hi) = everyone().fold((f32::MAX, f32::MIN), |(lo, hi), l| (lo.min(l.genes[gene]), hi.max(l.genes[gene])));
+            if lo > hi {
+                (0.0, 1.0)
+            } else if hi - lo < 1e-3 {
+                // A gene every bacterium shares still gets an axis around it
+                let pad = (lo.abs() * 0.1).max(0.01);
+                (lo - pad, hi + pad)
+            } else {
+                (lo, hi)
+            }
+        };
+        let (x_range, y_range) = (range(genes.0), range(genes.1));
+        let bin = |value: f32, (lo, hi): (f32, f32)| (((value - lo) / (hi - lo) * LANDSCAPE_BINS as f32) as usize).min(LANDSCAPE_BINS - 1);
+
+        let mut sums = [[(0.0f32, 0u32); LANDSCAPE_BINS]; LANDSCAPE_BINS];
+        for lifetime in &self.finished {
+            let (col, row) = (bin(lifetime.genes[genes.0], x_range), bin(lifetime.genes[genes.1], y_range));
+            sums[row][col].0 += lifetime.offspring as f32;
+            sums[row][col].1 += 1;
+        }
+        Estimate {
+            genes,
+            tick,
+            x_range,
+            y_range,
+            fitness: sums.map(|row| row.map(|(sum, n)| (n > 0).then(|| sum / n as f32))),
+            lifetimes: self.finished.len(),
+        }
+    }
+}
+
+impl Default for FitnessLandscape {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,185 @@
+use serde::{Deserialize, Serialize};
+
+use crate::params::SimulationParams;
+
+// Names a diet can use besides the configured species
+const FOOD: &str = "food";
+const BACTERIA: &str = "bacteria";
+const PREDATORS: &str = "predators";
+
+// Something a consumer eats: "food", "bacteria", "predators" or a species'
+// name, and the share of the meal's energy it keeps (of the prey's energy, or
+// of a food item's). Without an efficiency of its own, eating food keeps
+// `food_efficiency` and anything else `predation_efficiency`.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Diet {
+    pub eats: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub efficiency: Option<f32>,
+}
+
+impl Diet {
+    pub fn new(eats: &str) -> Self {
+        Self { eats: eats.to_string(), efficiency: None }
+    }
+}
+
+// A consumer species alongside the built-in predators, grazing and hunting by
+// its diet. Its body and starting count are read when a world is created;
+// `count` is per default-sized area, like `predator_count`.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SpeciesConfig {
+    pub name: String,
+    pub count: f32,
+    pub speed: f32,
+    pub size: f32,
+    pub sense_radius: f32,
+    pub energy: f32,
+    // Energy burned per tick at 1x speed
+    pub metabolism: f32,
+    pub reproduction_threshold: f32,
+    // Waits in ambush for prey rather than roaming (see `predator_ambush`)
+    pub ambush: bool,
+    // Ticks spent digesting after each kill (see `predator_handling_time`)
+    pub handling_time: f32,
+    pub diet: Vec<Diet>,
+}
+
+impl Default for SpeciesConfig {
+    fn default() -> Self {
+        Self {
+            name: String::new(),
+            count: 5.0,
+            speed: 2.5,
+            size: 12.0,
+            sense_radius: 100.0,
+            energy: 150.0,
+            metabolism: 0.2,
+            reproduction_threshold: 200.0,
+            ambush: false,
+            handling_time: 10.0,
+            diet: Vec::new(),
+        }
+    }
+}
+
+// What kind of thing an entity is, as far as diets go
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Prey {
+    Food,
+    Bacteria,
+    Predators,
+    // Index into `SimulationParams::species`
+    Species(usize),
+}
+
+impl Prey {
+    // Kinds there are with `species` configured species
+    pub fn count(species: usize) -> usize {
+        3 + species
+    }
+
+    // Position among `count` kinds, for tables by kind
+    pub fn index(self) -> usize {
+        match self {
+            Prey::Food => 0,
+            Prey::Bacteria => 1,
+            Prey::Predators => 2,
+            Prey::Species(i) => 3 + i,
+        }
+    }
+
+    fn named(name: &str, params: &SimulationParams) -> Option<Self> {
+        match name {
+            FOOD => Some(Prey::Food),
+            BACTERIA => Some(Prey::Bacteria),
+            PREDATORS => Some(Prey::Predators),
+            _ => params.species.iter().position(|s| s.name == name).map(Prey::Species),
+        }
+    }
+
+    // The kind of a consumer: a configured species or a built-in predator
+    pub fn consumer(species: Option<usize>) -> Self {
+        species.map_or(Prey::Predators, Prey::Species)
+    }
+}
+
+// Who eats what, resolved from the parameters once per system run
+pub struct FoodWeb {
+    // The built-in predators' diet, then each species'
+    diets: Vec<Vec<(Prey, f32)>>,
+    // Whether they lie in ambush, and how long they digest a meal, in the
+    // same order
+    ambush: Vec<bool>,
+    handling: Vec<f32>,
+}
+
+impl FoodWeb {
+    pub fn new(params: &SimulationParams) -> Self {
+        let resolve = |diet: &[Diet]| diet.iter()
+            .filter_map(|d| {
+                let prey = Prey::named(&d.eats, params)?;
+                let default = if prey == Prey::Food { params.food_efficiency } else { params.predation_efficiency };
+                Some((prey, d.efficiency.unwrap_or(default)))
+            })
+            .collect();
+        let mut diets = vec![resolve(&params.predator_diet)];
+        diets.extend(params.species.iter().map(|s| resolve(&s.diet)));
+        let mut ambush = vec![params.predator_ambush];
+        ambush.extend(params.species.iter().map(|s| s.ambush));
+        let mut handling = vec![params.predator_handling_time];
+        handling.extend(params.species.iter().map(|s| s.handling_time));
+        Self { diets, ambush, handling }
+    }
+
+    // What a consumer (a species by index, or a built-in predator) eats
+    pub fn diet(&self, species: Option<usize>) -> &[(Prey, f32)] {
+        self.diets.get(species.map_or(0, |i| i + 1)).map_or(&[], Vec::as_slice)
+    }
+
+    pub fn ambushes(&self, species: Option<usize>) -> bool {
+        self.ambush.get(species.map_or(0, |i| i + 1)).copied().unwrap_or(false)
+    }
+
+    // Whole ticks a consumer spends digesting each meal
+    pub fn handling_time(&self, species: Option<usize>) -> u32 {
+        self.handling.get(species.map_or(0, |i| i + 1)).map_or(0, |t| t.max(0.0).round() as u32)
+    }
+
+    // Every consumer kind with `prey` in its diet
+    pub fn hunters_of(&self, prey: Prey) -> Vec<Prey> {
+        self.diets.iter().enumerate()
+            .filter(|(_, diet)| diet.iter().any(|(p, _)| *p == prey))
+            .map(|(i, _)| Prey::consumer(i.checked_sub(1)))
+            .collect()
+    }
+}
+
+// Species names have to be unique and not taken by a built-in kind, and
+// every diet has to name something that exists
+pub fn check(params: &SimulationParams) -> Result<(), String> {
+    for (i, species) in params.species.iter().enumerate() {
+        if species.name.is_empty() || [FOOD, BACTERIA, PREDATORS].contains(&species.name.as_str()) {
+            return Err(format!("species can't be named '{}'", species.name));
+        }
+        if params.species[..i].iter().any(|s| s.name == species.name) {
+            return Err(format!("two species are named '{}'", species.name));
+        }
+    }
+    let diets = std::iter::once((PREDATORS, &params.predator_diet))
+        .chain(params.species.iter().map(|s| (s.name.as_str(), &s.diet)));
+    for (name, diet) in diets {
+        for d in diet {
+            if Prey::named(&d.eats, params).is_none() {
+                return Err(format!("{} eat unknown '{}'", name, d.eats));
+            }
+            if let Some(efficiency) = d.efficiency
+                && !(efficiency >= 0.0 && efficiency.is_finite())
+            {
+                return Err(format!("{} eating {} can't have efficiency {}", name, d.eats, efficiency));
+            }
+        }
+    }
+    Ok(())
+}
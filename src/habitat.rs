@@ -0,0 +1,254 @@
+use macroquad::prelude::*;
+use ::rand::Rng;
+use serde::{Deserialize, Serialize};
+
+// Width (world units) of the corridors joining neighbouring islands
+const CORRIDOR_WIDTH: f32 = 24.0;
+// Fraction of the world left as empty gap between islands
+const ISLAND_GAP: f32 = 0.12;
+const MIGRATION_INTERVAL: u64 = 600;
+const MIGRATION_RATE: f32 = 0.01;
+// Gap between the petri dish rim and the world edge
+const DISH_MARGIN: f32 = 5.0;
+// With a rich centre, this share of the dish's food lands in the inner disc of
+// `RICH_CENTER_RADIUS` times the dish radius
+const RICH_CENTER_SHARE: f32 = 0.5;
+const RICH_CENTER_RADIUS: f32 = 0.3;
+
+const REGION_COLORS: [Color; 4] = [
+    Color::new(0.3, 0.8, 1.0, 1.0),
+    Color::new(1.0, 0.8, 0.3, 1.0),
+    Color::new(0.8, 0.4, 1.0, 1.0),
+    Color::new(0.4, 1.0, 0.5, 1.0),
+];
+
+// How the world is split into habitats, read when a world is created
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum WorldLayout {
+    // One region covering the whole world
+    Open,
+    // Four islands joined by narrow corridors, with periodic migration
+    Islands,
+    // A single disc inscribed in the world, optionally with extra food in the middle
+    PetriDish { rich_center: bool },
+}
+
+#[derive(Clone, Copy, Debug)]
+pub enum Shape {
+    Rect(Rect),
+    Disc { center: Vec2, radius: f32 },
+}
+
+impl Shape {
+    // Edges count as inside, so organisms clamped to a wall stay walkable
+    pub fn contains(&self, p: Vec2) -> bool {
+        match *self {
+            Shape::Rect(r) => p.x >= r.x && p.x <= r.right() && p.y >= r.y && p.y <= r.bottom(),
+            Shape::Disc { center, radius } => p.distance_squared(center) <= radius * radius,
+        }
+    }
+
+    pub fn area(&self) -> f32 {
+        match *self {
+            Shape::Rect(r) => r.w * r.h,
+            Shape::Disc { radius, .. } => std::f32::consts::PI * radius * radius,
+        }
+    }
+
+    pub fn bounding_rect(&self) -> Rect {
+        match *self {
+            Shape::Rect(r) => r,
+            Shape::Disc { center, radius } => Rect::new(center.x - radius, center.y - radius, radius * 2.0, radius * 2.0),
+        }
+    }
+
+    // Uniformly distributed point inside the shape
+    pub fn random_pos(&self, rng: &mut impl Rng) -> Vec2 {
+        match *self {
+            Shape::Rect(r) => vec2(rng.gen_range(r.x..r.right()), rng.gen_range(r.y..r.bottom())),
+            Shape::Disc { center, radius } => random_in_disc(center, radius, rng),
+        }
+    }
+}
+
+fn random_in_disc(center: Vec2, radius: f32, rng: &mut impl Rng) -> Vec2 {
+    let angle = rng.gen_range(0.0f32..std::f32::consts::TAU);
+    let r = radius * rng.gen_range(0.0f32..1.0).sqrt();
+    center + vec2(angle.cos(), angle.sin()) * r
+}
+
+// A patch of the world organisms can live in, with its own food supply
+#[derive(Clone, Debug)]
+pub struct Region {
+    pub name: &'static str,
+    pub shape: Shape,
+    // Scales the food spawn rate and cap of this region
+    pub food_multiplier: f32,
+    // Concentrate food in the middle of a disc region
+    pub rich_center: bool,
+    pub color: Color,
+}
+
+impl Region {
+    fn new(name: &'static str, shape: Shape, color: Color) -> Self {
+        Self { name, shape, food_multiplier: 1.0, rich_center: false, color }
+    }
+
+    pub fn area(&self) -> f32 {
+        self.shape.area()
+    }
+
+    // Where the next food item of this region appears
+    pub fn random_food_pos(&self, rng: &mut impl Rng) -> Vec2 {
+        match self.shape {
+            Shape::Disc { center, radius } if self.rich_center && rng.gen_range(0.0f32..1.0) < RICH_CENTER_SHARE => {
+                random_in_disc(center, radius * RICH_CENTER_RADIUS, rng)
+            }
+            shape => shape.random_pos(rng),
+        }
+    }
+}
+
+// Where organisms can go: the regions plus the corridors between them.
+// Everything outside is wall. Every `migration_interval` ticks each organism
+// has `migration_rate` odds of being carried to another region.
+#[derive(Clone, Debug)]
+pub struct Habitat {
+    pub regions: Vec<Region>,
+    pub corridors: Vec<Rect>,
+    pub migration_interval: u64,
+    pub migration_rate: f32,
+}
+
+impl Habitat {
+    pub fn new(layout: WorldLayout, width: f32, height: f32) -> Self {
+        match layout {
+            WorldLayout::Open => Self::open(width, height),
+            WorldLayout::Islands => Self::islands(width, height),
+            WorldLayout::PetriDish { rich_center } => Self::petri_dish(width, height, rich_center),
+        }
+    }
+
+    pub fn open(width: f32, height: f32) -> Self {
+        Self::single(Region::new("World", Shape::Rect(Rect::new(0.0, 0.0, width, height)), REGION_COLORS[0]))
+    }
+
+    pub fn petri_dish(width: f32, height: f32, rich_center: bool) -> Self {
+        let shape = Shape::Disc {
+            center: vec2(width / 2.0, height / 2.0),
+            radius: width.min(height) / 2.0 - DISH_MARGIN,
+        };
+        Self::single(Region { rich_center, ..Region::new("Dish", shape, REGION_COLORS[0]) })
+    }
+
+    fn single(region: Region) -> Self {
+        Self {
+            regions: vec![region],
+            corridors: Vec::new(),
+            migration_interval: 0,
+            migration_rate: 0.0,
+        }
+    }
+
+    // 2x2 islands; rich and poor ones side by side so their populations diverge
+    pub fn islands(width: f32, height: f32) -> Self {
+        let (gap_x, gap_y) = (width * ISLAND_GAP, height * ISLAND_GAP);
+        let (cell_w, cell_h) = (width / 2.0, height / 2.0);
+        let names = ["North-west", "North-east", "South-west", "South-east"];
+        let food = [1.5, 1.0, 0.6, 1.0];
+
+        let bounds: Vec<Rect> = (0..4)
+            .map(|i| {
+                let (col, row) = ((i % 2) as f32, (i / 2) as f32);
+                Rect::new(col * cell_w + gap_x / 2.0, row * cell_h + gap_y / 2.0, cell_w - gap_x, cell_h - gap_y)
+            })
+            .collect();
+
+        // Horizontal corridors across the vertical gap and vice versa, overlapping
+        // the islands slightly so the seams are walkable
+        let mut corridors = Vec::new();
+        for row in 0..2 {
+            let (a, b) = (bounds[row * 2], bounds[row * 2 + 1]);
+            let y = a.center().y - CORRIDOR_WIDTH / 2.0;
+            corridors.push(Rect::new(a.right() - 1.0, y, b.x - a.right() + 2.0, CORRIDOR_WIDTH));
+        }
+        for col in 0..2 {
+            let (a, b) = (bounds[col], bounds[col + 2]);
+            let x = a.center().x - CORRIDOR_WIDTH / 2.0;
+            corridors.push(Rect::new(x, a.bottom() - 1.0, CORRIDOR_WIDTH, b.y - a.bottom() + 2.0));
+        }
+
+        let regions = (0..4)
+            .map(|i| Region { food_multiplier: food[i], ..Region::new(names[i], Shape::Rect(bounds[i]), REGION_COLORS[i]) })
+            .collect();
+
+        Self {
+            regions,
+            corridors,
+            migration_interval: MIGRATION_INTERVAL,
+            migration_rate: MIGRATION_RATE,
+        }
+    }
+
+    // A plain rectangle with nothing to draw or bounce off besides the world edge
+    pub fn is_open(&self) -> bool {
+        self.regions.len() == 1 && self.corridors.is_empty() && matches!(self.regions[0].shape, Shape::Rect(_))
+    }
+
+    // Index of the region containing `p`, if any (corridors belong to none)
+    pub fn region_at(&self, p: Vec2) -> Option<usize> {
+        self.regions.iter().position(|r| r.shape.contains(p))
+    }
+
+    pub fn is_walkable(&self, p: Vec2) -> bool {
+        self.region_at(p).is_some() || self.corridors.iter().any(|c| Shape::Rect(*c).contains(p))
+    }
+
+    pub fn total_area(&self) -> f32 {
+        self.regions.iter().map(Region::area).sum()
+    }
+
+    // Keep a step from `old` to `new` inside the habitat: a step that would leave
+    // it is cancelled and the heading reflected off the wall it hit
+    pub fn bounce(&self, old: Vec2, new: Vec2, vel: Vec2) -> (Vec2, Vec2) {
+        if self.is_walkable(new) {
+            return (new, vel);
+        }
+        // Round walls reflect about the rim normal
+        if let Some(Shape::Disc { center, .. }) = self.region_at(old).map(|i| self.regions[i].shape) {
+            let normal = (new - center).normalize_or_zero();
+            return (old, vel - 2.0 * vel.dot(normal) * normal);
+        }
+        reflect(old, new, vel, |p| self.is_walkable(p))
+    }
+
+    // Random point in a region picked in proportion to its area
+    pub fn random_pos(&self, rng: &mut impl Rng) -> Vec2 {
+        let region = if self.regions.len() == 1 {
+            0
+        } else {
+            let mut pick = rng.gen_range(0.0..self.total_area());
+            self.regions.iter()
+                .position(|r| {
+                    pick -= r.area();
+                    pick < 0.0
+                })
+                .unwrap_or(self.regions.len() - 1)
+        };
+        self.regions[region].shape.random_pos(rng)
+    }
+}
+
+// Cancel a step from `old` to `new` that ends off `walkable` ground and flip the
+// heading on whichever axis leads off it, or both at a corner
+pub fn reflect(old: Vec2, new: Vec2, mut vel: Vec2, walkable: impl Fn(Vec2) -> bool) -> (Vec2, Vec2) {
+    let blocked_x = !walkable(vec2(new.x, old.y));
+    let blocked_y = !walkable(vec2(old.x, new.y));
+    if blocked_x || !blocked_y {
+        vel.x *= -1.0;
+    }
+    if blocked_y || !blocked_x {
+        vel.y *= -1.0;
+    }
+    (old, vel)
+}
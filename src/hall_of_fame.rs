@@ -0,0 +1,157 @@
+use hecs::Entity;
+use macroquad::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::components::{Age, Genome, Lineage};
+use crate::dna::DNA;
+use crate::world::World;
+
+// Best genomes kept per record, and how often the living bacteria are checked
+const ENTRIES_PER_RECORD: usize = 10;
+const SCAN_EVERY: u64 = 50;
+
+// What earns a place in the hall of fame
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Record {
+    LongestLived,
+    MostOffspring,
+}
+
+impl Record {
+    pub const ALL: [Record; 2] = [Record::LongestLived, Record::MostOffspring];
+
+    pub fn title(self) -> &'static str {
+        match self {
+            Record::LongestLived => "LONGEST LIVED",
+            Record::MostOffspring => "MOST OFFSPRING",
+        }
+    }
+
+    fn score(self, inductee: &Inductee) -> f32 {
+        match self {
+            Record::LongestLived => inductee.age,
+            Record::MostOffspring => inductee.offspring as f32,
+        }
+    }
+}
+
+// An archived bacterium: its genes, and its age and offspring when last seen
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Inductee {
+    // Its gene list and markers, or in older libraries a field per core
+    // locus
+    #[serde(flatten)]
+    pub dna: DNA,
+    pub age: f32,
+    pub offspring: u32,
+    pub generation: u32,
+    // Tick it was last updated at
+    pub tick: u64,
+    // The organism while it's still alive in this world, so it's updated
+    // rather than archived twice
+    #[serde(skip)]
+    id: Option<Entity>,
+}
+
+impl Inductee {
+    fn of(id: Entity, dna: &DNA, age: f32, lineage: &Lineage, tick: u64) -> Self {
+        Self {
+            dna: dna.clone(),
+            age,
+            offspring: lineage.offspring,
+            generation: lineage.generation,
+            tick,
+            id: Some(id),
+        }
+    }
+}
+
+// The library as saved, one list per record, best first
+#[derive(Default, Serialize, Deserialize)]
+#[serde(default)]
+struct Library {
+    longest_lived: Vec<Inductee>,
+    most_offspring: Vec<Inductee>,
+}
+
+impl Library {
+    fn hall(&mut self, record: Record) -> &mut Vec<Inductee> {
+        match record {
+            Record::LongestLived => &mut self.longest_lived,
+            Record::MostOffspring => &mut self.most_offspring,
+        }
+    }
+}
+
+// Genomes of exceptional bacteria, kept in a JSON file across runs
+pub struct HallOfFame {
+    path: String,
+    library: Library,
+    last_scan: Option<u64>,
+}
+
+impl HallOfFame {
+    // An empty library if the file doesn't exist yet
+    pub fn load(path: &str) -> Result<Self, String> {
+        let library = match std::fs::read_to_string(path) {
+            Ok(text) => serde_json::from_str(&text).map_err(|e| e.to_string())?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Library::default(),
+            Err(e) => return Err(e.to_string()),
+        };
+        Ok(Self { path: path.to_string(), library, last_scan: None })
+    }
+
+    pub fn entries(&self, record: Record) -> &[Inductee] {
+        match record {
+            Record::LongestLived => &self.library.longest_lived,
+            Record::MostOffspring => &self.library.most_offspring,
+        }
+    }
+
+    // The world was replaced, so handles from the old one mean nothing
+    pub fn new_world(&mut self) {
+        for record in Record::ALL {
+            for inductee in self.library.hall(record) {
+                inductee.id = None;
+            }
+        }
+        self.last_scan = None;
+    }
+
+    // Every `SCAN_EVERY` ticks, update the living inductees and archive any
+    // bacterium beating an entry; saves when something changed
+    pub fn scan(&mut self, world: &World) -> Result<(), String> {
+        if self.last_scan.is_some_and(|t| t + SCAN_EVERY > world.tick) {
+            return Ok(());
+        }
+        self.last_scan = Some(world.tick);
+
+        let mut changed = false;
+        for (id, genome, age, lineage) in world.ecs.query::<(Entity, &Genome, &Age, &Lineage)>().iter() {
+            let candidate = Inductee::of(id, &genome.0, age.0, lineage, world.tick);
+            for record in Record::ALL {
+                let score = record.score(&candidate);
+                let hall = self.library.hall(record);
+                if let Some(entry) = hall.iter_mut().find(|e| e.id == Some(id)) {
+                    if record.score(entry) != score {
+                        *entry = candidate.clone();
+                        changed = true;
+                    }
+                } else if score > 0.0 && (hall.len() < ENTRIES_PER_RECORD || hall.iter().any(|e| record.score(e) < score)) {
+                    hall.push(candidate.clone());
+                    changed = true;
+                }
+            }
+        }
+        if !changed {
+            return Ok(());
+        }
+        for record in Record::ALL {
+            let hall = self.library.hall(record);
+            hall.sort_by(|a, b| record.score(b).total_cmp(&record.score(a)));
+            hall.truncate(ENTRIES_PER_RECORD);
+        }
+        let text = serde_json::to_string_pretty(&self.library).map_err(|e| e.to_string())?;
+        std::fs::write(&self.path, text).map_err(|e| e.to_string())
+    }
+}
@@ -0,0 +1,278 @@
+use macroquad::prelude::*;
+use std::collections::HashMap;
+
+// Everything a key shortcut can do
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Action {
+    ToggleUi,
+    Pause,
+    // Advance one tick while paused
+    Step,
+    SpeedUp,
+    SpeedDown,
+    // Start over with a new seed
+    Reset,
+    Profiler,
+    Screenshot,
+    Record,
+    ExportCharts,
+    GraphSettings,
+    Correlations,
+    // Realized fitness over two genes
+    FitnessLandscape,
+    // Selection differentials against responses
+    Selection,
+    // Clade frequencies over time
+    Muller,
+    HallOfFame,
+    ClearSelection,
+    Fullscreen,
+    Pollution,
+    Oxygen,
+    // Antibiotic bands and how resistant the bacteria are
+    Resistance,
+    TerrainEditor,
+    // Next way of colouring bacteria
+    ColorMode,
+    Mute,
+    // Debug overlays
+    SenseOverlay,
+    VelocityOverlay,
+    TargetOverlay,
+    // Revert the last parameter change
+    Undo,
+    // Replace the bacteria with the population file's
+    ImportPopulation,
+    // Write the survivors' phylogeny as Newick and GraphViz
+    ExportTree,
+    Achievements,
+    // Write a run summary report
+    Report,
+    // Drop-down command console
+    Console,
+}
+
+impl Action {
+    pub const ALL: [Action; 33] = [
+        Action::ToggleUi,
+        Action::Pause,
+        Action::Step,
+        Action::SpeedUp,
+        Action::SpeedDown,
+        Action::Reset,
+        Action::Profiler,
+        Action::Screenshot,
+        Action::Record,
+        Action::ExportCharts,
+        Action::GraphSettings,
+        Action::Correlations,
+        Action::FitnessLandscape,
+        Action::Selection,
+        Action::Muller,
+        Action::HallOfFame,
+        Action::ClearSelection,
+        Action::Fullscreen,
+        Action::Pollution,
+        Action::Oxygen,
+        Action::Resistance,
+        Action::TerrainEditor,
+        Action::ColorMode,
+        Action::Mute,
+        Action::SenseOverlay,
+        Action::VelocityOverlay,
+        Action::TargetOverlay,
+        Action::Undo,
+        Action::ImportPopulation,
+        Action::ExportTree,
+        Action::Achievements,
+        Action::Report,
+        Action::Console,
+    ];
+
+    // Name in the config file's `keybindings` section
+    pub fn name(self) -> &'static str {
+        match self {
+            Action::ToggleUi => "toggle_ui",
+            Action::Pause => "pause",
+            Action::Step => "step",
+            Action::SpeedUp => "speed_up",
+            Action::SpeedDown => "speed_down",
+            Action::Reset => "reset",
+            Action::Profiler => "profiler",
+            Action::Screenshot => "screenshot",
+            Action::Record => "record",
+            Action::ExportCharts => "export_charts",
+            Action::GraphSettings => "graph_settings",
+            Action::Correlations => "correlations",
+            Action::FitnessLandscape => "fitness_landscape",
+            Action::Selection => "selection",
+            Action::Muller => "muller_plot",
+            Action::HallOfFame => "hall_of_fame",
+            Action::ClearSelection => "clear_selection",
+            Action::Fullscreen => "fullscreen",
+            Action::Pollution => "pollution",
+            Action::Oxygen => "oxygen",
+            Action::Resistance => "resistance",
+            Action::TerrainEditor => "terrain_editor",
+            Action::ColorMode => "color_mode",
+            Action::Mute => "mute",
+            Action::SenseOverlay => "sense_overlay",
+            Action::VelocityOverlay => "velocity_overlay",
+            Action::TargetOverlay => "target_overlay",
+            Action::Undo => "undo",
+            Action::ImportPopulation => "import_population",
+            Action::ExportTree => "export_tree",
+            Action::Achievements => "achievements",
+            Action::Report => "report",
+            Action::Console => "console",
+        }
+    }
+
+    // Shown in the panel's controls list
+    pub fn help(self) -> &'static str {
+        match self {
+            Action::ToggleUi => "Show/Hide UI",
+            Action::Pause => "Pause",
+            Action::Step => "Step one tick (paused)",
+            Action::SpeedUp => "Faster",
+            Action::SpeedDown => "Slower",
+            Action::Reset => "Restart, new seed",
+            Action::Profiler => "Profiler",
+            Action::Screenshot => "Screenshot",
+            Action::Record => "Record clip",
+            Action::ExportCharts => "Export charts",
+            Action::GraphSettings => "Choose graphs",
+            Action::Correlations => "Gene correlations",
+            Action::FitnessLandscape => "Fitness landscape",
+            Action::Selection => "Selection & response",
+            Action::Muller => "Muller plot",
+            Action::HallOfFame => "Hall of fame",
+            Action::ClearSelection => "Clear selection",
+            Action::Fullscreen => "Fullscreen",
+            Action::Pollution => "Pollution overlay",
+            Action::Oxygen => "Oxygen overlay",
+            Action::Resistance => "Resistance overlay",
+            Action::TerrainEditor => "Terrain editor",
+            Action::ColorMode => "Colour bacteria by",
+            Action::Mute => "Mute sound",
+            Action::SenseOverlay => "Sense radii",
+            Action::VelocityOverlay => "Velocity vectors",
+            Action::TargetOverlay => "Target lines",
+            Action::Undo => "Undo parameter change",
+            Action::ImportPopulation => "Import population",
+            Action::ExportTree => "Export phylogeny",
+            Action::Achievements => "Achievements",
+            Action::Report => "Run report",
+            Action::Console => "Command console",
+        }
+    }
+
+    fn default_key(self) -> KeyCode {
+        match self {
+            Action::ToggleUi => KeyCode::Tab,
+            Action::Pause => KeyCode::Space,
+            Action::Step => KeyCode::Period,
+            Action::SpeedUp => KeyCode::Equal,
+            Action::SpeedDown => KeyCode::Minus,
+            Action::Reset => KeyCode::F5,
+            Action::Profiler => KeyCode::F3,
+            Action::Screenshot => KeyCode::F2,
+            Action::Record => KeyCode::R,
+            Action::ExportCharts => KeyCode::C,
+            Action::GraphSettings => KeyCode::G,
+            Action::Correlations => KeyCode::K,
+            Action::FitnessLandscape => KeyCode::L,
+            Action::Selection => KeyCode::E,
+            Action::Muller => KeyCode::U,
+            Action::HallOfFame => KeyCode::H,
+            Action::ClearSelection => KeyCode::Escape,
+            Action::Fullscreen => KeyCode::F11,
+            Action::Pollution => KeyCode::P,
+            Action::Oxygen => KeyCode::O,
+            Action::Resistance => KeyCode::B,
+            Action::TerrainEditor => KeyCode::T,
+            Action::ColorMode => KeyCode::V,
+            Action::Mute => KeyCode::M,
+            Action::SenseOverlay => KeyCode::F6,
+            Action::VelocityOverlay => KeyCode::F7,
+            Action::TargetOverlay => KeyCode::F8,
+            Action::Undo => KeyCode::Z,
+            Action::ImportPopulation => KeyCode::I,
+            Action::ExportTree => KeyCode::X,
+            Action::Achievements => KeyCode::J,
+            Action::Report => KeyCode::F9,
+            Action::Console => KeyCode::GraveAccent,
+        }
+    }
+
+    fn index(self) -> usize {
+        Action::ALL.iter().position(|&a| a == self).unwrap()
+    }
+}
+
+// Keys that can be bound, looked up by their `key_name`
+const BINDABLE: &[KeyCode] = &[
+    KeyCode::A, KeyCode::B, KeyCode::C, KeyCode::D, KeyCode::E, KeyCode::F, KeyCode::G, KeyCode::H, KeyCode::I,
+    KeyCode::J, KeyCode::K, KeyCode::L, KeyCode::M, KeyCode::N, KeyCode::O, KeyCode::P, KeyCode::Q, KeyCode::R,
+    KeyCode::S, KeyCode::T, KeyCode::U, KeyCode::V, KeyCode::W, KeyCode::X, KeyCode::Y, KeyCode::Z,
+    KeyCode::Key0, KeyCode::Key1, KeyCode::Key2, KeyCode::Key3, KeyCode::Key4,
+    KeyCode::Key5, KeyCode::Key6, KeyCode::Key7, KeyCode::Key8, KeyCode::Key9,
+    KeyCode::F1, KeyCode::F2, KeyCode::F3, KeyCode::F4, KeyCode::F5, KeyCode::F6,
+    KeyCode::F7, KeyCode::F8, KeyCode::F9, KeyCode::F10, KeyCode::F11, KeyCode::F12,
+    KeyCode::Space, KeyCode::Tab, KeyCode::Escape, KeyCode::Enter, KeyCode::Backspace,
+    KeyCode::Minus, KeyCode::Equal, KeyCode::Period, KeyCode::Comma, KeyCode::Slash, KeyCode::Semicolon,
+    KeyCode::Apostrophe, KeyCode::LeftBracket, KeyCode::RightBracket, KeyCode::Backslash, KeyCode::GraveAccent,
+    KeyCode::Home, KeyCode::End, KeyCode::PageUp, KeyCode::PageDown, KeyCode::Insert, KeyCode::Delete,
+    KeyCode::Left, KeyCode::Right, KeyCode::Up, KeyCode::Down,
+];
+
+// Upper-case name of a key, as shown in the panel and written in the config
+// file (case doesn't matter there): "SPACE", "F5", "EQUAL", "1"
+pub fn key_name(key: KeyCode) -> String {
+    let name = format!("{:?}", key);
+    name.strip_prefix("Key").filter(|rest| !rest.is_empty()).unwrap_or(&name).to_uppercase()
+}
+
+fn parse_key(name: &str) -> Option<KeyCode> {
+    BINDABLE.iter().copied().find(|&key| key_name(key).eq_ignore_ascii_case(name.trim()))
+}
+
+// Which key triggers each action
+#[derive(Clone, Copy, Debug)]
+pub struct Keymap {
+    keys: [KeyCode; Action::ALL.len()],
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        Self { keys: Action::ALL.map(Action::default_key) }
+    }
+}
+
+impl Keymap {
+    // The defaults with `bindings` (action name to key name) applied. Unknown
+    // actions or keys are skipped and reported.
+    pub fn with_bindings(bindings: &HashMap<String, String>) -> (Self, Vec<String>) {
+        let mut keymap = Self::default();
+        let mut problems = Vec::new();
+        for (action, key) in bindings {
+            let Some(action) = Action::ALL.into_iter().find(|a| a.name() == action) else {
+                problems.push(format!("unknown action '{}'", action));
+                continue;
+            };
+            match parse_key(key) {
+                Some(key) => keymap.keys[action.index()] = key,
+                None => problems.push(format!("unknown key '{}' for {}", key, action.name())),
+            }
+        }
+        (keymap, problems)
+    }
+
+    pub fn key(&self, action: Action) -> KeyCode {
+        self.keys[action.index()]
+    }
+
+    pub fn pressed(&self, action: Action) -> bool {
+        is_key_pressed(self.key(action))
+    }
+}
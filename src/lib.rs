@@ -0,0 +1,61 @@
+pub mod achievements;
+#[cfg(feature = "http-api")]
+pub mod api;
+pub mod audio;
+pub mod autosave;
+pub mod bench;
+pub mod cadence;
+pub mod camera;
+pub mod capture;
+pub mod chart_export;
+pub mod cli;
+pub mod coloring;
+pub mod compare;
+pub mod components;
+pub mod config;
+pub mod console;
+pub mod distances;
+pub mod dna;
+pub mod events;
+pub mod field;
+pub mod fitness;
+pub mod food_web;
+pub mod habitat;
+pub mod hall_of_fame;
+pub mod keymap;
+pub mod logging;
+pub mod muller;
+pub mod metrics;
+pub mod organism;
+#[cfg(feature = "parquet")]
+pub mod parquet_export;
+pub mod params;
+pub mod phylogeny;
+pub mod population;
+pub mod presets;
+pub mod profiler;
+pub mod render;
+pub mod report;
+#[cfg(feature = "sqlite")]
+pub mod run_log;
+pub mod scenario;
+pub mod sim_thread;
+pub mod snapshot_diff;
+pub mod space;
+pub mod stats;
+pub mod stats_csv;
+pub mod sweep;
+pub mod systems;
+pub mod telemetry;
+pub mod terrain;
+pub mod throttle;
+pub mod tournament;
+pub mod ui;
+pub mod world;
+pub mod world3d;
+
+pub const SCREEN_WIDTH: f32 = 1400.0;
+pub const SCREEN_HEIGHT: f32 = 900.0;
+pub const GRAPH_HEIGHT: f32 = 200.0;
+pub const UI_PANEL_WIDTH: f32 = 320.0;
+pub const MAX_HISTORY: usize = 300;
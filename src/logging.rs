@@ -0,0 +1,35 @@
+use std::fs::File;
+use std::sync::Mutex;
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{fmt, EnvFilter, Layer};
+
+// Log targets, so each area can be turned up on its own
+// (`--log-level sim=debug,perf=trace`)
+pub const SIM: &str = "sim";
+pub const PERF: &str = "perf";
+pub const UI: &str = "ui";
+
+// Levels when neither `--log-level` nor `RUST_LOG` says otherwise: warnings,
+// and what the simulation and the window report doing
+const DEFAULT_FILTER: &str = "warn,sim=info,ui=info";
+
+// Send log lines to stderr and, given a path, to that file too. `filter` is
+// a level (`info`) or per-target directives (`warn,sim=debug`); without one
+// `RUST_LOG` is used, then `DEFAULT_FILTER`.
+pub fn init(filter: Option<&str>, file: Option<&str>) -> Result<(), String> {
+    let filter = match filter {
+        Some(filter) => filter.to_string(),
+        None => std::env::var("RUST_LOG").unwrap_or_else(|_| DEFAULT_FILTER.to_string()),
+    };
+    let filter = EnvFilter::try_new(&filter).map_err(|e| format!("bad log filter '{}': {}", filter, e))?;
+    let stderr = fmt::layer().with_writer(std::io::stderr).with_filter(filter.clone());
+    let file = match file {
+        Some(path) => {
+            let out = File::create(path).map_err(|e| format!("couldn't create log file {}: {}", path, e))?;
+            Some(fmt::layer().with_writer(Mutex::new(out)).with_ansi(false).with_filter(filter))
+        }
+        None => None,
+    };
+    tracing_subscriber::registry().with(stderr).with(file).try_init().map_err(|e| e.to_string())
+}
@@ -1,749 +1,1401 @@
+use clap::Parser;
 use macroquad::prelude::*;
-use ::rand::Rng;
+use tracing::{debug, info, warn};
+
+use bact_sim::achievements::Achievements;
+#[cfg(feature = "http-api")]
+use bact_sim::api::ControlApi;
+use bact_sim::audio::Audio;
+use bact_sim::autosave::{self, Autosave, WorldSave};
+use bact_sim::bench;
+use bact_sim::cadence::Cadence;
+use bact_sim::camera::{Camera, OrbitCamera};
+use bact_sim::capture::{save_frame, screenshot_path, ClipFormat, Recorder, Timelapse};
+use bact_sim::chart_export::export_charts;
+use bact_sim::cli::{AutosaveArgs, BenchArgs, Cli, Command, CompareArgs, DiffArgs, HeadlessArgs, OutputArgs, SessionArgs, SweepArgs, TournamentArgs, WindowArgs, WorldArgs};
+use bact_sim::coloring::{Coloring, Species};
+use bact_sim::compare;
+use bact_sim::sim_thread::SimThread;
+use bact_sim::snapshot_diff;
+use bact_sim::config::{Config, ConfigWatch};
+use bact_sim::console::Console;
+use bact_sim::fitness::FitnessLandscape;
+use bact_sim::muller::MullerPlot;
+use bact_sim::habitat::{Region, WorldLayout};
+use bact_sim::hall_of_fame::HallOfFame;
+use bact_sim::keymap::{key_name, Action, Keymap};
+use bact_sim::logging::{self, PERF, SIM, UI};
+use bact_sim::metrics::MetricsExporter;
+#[cfg(feature = "parquet")]
+use bact_sim::parquet_export::ParquetExporter;
+use bact_sim::phylogeny::Phylogeny;
+use bact_sim::params::SimulationParams;
+use bact_sim::population::{self, populate};
+use bact_sim::presets::ParamUndo;
+use bact_sim::profiler::Profiler;
+use bact_sim::report::RunReport;
+use bact_sim::render::{draw_debug_overlays, draw_minimap, draw_oxygen, draw_pollution, draw_resistance, draw_world3d, draw_selection, draw_world, minimap_rect, minimap_to_world, LodController, Sprites};
+#[cfg(feature = "sqlite")]
+use bact_sim::run_log::RunLog;
+use bact_sim::scenario::{Challenge, Scenario, Status};
+use bact_sim::stats::{Metric, ParamWatch, Stats, TickStats};
+use bact_sim::stats_csv::{Baseline, StatsCsv};
+use bact_sim::sweep::Sweep;
+use bact_sim::telemetry::TelemetryServer;
+use bact_sim::terrain::{Terrain, TerrainType};
+use bact_sim::throttle::TickThrottle;
+use bact_sim::tournament::{self, Entrant};
+use bact_sim::ui::{draw_achievements, draw_color_legend, draw_correlation_panel, draw_fitness_landscape, draw_graph_settings, draw_graphs_panel, draw_selection_panel, draw_muller_plot, muller_plot_rect, draw_hall_of_fame, draw_inspector, inspector_rect, graph_settings_rect, hall_of_fame_rect, fitness_landscape_rect, draw_recovery, recovery_rect, draw_console, console_rect, draw_region_panel, draw_scenario, draw_terrain_toolbar, draw_ui_panel, slider_entry_active, terrain_toolbar_rect, Layout, UIState};
+use bact_sim::world::World;
+use bact_sim::world3d::World3D;
+use bact_sim::{SCREEN_HEIGHT, SCREEN_WIDTH};
+
+// World units covered by the terrain brush
+const BRUSH_RADIUS: f32 = 30.0;
+const TERRAIN_FILE: &str = "terrain.txt";
+const HALL_OF_FAME_PATH: &str = "hall_of_fame.json";
+const ACHIEVEMENTS_PATH: &str = "achievements.json";
+const REPORT_DIR: &str = "reports";
+const TREE_DIR: &str = "trees";
+// Autosave defaults: the folder, how often the windowed mode saves, and how
+// many saves rotate
+const AUTOSAVE_DIR: &str = "autosave";
+const AUTOSAVE_EVERY: u64 = 5000;
+const AUTOSAVE_KEEP: usize = 3;
+// Ticks between headless throughput log lines
+const PERF_LOG_EVERY: u64 = 1000;
+const POPULATION_FILE: &str = "population.json";
+// Copies spawned by the hall of fame's "Clone" button
+const CLONES_REINTRODUCED: usize = 5;
+
+// `--terrain terrain.txt` loads a saved terrain, `--mask arena.png` builds one
+// from an image stretched over the world
+fn initial_terrain(args: &WorldArgs, params: &SimulationParams) -> Terrain {
+    let loaded = if let Some(path) = &args.terrain {
+        std::fs::read_to_string(path)
+            .map_err(|e| e.to_string())
+            .and_then(|text| Terrain::from_text(&text))
+            .map_err(|e| format!("Couldn't load terrain from {}: {}", path, e))
+    } else if let Some(path) = &args.mask {
+        std::fs::read(path)
+            .map_err(|e| e.to_string())
+            .and_then(|bytes| Image::from_file_with_format(&bytes, None).map_err(|e| e.to_string()))
+            .map(|image| Terrain::from_mask(&image, params.world_width, params.world_height))
+            .map_err(|e| format!("Couldn't load mask from {}: {}", path, e))
+    } else {
+        return Terrain::new(params.world_width, params.world_height);
+    };
+    loaded.unwrap_or_else(|e| {
+        warn!("{}", e);
+        Terrain::new(params.world_width, params.world_height)
+    })
+}
+
+fn window_conf() -> Conf {
+    Conf {
+        window_title: "Bacterial Ecosystem".to_owned(),
+        window_width: SCREEN_WIDTH as i32,
+        window_height: SCREEN_HEIGHT as i32,
+        window_resizable: true,
+        ..Default::default()
+    }
+}
 
-const SCREEN_WIDTH: f32 = 1400.0;
-const SCREEN_HEIGHT: f32 = 900.0;
-const GRAPH_HEIGHT: f32 = 200.0;
-const UI_PANEL_WIDTH: f32 = 320.0;
-const MAX_HISTORY: usize = 300;
+// `--telemetry 127.0.0.1:9001` streams stats over WebSocket every
+// `--telemetry-every` ticks (default 1) and organism snapshots every
+// `--snapshot-every` ticks (default never)
+fn telemetry_server(args: &OutputArgs) -> Option<TelemetryServer> {
+    let addr = args.telemetry.as_ref()?;
+    let mut server = TelemetryServer::bind(addr)
+        .map_err(|e| warn!("Couldn't start telemetry on {}: {}", addr, e))
+        .ok()?;
+    if let Some(n) = args.telemetry_every {
+        server.stats = Cadence::new(n);
+    }
+    if let Some(n) = args.snapshot_every {
+        server.snapshots = Cadence::new(n);
+    }
+    info!(target: SIM, "Telemetry on ws://{}", addr);
+    Some(server)
+}
 
-// Mutable simulation parameters
-struct SimulationParams {
-    food_growth_rate: f32,
-    max_food: usize,
-    mutation_rate: f32,
-    mutation_strength: f32,
-    reproduction_threshold: f32,
-    initial_energy: f32,
-    speed_multiplier: f32,
-    predator_count: f32,
-    predator_reproduction_threshold: f32,
+// `--api 127.0.0.1:8080` serves the REST control API (built with the
+// `http-api` feature)
+#[cfg(feature = "http-api")]
+fn control_api(args: &OutputArgs) -> Option<ControlApi> {
+    let addr = args.api.as_ref()?;
+    let api = ControlApi::bind(addr)
+        .map_err(|e| warn!("Couldn't start the control API on {}: {}", addr, e))
+        .ok()?;
+    info!(target: SIM, "Control API on http://{}", addr);
+    Some(api)
 }
 
-impl Default for SimulationParams {
-    fn default() -> Self {
-        Self {
-            food_growth_rate: 2.0,
-            max_food: 1000,
-            mutation_rate: 0.1,
-            mutation_strength: 0.1,
-            reproduction_threshold: 150.0,
-            initial_energy: 100.0,
-            speed_multiplier: 1.0,
-            predator_count: 5.0,
-            predator_reproduction_threshold: 200.0,
+// `--metrics 127.0.0.1:9090` serves Prometheus gauges on /metrics
+fn metrics_exporter(args: &OutputArgs) -> Option<MetricsExporter> {
+    let addr = args.metrics.as_ref()?;
+    let exporter = MetricsExporter::bind(addr)
+        .map_err(|e| warn!("Couldn't start metrics on {}: {}", addr, e))
+        .ok()?;
+    info!(target: SIM, "Metrics on http://{}/metrics", addr);
+    Some(exporter)
+}
+
+// `--log-db run.sqlite` logs every tick, plus organism snapshots every
+// `--log-snapshot-every` ticks (default 100), to SQLite (built with the
+// `sqlite` feature)
+#[cfg(feature = "sqlite")]
+fn run_log(args: &OutputArgs, seed: u64) -> Option<RunLog> {
+    let path = args.log_db.as_ref()?;
+    let mut log = RunLog::open(path, seed)
+        .map_err(|e| warn!("Couldn't open run log {}: {}", path, e))
+        .ok()?;
+    if let Some(n) = args.log_snapshot_every {
+        log.snapshots = Cadence::new(n);
+    }
+    Some(log)
+}
+
+// Log the tick, giving up on the log after the first error
+#[cfg(feature = "sqlite")]
+fn log_tick(log: &mut Option<RunLog>, world: &World) {
+    if let Some(run_log) = log
+        && let Err(e) = run_log.record(world)
+    {
+        warn!("Stopped logging the run: {}", e);
+        *log = None;
+    }
+}
+
+// `--parquet-dir snapshots` exports the population every `--parquet-every`
+// ticks (default 1000) as Parquet (built with the `parquet` feature)
+#[cfg(feature = "parquet")]
+fn parquet_exporter(args: &OutputArgs) -> Option<ParquetExporter> {
+    let dir = args.parquet_dir.as_ref()?;
+    let mut exporter = ParquetExporter::new(dir)
+        .map_err(|e| warn!("Couldn't create snapshot folder {}: {}", dir, e))
+        .ok()?;
+    if let Some(n) = args.parquet_every {
+        exporter.every = Cadence::new(n);
+    }
+    Some(exporter)
+}
+
+#[cfg(feature = "parquet")]
+fn export_tick(exporter: &mut Option<ParquetExporter>, world: &World) {
+    if let Some(exporter) = exporter
+        && let Err(e) = exporter.record(world)
+    {
+        warn!("Couldn't export snapshot: {}", e);
+    }
+}
+
+// Sounds, when built with the `audio` feature; `--mute` starts muted
+async fn start_audio(ui_state: &mut UIState, mute: bool) -> Option<Audio> {
+    if !cfg!(feature = "audio") {
+        return None;
+    }
+    ui_state.sound.muted = mute;
+    Audio::new(&ui_state.sound).await
+        .map_err(|e| warn!("Couldn't start audio: {}", e))
+        .ok()
+}
+
+// `--population seeds.json` starts every world, and every restart, with the
+// bacteria in the file (see `population::Seed`). The import action reads it,
+// or `population.json` without the flag.
+fn population_path(args: &WorldArgs) -> String {
+    args.population.clone().unwrap_or_else(|| POPULATION_FILE.to_string())
+}
+
+fn import_population(world: &mut World, params: &SimulationParams, path: &str) -> bool {
+    match population::load(path, world.width, world.height) {
+        Ok(seeds) => {
+            populate(world, &seeds, params.initial_energy);
+            if params.neutral_markers > 0 {
+                world.seed_markers(params);
+            }
+            info!(target: SIM, "Imported {} bacteria from {}", world.bacteria_count(), path);
+            true
+        }
+        Err(e) => {
+            warn!("Couldn't import population {}: {}", path, e);
+            false
         }
     }
 }
 
-// Historical stats for graphing
-struct Stats {
-    population_history: Vec<f32>,
-    avg_speed_history: Vec<f32>,
-    avg_size_history: Vec<f32>,
-    predator_history: Vec<f32>,
+// New world, seeded from `--population` if given
+fn new_world(args: &WorldArgs, params: &SimulationParams, seed: u64) -> World {
+    let mut world = World::with_terrain(params, seed, initial_terrain(args, params));
+    if let Some(path) = &args.population {
+        import_population(&mut world, params, path);
+    }
+    world
+}
+
+// Genomes of the longest-lived and most prolific bacteria are archived to
+// `--hall-of-fame PATH`; the windowed 2D mode uses `default` without it
+fn hall_of_fame(args: &OutputArgs, default: Option<&str>) -> Option<HallOfFame> {
+    let path = args.hall_of_fame.as_deref().or(default)?;
+    HallOfFame::load(path)
+        .map_err(|e| warn!("Couldn't load hall of fame {}: {}", path, e))
+        .ok()
 }
 
-impl Stats {
-    fn new() -> Self {
-        Self {
-            population_history: Vec::new(),
-            avg_speed_history: Vec::new(),
-            avg_size_history: Vec::new(),
-            predator_history: Vec::new(),
+// Archive from this tick, giving up on the hall of fame after the first error
+fn archive_tick(hall: &mut Option<HallOfFame>, world: &World) {
+    if let Some(library) = hall
+        && let Err(e) = library.scan(world)
+    {
+        warn!("Stopped archiving the hall of fame: {}", e);
+        *hall = None;
+    }
+}
+
+// `--scenario speed.json` plays a challenge (see `Scenario`), its params
+// applied over the config and flags
+fn scenario(args: &OutputArgs, params: &mut SimulationParams) -> Option<Challenge> {
+    let path = args.scenario.as_ref()?;
+    let scenario = Scenario::load(path)
+        .map_err(|e| warn!("Couldn't load scenario {}: {}", path, e))
+        .ok()?;
+    if let Err(e) = params.merge(scenario.params.clone()) {
+        warn!("Ignoring the scenario's params: {}", e);
+    }
+    info!(target: SIM, "Scenario: {}", scenario.name);
+    if !scenario.description.is_empty() {
+        info!(target: SIM, "{}", scenario.description);
+    }
+    Some(Challenge::new(scenario))
+}
+
+// Check the scenario's objectives against the latest stats, marking each
+// outcome on the graphs
+fn play_tick(challenge: &mut Option<Challenge>, stats: &mut Stats, tick: u64, now: Option<f64>) {
+    let Some(challenge) = challenge else { return };
+    for outcome in challenge.update(stats) {
+        let (text, color) = challenge.describe(&outcome);
+        info!(target: SIM, tick, "{}", text);
+        stats.add_marker(tick, text, color);
+        if let Some(now) = now {
+            challenge.announce(&outcome, now);
         }
     }
+}
 
-    fn push(&mut self, pop: f32, speed: f32, size: f32, predators: f32) {
-        self.population_history.push(pop);
-        self.avg_speed_history.push(speed);
-        self.avg_size_history.push(size);
-        self.predator_history.push(predators);
+// Milestones are kept in `--achievements PATH`; the windowed 2D mode uses
+// `default` without it
+fn achievements(args: &OutputArgs, default: Option<&str>) -> Option<Achievements> {
+    let path = args.achievements.as_deref().or(default)?;
+    Achievements::load(path)
+        .map_err(|e| warn!("Couldn't load achievements {}: {}", path, e))
+        .ok()
+}
 
-        // Keep only last MAX_HISTORY entries
-        if self.population_history.len() > MAX_HISTORY {
-            self.population_history.remove(0);
-            self.avg_speed_history.remove(0);
-            self.avg_size_history.remove(0);
-            self.predator_history.remove(0);
+// Unlock this tick's achievements, marking each on the graphs; gives up on
+// them after the first error
+fn achieve_tick(achievements: &mut Option<Achievements>, world: &World, stats: &mut Stats) {
+    let Some(tracker) = achievements else { return };
+    match tracker.check(world) {
+        Ok(unlocked) => {
+            for achievement in unlocked {
+                info!(target: SIM, tick = world.tick, "Achievement unlocked: {} - {}", achievement.title(), achievement.description());
+                stats.add_marker(world.tick, format!("🏅 {}", achievement.title()), Color::new(1.0, 0.8, 0.3, 1.0));
+            }
+        }
+        Err(e) => {
+            warn!("Stopped tracking achievements: {}", e);
+            *achievements = None;
         }
     }
 }
 
-struct UIState {
-    show_ui: bool,
-    paused: bool,
+fn autosave_dir(args: &AutosaveArgs) -> &str {
+    args.autosave_dir.as_deref().unwrap_or(AUTOSAVE_DIR)
+}
+
+// Autosaves every `--autosave-every` ticks (`default_every` without it, 0 to
+// turn them off) into `--autosave-keep` rotating files
+fn start_autosave(args: &AutosaveArgs, default_every: Option<u64>) -> Option<Autosave> {
+    let every = args.autosave_every.or(default_every).filter(|&n| n > 0)?;
+    let keep = args.autosave_keep.unwrap_or(AUTOSAVE_KEEP);
+    let dir = autosave_dir(args);
+    Autosave::start(dir, every, keep)
+        .map_err(|e| warn!("Couldn't autosave to {}: {}", dir, e))
+        .ok()
+}
+
+// Autosave this tick if due, giving up on autosaving after the first error
+fn autosave_tick(autosave: &mut Option<Autosave>, world: &World, params: &SimulationParams) {
+    if let Some(saver) = autosave
+        && let Err(e) = saver.tick(world, params)
+    {
+        warn!("Stopped autosaving: {}", e);
+        *autosave = None;
+    }
+}
+
+// With `--sim-thread`, get the world back from the worker before changing it
+fn settle(sim: &mut Option<SimThread>, world: &mut World) {
+    if let Some(sim) = sim {
+        sim.wait(world);
+    }
+}
+
+// The newest autosave: with `--resume` always, otherwise only if the last
+// run crashed
+fn saved_run(args: &AutosaveArgs) -> Option<WorldSave> {
+    let dir = autosave_dir(args);
+    if !args.resume && !autosave::crashed(dir) {
+        return None;
+    }
+    let Some(path) = autosave::latest(dir) else {
+        warn!("No autosave in {} to resume", dir);
+        return None;
+    };
+    WorldSave::load(&path)
+        .map_err(|e| warn!("Couldn't load autosave {}: {}", path.display(), e))
+        .ok()
+}
+
+// The saved world and its parameters, or None (reported) if it can't be rebuilt
+fn resume_run(save: &WorldSave, seed: u64) -> Option<(World, SimulationParams)> {
+    match save.restore(seed) {
+        Ok(world) => {
+            info!(target: SIM, "Resumed the saved world at tick {}", save.tick);
+            Some((world, save.params.clone()))
+        }
+        Err(e) => {
+            warn!("Couldn't resume the saved world: {}", e);
+            None
+        }
+    }
 }
 
-impl Default for UIState {
-    fn default() -> Self {
-        Self {
-            show_ui: true,
-            paused: false,
+// Shortcuts pressed this frame, for the `ui` log target
+fn log_actions(shortcuts: bool, keys: &Keymap) {
+    if shortcuts && tracing::enabled!(target: UI, tracing::Level::DEBUG) {
+        for action in Action::ALL.into_iter().filter(|&a| keys.pressed(a)) {
+            debug!(target: UI, "Key {}: {}", key_name(keys.key(action)), action.name());
         }
     }
 }
 
-#[derive(Clone)]
-struct DNA {
-    speed: f32,
-    size: f32,
-    sense_radius: f32,
-    color: Color,
+// Write the run's summary report to `path`
+fn write_report(report: &RunReport, path: &str, params: &SimulationParams, world: &World) {
+    match report.write(path, params, world) {
+        Ok(()) => info!(target: SIM, "Saved a run report to {}", path),
+        Err(e) => warn!("Couldn't write the run report {}: {}", path, e),
+    }
+}
+
+// Write up to `sample` survivors' phylogeny to `path`, Newick or DOT by its
+// extension
+fn write_tree(phylogeny: &Phylogeny, path: &str, tick: u64, sample: usize) {
+    match phylogeny.write(path, tick, sample) {
+        Ok(()) => info!(target: SIM, "Saved a phylogeny to {}", path),
+        Err(e) => warn!("Couldn't write the phylogeny {}: {}", path, e),
+    }
+}
+
+// `--timelapse frames` saves a frame every `--timelapse-every` ticks
+// (default 100) into the folder
+fn timelapse(args: &WindowArgs) -> Option<Timelapse> {
+    let dir = args.timelapse.clone()?;
+    Some(Timelapse::new(dir, args.timelapse_every.unwrap_or(100)))
 }
 
-impl DNA {
-    fn random() -> Self {
-        let mut rng = ::rand::thread_rng();
-        Self {
-            speed: rng.gen_range(1.0f32..3.0f32),
-            size: rng.gen_range(3.0f32..8.0f32),
-            sense_radius: rng.gen_range(20.0f32..60.0f32),
-            color: Color::new(
-                rng.gen_range(0.2f32..1.0f32),
-                rng.gen_range(0.2f32..1.0f32),
-                rng.gen_range(0.2f32..1.0f32),
-                0.9f32,
-            ),
+// Save the frame drawn so far if a screenshot was asked for, and timelapse
+// frames when due
+fn capture_frame(tick: u64, timelapse: &mut Option<Timelapse>, screenshot: bool) {
+    if screenshot {
+        let path = screenshot_path(tick);
+        match save_frame(&path) {
+            Ok(()) => info!(target: UI, "Saved {}", path.display()),
+            Err(e) => warn!("Couldn't save screenshot {}: {}", path.display(), e),
         }
     }
+    if let Some(timelapse) = timelapse {
+        timelapse.capture(tick);
+    }
+}
 
-    fn mutate(&self, params: &SimulationParams) -> Self {
-        let mut rng = ::rand::thread_rng();
-        
-        let new_speed = if rng.gen_bool(params.mutation_rate as f64) {
-            let change = rng.gen_range(-params.mutation_strength..params.mutation_strength);
-            (self.speed * (1.0 + change)).clamp(0.1, 100.0)
-        } else {
-            self.speed
-        };
-        
-        let new_size = if rng.gen_bool(params.mutation_rate as f64) {
-            let change = rng.gen_range(-params.mutation_strength..params.mutation_strength);
-            (self.size * (1.0 + change)).clamp(0.1, 100.0)
-        } else {
-            self.size
-        };
-        
-        let new_sense = if rng.gen_bool(params.mutation_rate as f64) {
-            let change = rng.gen_range(-params.mutation_strength..params.mutation_strength);
-            (self.sense_radius * (1.0 + change)).clamp(0.1, 100.0)
-        } else {
-            self.sense_radius
-        };
+// Clip recording: `--record-format gif|mp4` (GIF when built with the `gif`
+// feature, otherwise MP4), `--record-seconds` (default 10) and `--record-fps`
+// (default 20)
+struct ClipSettings {
+    format: ClipFormat,
+    seconds: f32,
+    fps: f32,
+}
 
-        let new_r = (self.color.r + rng.gen_range(-0.05f32..0.05f32)).clamp(0.2, 1.0);
-        let new_g = (self.color.g + rng.gen_range(-0.05f32..0.05f32)).clamp(0.2, 1.0);
-        let new_b = (self.color.b + rng.gen_range(-0.05f32..0.05f32)).clamp(0.2, 1.0);
+fn clip_settings(args: &WindowArgs) -> ClipSettings {
+    let default_format = if cfg!(feature = "gif") { ClipFormat::Gif } else { ClipFormat::Mp4 };
+    ClipSettings {
+        format: args.record_format.unwrap_or(default_format),
+        seconds: args.record_seconds.unwrap_or(10.0),
+        fps: args.record_fps.unwrap_or(20.0),
+    }
+}
 
-        Self {
-            speed: new_speed,
-            size: new_size,
-            sense_radius: new_sense,
-            color: Color::new(new_r, new_g, new_b, 0.9f32),
+// Start, feed or finish the clip recording of the sim area to follow
+// `ui_state.recording`, which is cleared once the clip is long enough
+fn update_recording(ui_state: &mut UIState, recorder: &mut Option<Recorder>, settings: &ClipSettings, sim: Rect, tick: u64) {
+    if ui_state.recording && recorder.is_none() {
+        let path = std::path::Path::new("recordings").join(format!("clip_{:08}.{}", tick, settings.format.extension()));
+        match Recorder::start(path, settings.format, sim, settings.fps, settings.seconds) {
+            Ok(started) => *recorder = Some(started),
+            Err(e) => {
+                warn!("Couldn't start recording: {}", e);
+                ui_state.recording = false;
+            }
         }
     }
+    let Some(active) = recorder else { return };
+    if ui_state.recording && active.capture(get_frame_time()) {
+        return;
+    }
+    ui_state.recording = false;
+    match recorder.take().map(Recorder::finish) {
+        Some(Ok(path)) => info!(target: UI, "Saved {}", path.display()),
+        Some(Err(e)) => warn!("Couldn't finish recording: {}", e),
+        None => {}
+    }
+}
+
+// Folder the export key writes charts into
+const CHART_DIR: &str = "charts";
+
+// Write the graphs out as PNGs. Has to run before the frame itself is drawn,
+// since charts are rendered off screen.
+fn export_chart_files(stats: &Stats, regions: &[Region], metrics: &[Metric]) {
+    match export_charts(stats, regions, metrics, std::path::Path::new(CHART_DIR)) {
+        Ok(paths) => info!(target: UI, "Saved {} charts to {}", paths.len(), CHART_DIR),
+        Err(e) => warn!("Couldn't export charts: {}", e),
+    }
+}
+
+// Speed shortcuts move the speed slider a notch, within its range
+fn change_speed(params: &mut SimulationParams, up: bool, down: bool) {
+    let notch = if up { 0.1 } else if down { -0.1 } else { return };
+    params.speed_multiplier = (params.speed_multiplier + notch).clamp(0.1, 3.0);
+}
+
+// `--stats-csv stats.csv` writes every tick's stats, one row per tick
+fn stats_csv(args: &OutputArgs) -> Option<StatsCsv> {
+    let path = args.stats_csv.as_ref()?;
+    StatsCsv::create(path)
+        .map_err(|e| warn!("Couldn't create {}: {}", path, e))
+        .ok()
+}
+
+// Write the latest stats, giving up on the file after the first error
+fn write_stats(csv: &mut Option<StatsCsv>, stats: &Stats) {
+    if let Some(writer) = csv
+        && let Err(e) = writer.record(stats)
+    {
+        warn!("Stopped writing stats: {}", e);
+        *csv = None;
+    }
+}
+
+// `--baseline stats.csv` overlays an earlier run's `--stats-csv` output on
+// the graphs
+fn baseline(args: &WindowArgs) -> Option<Baseline> {
+    let path = args.baseline.as_ref()?;
+    Baseline::load(path)
+        .map_err(|e| warn!("Couldn't load baseline {}: {}", path, e))
+        .ok()
+}
+
+// The config file's parameters, then the command-line flags over them
+fn params_from_args(config: Config, args: &WorldArgs) -> SimulationParams {
+    let mut params = SimulationParams::default();
+    if let Err(e) = params.merge(config.params) {
+        warn!("Ignoring the config's params: {}", e);
+    }
+    if let Some((w, h)) = args.world {
+        params.world_width = w;
+        params.world_height = h;
+    }
+    if args.islands {
+        params.world_layout = WorldLayout::Islands;
+    }
+    if args.petri {
+        params.world_layout = WorldLayout::PetriDish { rich_center: args.rich_center };
+    }
+    // Each `--ph-zone x,y,radius,ph` adds a zone; giving any replaces the defaults
+    if !args.ph_zone.is_empty() {
+        params.ph_zones = args.ph_zone.clone();
+    }
+    if let Some(stride) = args.sense_stride {
+        params.sense_stride = stride;
+    }
+    params
 }
 
-struct Bacterium {
-    pos: Vec2,
-    vel: Vec2,
-    dna: DNA,
-    energy: f32,
-    age: f32,
+// `--config settings.json` loads parameters and key bindings (see `Config`)
+fn load_config(args: &WorldArgs) -> Config {
+    let Some(path) = &args.config else { return Config::default() };
+    Config::load(path).unwrap_or_else(|e| {
+        warn!("Couldn't load config {}: {}", path, e);
+        Config::default()
+    })
 }
 
-impl Bacterium {
-    fn new(pos: Vec2, initial_energy: f32) -> Self {
-        let mut rng = ::rand::thread_rng();
-        let angle = rng.gen_range(0.0f32..std::f32::consts::TAU);
-        let dna = DNA::random();
-        Self {
-            pos,
-            vel: vec2(angle.cos(), angle.sin()),
-            dna,
-            energy: initial_energy,
-            age: 0.0,
+// With `--config`, the file is watched for parameter edits to apply live
+fn config_watch(args: &WorldArgs) -> Option<ConfigWatch> {
+    args.config.as_ref().map(|path| ConfigWatch::new(path))
+}
+
+// Apply the parameters edited in the config file since it was last read.
+// Edits `merge` refuses, such as a value out of range, are reported once per
+// save like a file that no longer parses, and none of that save's are applied.
+fn reload_config(watch: &mut Option<ConfigWatch>, params: &mut SimulationParams) {
+    let Some(watch) = watch else { return };
+    let reloaded = watch.poll().and_then(|changes| {
+        let applied: Vec<String> = changes.iter().map(|(name, value)| format!("{} = {}", name, value)).collect();
+        if !changes.is_empty() {
+            params.merge(changes)?;
+        }
+        Ok(applied)
+    });
+    match reloaded {
+        Ok(applied) => {
+            for change in applied {
+                info!(target: SIM, "Config reloaded: {}", change);
+            }
         }
+        Err(e) => warn!("Couldn't reload config: {}", e),
     }
+}
+
+// `compare a.csv b.csv` reports how two sets of `--stats-csv` runs differ
+// (several runs per side comma-separated) instead of simulating
+fn run_compare(args: &CompareArgs) {
+    let (a, b) = (&args.a, &args.b);
+    let load = |list: &str| -> Option<Vec<Baseline>> {
+        list.split(',')
+            .map(|path| Baseline::load(path).map_err(|e| warn!("Couldn't load {}: {}", path, e)).ok())
+            .collect()
+    };
+    let (Some(runs_a), Some(runs_b)) = (load(a), load(b)) else { return };
+    println!("{}", compare::report(&runs_a, &runs_b, (a, b)));
+}
+
+// `diff a.json b.json` reports what changed between two saved worlds
+fn run_diff(args: &DiffArgs) {
+    let load = |path: &str| WorldSave::load(std::path::Path::new(path)).map_err(|e| warn!("Couldn't load {}: {}", path, e)).ok();
+    let (Some(a), Some(b)) = (load(&args.a), load(&args.b)) else { return };
+    match snapshot_diff::report(&a, &b, (&args.a, &args.b)) {
+        Ok(report) => println!("{}", report),
+        Err(e) => warn!("Couldn't compare the saves: {}", e),
+    }
+}
+
+// The parameters and key bindings the world flags and config file ask for
+fn setup(args: &WorldArgs) -> (SimulationParams, Keymap) {
+    let config = load_config(args);
+    let (keys, problems) = Keymap::with_bindings(&config.keybindings);
+    for problem in problems {
+        warn!("Ignoring key binding: {}", problem);
+    }
+    (params_from_args(config, args), keys)
+}
+
+// `--seed`, or the clock without it
+fn seed(args: &WorldArgs) -> u64 {
+    args.seed.unwrap_or_else(|| macroquad::miniquad::date::now() as u64)
+}
 
-    fn update(&mut self, screen_w: f32, screen_h: f32, food: &Vec<Vec2>, speed_mult: f32, predators: &Vec<Predator>) {
-        let mut rng = ::rand::thread_rng();
-        
-        // Movement physics
-        self.pos += self.vel * self.dna.speed * speed_mult;
-        
-        // Bounce off walls
-        if self.pos.x < 0.0 || self.pos.x > screen_w {
-            self.vel.x *= -1.0;
-            self.pos.x = self.pos.x.clamp(0.0, screen_w);
+fn main() {
+    let cli = Cli::parse();
+    // `--log-level debug` (or `warn,sim=debug` per target) and `--log-file run.log`
+    if let Err(e) = logging::init(cli.log_level.as_deref(), cli.log_file.as_deref()) {
+        eprintln!("Couldn't start logging: {}", e);
+    }
+    match cli.command.unwrap_or(Command::Run(cli.run)) {
+        Command::Run(args) => run_window(args.session, args.three_d, None),
+        Command::Replay(args) => match WorldSave::load(std::path::Path::new(&args.file)) {
+            Ok(save) => run_window(args.session, false, Some(save)),
+            Err(e) => warn!("Couldn't load {}: {}", args.file, e),
+        },
+        Command::Headless(args) => {
+            let (mut params, _) = setup(&args.world);
+            let challenge = scenario(&args.output, &mut params);
+            run_headless(params, seed(&args.world), challenge, &args);
         }
-        if self.pos.y < 0.0 || self.pos.y > screen_h {
-            self.vel.y *= -1.0;
-            self.pos.y = self.pos.y.clamp(0.0, screen_h);
+        Command::Sweep(args) => run_sweep(&args),
+        Command::Bench(args) => run_bench(&args),
+        Command::Compare(args) => run_compare(&args),
+        Command::Diff(args) => run_diff(&args),
+        Command::Tournament(args) => {
+            let (params, _) = setup(&args.world);
+            run_tournament(params, seed(&args.world), &args);
         }
+    }
+}
+
+// Open the window, starting from `save` if given
+fn run_window(args: SessionArgs, three_d: bool, save: Option<WorldSave>) {
+    let (mut params, keys) = setup(&args.world);
+    let challenge = scenario(&args.output, &mut params);
+    let seed = seed(&args.world);
+    if three_d {
+        macroquad::Window::from_config(window_conf(), run_3d(params, seed, keys, args));
+    } else {
+        macroquad::Window::from_config(window_conf(), run_2d(params, seed, keys, challenge, args, save));
+    }
+}
 
-        // Check for nearby predators and flee
-        let mut flee_dir = vec2(0.0, 0.0);
-        for p in predators {
-            let dist = self.pos.distance(p.pos);
-            if dist < 80.0 && dist > 0.1 {
-                let away = (self.pos - p.pos).normalize();
-                flee_dir += away / dist;
+// No window: step as fast as possible, for `--ticks` ticks or forever, and
+// report through telemetry
+fn run_headless(mut params: SimulationParams, seed: u64, mut challenge: Option<Challenge>, args: &HeadlessArgs) {
+    let output = &args.output;
+    let mut telemetry = telemetry_server(output);
+    let mut metrics = metrics_exporter(output);
+    #[cfg(feature = "http-api")]
+    let api = control_api(output);
+    #[cfg(feature = "sqlite")]
+    let mut log = run_log(output, seed);
+    #[cfg(feature = "parquet")]
+    let mut parquet = parquet_exporter(output);
+    let mut csv = stats_csv(output);
+    let mut hall = hall_of_fame(output, None);
+    let mut achievements = achievements(output, None);
+    // `--report run.html` (or `.md`) summarizes the run once it ends
+    let report_path = &output.report;
+    let mut report = report_path.as_ref().map(|_| RunReport::new(seed));
+    // `--export-tree tree.nwk` (or `.dot`) records the ancestry for the end
+    let mut phylogeny = output.export_tree.as_ref().map(|_| Phylogeny::new());
+    let mut stats = Stats::new();
+    let ticks = args.ticks;
+    let mut world = new_world(&args.world, &params, seed);
+    // A crashed run is only picked up with `--resume`
+    match saved_run(&args.autosave) {
+        Some(save) if args.autosave.resume => {
+            if let Some((saved, saved_params)) = resume_run(&save, seed) {
+                world = saved;
+                params = saved_params;
             }
         }
-        
-        if flee_dir.length() > 0.1 {
-            // Flee from predators
-            self.vel = (self.vel + flee_dir.normalize() * 0.5).normalize();
-        } else {
-            // Normal behavior: random jitter / steering
-            let jitter_angle = rng.gen_range(-0.2f32..0.2f32);
-            let new_angle = self.vel.y.atan2(self.vel.x) + jitter_angle;
-            self.vel = vec2(new_angle.cos(), new_angle.sin());
-
-            // Find nearest food
-            if !food.is_empty() {
-                 let mut nearest_dist = f32::MAX;
-                 let mut nearest_idx = Option::None;
-
-                 for (i, f) in food.iter().enumerate() {
-                     let d = self.pos.distance(*f);
-                     if d < self.dna.sense_radius && d < nearest_dist {
-                         nearest_dist = d;
-                         nearest_idx = Some(i);
-                     }
-                 }
-
-                 if let Some(idx) = nearest_idx {
-                     // Steer towards food
-                     let target = food[idx];
-                     let dir = (target - self.pos).normalize();
-                     self.vel = (self.vel + dir * 0.2).normalize();
-                 }
-            }
-        }
-
-        // Metabolism
-        let cost = (self.dna.speed * self.dna.speed * self.dna.size * 0.005) + 0.1;
-        self.energy -= cost * speed_mult;
-        self.age += 1.0;
-    }
-}
-
-struct Predator {
-    pos: Vec2,
-    vel: Vec2,
-    energy: f32,
-    speed: f32,
-    size: f32,
-    sense_radius: f32,
-}
-
-impl Predator {
-    fn new(pos: Vec2) -> Self {
-        let mut rng = ::rand::thread_rng();
-        let angle = rng.gen_range(0.0f32..std::f32::consts::TAU);
-        Self {
-            pos,
-            vel: vec2(angle.cos(), angle.sin()),
-            energy: 150.0,
-            speed: 2.5,
-            size: 12.0,
-            sense_radius: 100.0,
-        }
-    }
-
-    fn update(&mut self, screen_w: f32, screen_h: f32, bacteria: &Vec<Bacterium>, speed_mult: f32) {
-        let mut rng = ::rand::thread_rng();
-        
-        // Movement physics
-        self.pos += self.vel * self.speed * speed_mult;
-        
-        // Bounce off walls
-        if self.pos.x < 0.0 || self.pos.x > screen_w {
-            self.vel.x *= -1.0;
-            self.pos.x = self.pos.x.clamp(0.0, screen_w);
-        }
-        if self.pos.y < 0.0 || self.pos.y > screen_h {
-            self.vel.y *= -1.0;
-            self.pos.y = self.pos.y.clamp(0.0, screen_h);
-        }
-
-        // Hunt nearest bacterium
-        if !bacteria.is_empty() {
-            let mut nearest_dist = f32::MAX;
-            let mut nearest_pos = None;
-
-            for b in bacteria {
-                let d = self.pos.distance(b.pos);
-                if d < self.sense_radius && d < nearest_dist {
-                    nearest_dist = d;
-                    nearest_pos = Some(b.pos);
+        Some(save) => info!(target: SIM, "The last autosaving run didn't finish; pass --resume to continue from tick {}", save.tick),
+        None => {}
+    }
+    let mut autosave = start_autosave(&args.autosave, None);
+    let mut config = config_watch(&args.world);
+    let mut param_watch = ParamWatch::new(&params);
+    let mut throughput = (std::time::Instant::now(), world.tick);
+    #[cfg(feature = "http-api")]
+    let mut paused = false;
+    while ticks.is_none_or(|n| world.tick < n) {
+        // Wait here while paused through the API
+        #[cfg(feature = "http-api")]
+        if let Some(api) = &api {
+            loop {
+                api.handle_pending(&mut world, &mut params, &mut paused, |_| ());
+                if !paused {
+                    break;
                 }
+                std::thread::sleep(std::time::Duration::from_millis(10));
             }
+        }
+        reload_config(&mut config, &mut params);
+        let started = std::time::Instant::now();
+        world.step(&params);
+        if let Some(server) = &mut telemetry {
+            server.publish(&world);
+        }
+        #[cfg(feature = "sqlite")]
+        log_tick(&mut log, &world);
+        #[cfg(feature = "parquet")]
+        export_tick(&mut parquet, &world);
+        archive_tick(&mut hall, &world);
+        if let Some(phylogeny) = &mut phylogeny {
+            phylogeny.record(&world);
+        }
+        achieve_tick(&mut achievements, &world, &mut stats);
+        autosave_tick(&mut autosave, &world, &params);
+        if csv.is_some() || challenge.is_some() || report.is_some() {
+            stats.record(&world);
+            param_watch.annotate(&params, &mut stats, world.tick);
+            write_stats(&mut csv, &stats);
+            play_tick(&mut challenge, &mut stats, world.tick, None);
+            if let Some(report) = &mut report {
+                report.observe(&stats);
+            }
+        }
+        if let Some(metrics) = &mut metrics {
+            metrics.update(&world, started.elapsed().as_secs_f32());
+        }
+        if world.tick.is_multiple_of(PERF_LOG_EVERY) {
+            let (since, from) = throughput;
+            let rate = (world.tick - from) as f32 / since.elapsed().as_secs_f32();
+            debug!(target: PERF, tick = world.tick, bacteria = world.bacteria_count(), "{:.0} ticks/s", rate);
+            throughput = (std::time::Instant::now(), world.tick);
+        }
+    }
+    info!(target: SIM, tick = world.tick, "{} bacteria, {} predators, {} food", world.bacteria_count(), world.predator_count(), world.food_count());
+    if let Some(challenge) = &challenge {
+        for (objective, progress) in challenge.scenario.objectives.iter().zip(&challenge.progress) {
+            let status = match progress.status {
+                Status::Active => format!("{:.0}% done", progress.fraction * 100.0),
+                Status::Complete(tick) => format!("complete at tick {}", tick),
+                Status::Failed(tick) => format!("failed at tick {}", tick),
+            };
+            info!(target: SIM, "{}: {}", objective.goal, status);
+        }
+    }
+    if let (Some(report), Some(path)) = (&report, report_path) {
+        write_report(report, path, &params, &world);
+    }
+    if let (Some(phylogeny), Some(path)) = (&phylogeny, &output.export_tree) {
+        write_tree(phylogeny, path, world.tick, output.tree_sample);
+    }
+    if let Some(autosave) = &autosave {
+        autosave.finish();
+    }
+}
 
-            if let Some(target) = nearest_pos {
-                // Chase bacterium
-                let dir = (target - self.pos).normalize();
-                self.vel = (self.vel + dir * 0.3).normalize();
-            } else {
-                // Random wandering
-                let jitter_angle = rng.gen_range(-0.15f32..0.15f32);
-                let new_angle = self.vel.y.atan2(self.vel.x) + jitter_angle;
-                self.vel = vec2(new_angle.cos(), new_angle.sin());
-            }
-        }
-
-        // Metabolism - predators consume more energy
-        let cost = 0.2;
-        self.energy -= cost * speed_mult;
-    }
-}
-
-// UI Helper Functions
-fn draw_section_header(x: f32, y: f32, width: f32, text: &str, color: Color) -> f32 {
-    draw_text(text, x, y, 20.0, color);
-    draw_line(x, y + 5.0, x + width, y + 5.0, 2.0, color);
-    y + 25.0
-}
-
-fn draw_slider(x: f32, y: f32, width: f32, label: &str, value: &mut f32, min: f32, max: f32, unit: &str) {
-    let height = 22.0;
-    let (mouse_x, mouse_y) = mouse_position();
-    let mouse_down = is_mouse_button_down(MouseButton::Left);
-    
-    // Draw label with unit
-    let label_text = format!("{}", label);
-    draw_text(&label_text, x, y - 5.0, 16.0, WHITE);
-    
-    // Draw track
-    draw_rectangle(x, y, width, height, Color::new(0.15, 0.15, 0.15, 0.9));
-    draw_rectangle_lines(x, y, width, height, 1.0, Color::new(0.4, 0.4, 0.4, 0.8));
-    
-    // Calculate slider position
-    let norm = ((*value - min) / (max - min)).clamp(0.0, 1.0);
-    let handle_x = x + norm * width;
-    
-    // Draw fill with gradient effect
-    draw_rectangle(x, y, norm * width, height, Color::new(0.2, 0.5, 0.9, 0.9));
-    
-    // Draw handle
-    draw_rectangle(handle_x - 6.0, y - 2.0, 12.0, height + 4.0, Color::new(0.9, 0.95, 1.0, 1.0));
-    draw_rectangle_lines(handle_x - 6.0, y - 2.0, 12.0, height + 4.0, 1.0, Color::new(0.3, 0.3, 0.3, 1.0));
-    
-    // Draw value text with unit
-    let value_text = if unit == "%" {
-        format!("{:.0}{}", *value * 100.0, unit)
-    } else if unit == "x" {
-        format!("{:.1}{}", value, unit)
-    } else {
-        format!("{:.0} {}", value, unit)
+// `tournament a.json b.json` pits population files against each other:
+// `--trials` worlds (seeded one after another) run `--ticks` ticks each, and
+// the entrant with the most living descendants wins a trial
+fn run_tournament(params: SimulationParams, seed: u64, args: &TournamentArgs) {
+    let mut entrants = Vec::new();
+    for path in &args.entrants {
+        match population::load(path, params.world_width, params.world_height) {
+            Ok(seeds) => entrants.push(Entrant { name: path.to_string(), seeds }),
+            Err(e) => {
+                warn!("Couldn't load entrant {}: {}", path, e);
+                return;
+            }
+        }
+    }
+    let mut results = Vec::new();
+    for trial in 0..args.trials {
+        let result = tournament::run_trial(&entrants, &params, args.ticks, seed + trial, |seed| {
+            World::with_terrain(&params, seed, initial_terrain(&args.world, &params))
+        });
+        let counts: Vec<String> = entrants.iter().zip(&result.survivors).map(|(e, n)| format!("{} {}", e.name, n)).collect();
+        let winner = result.winner().map_or("draw", |i| entrants[i].name.as_str());
+        println!("trial {} (seed {}, {} ticks): {} -> {}", trial + 1, result.seed, result.ticks, counts.join(", "), winner);
+        results.push(result);
+    }
+    println!();
+    println!("{}", tournament::summary(&entrants, &results));
+}
+
+// `sweep plan.json` runs every combination of the plan's parameter values
+// (see `Sweep`) headless, `--jobs` at a time, over the config and world flags,
+// and writes a CSV row per run to `--out` or stdout
+fn run_sweep(args: &SweepArgs) {
+    let sweep = match Sweep::load(&args.plan) {
+        Ok(sweep) => sweep,
+        Err(e) => {
+            warn!("Couldn't load sweep {}: {}", args.plan, e);
+            return;
+        }
     };
-    draw_text(&value_text, x + width + 10.0, y + 16.0, 16.0, Color::new(0.8, 1.0, 0.8, 1.0));
-    
-    // Check for interaction
-    if mouse_down && mouse_x >= x && mouse_x <= x + width && mouse_y >= y - 5.0 && mouse_y <= y + height + 5.0 {
-        let new_norm = ((mouse_x - x) / width).clamp(0.0, 1.0);
-        *value = min + new_norm * (max - min);
-    }
-}
-
-fn draw_button(x: f32, y: f32, width: f32, height: f32, label: &str, color: Color) -> bool {
-    let (mouse_x, mouse_y) = mouse_position();
-    let mouse_clicked = is_mouse_button_pressed(MouseButton::Left);
-    
-    let hovered = mouse_x >= x && mouse_x <= x + width && mouse_y >= y && mouse_y <= y + height;
-    
-    let btn_color = if hovered {
-        Color::new((color.r * 1.3).min(1.0), (color.g * 1.3).min(1.0), (color.b * 1.3).min(1.0), color.a)
-    } else {
-        color
+    let (params, _) = setup(&args.world);
+    let jobs = args.jobs.unwrap_or_else(|| std::thread::available_parallelism().map_or(1, |n| n.get()));
+    let outcomes = match sweep.run(&params, jobs, |params, seed| new_world(&args.world, params, seed)) {
+        Ok(outcomes) => outcomes,
+        Err(e) => {
+            warn!("Couldn't run sweep {}: {}", args.plan, e);
+            return;
+        }
     };
-    
-    draw_rectangle(x, y, width, height, btn_color);
-    draw_rectangle_lines(x, y, width, height, 2.0, WHITE);
-    
-    // Center text
-    let text_size = 20.0;
-    let text_dims = measure_text(label, None, text_size as u16, 1.0);
-    draw_text(label, x + (width - text_dims.width) / 2.0, y + (height + text_size) / 2.0 - 2.0, text_size, WHITE);
-    
-    hovered && mouse_clicked
-}
-
-fn draw_line_graph(x: f32, y: f32, width: f32, height: f32, data: &Vec<f32>, color: Color, label: &str, max_val: Option<f32>) {
-    // Background with slight gradient
-    draw_rectangle(x, y, width, height, Color::new(0.08, 0.08, 0.1, 0.85));
-    draw_rectangle_lines(x, y, width, height, 2.0, Color::new(0.3, 0.3, 0.3, 0.6));
-    
-    if data.is_empty() {
-        return;
+    let written = match &args.out {
+        Some(path) => std::fs::File::create(path).and_then(|file| sweep.write_csv(&outcomes, std::io::BufWriter::new(file))),
+        None => sweep.write_csv(&outcomes, std::io::stdout().lock()),
+    };
+    match (written, &args.out) {
+        (Ok(()), Some(path)) => info!(target: SIM, "Saved {} runs to {}", outcomes.len(), path),
+        (Ok(()), None) => {}
+        (Err(e), _) => warn!("Couldn't write the sweep's results: {}", e),
     }
-    
-    // Find max value for scaling
-    let max_value = max_val.unwrap_or_else(|| {
-        data.iter().cloned().fold(f32::NEG_INFINITY, f32::max).max(1.0)
-    });
-    
-    // Draw grid lines
-    for i in 0..=4 {
-        let grid_y = y + (i as f32 / 4.0) * height;
-        draw_line(x, grid_y, x + width, grid_y, 1.0, Color::new(0.2, 0.2, 0.2, 0.4));
-    }
-    
-    // Draw data points with glow effect
-    let step = width / (MAX_HISTORY as f32);
-    for i in 0..data.len().saturating_sub(1) {
-        let x1 = x + (i as f32) * step;
-        let y1 = y + height - (data[i] / max_value * height).min(height);
-        let x2 = x + ((i + 1) as f32) * step;
-        let y2 = y + height - (data[i + 1] / max_value * height).min(height);
-        
-        // Glow effect
-        draw_line(x1, y1, x2, y2, 4.0, Color::new(color.r, color.g, color.b, 0.3));
-        draw_line(x1, y1, x2, y2, 2.0, color);
-    }
-    
-    // Draw label and current value with background
-    draw_rectangle(x + 3.0, y + 3.0, 150.0, 42.0, Color::new(0.0, 0.0, 0.0, 0.6));
-    draw_text(label, x + 8.0, y + 20.0, 18.0, WHITE);
-    if let Some(last) = data.last() {
-        let value_text = format!("{:.0}", last);
-        draw_text(&value_text, x + 8.0, y + 38.0, 22.0, color);
-    }
-    
-    // Draw max value
-    let max_text = format!("max: {:.0}", max_value);
-    draw_text(&max_text, x + width - 70.0, y + 18.0, 15.0, Color::new(0.7, 0.7, 0.7, 1.0));
-}
-
-fn draw_ui_panel(params: &mut SimulationParams, ui_state: &mut UIState, bacteria_count: usize, food_count: usize, predator_count: usize) {
-    let panel_x = SCREEN_WIDTH - UI_PANEL_WIDTH;
-    let panel_y = 0.0;
-    
-    // Dark background with border
-    draw_rectangle(panel_x, panel_y, UI_PANEL_WIDTH, SCREEN_HEIGHT, Color::new(0.02, 0.02, 0.03, 0.95));
-    draw_line(panel_x, 0.0, panel_x, SCREEN_HEIGHT, 3.0, Color::new(0.3, 0.4, 0.5, 0.8));
-    
-    let mut current_y = 25.0;
-    let slider_width = UI_PANEL_WIDTH - 140.0;
-    let x_offset = panel_x + 20.0;
-    
-    // Main Title
-    draw_text("SIMULATION", x_offset, current_y, 28.0, Color::new(0.4, 0.7, 1.0, 1.0));
-    current_y += 40.0;
-    
-    // STATS SECTION
-    current_y = draw_section_header(x_offset, current_y, slider_width + 60.0, "📊 POPULATIONS", Color::new(0.3, 0.9, 0.3, 1.0));
-    draw_text(&format!("🦠 Bacteria: {}", bacteria_count), x_offset, current_y, 18.0, Color::new(0.5, 1.0, 0.5, 1.0));
-    current_y += 23.0;
-    draw_text(&format!("🍃 Food: {}", food_count), x_offset, current_y, 18.0, Color::new(0.3, 0.9, 0.5, 1.0));
-    current_y += 23.0;
-    draw_text(&format!("🦖 Predators: {}", predator_count), x_offset, current_y, 18.0, Color::new(1.0, 0.4, 0.3, 1.0));
-    current_y += 45.0;
-    
-    // SIMULATION SECTION
-    current_y = draw_section_header(x_offset, current_y, slider_width + 60.0, "⚙️ SIMULATION", Color::new(0.5, 0.8, 1.0, 1.0));
-    draw_slider(x_offset, current_y, slider_width, "Food/Frame", &mut params.food_growth_rate, 0.0, 10.0, "/f");
-    current_y += 50.0;
-    
-    draw_slider(x_offset, current_y, slider_width, "Sim. Speed", &mut params.speed_multiplier, 0.1, 3.0, "x");
-    current_y += 55.0;
-    
-    // EVOLUTION SECTION
-    current_y = draw_section_header(x_offset, current_y, slider_width + 60.0, "🧬 EVOLUTION", Color::new(0.9, 0.5, 0.9, 1.0));
-    draw_slider(x_offset, current_y, slider_width, "Mutation Rate", &mut params.mutation_rate, 0.0, 0.5, "%");
-    current_y += 50.0;
-    
-    draw_slider(x_offset, current_y, slider_width, "Mutation Str.", &mut params.mutation_strength, 0.0, 0.5, "%");
-    current_y += 55.0;
-    
-    // ENERGY SECTION
-    current_y = draw_section_header(x_offset, current_y, slider_width + 60.0, "⚡ ENERGY", Color::new(1.0, 0.9, 0.3, 1.0));
-    draw_slider(x_offset, current_y, slider_width, "Initial Energy", &mut params.initial_energy, 50.0, 200.0, "");
-    current_y += 50.0;
-    
-    draw_slider(x_offset, current_y, slider_width, "Reproduction", &mut params.reproduction_threshold, 50.0, 300.0, "");
-    current_y += 55.0;
-    
-    // PREDATORS SECTION
-    current_y = draw_section_header(x_offset, current_y, slider_width + 60.0, "🦖 PREDATORS", Color::new(1.0, 0.4, 0.3, 1.0));
-    draw_slider(x_offset, current_y, slider_width, "Pred. Repro.", &mut params.predator_reproduction_threshold, 100.0, 400.0, "");
-    current_y += 60.0;
-    
-    // CONTROLS
-    let btn_width = (UI_PANEL_WIDTH - 50.0) / 2.0;
-    if draw_button(x_offset, current_y, btn_width, 40.0, 
-                    if ui_state.paused { "▶ PLAY" } else { "⏸ PAUSE" },
-                    Color::new(0.2, 0.5, 0.9, 0.95)) {
-        ui_state.paused = !ui_state.paused;
-    }
-    
-    // Instructions at bottom
-    current_y = SCREEN_HEIGHT - 70.0;
-    draw_rectangle(x_offset - 10.0, current_y - 15.0, UI_PANEL_WIDTH - 20.0, 60.0, Color::new(0.1, 0.1, 0.1, 0.5));
-    draw_text("CONTROLS:", x_offset, current_y, 16.0, Color::new(0.7, 0.7, 0.7, 1.0));
-    current_y += 20.0;
-    draw_text("TAB   →  Show/Hide UI", x_offset, current_y, 14.0, DARKGRAY);
-    current_y += 18.0;
-    draw_text("SPACE →  Pause", x_offset, current_y, 14.0, DARKGRAY);
-}
-
-fn draw_graphs_panel(stats: &Stats) {
-    let panel_y = SCREEN_HEIGHT - GRAPH_HEIGHT - 10.0;
-    let graph_width = (SCREEN_WIDTH - UI_PANEL_WIDTH - 50.0) / 4.0;
-    let x_start = 10.0;
-    
-    // Population graph
-    draw_line_graph(x_start, panel_y, graph_width, GRAPH_HEIGHT, 
-                     &stats.population_history, 
-                     Color::new(0.3, 1.0, 0.3, 1.0), 
-                     "🦠 Bacteria", None);
-    
-    // Predator graph
-    draw_line_graph(x_start + graph_width + 10.0, panel_y, graph_width, GRAPH_HEIGHT,
-                     &stats.predator_history,
-                     Color::new(1.0, 0.4, 0.3, 1.0),
-                     "🦖 Predators", None);
-    
-    // Speed graph
-    draw_line_graph(x_start + (graph_width + 10.0) * 2.0, panel_y, graph_width, GRAPH_HEIGHT,
-                     &stats.avg_speed_history,
-                     Color::new(0.9, 0.7, 0.2, 1.0),
-                     "⚡ Speed", Some(5.0));
-    
-    // Size graph
-    draw_line_graph(x_start + (graph_width + 10.0) * 3.0, panel_y, graph_width, GRAPH_HEIGHT,
-                     &stats.avg_size_history,
-                     Color::new(0.5, 0.5, 1.0, 1.0),
-                     "📏 Size", Some(15.0));
-}
-
-#[macroquad::main("Bacterial Ecosystem")]
-async fn main() {
-    request_new_screen_size(SCREEN_WIDTH, SCREEN_HEIGHT);
-    macroquad::rand::srand(macroquad::miniquad::date::now() as u64);
+}
 
-    let mut params = SimulationParams::default();
-    let mut ui_state = UIState::default();
+// `bench` times `--ticks` ticks at each of `--populations`, the world scaled
+// to keep the windowed default's density and food following its area
+fn run_bench(args: &BenchArgs) {
+    println!("{:>10}  {:>10}  {:>8}", "bacteria", "ticks/s", "ms/tick");
+    for &population in &args.populations {
+        let (mut world, params) = bench::world(population, args.sense_stride, args.seed);
+        let started = std::time::Instant::now();
+        for _ in 0..args.ticks {
+            world.step(&params);
+        }
+        let seconds = started.elapsed().as_secs_f64();
+        println!("{:>10}  {:>10.1}  {:>8.3}", population, args.ticks as f64 / seconds, seconds * 1000.0 / args.ticks as f64);
+    }
+}
+
+// The 2D view, carrying on from `replay` if given (the `replay` subcommand)
+async fn run_2d(mut params: SimulationParams, seed: u64, keys: Keymap, mut challenge: Option<Challenge>, args: SessionArgs, replay: Option<WorldSave>) {
+    let output = &args.output;
+    let mut ui_state = UIState { keymap: keys, ..UIState::default() };
     let mut stats = Stats::new();
-    
-    let mut bacteria: Vec<Bacterium> = Vec::new();
-    let mut predators: Vec<Predator> = Vec::new();
-    let mut food: Vec<Vec2> = Vec::new();
-
-    // Initialize population
-    let initial_bacteria = 50;
-    for _ in 0..initial_bacteria {
-        bacteria.push(Bacterium::new(vec2(
-            macroquad::rand::gen_range(0.0f32, SCREEN_WIDTH - UI_PANEL_WIDTH),
-            macroquad::rand::gen_range(0.0f32, SCREEN_HEIGHT - GRAPH_HEIGHT),
-        ), params.initial_energy));
-    }
-
-    // Initialize predators
-    let initial_predators = params.predator_count as usize;
-    for _ in 0..initial_predators {
-        predators.push(Predator::new(vec2(
-            macroquad::rand::gen_range(0.0f32, SCREEN_WIDTH - UI_PANEL_WIDTH),
-            macroquad::rand::gen_range(0.0f32, SCREEN_HEIGHT - GRAPH_HEIGHT),
-        )));
-    }
-
-    // Initialize food
-    let initial_food = 200;
-    for _ in 0..initial_food {
-        food.push(vec2(
-            macroquad::rand::gen_range(0.0f32, SCREEN_WIDTH - UI_PANEL_WIDTH),
-            macroquad::rand::gen_range(0.0f32, SCREEN_HEIGHT - GRAPH_HEIGHT),
-        ));
+    let mut landscape = FitnessLandscape::new();
+    let mut muller = MullerPlot::new();
+    let mut phylogeny = Phylogeny::new();
+    let sprites = Sprites::new();
+    let mut lod = LodController::new();
+    let mut species = Species::default();
+    let mut hall = hall_of_fame(output, Some(HALL_OF_FAME_PATH));
+    let mut achievements = achievements(output, Some(ACHIEVEMENTS_PATH));
+    // Summary report, on demand and (with `--report`) on closing the window
+    let report_path = &output.report;
+    let mut report = RunReport::new(seed);
+    // A crashed run's autosave is offered before autosaving starts over it,
+    // unless a saved world is being replayed
+    let mut recovery = if replay.is_none() { saved_run(&args.autosave) } else { None };
+    let mut recovery_choice = None;
+    // A world to carry on from: the replayed or recovered save, or one loaded
+    // in the console
+    let mut loaded = replay;
+    let mut console = Console::default();
+    let mut autosave = start_autosave(&args.autosave, Some(AUTOSAVE_EVERY));
+    if report_path.is_some() || output.export_tree.is_some() || autosave.is_some() {
+        prevent_quit();
+    }
+    let mut audio = start_audio(&mut ui_state, args.window.mute).await;
+    let mut profiler = Profiler::new();
+
+    let mut telemetry = telemetry_server(output);
+    let mut metrics = metrics_exporter(output);
+    #[cfg(feature = "http-api")]
+    let api = control_api(output);
+    #[cfg(feature = "sqlite")]
+    let mut log = run_log(output, seed);
+    #[cfg(feature = "parquet")]
+    let mut parquet = parquet_exporter(output);
+    let mut csv = stats_csv(output);
+    let baseline = baseline(&args.window);
+    let mut world = new_world(&args.world, &params, seed);
+    if args.autosave.resume && recovery.is_some() {
+        recovery_choice = Some(true);
+    } else if recovery.is_some() {
+        ui_state.paused = true;
     }
+    let mut param_watch = ParamWatch::new(&params);
+    let mut undo = ParamUndo::new(&params);
+    let mut config = config_watch(&args.world);
+    let mut timelapse = timelapse(&args.window);
+    let clip = clip_settings(&args.window);
+    let mut recorder = None;
+    let mut throttle = TickThrottle::new(args.window.ticks_per_frame.unwrap_or(1), args.window.frame_budget);
+    let mut sim = args.window.sim_thread.then(SimThread::start);
+    let mut camera = Camera::new(Layout::compute(ui_state.show_ui).sim, world.width, world.height);
 
     loop {
-        let sim_w = SCREEN_WIDTH - UI_PANEL_WIDTH;
-        let sim_h = SCREEN_HEIGHT - GRAPH_HEIGHT;
-
-        // Handle input
-        if is_key_pressed(KeyCode::Tab) {
+        let frame_start = std::time::Instant::now();
+        let mut batch = sim.as_mut().and_then(|sim| sim.poll(&mut world));
+        // Handle input; shortcuts wait while a slider value, preset name or
+        // console command is being typed
+        let typing = slider_entry_active() || ui_state.naming_preset;
+        if !typing && keys.pressed(Action::Console) {
+            console.open = !console.open;
+            // Drop the key's own character
+            while get_char_pressed().is_some() {}
+        }
+        let shortcuts = !typing && !console.open;
+        let pressed = |action| shortcuts && keys.pressed(action);
+        log_actions(shortcuts, &keys);
+        if pressed(Action::ToggleUi) {
             ui_state.show_ui = !ui_state.show_ui;
         }
-        if is_key_pressed(KeyCode::Space) {
+        if pressed(Action::Pause) {
             ui_state.paused = !ui_state.paused;
         }
-
-        // Update Game State (only if not paused)
-        if !ui_state.paused {
-            // Add random food
-            if food.len() < params.max_food {
-                let to_add = params.food_growth_rate as usize;
-                for _ in 0..to_add {
-                     food.push(vec2(
-                        macroquad::rand::gen_range(0.0f32, sim_w),
-                        macroquad::rand::gen_range(0.0f32, sim_h),
-                    ));
+        let step = ui_state.paused && pressed(Action::Step);
+        change_speed(&mut params, pressed(Action::SpeedUp), pressed(Action::SpeedDown));
+        reload_config(&mut config, &mut params);
+        let mut replacement = None;
+        if pressed(Action::Reset) {
+            let seed = macroquad::miniquad::date::now() as u64;
+            info!(target: UI, "Restarted with seed {}", seed);
+            replacement = Some((new_world(&args.world, &params, seed), seed));
+        }
+        if let Some(resume) = recovery_choice.take() {
+            if resume {
+                loaded = recovery.take();
+            }
+            recovery = None;
+            ui_state.paused = false;
+        }
+        if let Some(save) = loaded.take()
+            && let Some((saved, saved_params)) = resume_run(&save, seed)
+        {
+            params = saved_params;
+            param_watch = ParamWatch::new(&params);
+            undo = ParamUndo::new(&params);
+            replacement = Some((saved, seed));
+        }
+        if let Some((new_world, seed)) = replacement {
+            if let Some(sim) = &mut sim {
+                sim.cancel();
+                batch = None;
+            }
+            world = new_world;
+            camera = Camera::new(Layout::compute(ui_state.show_ui).sim, world.width, world.height);
+            stats = Stats::new();
+            landscape.new_world();
+            muller.new_world();
+            phylogeny.new_world();
+            ui_state.selected = None;
+            if let Some(hall) = &mut hall {
+                hall.new_world();
+            }
+            if let Some(challenge) = &mut challenge {
+                challenge.restart();
+            }
+            if let Some(achievements) = &mut achievements {
+                achievements.new_world();
+            }
+            report = RunReport::new(seed);
+        }
+        if pressed(Action::Record) {
+            ui_state.recording = !ui_state.recording;
+        }
+        if pressed(Action::GraphSettings) {
+            ui_state.show_graph_settings = !ui_state.show_graph_settings;
+        }
+        if pressed(Action::Correlations) {
+            ui_state.show_correlations = !ui_state.show_correlations;
+        }
+        if pressed(Action::FitnessLandscape) {
+            ui_state.show_landscape = !ui_state.show_landscape;
+        }
+        if pressed(Action::Selection) {
+            ui_state.show_selection = !ui_state.show_selection;
+        }
+        if pressed(Action::Muller) {
+            ui_state.show_muller = !ui_state.show_muller;
+        }
+        if pressed(Action::HallOfFame) {
+            ui_state.show_hall_of_fame = !ui_state.show_hall_of_fame;
+        }
+        if pressed(Action::Achievements) {
+            ui_state.show_achievements = !ui_state.show_achievements;
+        }
+        if pressed(Action::ImportPopulation) || std::mem::take(&mut ui_state.import_requested) {
+            settle(&mut sim, &mut world);
+            if import_population(&mut world, &params, &population_path(&args.world)) {
+                ui_state.selected = None;
+                stats.add_marker(world.tick, "population imported", Color::new(0.6, 0.9, 1.0, 1.0));
+            }
+        }
+        if pressed(Action::ExportTree) || std::mem::take(&mut ui_state.export_tree_requested) {
+            for extension in ["nwk", "dot"] {
+                let path = format!("{}/tree_{:08}.{}", TREE_DIR, world.tick, extension);
+                write_tree(&phylogeny, &path, world.tick, output.tree_sample);
+            }
+        }
+        if pressed(Action::Profiler) {
+            profiler.visible = !profiler.visible;
+        }
+        if pressed(Action::ClearSelection) {
+            ui_state.selected = None;
+        }
+        if pressed(Action::Fullscreen) {
+            ui_state.fullscreen = !ui_state.fullscreen;
+            set_fullscreen(ui_state.fullscreen);
+        }
+        if pressed(Action::Pollution) {
+            ui_state.show_pollution = !ui_state.show_pollution;
+        }
+        if pressed(Action::Oxygen) {
+            ui_state.show_oxygen = !ui_state.show_oxygen;
+        }
+        if pressed(Action::Resistance) {
+            ui_state.show_resistance = !ui_state.show_resistance;
+        }
+        if pressed(Action::ColorMode) {
+            ui_state.color_mode = ui_state.color_mode.next();
+        }
+        if pressed(Action::Mute) {
+            ui_state.sound.muted = !ui_state.sound.muted;
+        }
+        if pressed(Action::SenseOverlay) {
+            ui_state.debug.sense = !ui_state.debug.sense;
+        }
+        if pressed(Action::VelocityOverlay) {
+            ui_state.debug.velocity = !ui_state.debug.velocity;
+        }
+        if pressed(Action::TargetOverlay) {
+            ui_state.debug.targets = !ui_state.debug.targets;
+        }
+        if pressed(Action::TerrainEditor) {
+            ui_state.terrain_brush = match ui_state.terrain_brush {
+                Some(_) => None,
+                None => Some(TerrainType::Fertile),
+            };
+        }
+        if shortcuts && ui_state.terrain_brush.is_some() {
+            let keys = [KeyCode::Key1, KeyCode::Key2, KeyCode::Key3, KeyCode::Key4, KeyCode::Key5, KeyCode::Key6, KeyCode::Key7];
+            for (key, kind) in keys.into_iter().zip(TerrainType::ALL) {
+                if is_key_pressed(key) {
+                    ui_state.terrain_brush = Some(kind);
                 }
             }
-
-            let mut next_gen_bacteria = Vec::new();
-            let mut next_gen_predators = Vec::new();
-            let mut eaten_food = std::collections::HashSet::new();
-            let mut eaten_bacteria = std::collections::HashSet::new();
-
-            // Update bacteria
-            for (idx, b) in bacteria.iter_mut().enumerate() {
-                b.update(sim_w, sim_h, &food, params.speed_multiplier, &predators);
-
-                // Eat food
-                for (i, f) in food.iter().enumerate() {
-                    if !eaten_food.contains(&i) {
-                         if b.pos.distance(*f) < b.dna.size + 2.0 {
-                             b.energy += 30.0;
-                             eaten_food.insert(i);
-                         }
+            let ctrl = is_key_down(KeyCode::LeftControl) || is_key_down(KeyCode::RightControl);
+            if ctrl && is_key_pressed(KeyCode::S) && let Err(e) = std::fs::write(TERRAIN_FILE, world.terrain.to_text()) {
+                warn!("Couldn't save terrain to {}: {}", TERRAIN_FILE, e);
+            }
+        }
+        let layout = Layout::compute(ui_state.show_ui);
+
+        camera.view = layout.sim;
+        camera.handle_input(world.width, world.height);
+
+        // Click the minimap to jump there, or elsewhere in the sim area to select an
+        // organism (or paint terrain while the editor is open)
+        let mouse = Vec2::from(mouse_position());
+        let show_minimap = !camera.shows_whole_world(world.width, world.height);
+        let over_minimap = show_minimap && minimap_rect(&world, &camera).contains(mouse);
+        let over_popup = (ui_state.show_graph_settings && graph_settings_rect(&layout).contains(mouse))
+            || (ui_state.show_landscape && fitness_landscape_rect(&layout).contains(mouse))
+            || (ui_state.show_muller && muller_plot_rect(&layout).contains(mouse))
+            || (ui_state.selected.is_some() && inspector_rect(&layout).contains(mouse))
+            || (ui_state.show_hall_of_fame && hall.is_some() && hall_of_fame_rect(&layout).contains(mouse))
+            || (recovery.is_some() && recovery_rect(&layout).contains(mouse))
+            || (console.open && console_rect(&layout).contains(mouse));
+        if is_mouse_button_pressed(MouseButton::Left) && over_minimap {
+            camera.center = minimap_to_world(&world, &camera, mouse);
+        } else if camera.view.contains(mouse) && !over_minimap && !over_popup {
+            match ui_state.terrain_brush {
+                Some(kind) => {
+                    if is_mouse_button_down(MouseButton::Left) && !terrain_toolbar_rect(&layout).contains(mouse) {
+                        settle(&mut sim, &mut world);
+                        world.terrain.paint(camera.screen_to_world(mouse), BRUSH_RADIUS, kind);
                     }
                 }
-
-                // Check if eaten by predator
-                let mut is_eaten = false;
-                for p in &predators {
-                    if b.pos.distance(p.pos) < p.size + b.dna.size {
-                        eaten_bacteria.insert(idx);
-                        is_eaten = true;
-                        break;
+                None => {
+                    if is_mouse_button_pressed(MouseButton::Left) {
+                        ui_state.selected = world.organism_at(camera.screen_to_world(mouse), 6.0 / camera.scale);
                     }
                 }
-
-                // Reproduce (if not eaten)
-                if !is_eaten && b.energy > params.reproduction_threshold {
-                    b.energy *= 0.5;
-                    let offspring = Bacterium {
-                        pos: b.pos,
-                        vel: -b.vel,
-                        dna: b.dna.mutate(&params),
-                        energy: b.energy,
-                        age: 0.0,
-                    };
-                    next_gen_bacteria.push(offspring);
-                }
             }
+        }
 
-            // Update predators
-            for p in predators.iter_mut() {
-                p.update(sim_w, sim_h, &bacteria, params.speed_multiplier);
+        // Requests wait while a batch is running
+        #[cfg(feature = "http-api")]
+        if let Some(api) = &api
+            && sim.as_ref().is_none_or(SimThread::idle)
+        {
+            api.handle_pending(&mut world, &mut params, &mut ui_state.paused, |world| settle(&mut sim, world));
+        }
 
-                // Eat bacteria
-                for (i, b) in bacteria.iter().enumerate() {
-                    if !eaten_bacteria.contains(&i) {
-                        if p.pos.distance(b.pos) < p.size + b.dna.size {
-                            p.energy += 80.0; // Predators gain energy from eating
-                            eaten_bacteria.insert(i);
-                        }
-                    }
+        // Update Game State (only if not paused, or stepping), as many ticks
+        // as the throttle allows this frame. With `--sim-thread` the worker
+        // runs them, and the trackers below see the world once its batch is
+        // done: saves and exports due within it happen then, and the
+        // ancestry trackers pick up its births from the world. The stats
+        // still get every tick, taken by the worker as it ran them.
+        let mut events = Vec::new();
+        let ticks = if !sim.as_ref().is_none_or(SimThread::idle) {
+            0
+        } else if ui_state.paused {
+            step as u32
+        } else {
+            throttle.ticks()
+        };
+        let tick_start = std::time::Instant::now();
+        let mut tick_stats = Vec::new();
+        let (ran, tracked) = match batch {
+            Some(batch) => {
+                events = batch.events;
+                tick_stats = batch.stats;
+                for (name, ms) in batch.timings {
+                    profiler.record(name, ms);
                 }
-
-                // Reproduce
-                if p.energy > params.predator_reproduction_threshold {
-                    p.energy *= 0.5;
-                    let offspring = Predator {
-                        pos: p.pos,
-                        vel: -p.vel,
-                        energy: p.energy,
-                        speed: p.speed,
-                        size: p.size,
-                        sense_radius: p.sense_radius,
-                    };
-                    next_gen_predators.push(offspring);
+                (batch.ticks, 1)
+            }
+            None if sim.is_some() => (0, 0),
+            None => (ticks, ticks),
+        };
+        for _ in 0..tracked {
+            if sim.is_none() {
+                events.extend(world.step(&params));
+                for &(name, ms) in &world.timings {
+                    profiler.record(name, ms);
                 }
+                tick_stats.push(TickStats::of(&world));
+            }
+            if let Some(server) = &mut telemetry {
+                server.publish(&world);
             }
+            #[cfg(feature = "sqlite")]
+            log_tick(&mut log, &world);
+            #[cfg(feature = "parquet")]
+            export_tick(&mut parquet, &world);
+            archive_tick(&mut hall, &world);
+            phylogeny.record(&world);
+            muller.record(&world);
+            achieve_tick(&mut achievements, &world, &mut stats);
+            autosave_tick(&mut autosave, &world, &params);
+        }
+        let tick_time = tick_start.elapsed().as_secs_f32();
+        if let Some(sim) = &mut sim
+            && ticks > 0
+        {
+            sim.run(&mut world, &params, ticks);
+        }
+        if let Some(audio) = &mut audio {
+            audio.update(&ui_state.sound, &events, world.predator_count());
+        }
 
-            // Remove eaten food
-            let mut new_food = Vec::new();
-            for (i, f) in food.iter().enumerate() {
-                if !eaten_food.contains(&i) {
-                    new_food.push(*f);
-                }
+        // Calculate stats
+        // Every tick run since the last frame, or the world again if none was
+        profiler.time("stats", || {
+            if tick_stats.is_empty() {
+                stats.record(&world);
+            } else {
+                stats.record_ticks(&world, tick_stats);
+            }
+            landscape.record(&world, ui_state.landscape_genes);
+        });
+        play_tick(&mut challenge, &mut stats, world.tick, Some(get_time()));
+        if pressed(Action::Undo) || std::mem::take(&mut ui_state.undo_requested) {
+            undo.undo(&mut params);
+        }
+        if !is_mouse_button_down(MouseButton::Left) {
+            undo.record(&params);
+            param_watch.annotate(&params, &mut stats, world.tick);
+        }
+        report.observe(&stats);
+        if pressed(Action::Report) {
+            write_report(&report, &format!("{}/run_{:08}.html", REPORT_DIR, world.tick), &params, &world);
+        }
+        if is_quit_requested() {
+            if let Some(path) = report_path {
+                write_report(&report, path, &params, &world);
+            }
+            if let Some(path) = &output.export_tree {
+                write_tree(&phylogeny, path, world.tick, output.tree_sample);
+            }
+            if let Some(autosave) = &autosave {
+                autosave.finish();
             }
-            food = new_food;
+            break;
+        }
+        write_stats(&mut csv, &stats);
+        if let Some(metrics) = &mut metrics {
+            metrics.update(&world, get_frame_time());
+        }
+        let count = world.bacteria_count();
+        let predator_count = world.predator_count();
 
-            // Remove eaten bacteria (iterate backwards to preserve indices)
-            let mut indices: Vec<_> = eaten_bacteria.iter().collect();
-            indices.sort_by(|a, b| b.cmp(a)); // Sort descending
-            for &idx in indices {
-                if idx < bacteria.len() {
-                    bacteria.swap_remove(idx);
-                }
+        // Draw
+        if pressed(Action::ExportCharts) {
+            export_chart_files(&stats, &world.habitat.regions, &ui_state.graphs);
+        }
+        clear_background(Color::new(0.03f32, 0.03f32, 0.05f32, 1.0f32));
+
+        let level = lod.update(get_frame_time(), count + predator_count, camera.scale);
+        let coloring = Coloring::new(ui_state.color_mode, ui_state.palette, &world, &mut species);
+        profiler.time("rendering", || {
+            set_camera(&camera.to_camera2d());
+            draw_world(&world, &sprites, level, camera.visible_rect(), &coloring, ui_state.shapes.then_some(params.reproduction_threshold));
+            if ui_state.show_oxygen {
+                draw_oxygen(&world, camera.visible_rect());
+            }
+            if ui_state.show_pollution {
+                draw_pollution(&world, camera.visible_rect(), params.waste_threshold);
+            }
+            if ui_state.show_resistance {
+                draw_resistance(&world, &params, camera.visible_rect());
+            }
+            draw_debug_overlays(&world, camera.visible_rect(), ui_state.debug);
+            if let Some(id) = ui_state.selected {
+                draw_selection(&world, id);
+            }
+            set_default_camera();
+
+            if show_minimap {
+                draw_minimap(&world, &camera, ui_state.palette);
+            }
+            if ui_state.terrain_brush.is_some() {
+                draw_terrain_toolbar(&mut ui_state, &layout);
+            }
+            draw_color_legend(&coloring, &layout);
+            if let Some(challenge) = &mut challenge {
+                draw_scenario(challenge, &layout, ui_state.terrain_brush.is_some());
             }
 
-            // Add offspring
-            bacteria.append(&mut next_gen_bacteria);
-            predators.append(&mut next_gen_predators);
+            // Draw graphs
+            draw_graphs_panel(&stats, &world.habitat.regions, baseline.as_ref(), &layout, &mut ui_state);
+        });
 
-            // Remove dead bacteria and predators
-            bacteria.retain(|b| b.energy > 0.0);
-            predators.retain(|p| p.energy > 0.0);
+        // Draw UI
+        if ui_state.show_ui {
+            profiler.time("ui", || {
+                draw_ui_panel(&mut params, &mut ui_state, &layout, count, stats.selection.effective_size(), world.food_count(), predator_count);
+                if !world.habitat.is_open() {
+                    draw_region_panel(&mut world, &layout);
+                }
+            });
+        }
 
-            // Fail safe if extinction
-            if bacteria.is_empty() {
-                 for _ in 0..10 {
-                    bacteria.push(Bacterium::new(vec2(
-                        macroquad::rand::gen_range(0.0f32, sim_w),
-                        macroquad::rand::gen_range(0.0f32, sim_h),
-                    ), params.initial_energy));
-                 }
+        // Forget the selection once the organism is gone
+        if let Some(id) = ui_state.selected {
+            if world.ecs.contains(id) {
+                draw_inspector(&world, id, &phylogeny, &mut ui_state, &layout);
+            } else {
+                ui_state.selected = None;
             }
         }
+        if ui_state.show_correlations {
+            draw_correlation_panel(&stats, &layout);
+        }
+        if ui_state.show_landscape {
+            draw_fitness_landscape(&landscape, &mut ui_state, &layout);
+        }
+        if ui_state.show_selection {
+            draw_selection_panel(&stats, &layout);
+        }
+        if ui_state.show_muller {
+            draw_muller_plot(&muller, ui_state.palette, &layout);
+        }
+        if ui_state.show_graph_settings {
+            draw_graph_settings(&mut ui_state, &layout);
+        }
+        if let Some(save) = &recovery {
+            recovery_choice = draw_recovery(save.tick, &layout);
+        }
+        if console.open && draw_console(&mut console, &params, &layout) {
+            settle(&mut sim, &mut world);
+            loaded = console.submit(&mut world, &mut params).or(loaded);
+        }
+        if ui_state.show_achievements && let Some(achievements) = &achievements {
+            draw_achievements(achievements, &layout);
+        }
+        if ui_state.show_hall_of_fame
+            && let Some(library) = &hall
+            && let Some(dna) = draw_hall_of_fame(library, &mut ui_state, &layout)
+        {
+            settle(&mut sim, &mut world);
+            world.spawn_clones(&dna, CLONES_REINTRODUCED, params.initial_energy);
+            info!(target: UI, "Reintroduced {} clones", CLONES_REINTRODUCED);
+        }
 
-        // Calculate stats
-        let count = bacteria.len();
-        let mut total_speed = 0.0;
-        let mut total_size = 0.0;
-        for b in &bacteria {
-            total_speed += b.dna.speed;
-            total_size += b.dna.size;
-        }
-        let avg_speed = if count > 0 { total_speed / count as f32 } else { 0.0 };
-        let avg_size = if count > 0 { total_size / count as f32 } else { 0.0 };
-        
-        stats.push(count as f32, avg_speed, avg_size, predators.len() as f32);
+        capture_frame(world.tick, &mut timelapse, pressed(Action::Screenshot));
+        update_recording(&mut ui_state, &mut recorder, &clip, layout.sim, world.tick);
 
-        // Draw
-        clear_background(Color::new(0.03f32, 0.03f32, 0.05f32, 1.0f32));
+        // Draw FPS and the ticks per second actually run, in orange while the
+        // frame budget holds them back
+        draw_text(format!("FPS: {}  LOD: {}", get_fps(), level.label()), 10.0, 25.0, 20.0, Color::new(0.3, 1.0, 0.3, 1.0));
+        let (tps, tps_color) = if throttle.throttled() {
+            (format!("TPS: {:.0} (throttled, {:.2}/frame)", throttle.ticks_per_second, throttle.rate), Color::new(1.0, 0.6, 0.2, 1.0))
+        } else {
+            (format!("TPS: {:.0}", throttle.ticks_per_second), Color::new(0.3, 1.0, 0.3, 1.0))
+        };
+        draw_text(&tps, 220.0, 25.0, 20.0, tps_color);
 
-        // Draw food
-        for f in &food {
-            draw_circle(f.x, f.y, 2.5, Color::new(0.2f32, 1.0f32, 0.6f32, 0.7f32));
-            draw_circle(f.x, f.y, 1.5, Color::new(0.5f32, 1.0f32, 0.8f32, 0.9f32));
-        }
+        profiler.end_frame();
+        profiler.draw(10.0, 40.0);
+
+        throttle.update(ran, tick_time, frame_start.elapsed().as_secs_f32(), get_frame_time());
+        next_frame().await
+    }
+}
+
+// The optional 3D mode: the box seen through an orbit camera, with the same
+// panel and graphs as the 2D view
+async fn run_3d(mut params: SimulationParams, seed: u64, keys: Keymap, args: SessionArgs) {
+    let mut ui_state = UIState { keymap: keys, ..UIState::default() };
+    let mut stats = Stats::new();
+    let mut csv = stats_csv(&args.output);
+    let baseline = baseline(&args.window);
+    let mut param_watch = ParamWatch::new(&params);
+    let mut undo = ParamUndo::new(&params);
+    let mut config = config_watch(&args.world);
+    let mut timelapse = timelapse(&args.window);
+    let clip = clip_settings(&args.window);
+    let mut recorder = None;
+    let mut world = World3D::in_box(&params, seed);
+    let mut camera = OrbitCamera::new(Layout::compute(ui_state.show_ui).sim, world.extent());
+    let mut audio = start_audio(&mut ui_state, args.window.mute).await;
 
-        // Draw bacteria
-        for b in &bacteria {
-            // Glow effect
-            draw_circle(b.pos.x, b.pos.y, b.dna.size + 2.0, Color::new(b.dna.color.r, b.dna.color.g, b.dna.color.b, 0.2));
-            draw_circle(b.pos.x, b.pos.y, b.dna.size, b.dna.color);
+    loop {
+        // Shortcuts wait while a slider value or preset name is being typed
+        let shortcuts = !slider_entry_active() && !ui_state.naming_preset;
+        let pressed = |action| shortcuts && keys.pressed(action);
+        log_actions(shortcuts, &keys);
+        if pressed(Action::ToggleUi) {
+            ui_state.show_ui = !ui_state.show_ui;
+        }
+        if pressed(Action::Pause) {
+            ui_state.paused = !ui_state.paused;
+        }
+        let step = ui_state.paused && pressed(Action::Step);
+        change_speed(&mut params, pressed(Action::SpeedUp), pressed(Action::SpeedDown));
+        reload_config(&mut config, &mut params);
+        if pressed(Action::Reset) {
+            let seed = macroquad::miniquad::date::now() as u64;
+            world = World3D::in_box(&params, seed);
+            stats = Stats::new();
+            info!(target: UI, "Restarted with seed {}", seed);
+        }
+        if pressed(Action::Fullscreen) {
+            ui_state.fullscreen = !ui_state.fullscreen;
+            set_fullscreen(ui_state.fullscreen);
+        }
+        if pressed(Action::Record) {
+            ui_state.recording = !ui_state.recording;
+        }
+        if pressed(Action::GraphSettings) {
+            ui_state.show_graph_settings = !ui_state.show_graph_settings;
+        }
+        if pressed(Action::Correlations) {
+            ui_state.show_correlations = !ui_state.show_correlations;
         }
+        if pressed(Action::Mute) {
+            ui_state.sound.muted = !ui_state.sound.muted;
+        }
+        let layout = Layout::compute(ui_state.show_ui);
+        camera.view = layout.sim;
+        camera.handle_input();
 
-        // Draw predators
-        for p in &predators {
-            // Glow effect
-            draw_circle(p.pos.x, p.pos.y, p.size + 3.0, Color::new(1.0, 0.2, 0.1, 0.3));
-            draw_circle(p.pos.x, p.pos.y, p.size, Color::new(0.95, 0.25, 0.15, 0.95));
-            draw_circle(p.pos.x, p.pos.y, p.size * 0.6, Color::new(1.0, 0.5, 0.3, 0.8));
-            // Eyes
-            draw_circle(p.pos.x - 3.0, p.pos.y - 2.0, 2.0, Color::new(1.0, 1.0, 0.0, 0.9));
-            draw_circle(p.pos.x + 3.0, p.pos.y - 2.0, 2.0, Color::new(1.0, 1.0, 0.0, 0.9));
+        let events = if !ui_state.paused || step { world.step(&params) } else { Vec::new() };
+        if let Some(audio) = &mut audio {
+            audio.update(&ui_state.sound, &events, world.predator_count());
+        }
+        stats.record(&world);
+        if pressed(Action::Undo) || std::mem::take(&mut ui_state.undo_requested) {
+            undo.undo(&mut params);
+        }
+        if !is_mouse_button_down(MouseButton::Left) {
+            undo.record(&params);
+            param_watch.annotate(&params, &mut stats, world.tick);
         }
+        write_stats(&mut csv, &stats);
+        if pressed(Action::ExportCharts) {
+            export_chart_files(&stats, &[], &ui_state.graphs);
+        }
+        let count = world.bacteria_count();
+        let predator_count = world.predator_count();
 
-        // Draw graphs
-        draw_graphs_panel(&stats);
+        clear_background(Color::new(0.03f32, 0.03f32, 0.05f32, 1.0f32));
+        set_camera(&camera.to_camera3d());
+        draw_world3d(&world, ui_state.palette);
+        set_default_camera();
 
-        // Draw UI
+        draw_graphs_panel(&stats, &[], baseline.as_ref(), &layout, &mut ui_state);
         if ui_state.show_ui {
-            draw_ui_panel(&mut params, &mut ui_state, count, food.len(), predators.len());
+            draw_ui_panel(&mut params, &mut ui_state, &layout, count, stats.selection.effective_size(), world.food_count(), predator_count);
         }
-
-        // Draw FPS
-        draw_text(&format!("FPS: {}", get_fps()), 10.0, 25.0, 20.0, Color::new(0.3, 1.0, 0.3, 1.0));
+        if ui_state.show_correlations {
+            draw_correlation_panel(&stats, &layout);
+        }
+        if ui_state.show_graph_settings {
+            draw_graph_settings(&mut ui_state, &layout);
+        }
+        capture_frame(world.tick, &mut timelapse, pressed(Action::Screenshot));
+        update_recording(&mut ui_state, &mut recorder, &clip, layout.sim, world.tick);
+        draw_text(format!("FPS: {}  3D  (drag to orbit, wheel to zoom)", get_fps()), 10.0, 25.0, 20.0, Color::new(0.3, 1.0, 0.3, 1.0));
 
         next_frame().await
     }
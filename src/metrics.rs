@@ -0,0 +1,106 @@
+use std::fmt::Write as _;
+use std::io::{BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+use tracing::warn;
+
+use crate::stats::averages;
+use crate::world::World;
+
+// How often the tick rate is re-measured, in seconds
+const TICK_RATE_WINDOW: f32 = 1.0;
+
+// Latest values of every exported gauge
+#[derive(Clone, Copy, Default)]
+struct Gauges {
+    tick: u64,
+    bacteria: usize,
+    predators: usize,
+    food: usize,
+    avg_speed: f32,
+    avg_size: f32,
+    tick_rate: f32,
+    frame_seconds: f32,
+}
+
+impl Gauges {
+    // Prometheus text exposition format
+    fn render(&self) -> String {
+        let gauges: [(&str, &str, f64); 8] = [
+            ("bactsim_tick", "Current simulation tick", self.tick as f64),
+            ("bactsim_bacteria", "Living bacteria", self.bacteria as f64),
+            ("bactsim_predators", "Living predators", self.predators as f64),
+            ("bactsim_food", "Food items in the world", self.food as f64),
+            ("bactsim_avg_speed", "Average speed gene of the bacteria", self.avg_speed as f64),
+            ("bactsim_avg_size", "Average size gene of the bacteria", self.avg_size as f64),
+            ("bactsim_tick_rate", "Simulation ticks per second", self.tick_rate as f64),
+            ("bactsim_frame_seconds", "Duration of the last frame (or headless tick)", self.frame_seconds as f64),
+        ];
+        let mut text = String::new();
+        for (name, help, value) in gauges {
+            let _ = writeln!(text, "# HELP {} {}\n# TYPE {} gauge\n{} {}", name, help, name, name, value);
+        }
+        text
+    }
+}
+
+// Serves population counts, trait averages, tick rate and frame time as
+// Prometheus gauges on `GET /metrics`. Scrapes are answered on a background
+// thread from the values of the last `update`.
+pub struct MetricsExporter {
+    gauges: Arc<Mutex<Gauges>>,
+    window_start: Instant,
+    window_tick: u64,
+}
+
+impl MetricsExporter {
+    pub fn bind(addr: impl ToSocketAddrs) -> std::io::Result<Self> {
+        let listener = TcpListener::bind(addr)?;
+        let gauges = Arc::new(Mutex::new(Gauges::default()));
+        let scraped = Arc::clone(&gauges);
+        std::thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                let text = scraped.lock().unwrap().render();
+                if let Err(e) = answer(stream, &text) {
+                    warn!("Couldn't answer metrics scrape: {}", e);
+                }
+            }
+        });
+        Ok(Self { gauges, window_start: Instant::now(), window_tick: 0 })
+    }
+
+    pub fn update(&mut self, world: &World, frame_seconds: f32) {
+        let avg = averages(&world.ecs);
+        let mut gauges = self.gauges.lock().unwrap();
+        let elapsed = self.window_start.elapsed().as_secs_f32();
+        if elapsed >= TICK_RATE_WINDOW {
+            gauges.tick_rate = world.tick.saturating_sub(self.window_tick) as f32 / elapsed;
+            self.window_start = Instant::now();
+            self.window_tick = world.tick;
+        }
+        gauges.tick = world.tick;
+        gauges.bacteria = world.bacteria_count();
+        gauges.predators = world.predator_count();
+        gauges.food = world.food_count();
+        gauges.avg_speed = avg.speed;
+        gauges.avg_size = avg.size;
+        gauges.frame_seconds = frame_seconds;
+    }
+}
+
+fn answer(mut stream: TcpStream, metrics: &str) -> std::io::Result<()> {
+    let mut request_line = String::new();
+    BufReader::new(&stream).read_line(&mut request_line)?;
+    let path = request_line.split_whitespace().nth(1).unwrap_or("");
+    let (status, content_type, body) = if path == "/metrics" {
+        ("200 OK", "text/plain; version=0.0.4", metrics)
+    } else {
+        ("404 Not Found", "text/plain", "not found\n")
+    };
+    write!(
+        stream,
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status, content_type, body.len(), body
+    )
+}
@@ -0,0 +1,204 @@
+use hecs::Entity;
+use std::collections::{HashMap, VecDeque};
+
+use crate::components::{Genome, Lineage};
+use crate::dna::{SENSE_RADIUS, SIZE, SPEED};
+use crate::world::World;
+
+// Ticks between samples, and samples kept, the oldest dropped first
+pub const MULLER_EVERY: u64 = 20;
+const MULLER_SAMPLES: usize = 500;
+
+// Relative change in speed, size or sense radius from a clade's founding
+// genome past which an offspring founds a clade of its own, nested in its
+// parent's
+const CLADE_SPLIT: f32 = 0.1;
+
+// Clades that never reach this share of the bacteria within the kept samples
+// are drawn as part of their parent
+const MIN_SHARE: f32 = 0.01;
+
+// Descendants of one bacterium: a founder, or an offspring whose genes had
+// drifted far enough from its parent clade's
+pub struct Clade {
+    // Clade it split off from; none for a founder's
+    pub parent: Option<usize>,
+    // Its first bacterium, and that one's speed, size and sense radius
+    pub founder: Entity,
+    genes: [f32; 3],
+    pub born: u64,
+}
+
+// One clade's band in a sample, as shares of the bacteria from the bottom
+#[derive(Clone, Copy, Debug)]
+pub struct Band {
+    pub clade: usize,
+    pub lo: f32,
+    pub hi: f32,
+}
+
+// Clone frequencies over time for a Muller plot: every bacterium is assigned
+// a clade at birth and the clades' sizes are sampled every `MULLER_EVERY`
+// ticks
+pub struct MullerPlot {
+    members: HashMap<Entity, usize>,
+    pub clades: Vec<Clade>,
+    // Tick and living bacteria per clade, oldest first
+    samples: VecDeque<(u64, HashMap<usize, u32>)>,
+    last_tick: Option<u64>,
+}
+
+impl MullerPlot {
+    pub fn new() -> Self {
+        Self { members: HashMap::new(), clades: Vec::new(), samples: VecDeque::new(), last_tick: None }
+    }
+
+    // The world was replaced, so handles from the old one mean nothing
+    pub fn new_world(&mut self) {
+        *self = Self::new();
+    }
+
+    // Once a tick, assign the bacteria born since the last one to clades.
+    // Ticks may have gone by unseen (a batch on the worker thread): the
+    // world's births cover those, so a clade founded and lost in between
+    // still sits between its parent and descendants.
+    pub fn record(&mut self, world: &World) {
+        if self.last_tick == Some(world.tick) {
+            return;
+        }
+        self.last_tick = Some(world.tick);
+        for birth in &world.born {
+            if !self.members.contains_key(&birth.id) {
+                let parent_clade = birth.parent.and_then(|p| self.members.get(&p).copied());
+                // Speed, size and sense radius lead `CORRELATED_GENES`
+                let clade = self.assign(birth.id, parent_clade, [birth.genes[0], birth.genes[1], birth.genes[2]], birth.tick);
+                self.members.insert(birth.id, clade);
+            }
+        }
+        let mut bacteria: Vec<(Entity, Option<Entity>, u32, [f32; 3])> = world.ecs.query::<(Entity, &Genome, &Lineage)>().iter()
+            .map(|(id, genome, lineage)| (id, lineage.parent, lineage.generation, [genome.0.gene(SPEED), genome.0.gene(SIZE), genome.0.gene(SENSE_RADIUS)]))
+            .collect();
+        // Parents before their offspring, for those born in the same tick
+        bacteria.sort_by_key(|&(_, _, generation, _)| generation);
+
+        let mut members = HashMap::with_capacity(bacteria.len());
+        for (id, parent, _, genes) in bacteria {
+            let known = self.members.get(&id).or_else(|| members.get(&id)).copied();
+            let clade = match known {
+                Some(clade) => clade,
+                None => {
+                    let parent_clade = parent.and_then(|p| self.members.get(&p).or_else(|| members.get(&p)).copied());
+                    self.assign(id, parent_clade, genes, world.tick)
+                }
+            };
+            members.insert(id, clade);
+        }
+        self.members = members;
+
+        if self.samples.back().is_none_or(|(tick, _)| tick + MULLER_EVERY <= world.tick) {
+            let mut counts = HashMap::new();
+            for &clade in self.members.values() {
+                *counts.entry(clade).or_insert(0) += 1;
+            }
+            self.samples.push_back((world.tick, counts));
+            if self.samples.len() > MULLER_SAMPLES {
+                self.samples.pop_front();
+            }
+        }
+    }
+
+    // The clade of a bacterium first seen: its parent's, unless its genes
+    // drifted far enough from that clade's to found one of its own
+    fn assign(&mut self, id: Entity, parent_clade: Option<usize>, genes: [f32; 3], born: u64) -> usize {
+        match parent_clade {
+            Some(c) if !drifted(genes, self.clades[c].genes) => c,
+            _ => {
+                self.clades.push(Clade { parent: parent_clade, founder: id, genes, born });
+                self.clades.len() - 1
+            }
+        }
+    }
+
+    // Ticks of the kept samples, oldest first
+    pub fn ticks(&self) -> impl Iterator<Item = u64> + '_ {
+        self.samples.iter().map(|(tick, _)| *tick)
+    }
+
+    // Each sample's bands, nested: a clade's band holds its shown
+    // descendants' in the middle, with its own bacteria split above and below
+    // them. Parents come before their children, so drawing in order shows
+    // every clade.
+    pub fn bands(&self) -> Vec<Vec<Band>> {
+        let n = self.clades.len();
+        // Share of each clade with its descendants, per sample
+        let totals: Vec<Vec<f32>> = self.samples.iter()
+            .map(|(_, counts)| {
+                let mut total = vec![0.0f32; n];
+                for (&clade, &count) in counts {
+                    total[clade] = count as f32;
+                }
+                // Children are always created after their parent
+                for clade in (0..n).rev() {
+                    if let Some(parent) = self.clades[clade].parent {
+                        total[parent] += total[clade];
+                    }
+                }
+                let population: f32 = counts.values().sum::<u32>() as f32;
+                total.iter().map(|t| if population > 0.0 { t / population } else { 0.0 }).collect()
+            })
+            .collect();
+        let shown: Vec<bool> = (0..n)
+            .map(|c| self.clades[c].parent.is_none() || totals.iter().any(|t| t[c] >= MIN_SHARE))
+            .collect();
+        // Nearest shown ancestor's children
+        let mut children: Vec<Vec<usize>> = vec![Vec::new(); n];
+        let mut roots = Vec::new();
+        for clade in (0..n).filter(|&c| shown[c]) {
+            let mut parent = self.clades[clade].parent;
+            while let Some(p) = parent.filter(|&p| !shown[p]) {
+                parent = self.clades[p].parent;
+            }
+            match parent {
+                Some(p) => children[p].push(clade),
+                None => roots.push(clade),
+            }
+        }
+
+        totals.iter()
+            .map(|total| {
+                let mut bands = Vec::new();
+                let mut lo = 0.0;
+                for &root in &roots {
+                    place(root, lo, total, &children, &mut bands);
+                    lo += total[root];
+                }
+                bands
+            })
+            .collect()
+    }
+}
+
+impl Default for MullerPlot {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn drifted(genes: [f32; 3], founding: [f32; 3]) -> bool {
+    genes.iter().zip(founding).any(|(g, f)| (g - f).abs() > CLADE_SPLIT * f.abs().max(1e-3))
+}
+
+// `clade`'s band from `lo` up, then its shown children's inside it
+fn place(clade: usize, lo: f32, total: &[f32], children: &[Vec<usize>], bands: &mut Vec<Band>) {
+    let hi = lo + total[clade];
+    if hi <= lo {
+        return;
+    }
+    bands.push(Band { clade, lo, hi });
+    let inner: f32 = children[clade].iter().map(|&c| total[c]).sum();
+    let mut child_lo = lo + (total[clade] - inner) / 2.0;
+    for &child in &children[clade] {
+        place(child, child_lo, total, children, bands);
+        child_lo += total[child];
+    }
+}
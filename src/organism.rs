@@ -0,0 +1,164 @@
+use macroquad::prelude::*;
+use ::rand::Rng;
+
+use crate::components::{Age, Aversion, Body, Camouflage, Digesting, Energy, Expression, Genome, Imprint, Lineage, Metabolism, PhTolerance, Plasmids, Position, PredatorTag, Refractory, Respiration, Species, Startle, Target, Velocity};
+use crate::dna::{AEROBIC, DNA, Plasmid};
+use crate::food_web::SpeciesConfig;
+use crate::space::Space;
+
+// Blueprints for organisms that are about to be spawned. Events carry these so a
+// recorded tick holds everything needed to recreate births and respawns.
+
+#[derive(Clone, Debug)]
+pub struct Bacterium<V = Vec2> {
+    pub pos: V,
+    pub vel: V,
+    pub dna: DNA,
+    pub energy: f32,
+    pub age: f32,
+    // Body size while still growing towards the genome's; none when full-grown
+    pub size: Option<f32>,
+    pub expression: Expression,
+    // Epigenetic mark inherited from the parent
+    pub imprint: f32,
+    pub plasmids: Vec<Plasmid>,
+    pub lineage: Lineage,
+}
+
+impl<V: Space> Bacterium<V> {
+    pub fn new(pos: V, initial_energy: f32, rng: &mut impl Rng) -> Self {
+        let vel = V::random_dir(rng);
+        let dna = DNA::random(rng);
+        Self {
+            pos,
+            vel,
+            dna,
+            energy: initial_energy,
+            age: 0.0,
+            size: None,
+            expression: Expression::default(),
+            imprint: 0.0,
+            plasmids: Vec::new(),
+            lineage: Lineage { parent: None, generation: 0, founder: None, offspring: 0, origin: None },
+        }
+    }
+
+    pub fn spawn(self, ecs: &mut hecs::World) -> hecs::Entity {
+        ecs.spawn(self.builder().build())
+    }
+
+    // Under a given handle, e.g. one restored from a save
+    pub fn spawn_at(self, ecs: &mut hecs::World, id: hecs::Entity) {
+        ecs.spawn_at(id, self.builder().build());
+    }
+
+    // Built up in parts, as there are more components than a bundle tuple
+    // takes
+    fn builder(self) -> hecs::EntityBuilder {
+        let mut body = Body::expressed(&self.dna, &self.expression);
+        if let Some(size) = self.size {
+            body.size = size;
+        }
+        let mut builder = hecs::EntityBuilder::new();
+        builder.add_bundle((
+            Position(self.pos),
+            Velocity(self.vel),
+            Target::<V>::default(),
+            body,
+            Metabolism::for_bacterium(&body, &self.dna),
+            Respiration(self.dna.gene(AEROBIC)),
+            PhTolerance::from_dna(&self.dna),
+            Startle::from_dna(&self.dna),
+        ));
+        builder.add_bundle((
+            Digesting::default(),
+            Refractory::default(),
+            self.expression,
+            Imprint(self.imprint),
+            Plasmids(self.plasmids),
+            Genome(self.dna),
+            Energy(self.energy),
+            Age(self.age),
+            self.lineage,
+        ));
+        builder
+    }
+}
+
+// A consumer: a built-in predator, or one of a configured species
+#[derive(Clone, Debug)]
+pub struct Predator<V = Vec2> {
+    pub pos: V,
+    pub vel: V,
+    pub energy: f32,
+    pub speed: f32,
+    pub size: f32,
+    pub sense_radius: f32,
+    pub metabolism: f32,
+    pub species: Option<usize>,
+    pub lineage: Lineage,
+}
+
+impl<V: Space> Predator<V> {
+    pub fn new(pos: V, rng: &mut impl Rng) -> Self {
+        Self {
+            pos,
+            vel: V::random_dir(rng),
+            energy: 150.0,
+            speed: 2.5,
+            size: 12.0,
+            sense_radius: 100.0,
+            metabolism: Metabolism::for_predator().0,
+            species: None,
+            lineage: Lineage { parent: None, generation: 0, founder: None, offspring: 0, origin: None },
+        }
+    }
+
+    // A founder of the species at `index`
+    pub fn of_species(index: usize, config: &SpeciesConfig, pos: V, rng: &mut impl Rng) -> Self {
+        Self {
+            energy: config.energy,
+            speed: config.speed,
+            size: config.size,
+            sense_radius: config.sense_radius,
+            metabolism: config.metabolism,
+            species: Some(index),
+            ..Self::new(pos, rng)
+        }
+    }
+
+    pub fn spawn(self, ecs: &mut hecs::World) -> hecs::Entity {
+        let species = self.species;
+        let id = ecs.spawn(self.bundle());
+        if let Some(index) = species {
+            let _ = ecs.insert_one(id, Species(index));
+        }
+        id
+    }
+
+    pub fn spawn_at(self, ecs: &mut hecs::World, id: hecs::Entity) {
+        let species = self.species;
+        ecs.spawn_at(id, self.bundle());
+        if let Some(index) = species {
+            let _ = ecs.insert_one(id, Species(index));
+        }
+    }
+
+    fn bundle(self) -> impl hecs::DynamicBundle {
+        (
+            Position(self.pos),
+            Velocity(self.vel),
+            Target::<V>::default(),
+            Body { speed: self.speed, size: self.size, sense_radius: self.sense_radius },
+            Metabolism(self.metabolism),
+            Energy(self.energy),
+            Age(0.0),
+            self.lineage,
+            Camouflage::default(),
+            Aversion::default(),
+            Digesting::default(),
+            Refractory::default(),
+            PredatorTag,
+        )
+    }
+}
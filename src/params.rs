@@ -0,0 +1,696 @@
+use serde::{Deserialize, Serialize};
+
+use crate::food_web::{self, Diet, SpeciesConfig};
+use crate::habitat::WorldLayout;
+use crate::{GRAPH_HEIGHT, SCREEN_HEIGHT, SCREEN_WIDTH, UI_PANEL_WIDTH};
+
+// World size the population and food defaults are tuned for: the sim area of
+// the default window
+pub const DEFAULT_WORLD_WIDTH: f32 = SCREEN_WIDTH - UI_PANEL_WIDTH;
+pub const DEFAULT_WORLD_HEIGHT: f32 = SCREEN_HEIGHT - GRAPH_HEIGHT;
+// Bounds on a world's width and height
+pub const MIN_WORLD_SIZE: f32 = 100.0;
+pub const MAX_WORLD_SIZE: f32 = 50_000.0;
+// Most food per default-sized area, neutral marker loci and ticks between looks
+pub const MAX_FOOD: usize = 100_000;
+pub const MAX_MARKERS: usize = 32;
+pub const MAX_SENSE_STRIDE: usize = 100;
+
+// How a food item touched by several bacteria in the same tick is shared
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum FoodContention {
+    // The bacterium closest to the food gets all of it
+    ClosestWins,
+    // Every bacterium touching it gets an equal share
+    Split,
+}
+
+impl FoodContention {
+    pub fn label(self) -> &'static str {
+        match self {
+            FoodContention::ClosestWins => "Closest",
+            FoodContention::Split => "Split",
+        }
+    }
+
+    pub fn next(self) -> Self {
+        match self {
+            FoodContention::ClosestWins => FoodContention::Split,
+            FoodContention::Split => FoodContention::ClosestWins,
+        }
+    }
+}
+
+// A patch of acid or alkaline water. Position and radius are fractions of the
+// world size (radius of its shorter side) so a zone scales with the world. The
+// pH shifts from neutral at the rim to `ph` at the centre.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct PhZone {
+    pub x: f32,
+    pub y: f32,
+    pub radius: f32,
+    pub ph: f32,
+}
+
+impl PhZone {
+    // `x,y,radius,ph`, as given to `--ph-zone`
+    pub fn parse(s: &str) -> Option<Self> {
+        let v: Vec<f32> = s.split(',').map(|p| p.trim().parse().ok()).collect::<Option<_>>()?;
+        match v[..] {
+            [x, y, radius, ph] => Some(Self { x, y, radius, ph }),
+            _ => None,
+        }
+    }
+}
+
+// Mutable simulation parameters
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SimulationParams {
+    pub food_growth_rate: f32,
+    pub max_food: usize,
+    pub mutation_rate: f32,
+    pub mutation_strength: f32,
+    // Bacteria mutate at the rate of their own `mutation_rate` gene instead of
+    // `mutation_rate`
+    #[serde(default)]
+    pub evolve_mutation_rate: bool,
+    pub reproduction_threshold: f32,
+    pub initial_energy: f32,
+    pub speed_multiplier: f32,
+    pub predator_count: f32,
+    pub predator_reproduction_threshold: f32,
+    // Energy lost to dividing, then the share of what's left that goes to the
+    // offspring; with `evolve_offspring_share` each bacterium's `investment`
+    // gene sets its share instead
+    #[serde(default)]
+    pub reproduction_cost: f32,
+    #[serde(default = "default_offspring_share")]
+    pub offspring_share: f32,
+    #[serde(default)]
+    pub evolve_offspring_share: bool,
+    // Age before an organism can first divide, and ticks between divisions;
+    // with `evolve_reproduction_timing` bacteria take theirs from the
+    // `maturation` and `cooldown` genes instead
+    #[serde(default)]
+    pub maturation_age: f32,
+    #[serde(default)]
+    pub reproduction_cooldown: f32,
+    #[serde(default)]
+    pub evolve_reproduction_timing: bool,
+    // Share added to a bacterium's metabolic cost for every division it has
+    // been the parent of: the old pole keeps the damage and the offspring
+    // starts fresh. 0 for symmetric division.
+    #[serde(default)]
+    pub division_damage: f32,
+    // Share of its genetic size a bacterium is born at, and how much it can
+    // grow per tick towards the full size, paid for in energy, while young
+    #[serde(default = "default_birth_size")]
+    pub birth_size: f32,
+    #[serde(default = "default_growth_rate")]
+    pub growth_rate: f32,
+    // Most a newborn's speed, size and sense radius come out off its genes,
+    // as a share either way; not inherited
+    #[serde(default)]
+    pub developmental_noise: f32,
+    // Hard times leave bacteria an epigenetic mark that makes them thriftier
+    // but slower, of which offspring inherit `epigenetic_inheritance`
+    #[serde(default)]
+    pub epigenetics: bool,
+    #[serde(default = "default_epigenetic_inheritance")]
+    pub epigenetic_inheritance: f32,
+    // Experimental: offspring genes take `lamarckian_share` of the way from the
+    // parent's genes to the traits it actually ended up with (grown size,
+    // developmental offsets)
+    #[serde(default)]
+    pub lamarckian: bool,
+    #[serde(default = "default_lamarckian_share")]
+    pub lamarckian_share: f32,
+    // Chance per offspring that one of its genes is copied onto the end of
+    // its genome, and that one is lost
+    #[serde(default)]
+    pub gene_duplication_rate: f32,
+    #[serde(default)]
+    pub gene_deletion_rate: f32,
+    // Energy per tick the antibiotic in the water costs a bacterium without
+    // resistance. Plasmids cost `plasmid_cost` per gene per tick, are copied
+    // to a touching bacterium with `conjugation_rate` per tick and lost by an
+    // offspring with `plasmid_loss_rate`; `plasmid_share` of the bacteria a
+    // world starts with carry a resistance plasmid.
+    #[serde(default)]
+    pub antibiotic: f32,
+    // Antibiotic added on top in steps from the left edge of the world, up to
+    // this much at the right
+    #[serde(default)]
+    pub antibiotic_gradient: f32,
+    #[serde(default)]
+    pub plasmid_share: f32,
+    #[serde(default = "default_plasmid_cost")]
+    pub plasmid_cost: f32,
+    #[serde(default = "default_conjugation_rate")]
+    pub conjugation_rate: f32,
+    #[serde(default = "default_plasmid_loss_rate")]
+    pub plasmid_loss_rate: f32,
+    // Neutral marker loci for studying drift: each founder gets one of
+    // `marker_alleles` alleles at random at every locus, and an offspring's
+    // marker switches to another allele with `marker_mutation_rate`
+    #[serde(default)]
+    pub neutral_markers: usize,
+    #[serde(default = "default_marker_alleles")]
+    pub marker_alleles: usize,
+    #[serde(default)]
+    pub marker_mutation_rate: f32,
+    // Energy a food item holds
+    #[serde(default = "default_food_energy")]
+    pub food_energy: f32,
+    // Share of a meal's energy the eater keeps: of a food item's for bacteria,
+    // of the prey's for predators. Diet entries without their own efficiency
+    // use these too.
+    #[serde(default = "default_food_efficiency")]
+    pub food_efficiency: f32,
+    #[serde(default = "default_predation_efficiency")]
+    pub predation_efficiency: f32,
+    // How much hunters of a kind work together, 0 (not at all) to 1: how far
+    // they share what they've seen and how widely they flank
+    #[serde(default)]
+    pub pack_cooperation: f32,
+    // Predators wait still for prey to come to them, hidden once settled,
+    // instead of roaming
+    #[serde(default)]
+    pub predator_ambush: bool,
+    // Ticks an organism spends digesting each meal, moving at half speed and
+    // eating nothing more. Caps how fast a consumer can eat however plentiful
+    // its prey (a Type II functional response).
+    #[serde(default = "default_predator_handling_time")]
+    pub predator_handling_time: f32,
+    #[serde(default)]
+    pub bacteria_handling_time: f32,
+    // What the built-in predators eat (see `food_web`)
+    #[serde(default = "default_predator_diet")]
+    pub predator_diet: Vec<Diet>,
+    // Consumer species alongside them, making up the rest of the food web
+    #[serde(default)]
+    pub species: Vec<SpeciesConfig>,
+    pub food_contention: FoodContention,
+    // Waste left in the world per unit of energy burned
+    pub waste_per_energy: f32,
+    // Fraction of the waste that breaks down each tick
+    pub waste_decay: f32,
+    // Waste in a cell at which food stops growing there and organisms start
+    // losing `waste_toxicity` energy per tick for every unit above it
+    pub waste_threshold: f32,
+    pub waste_toxicity: f32,
+    // Acid and alkaline zones, read when a world is created; elsewhere the water
+    // is neutral (pH 7)
+    pub ph_zones: Vec<PhZone>,
+    // Energy lost per tick per pH unit outside a bacterium's tolerance
+    pub ph_damage: f32,
+    // World dimensions, read when a world is created. Food rates, the food cap
+    // and starting populations are given per default-sized area and scaled up
+    // with the world so density stays the same.
+    pub world_width: f32,
+    pub world_height: f32,
+    pub world_layout: WorldLayout,
+    // Ticks between an organism's looks around for food or prey, staggered so
+    // a share of them looks each tick. In between it keeps after what it last
+    // saw, which is much cheaper in big populations at the cost of reacting
+    // late. Fleeing is still checked every tick.
+    #[serde(default = "default_sense_stride")]
+    pub sense_stride: usize,
+}
+
+fn default_predator_diet() -> Vec<Diet> {
+    vec![Diet::new("bacteria")]
+}
+
+fn default_sense_stride() -> usize {
+    1
+}
+
+fn default_plasmid_cost() -> f32 {
+    0.02
+}
+
+fn default_conjugation_rate() -> f32 {
+    0.05
+}
+
+fn default_plasmid_loss_rate() -> f32 {
+    0.01
+}
+
+fn default_marker_alleles() -> usize {
+    4
+}
+
+fn default_offspring_share() -> f32 {
+    0.5
+}
+
+fn default_birth_size() -> f32 {
+    1.0
+}
+
+fn default_growth_rate() -> f32 {
+    0.05
+}
+
+fn default_epigenetic_inheritance() -> f32 {
+    0.5
+}
+
+fn default_lamarckian_share() -> f32 {
+    0.5
+}
+
+fn default_food_energy() -> f32 {
+    30.0
+}
+
+fn default_food_efficiency() -> f32 {
+    1.0
+}
+
+fn default_predation_efficiency() -> f32 {
+    0.8
+}
+
+fn default_predator_handling_time() -> f32 {
+    10.0
+}
+
+// A parameter with a slider in the panel: its range and what it does, shown
+// as a tooltip
+pub struct ParamInfo {
+    // Field name, as in the config file and the control API
+    pub key: &'static str,
+    pub label: &'static str,
+    pub min: f32,
+    pub max: f32,
+    // Display unit; "%" shows the value as a percentage
+    pub unit: &'static str,
+    pub description: &'static str,
+    pub value: fn(&mut SimulationParams) -> &mut f32,
+}
+
+pub const PARAM_INFO: &[ParamInfo] = &[
+    ParamInfo {
+        key: "food_growth_rate",
+        label: "Food/Frame",
+        min: 0.0,
+        max: 10.0,
+        unit: "/f",
+        description: "Food items grown per tick (per default-sized area), until the food cap is reached. More food carries a larger population.",
+        value: |p| &mut p.food_growth_rate,
+    },
+    ParamInfo {
+        key: "speed_multiplier",
+        label: "Sim. Speed",
+        min: 0.1,
+        max: 3.0,
+        unit: "x",
+        description: "Scales how far organisms move per tick, and the metabolism they pay for it. Faster runs evolve sooner but burn energy sooner too.",
+        value: |p| &mut p.speed_multiplier,
+    },
+    ParamInfo {
+        key: "mutation_rate",
+        label: "Mutation Rate",
+        min: 0.0,
+        max: 0.5,
+        unit: "%",
+        description: "Chance that each gene of an offspring mutates. Higher rates explore more but let well-adapted lineages drift apart.",
+        value: |p| &mut p.mutation_rate,
+    },
+    ParamInfo {
+        key: "mutation_strength",
+        label: "Mutation Str.",
+        min: 0.0,
+        max: 0.5,
+        unit: "%",
+        description: "Largest change a single mutation makes to a gene. Small steps fine-tune, large ones jump to new strategies.",
+        value: |p| &mut p.mutation_strength,
+    },
+    ParamInfo {
+        key: "initial_energy",
+        label: "Initial Energy",
+        min: 50.0,
+        max: 200.0,
+        unit: "",
+        description: "Energy of newly spawned bacteria (not offspring, which get half their parent's). Sets how long founders survive before they must find food.",
+        value: |p| &mut p.initial_energy,
+    },
+    ParamInfo {
+        key: "reproduction_threshold",
+        label: "Reproduction",
+        min: 50.0,
+        max: 300.0,
+        unit: "",
+        description: "Energy a bacterium must exceed to divide, splitting it with its offspring. A high threshold means fewer, sturdier offspring; since metabolism drains energy all the time, fast and large bacteria need more food to reach it.",
+        value: |p| &mut p.reproduction_threshold,
+    },
+    ParamInfo {
+        key: "predator_reproduction_threshold",
+        label: "Pred. Repro.",
+        min: 100.0,
+        max: 400.0,
+        unit: "",
+        description: "Energy a predator must exceed to divide. Lower values let predators boom after a few meals, making crashes of both populations more likely.",
+        value: |p| &mut p.predator_reproduction_threshold,
+    },
+    ParamInfo {
+        key: "reproduction_cost",
+        label: "Repro. Cost",
+        min: 0.0,
+        max: 100.0,
+        unit: "",
+        description: "Energy lost every time an organism divides, before the rest is split. Costly division favours waiting for more energy and having fewer, better-provisioned offspring.",
+        value: |p| &mut p.reproduction_cost,
+    },
+    ParamInfo {
+        key: "offspring_share",
+        label: "Offspring Share",
+        min: 0.05,
+        max: 0.95,
+        unit: "%",
+        description: "Share of the parent's energy (after the cost) its offspring gets. Small shares make many cheap offspring that often starve; large ones few that start strong but leave the parent weak.",
+        value: |p| &mut p.offspring_share,
+    },
+    ParamInfo {
+        key: "maturation_age",
+        label: "Maturation",
+        min: 0.0,
+        max: 500.0,
+        unit: "",
+        description: "Ticks an organism has to live before it can first divide. Delays booms when food is plentiful, since newborns can't turn a windfall into offspring straight away.",
+        value: |p| &mut p.maturation_age,
+    },
+    ParamInfo {
+        key: "reproduction_cooldown",
+        label: "Repro. Cooldown",
+        min: 0.0,
+        max: 500.0,
+        unit: "",
+        description: "Ticks an organism must wait between divisions, however much energy it has. Stops well-fed lineages dividing every few ticks.",
+        value: |p| &mut p.reproduction_cooldown,
+    },
+    ParamInfo {
+        key: "division_damage",
+        label: "Division Aging",
+        min: 0.0,
+        max: 0.5,
+        unit: "%",
+        description: "Extra metabolic cost a bacterium takes on each time it divides, while its offspring starts fresh. Old mothers grow costly to run and eventually starve, so lineages are carried by the young: replicative senescence.",
+        value: |p| &mut p.division_damage,
+    },
+    ParamInfo {
+        key: "birth_size",
+        label: "Birth Size",
+        min: 0.2,
+        max: 1.0,
+        unit: "%",
+        description: "Share of its genetic size a bacterium is born at. Below 100%, newborns grow towards full size by spending energy while young, so those that go hungry stay stunted: small, cheap to run and easy to swallow.",
+        value: |p| &mut p.birth_size,
+    },
+    ParamInfo {
+        key: "growth_rate",
+        label: "Growth Rate",
+        min: 0.01,
+        max: 0.2,
+        unit: "",
+        description: "Size a growing bacterium can put on per tick, if it has the energy to spare. Slow growth leaves bacteria small and vulnerable for longer.",
+        value: |p| &mut p.growth_rate,
+    },
+    ParamInfo {
+        key: "developmental_noise",
+        label: "Dev. Noise",
+        min: 0.0,
+        max: 0.5,
+        unit: "%",
+        description: "How far a newborn's speed, size and sense radius can come out from what its genes say. The offset isn't inherited, so the more noise, the less of the variation selection sees is heritable and the slower traits evolve (see the heritability under the gene correlations).",
+        value: |p| &mut p.developmental_noise,
+    },
+    ParamInfo {
+        key: "epigenetic_inheritance",
+        label: "Epigenetic Inh.",
+        min: 0.0,
+        max: 1.0,
+        unit: "%",
+        description: "With epigenetics on, the share of its stress mark a bacterium passes to each offspring. Higher values carry a parent's hard times further down the line, fading a little each generation.",
+        value: |p| &mut p.epigenetic_inheritance,
+    },
+    ParamInfo {
+        key: "lamarckian_share",
+        label: "Lamarckian",
+        min: 0.0,
+        max: 1.0,
+        unit: "%",
+        description: "With Lamarckian inheritance on, how much of what a parent acquired in life (a stunted or grown size, developmental offsets of speed, size and sense) is written into its offspring's genes.",
+        value: |p| &mut p.lamarckian_share,
+    },
+    ParamInfo {
+        key: "gene_duplication_rate",
+        label: "Duplication",
+        min: 0.0,
+        max: 0.2,
+        unit: "%",
+        description: "Chance per offspring that one of its genes is duplicated, growing the genome by a copy. Only the first copy of a gene is expressed; later ones drift freely until it's lost.",
+        value: |p| &mut p.gene_duplication_rate,
+    },
+    ParamInfo {
+        key: "gene_deletion_rate",
+        label: "Deletion",
+        min: 0.0,
+        max: 0.2,
+        unit: "%",
+        description: "Chance per offspring that one of its genes is lost. Losing a gene's only copy knocks it out. Against duplication, sets how long genomes get.",
+        value: |p| &mut p.gene_deletion_rate,
+    },
+    ParamInfo {
+        key: "antibiotic",
+        label: "Antibiotic",
+        min: 0.0,
+        max: 1.0,
+        unit: "",
+        description: "Energy per tick the antibiotic costs every bacterium without a resistance plasmid. Strong doses wipe out the sensitive and sweep resistance through the population; without it, resistance is dead weight.",
+        value: |p| &mut p.antibiotic,
+    },
+    ParamInfo {
+        key: "antibiotic_gradient",
+        label: "Antibiotic Grad.",
+        min: 0.0,
+        max: 2.0,
+        unit: "",
+        description: "Antibiotic added in bands rising from nothing at the left edge of the world to this at the right, as on a MEGA-plate. Sensitive bacteria are held back at the first band they can't survive, and resistance spreads across the bands as a wave.",
+        value: |p| &mut p.antibiotic_gradient,
+    },
+    ParamInfo {
+        key: "plasmid_cost",
+        label: "Plasmid Cost",
+        min: 0.0,
+        max: 0.2,
+        unit: "",
+        description: "Energy per tick each gene on a plasmid costs its carrier. Costly plasmids die out unless something, such as the antibiotic, makes their genes worth carrying.",
+        value: |p| &mut p.plasmid_cost,
+    },
+    ParamInfo {
+        key: "conjugation_rate",
+        label: "Conjugation",
+        min: 0.0,
+        max: 0.5,
+        unit: "%",
+        description: "Chance per tick that a bacterium touching a plasmid carrier picks up a copy of a plasmid it lacks. Plasmids spread sideways this way, between unrelated lineages, even when they don't pay.",
+        value: |p| &mut p.conjugation_rate,
+    },
+    ParamInfo {
+        key: "plasmid_loss_rate",
+        label: "Plasmid Loss",
+        min: 0.0,
+        max: 0.2,
+        unit: "%",
+        description: "Chance that an offspring misses each of its parent's plasmids at division. Lost plasmids only come back through conjugation.",
+        value: |p| &mut p.plasmid_loss_rate,
+    },
+    ParamInfo {
+        key: "marker_mutation_rate",
+        label: "Marker Mut.",
+        min: 0.0,
+        max: 0.05,
+        unit: "%",
+        description: "Chance that an offspring's neutral marker switches to another allele, per locus. At 0 markers only drift, and each locus ends up fixed for one allele.",
+        value: |p| &mut p.marker_mutation_rate,
+    },
+    ParamInfo {
+        key: "food_energy",
+        label: "Food Energy",
+        min: 5.0,
+        max: 100.0,
+        unit: "",
+        description: "Energy each food item holds. Richer food carries more bacteria on the same growth rate.",
+        value: |p| &mut p.food_energy,
+    },
+    ParamInfo {
+        key: "food_efficiency",
+        label: "Food Effic.",
+        min: 0.1,
+        max: 1.0,
+        unit: "%",
+        description: "Share of a food item's energy a bacterium keeps when it eats it. The rest is lost, so lower efficiency starves the whole web.",
+        value: |p| &mut p.food_efficiency,
+    },
+    ParamInfo {
+        key: "predation_efficiency",
+        label: "Pred. Effic.",
+        min: 0.1,
+        max: 1.0,
+        unit: "%",
+        description: "Share of a bacterium's stored energy a predator gains by eating it. Well-fed prey are worth more; low efficiency makes predators need many meals to divide.",
+        value: |p| &mut p.predation_efficiency,
+    },
+    ParamInfo {
+        key: "pack_cooperation",
+        label: "Pack Hunting",
+        min: 0.0,
+        max: 1.0,
+        unit: "%",
+        description: "How much predators hunt together. Those that see no prey join a nearby packmate's chase, and several chasing the same prey come at it from different sides, so fleeing straight away from one runs into another.",
+        value: |p| &mut p.pack_cooperation,
+    },
+    ParamInfo {
+        key: "predator_handling_time",
+        label: "Pred. Handling",
+        min: 0.0,
+        max: 60.0,
+        unit: "",
+        description: "Ticks a predator spends digesting each kill, at half speed and unable to eat. Caps how many prey a predator can take however many there are, so abundant prey can outgrow their hunters.",
+        value: |p| &mut p.predator_handling_time,
+    },
+    ParamInfo {
+        key: "bacteria_handling_time",
+        label: "Bact. Handling",
+        min: 0.0,
+        max: 30.0,
+        unit: "",
+        description: "Ticks a bacterium spends digesting each food item, at half speed and unable to eat. At zero bacteria eat everything they touch.",
+        value: |p| &mut p.bacteria_handling_time,
+    },
+];
+
+// What the food contest setting decides, for its button's tooltip
+pub const FOOD_CONTENTION_HELP: &str = "How a food item touched by several bacteria in the same tick is shared: all to the closest, or split evenly between them.";
+
+pub fn param_info(key: &str) -> Option<&'static ParamInfo> {
+    PARAM_INFO.iter().find(|info| info.key == key)
+}
+
+// Ranges of the numbers without a slider: shares kept to 0..1 like those
+// with one, and sizes and counts to what a world can hold
+const LIMITS: &[(&str, f32, f32)] = &[
+    ("plasmid_share", 0.0, 1.0),
+    ("waste_decay", 0.0, 1.0),
+    ("world_width", MIN_WORLD_SIZE, MAX_WORLD_SIZE),
+    ("world_height", MIN_WORLD_SIZE, MAX_WORLD_SIZE),
+    ("max_food", 0.0, MAX_FOOD as f32),
+    ("neutral_markers", 0.0, MAX_MARKERS as f32),
+    ("sense_stride", 1.0, MAX_SENSE_STRIDE as f32),
+];
+
+// A number for `key` outside its slider's range or its limits, which could
+// leave the simulation panicking or running away. Values of the wrong type
+// are left to deserializing to refuse.
+fn check_range(key: &str, value: &serde_json::Value) -> Result<(), String> {
+    let (min, max) = match (param_info(key), LIMITS.iter().find(|(name, _, _)| *name == key)) {
+        (Some(info), _) => (info.min, info.max),
+        (None, Some(&(_, min, max))) => (min, max),
+        (None, None) => return Ok(()),
+    };
+    match value.as_f64() {
+        Some(v) if !(min..=max).contains(&(v as f32)) => Err(format!("{} must be between {} and {}, not {}", key, min, max, v)),
+        _ => Ok(()),
+    }
+}
+
+impl SimulationParams {
+    // Overwrite the parameters named in `changes`, leaving the rest. Nothing
+    // changes if any name is unknown, any value has the wrong type or is out
+    // of range, or the food web doesn't add up.
+    pub fn merge(&mut self, changes: serde_json::Map<String, serde_json::Value>) -> Result<(), String> {
+        let mut merged = serde_json::to_value(&*self).map_err(|e| e.to_string())?;
+        for (key, value) in changes {
+            match merged.get_mut(&key) {
+                Some(slot) => {
+                    check_range(&key, &value)?;
+                    *slot = value;
+                }
+                None => return Err(format!("unknown parameter '{}'", key)),
+            }
+        }
+        let merged: Self = serde_json::from_value(merged).map_err(|e| e.to_string())?;
+        food_web::check(&merged)?;
+        *self = merged;
+        Ok(())
+    }
+}
+
+impl Default for SimulationParams {
+    fn default() -> Self {
+        Self {
+            food_growth_rate: 2.0,
+            max_food: 1000,
+            mutation_rate: 0.1,
+            mutation_strength: 0.1,
+            evolve_mutation_rate: false,
+            reproduction_threshold: 150.0,
+            initial_energy: 100.0,
+            speed_multiplier: 1.0,
+            predator_count: 5.0,
+            predator_reproduction_threshold: 200.0,
+            reproduction_cost: 0.0,
+            offspring_share: default_offspring_share(),
+            evolve_offspring_share: false,
+            maturation_age: 0.0,
+            reproduction_cooldown: 0.0,
+            evolve_reproduction_timing: false,
+            division_damage: 0.0,
+            birth_size: default_birth_size(),
+            growth_rate: default_growth_rate(),
+            developmental_noise: 0.0,
+            epigenetics: false,
+            epigenetic_inheritance: default_epigenetic_inheritance(),
+            lamarckian: false,
+            lamarckian_share: default_lamarckian_share(),
+            gene_duplication_rate: 0.0,
+            gene_deletion_rate: 0.0,
+            antibiotic: 0.0,
+            antibiotic_gradient: 0.0,
+            plasmid_share: 0.0,
+            plasmid_cost: default_plasmid_cost(),
+            conjugation_rate: default_conjugation_rate(),
+            plasmid_loss_rate: default_plasmid_loss_rate(),
+            neutral_markers: 0,
+            marker_alleles: default_marker_alleles(),
+            marker_mutation_rate: 0.0,
+            food_energy: default_food_energy(),
+            food_efficiency: default_food_efficiency(),
+            predation_efficiency: default_predation_efficiency(),
+            pack_cooperation: 0.0,
+            predator_ambush: false,
+            predator_handling_time: default_predator_handling_time(),
+            bacteria_handling_time: 0.0,
+            predator_diet: default_predator_diet(),
+            species: Vec::new(),
+            food_contention: FoodContention::ClosestWins,
+            waste_per_energy: 1.0,
+            waste_decay: 0.002,
+            waste_threshold: 50.0,
+            waste_toxicity: 0.01,
+            ph_zones: vec![
+                PhZone { x: 0.25, y: 0.3, radius: 0.3, ph: 4.0 },
+                PhZone { x: 0.75, y: 0.7, radius: 0.3, ph: 10.0 },
+            ],
+            ph_damage: 0.1,
+            world_width: DEFAULT_WORLD_WIDTH,
+            world_height: DEFAULT_WORLD_HEIGHT,
+            world_layout: WorldLayout::Open,
+            sense_stride: default_sense_stride(),
+        }
+    }
+}
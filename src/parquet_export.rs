@@ -0,0 +1,107 @@
+use arrow_array::{ArrayRef, Float32Array, RecordBatch, StringArray, UInt32Array, UInt64Array};
+use arrow_schema::{DataType, Field, Schema};
+use parquet::arrow::ArrowWriter;
+use std::fs::File;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use crate::cadence::Cadence;
+use crate::components::{Age, Body, Energy, Genome, Lineage, Position, PredatorTag};
+use crate::dna::{AEROBIC, AGGRESSION, ARMOR, BURST, COLOR_B, COLOR_G, COLOR_R, COOLDOWN, CROWDING, DISPERSAL, FLEE_ENERGY, INVESTMENT, MATURATION, MUTATION_RATE, PH_OPTIMUM, PH_TOLERANCE, TOXIN, VIGILANCE};
+use crate::world::World;
+
+// Writes a full population snapshot every `every` ticks to
+// `dir/tick_<tick>.parquet`: one row per organism with its position, energy,
+// age, body and genes. Gene and generation columns are null for predators.
+pub struct ParquetExporter {
+    dir: PathBuf,
+    pub every: Cadence,
+}
+
+impl ParquetExporter {
+    pub fn new(dir: impl Into<PathBuf>) -> std::io::Result<Self> {
+        let dir = dir.into();
+        std::fs::create_dir_all(&dir)?;
+        Ok(Self { dir, every: Cadence::new(1000) })
+    }
+
+    // Export if a snapshot came due since the last call
+    pub fn record(&mut self, world: &World) -> Result<(), String> {
+        if !self.every.due(world.tick) {
+            return Ok(());
+        }
+        let path = self.dir.join(format!("tick_{:08}.parquet", world.tick));
+        let batch = snapshot_batch(world).map_err(|e| e.to_string())?;
+        let file = File::create(&path).map_err(|e| format!("{}: {}", path.display(), e))?;
+        let mut writer = ArrowWriter::try_new(file, batch.schema(), None).map_err(|e| e.to_string())?;
+        writer.write(&batch).map_err(|e| e.to_string())?;
+        writer.close().map_err(|e| e.to_string())?;
+        Ok(())
+    }
+}
+
+fn snapshot_batch(world: &World) -> Result<RecordBatch, arrow_schema::ArrowError> {
+    let mut ids = Vec::new();
+    let mut kinds = Vec::new();
+    let mut floats: [Vec<f32>; 7] = Default::default();
+    let mut genes: [Vec<Option<f32>>; 18] = Default::default();
+    let mut generations = Vec::new();
+
+    let mut query = world.ecs.query::<(hecs::Entity, &Position, &Energy, &Age, &Body, Option<&Genome>, Option<&Lineage>, Option<&PredatorTag>)>();
+    let mut rows: Vec<_> = query.iter().filter(|row| row.5.is_some() || row.7.is_some()).collect();
+    rows.sort_by_key(|row| row.0);
+    for (id, pos, energy, age, body, genome, lineage, predator) in rows {
+        ids.push(id.to_bits().get());
+        kinds.push(if predator.is_some() { "predator" } else { "bacterium" });
+        for (column, value) in floats.iter_mut().zip([pos.0.x, pos.0.y, energy.0, age.0, body.speed, body.size, body.sense_radius]) {
+            column.push(value);
+        }
+        let dna = genome.map(|g| &g.0);
+        let values = [
+            dna.map(|d| d.gene(AEROBIC)),
+            dna.map(|d| d.gene(PH_OPTIMUM)),
+            dna.map(|d| d.gene(PH_TOLERANCE)),
+            dna.map(|d| d.gene(BURST)),
+            dna.map(|d| d.gene(VIGILANCE)),
+            dna.map(|d| d.gene(AGGRESSION)),
+            dna.map(|d| d.gene(ARMOR)),
+            dna.map(|d| d.gene(TOXIN)),
+            dna.map(|d| d.gene(INVESTMENT)),
+            dna.map(|d| d.gene(MATURATION)),
+            dna.map(|d| d.gene(COOLDOWN)),
+            dna.map(|d| d.gene(DISPERSAL)),
+            dna.map(|d| d.gene(FLEE_ENERGY)),
+            dna.map(|d| d.gene(CROWDING)),
+            dna.map(|d| d.gene(MUTATION_RATE)),
+            dna.map(|d| d.gene(COLOR_R)),
+            dna.map(|d| d.gene(COLOR_G)),
+            dna.map(|d| d.gene(COLOR_B)),
+        ];
+        for (column, value) in genes.iter_mut().zip(values) {
+            column.push(value);
+        }
+        generations.push(lineage.map(|l| l.generation));
+    }
+
+    let float_names = ["x", "y", "energy", "age", "speed", "size", "sense_radius"];
+    let gene_names = ["aerobic", "ph_optimum", "ph_tolerance", "burst", "vigilance", "aggression", "armor", "toxin", "investment", "maturation", "cooldown", "dispersal", "flee_energy", "crowding", "mutation_rate", "color_r", "color_g", "color_b"];
+    let mut fields = vec![
+        Field::new("tick", DataType::UInt64, false),
+        Field::new("id", DataType::UInt64, false),
+        Field::new("kind", DataType::Utf8, false),
+    ];
+    fields.extend(float_names.iter().map(|name| Field::new(*name, DataType::Float32, false)));
+    fields.extend(gene_names.iter().map(|name| Field::new(*name, DataType::Float32, true)));
+    fields.push(Field::new("generation", DataType::UInt32, true));
+
+    let mut columns: Vec<ArrayRef> = vec![
+        Arc::new(UInt64Array::from(vec![world.tick; ids.len()])),
+        Arc::new(UInt64Array::from(ids)),
+        Arc::new(StringArray::from(kinds)),
+    ];
+    columns.extend(floats.into_iter().map(|c| Arc::new(Float32Array::from(c)) as ArrayRef));
+    columns.extend(genes.into_iter().map(|c| Arc::new(Float32Array::from(c)) as ArrayRef));
+    columns.push(Arc::new(UInt32Array::from(generations)));
+
+    RecordBatch::try_new(Arc::new(Schema::new(fields)), columns)
+}
@@ -0,0 +1,349 @@
+use hecs::Entity;
+use ::rand::rngs::StdRng;
+use ::rand::seq::SliceRandom;
+use ::rand::SeedableRng;
+use std::collections::{HashMap, HashSet};
+use std::fmt::Write as _;
+
+use crate::components::{Genome, Lineage};
+use crate::stats::CORRELATED_GENES;
+use crate::world::World;
+
+// Every gene of `CORRELATED_GENES`, in its order
+pub type Genes = [f32; CORRELATED_GENES.len()];
+
+// A bacterium in the recorded ancestry
+struct Node {
+    parent: Option<Entity>,
+    born: u64,
+    generation: u32,
+    genes: Genes,
+    // Children still in the ancestry
+    children: u32,
+}
+
+// One bacterium along a line of descent, with its genes at birth
+pub struct Ancestor {
+    pub id: Entity,
+    pub born: u64,
+    pub generation: u32,
+    pub genes: Genes,
+}
+
+// A node of an exported tree, after single-child chains were collapsed
+struct TreeNode {
+    // Survivors by handle; inner nodes have none
+    label: Option<String>,
+    // Ticks from the parent node's birth to this one's
+    length: u64,
+    children: Vec<usize>,
+}
+
+// The ancestry of the living bacteria: every bacterium is recorded at birth
+// and dropped once it and all its descendants have died, so only the lines
+// leading to survivors are kept
+pub struct Phylogeny {
+    nodes: HashMap<Entity, Node>,
+    alive: HashSet<Entity>,
+    last_tick: Option<u64>,
+}
+
+impl Phylogeny {
+    pub fn new() -> Self {
+        Self { nodes: HashMap::new(), alive: HashSet::new(), last_tick: None }
+    }
+
+    // The world was replaced, so handles from the old one mean nothing
+    pub fn new_world(&mut self) {
+        *self = Self::new();
+    }
+
+    // Once a tick, add the bacteria born since the last one and prune the
+    // lines that died out. Ticks may have gone by unseen (a batch on the
+    // worker thread): the world's births cover those, so bacteria born and
+    // dead in between still link their offspring to the rest.
+    pub fn record(&mut self, world: &World) {
+        if self.last_tick == Some(world.tick) {
+            return;
+        }
+        self.last_tick = Some(world.tick);
+        for birth in &world.born {
+            self.add(birth.id, birth.parent, birth.tick, birth.generation, birth.genes);
+        }
+        let mut living = HashSet::new();
+        let mut born = Vec::new();
+        for (id, genome, lineage) in world.ecs.query::<(Entity, &Genome, &Lineage)>().iter() {
+            living.insert(id);
+            if !self.alive.contains(&id) {
+                born.push((id, lineage.parent, lineage.generation, CORRELATED_GENES.map(|(_, tag)| genome.0.gene(tag))));
+            }
+        }
+        // Parents before their offspring, for those born in the same tick
+        born.sort_by_key(|&(_, _, generation, _)| generation);
+        for (id, parent, generation, genes) in born {
+            self.add(id, parent, world.tick, generation, genes);
+        }
+
+        let died: Vec<Entity> = self.alive.iter().filter(|id| !living.contains(id)).copied().collect();
+        for id in died {
+            self.alive.remove(&id);
+            self.prune(id);
+        }
+    }
+
+    fn add(&mut self, id: Entity, parent: Option<Entity>, born: u64, generation: u32, genes: Genes) {
+        if self.alive.contains(&id) {
+            return;
+        }
+        let parent = parent.filter(|p| self.nodes.contains_key(p));
+        if let Some(node) = parent.and_then(|p| self.nodes.get_mut(&p)) {
+            node.children += 1;
+        }
+        self.nodes.insert(id, Node { parent, born, generation, genes, children: 0 });
+        self.alive.insert(id);
+    }
+
+    // A living bacterium's recorded line of descent, from the oldest ancestor
+    // known down to it; empty if it isn't recorded
+    pub fn ancestry(&self, id: Entity) -> Vec<Ancestor> {
+        let mut line = Vec::new();
+        let mut next = Some(id);
+        while let Some(id) = next && let Some(node) = self.nodes.get(&id) {
+            line.push(Ancestor { id, born: node.born, generation: node.generation, genes: node.genes });
+            next = node.parent;
+        }
+        line.reverse();
+        line
+    }
+
+    // Drop a dead bacterium without descendants, and any ancestors that
+    // leaves without any
+    fn prune(&mut self, mut id: Entity) {
+        while !self.alive.contains(&id) && self.nodes.get(&id).is_some_and(|n| n.children == 0) {
+            let Some(parent) = self.nodes.remove(&id).and_then(|n| n.parent) else { break };
+            let Some(node) = self.nodes.get_mut(&parent) else { break };
+            node.children -= 1;
+            id = parent;
+        }
+    }
+
+    // Up to `sample` survivors at random (all of them for 0), drawn the same
+    // way for the same tick, with their ancestry. Branch lengths are ticks
+    // between births. Ancestors with a single line through them are
+    // collapsed, and a survivor with sampled descendants sits at its
+    // branching point as a tip of length 0. The first node is the root.
+    fn tree(&self, tick: u64, sample: usize) -> Vec<TreeNode> {
+        let mut survivors: Vec<Entity> = self.alive.iter().copied().collect();
+        survivors.sort_by_key(|id| id.to_bits());
+        if sample > 0 && survivors.len() > sample {
+            let mut rng = StdRng::seed_from_u64(tick);
+            survivors = survivors.choose_multiple(&mut rng, sample).copied().collect();
+        }
+        let sampled: HashSet<Entity> = survivors.iter().copied().collect();
+
+        // Children of every ancestor of a sampled survivor
+        let mut children: HashMap<Entity, Vec<Entity>> = HashMap::new();
+        let mut roots = Vec::new();
+        for &leaf in &survivors {
+            // Already reached as another survivor's ancestor
+            if children.contains_key(&leaf) {
+                continue;
+            }
+            children.insert(leaf, Vec::new());
+            let mut id = leaf;
+            loop {
+                let Some(parent) = self.nodes.get(&id).and_then(|n| n.parent) else {
+                    roots.push(id);
+                    break;
+                };
+                let seen = children.contains_key(&parent);
+                children.entry(parent).or_default().push(id);
+                if seen {
+                    break;
+                }
+                id = parent;
+            }
+        }
+        for kids in children.values_mut() {
+            kids.sort_by_key(|id| id.to_bits());
+        }
+        roots.sort_by_key(|id| id.to_bits());
+
+        let born = |id: &Entity| self.nodes.get(id).map_or(0, |n| n.born);
+        let mut tree = vec![TreeNode { label: None, length: 0, children: Vec::new() }];
+        // Ancestry nodes still to add: handle, parent tree node and the
+        // birth tick its branch starts from
+        let mut pending: Vec<(Entity, usize, u64)> = roots.iter().rev().map(|&id| (id, 0, 0)).collect();
+        while let Some((mut id, parent, start)) = pending.pop() {
+            // Skip ancestors with a single line through them
+            while !sampled.contains(&id) && children[&id].len() == 1 {
+                id = children[&id][0];
+            }
+            let index = tree.len();
+            let length = born(&id).saturating_sub(start);
+            tree[parent].children.push(index);
+            let kids = &children[&id];
+            if kids.is_empty() {
+                tree.push(TreeNode { label: Some(format!("{:?}", id)), length, children: Vec::new() });
+                continue;
+            }
+            tree.push(TreeNode { label: None, length, children: Vec::new() });
+            if sampled.contains(&id) {
+                tree[index].children.push(index + 1);
+                tree.push(TreeNode { label: Some(format!("{:?}", id)), length: 0, children: Vec::new() });
+            }
+            for &kid in kids.iter().rev() {
+                pending.push((kid, index, born(&id)));
+            }
+        }
+        tree
+    }
+
+    // The sampled tree in Newick format, e.g. `((12v1:40,15v1:25):300,3v1:10);`
+    pub fn newick(&self, tick: u64, sample: usize) -> String {
+        let tree = self.tree(tick, sample);
+        let mut out = String::new();
+        write_newick(&tree, 0, &mut out);
+        out.push_str(";\n");
+        out
+    }
+
+    // The sampled tree as a GraphViz digraph, root on the left, survivors
+    // filled and each edge labelled with its length in ticks
+    pub fn dot(&self, tick: u64, sample: usize) -> String {
+        let tree = self.tree(tick, sample);
+        let mut out = String::from("digraph phylogeny {\n  rankdir=LR;\n  node [shape=point];\n");
+        for (i, node) in tree.iter().enumerate() {
+            if let Some(label) = &node.label {
+                let _ = writeln!(out, "  n{} [shape=box, style=filled, fillcolor=\"#b8e0b0\", label=\"{}\"];", i, label);
+            }
+            for &child in &node.children {
+                let _ = writeln!(out, "  n{} -> n{} [label=\"{}\"];", i, child, tree[child].length);
+            }
+        }
+        out.push_str("}\n");
+        out
+    }
+
+    // GraphViz DOT for a `.dot` or `.gv` path, Newick otherwise
+    pub fn write(&self, path: &str, tick: u64, sample: usize) -> std::io::Result<()> {
+        let dot = matches!(std::path::Path::new(path).extension().and_then(|e| e.to_str()), Some("dot" | "gv"));
+        let text = if dot { self.dot(tick, sample) } else { self.newick(tick, sample) };
+        if let Some(dir) = std::path::Path::new(path).parent().filter(|d| !d.as_os_str().is_empty()) {
+            std::fs::create_dir_all(dir)?;
+        }
+        std::fs::write(path, text)
+    }
+}
+
+impl Default for Phylogeny {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn write_newick(tree: &[TreeNode], index: usize, out: &mut String) {
+    let node = &tree[index];
+    if !node.children.is_empty() {
+        out.push('(');
+        for (i, &child) in node.children.iter().enumerate() {
+            if i > 0 {
+                out.push(',');
+            }
+            write_newick(tree, child, out);
+        }
+        out.push(')');
+    }
+    if let Some(label) = &node.label {
+        out.push_str(label);
+    }
+    if index > 0 {
+        let _ = write!(out, ":{}", node.length);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Handles 0v1, 1v1, ... in order
+    fn handles(n: usize) -> Vec<Entity> {
+        let mut ecs = hecs::World::new();
+        (0..n).map(|_| ecs.spawn(())).collect()
+    }
+
+    fn die(phylogeny: &mut Phylogeny, id: Entity) {
+        phylogeny.alive.remove(&id);
+        phylogeny.prune(id);
+    }
+
+    // A founder born at 0 with offspring A at 10 and B at 20, and A's
+    // offspring C at 50 and D at 60. The founder and B have died.
+    fn family() -> Phylogeny {
+        let [founder, a, b, c, d] = handles(5).try_into().unwrap();
+        let mut phylogeny = Phylogeny::new();
+        let genes = [0.0; CORRELATED_GENES.len()];
+        phylogeny.add(founder, None, 0, 0, genes);
+        phylogeny.add(a, Some(founder), 10, 1, genes);
+        phylogeny.add(b, Some(founder), 20, 1, genes);
+        phylogeny.add(c, Some(a), 50, 2, genes);
+        phylogeny.add(d, Some(a), 60, 2, genes);
+        die(&mut phylogeny, founder);
+        die(&mut phylogeny, b);
+        phylogeny
+    }
+
+    #[test]
+    fn dead_lines_are_pruned() {
+        let phylogeny = family();
+        // B went with no descendants; the founder stays as A's ancestor
+        assert_eq!(phylogeny.nodes.len(), 4);
+        let line: Vec<_> = phylogeny.ancestry(handles(5)[3]).iter().map(|a| (a.born, a.generation)).collect();
+        assert_eq!(line, vec![(0, 0), (10, 1), (50, 2)]);
+    }
+
+    #[test]
+    fn newick_collapses_single_lines_and_puts_living_ancestors_at_their_branch() {
+        // The founder only leads to A, so it's folded into A's branch of 10
+        // ticks; A is alive, so it's a tip of length 0 beside C and D
+        assert_eq!(family().newick(100, 0), "((1v1:0,3v1:40,4v1:50):10);\n");
+    }
+
+    #[test]
+    fn newick_of_a_single_line() {
+        let [founder, a, b] = handles(3).try_into().unwrap();
+        let mut phylogeny = Phylogeny::new();
+        let genes = [0.0; CORRELATED_GENES.len()];
+        phylogeny.add(founder, None, 0, 0, genes);
+        phylogeny.add(a, Some(founder), 10, 1, genes);
+        phylogeny.add(b, Some(a), 30, 2, genes);
+        die(&mut phylogeny, founder);
+        die(&mut phylogeny, a);
+        assert_eq!(phylogeny.newick(100, 0), "(2v1:30);\n");
+    }
+
+    #[test]
+    fn sampling_is_the_same_for_the_same_tick() {
+        let phylogeny = family();
+        let tree = phylogeny.newick(100, 1);
+        assert_eq!(tree, phylogeny.newick(100, 1));
+        assert_eq!(tree.matches('v').count(), 1, "{}", tree);
+    }
+
+    #[test]
+    fn dot_draws_the_same_tree() {
+        let expected = "digraph phylogeny {
+  rankdir=LR;
+  node [shape=point];
+  n0 -> n1 [label=\"10\"];
+  n1 -> n2 [label=\"0\"];
+  n1 -> n3 [label=\"40\"];
+  n1 -> n4 [label=\"50\"];
+  n2 [shape=box, style=filled, fillcolor=\"#b8e0b0\", label=\"1v1\"];
+  n3 [shape=box, style=filled, fillcolor=\"#b8e0b0\", label=\"3v1\"];
+  n4 [shape=box, style=filled, fillcolor=\"#b8e0b0\", label=\"4v1\"];
+}
+";
+        assert_eq!(family().dot(100, 0), expected);
+    }
+}
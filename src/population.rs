@@ -0,0 +1,226 @@
+use macroquad::prelude::*;
+use serde::Deserialize;
+
+use crate::dna::{
+    Gene, AEROBIC, AGGRESSION, ARMOR, BURST, COLOR_B, COLOR_G, COLOR_R, COOLDOWN, CROWDING, DISPERSAL, DNA, FLEE_ENERGY, INVESTMENT,
+    MATURATION, MAX_MUTATION_RATE, MIN_MUTATION_RATE, MUTATION_RATE, PH_OPTIMUM, PH_TOLERANCE, SENSE_RADIUS, SIZE, SPEED, TOXIN, VIGILANCE,
+};
+use crate::organism::Bacterium;
+use crate::world::World;
+
+// Starting bacteria with set genes, read from a JSON file
+// (`--population seeds.json`):
+//
+//     [
+//         { "speed": 2.0, "size": 5.0, "sense_radius": 40.0, "x": 100.0, "y": 200.0 },
+//         { "speed": 1.2, "size": 7.5, "sense_radius": 30.0, "aerobic": 0.1, "count": 20 }
+//     ]
+//
+// Genes left out take the middle of their random range, so a file always
+// gives the same genomes. Without `x` and `y` the bacteria appear where new
+// organisms spawn; `count` makes that many copies and `energy` defaults to
+// the initial energy.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Seed {
+    pub speed: f32,
+    pub size: f32,
+    pub sense_radius: f32,
+    #[serde(default = "default_color")]
+    pub color: [f32; 3],
+    #[serde(default = "default_aerobic")]
+    pub aerobic: f32,
+    #[serde(default = "default_ph_optimum")]
+    pub ph_optimum: f32,
+    #[serde(default = "default_ph_tolerance")]
+    pub ph_tolerance: f32,
+    #[serde(default = "default_burst")]
+    pub burst: f32,
+    #[serde(default = "default_vigilance")]
+    pub vigilance: f32,
+    #[serde(default = "default_aggression")]
+    pub aggression: f32,
+    #[serde(default = "default_armor")]
+    pub armor: f32,
+    #[serde(default = "default_toxin")]
+    pub toxin: f32,
+    #[serde(default = "default_investment")]
+    pub investment: f32,
+    #[serde(default = "default_maturation")]
+    pub maturation: f32,
+    #[serde(default = "default_cooldown")]
+    pub cooldown: f32,
+    #[serde(default = "default_dispersal")]
+    pub dispersal: f32,
+    #[serde(default = "default_flee_energy")]
+    pub flee_energy: f32,
+    #[serde(default = "default_crowding")]
+    pub crowding: f32,
+    #[serde(default = "default_mutation_rate")]
+    pub mutation_rate: f32,
+    pub x: Option<f32>,
+    pub y: Option<f32>,
+    pub energy: Option<f32>,
+    #[serde(default = "default_count")]
+    pub count: usize,
+}
+
+fn default_color() -> [f32; 3] {
+    [0.6, 0.6, 0.6]
+}
+
+fn default_aerobic() -> f32 {
+    0.5
+}
+
+fn default_ph_optimum() -> f32 {
+    7.0
+}
+
+fn default_ph_tolerance() -> f32 {
+    1.25
+}
+
+fn default_burst() -> f32 {
+    0.5
+}
+
+fn default_vigilance() -> f32 {
+    0.5
+}
+
+fn default_aggression() -> f32 {
+    0.5
+}
+
+fn default_armor() -> f32 {
+    0.25
+}
+
+fn default_toxin() -> f32 {
+    0.15
+}
+
+fn default_investment() -> f32 {
+    0.5
+}
+
+fn default_maturation() -> f32 {
+    50.0
+}
+
+fn default_cooldown() -> f32 {
+    50.0
+}
+
+fn default_dispersal() -> f32 {
+    0.25
+}
+
+fn default_flee_energy() -> f32 {
+    20.0
+}
+
+fn default_crowding() -> f32 {
+    12.5
+}
+
+fn default_mutation_rate() -> f32 {
+    0.1
+}
+
+fn default_count() -> usize {
+    1
+}
+
+impl Seed {
+    pub fn dna(&self) -> DNA {
+        let genes = [
+            (SPEED, self.speed),
+            (SIZE, self.size),
+            (SENSE_RADIUS, self.sense_radius),
+            (COLOR_R, self.color[0]),
+            (COLOR_G, self.color[1]),
+            (COLOR_B, self.color[2]),
+            (AEROBIC, self.aerobic),
+            (PH_OPTIMUM, self.ph_optimum),
+            (PH_TOLERANCE, self.ph_tolerance),
+            (BURST, self.burst),
+            (VIGILANCE, self.vigilance),
+            (AGGRESSION, self.aggression),
+            (ARMOR, self.armor),
+            (TOXIN, self.toxin),
+            (INVESTMENT, self.investment),
+            (MATURATION, self.maturation),
+            (COOLDOWN, self.cooldown),
+            (DISPERSAL, self.dispersal),
+            (FLEE_ENERGY, self.flee_energy),
+            (CROWDING, self.crowding),
+            (MUTATION_RATE, self.mutation_rate),
+        ];
+        DNA { genes: genes.into_iter().map(|(tag, value)| Gene::new(tag, value)).collect(), markers: Vec::new() }
+    }
+
+    // Checked against a world `width` by `height`
+    fn check(&self, width: f32, height: f32) -> Result<(), String> {
+        if self.speed <= 0.0 || self.size <= 0.0 || self.sense_radius < 0.0 {
+            return Err("speed and size must be positive and sense_radius not negative".to_string());
+        }
+        if !(0.0..=1.0).contains(&self.aerobic) || self.ph_tolerance < 0.0 {
+            return Err("aerobic must be between 0 and 1 and ph_tolerance not negative".to_string());
+        }
+        if [self.burst, self.vigilance, self.aggression, self.armor, self.toxin, self.dispersal].iter().any(|gene| !(0.0..=1.0).contains(gene)) {
+            return Err("burst, vigilance, aggression, armor, toxin and dispersal must be between 0 and 1".to_string());
+        }
+        if !(0.05..=0.95).contains(&self.investment) {
+            return Err("investment must be between 0.05 and 0.95".to_string());
+        }
+        if [self.maturation, self.cooldown, self.flee_energy, self.crowding].iter().any(|gene| *gene < 0.0) {
+            return Err("maturation, cooldown, flee_energy and crowding must not be negative".to_string());
+        }
+        if !(MIN_MUTATION_RATE..=MAX_MUTATION_RATE).contains(&self.mutation_rate) {
+            return Err(format!("mutation_rate must be between {} and {}", MIN_MUTATION_RATE, MAX_MUTATION_RATE));
+        }
+        match (self.x, self.y) {
+            (Some(x), Some(y)) if !(0.0..=width).contains(&x) || !(0.0..=height).contains(&y) => {
+                Err(format!("({}, {}) is outside the {}x{} world", x, y, width, height))
+            }
+            (Some(_), None) | (None, Some(_)) => Err("give both x and y, or neither".to_string()),
+            _ => Ok(()),
+        }
+    }
+}
+
+// The seeds in `path`, for a world `width` by `height`
+pub fn load(path: &str, width: f32, height: f32) -> Result<Vec<Seed>, String> {
+    let text = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
+    let seeds: Vec<Seed> = serde_json::from_str(&text).map_err(|e| e.to_string())?;
+    for (i, seed) in seeds.iter().enumerate() {
+        seed.check(width, height).map_err(|e| format!("bacterium {}: {}", i + 1, e))?;
+    }
+    Ok(seeds)
+}
+
+// Replace the world's bacteria with the seeded ones
+pub fn populate(world: &mut World, seeds: &[Seed], initial_energy: f32) {
+    world.remove_bacteria();
+    spawn_seeds(world, seeds, initial_energy, None);
+}
+
+// Add the seeded bacteria, their lineage tagged with `origin`
+pub fn spawn_seeds(world: &mut World, seeds: &[Seed], initial_energy: f32, origin: Option<usize>) {
+    for seed in seeds {
+        let dna = seed.dna();
+        let energy = seed.energy.unwrap_or(initial_energy);
+        let positions = match (seed.x, seed.y) {
+            (Some(x), Some(y)) => vec![vec2(x, y); seed.count],
+            _ => world.spawn_positions(seed.count),
+        };
+        for pos in positions {
+            let mut bacterium = Bacterium::new(pos, energy, &mut *world.rng());
+            bacterium.dna = dna.clone();
+            bacterium.lineage.origin = origin;
+            bacterium.spawn(&mut world.ecs);
+        }
+    }
+}
@@ -0,0 +1,87 @@
+use std::path::PathBuf;
+use tracing::warn;
+
+use crate::params::SimulationParams;
+
+// Folder presets are saved in, one `<name>.json` each
+pub const PRESET_DIR: &str = "presets";
+
+// Oldest settings are forgotten past this many undo steps
+const UNDO_LIMIT: usize = 50;
+
+fn preset_path(name: &str) -> PathBuf {
+    PathBuf::from(PRESET_DIR).join(format!("{}.json", name))
+}
+
+// Names can't leave the preset folder or be empty
+pub fn valid_name(name: &str) -> bool {
+    !name.trim().is_empty() && name.chars().all(|c| c.is_alphanumeric() || c == ' ' || c == '-' || c == '_')
+}
+
+pub fn save(name: &str, params: &SimulationParams) -> Result<(), String> {
+    if !valid_name(name) {
+        return Err(format!("bad preset name '{}'", name));
+    }
+    std::fs::create_dir_all(PRESET_DIR).map_err(|e| e.to_string())?;
+    let text = serde_json::to_string_pretty(params).map_err(|e| e.to_string())?;
+    std::fs::write(preset_path(name), text).map_err(|e| e.to_string())
+}
+
+// Apply a saved preset over `params`; like the config file, it may hold any
+// subset of the parameters
+pub fn load(name: &str, params: &mut SimulationParams) -> Result<(), String> {
+    let text = std::fs::read_to_string(preset_path(name)).map_err(|e| e.to_string())?;
+    let changes = serde_json::from_str(&text).map_err(|e| e.to_string())?;
+    params.merge(changes)
+}
+
+// Names of the saved presets, sorted
+pub fn list() -> Vec<String> {
+    let Ok(entries) = std::fs::read_dir(PRESET_DIR) else { return Vec::new() };
+    let mut names: Vec<String> = entries
+        .flatten()
+        .filter_map(|entry| {
+            let path = entry.path();
+            let is_json = path.extension().is_some_and(|ext| ext == "json");
+            is_json.then(|| path.file_stem()?.to_str().map(str::to_owned)).flatten()
+        })
+        .collect();
+    names.sort();
+    names
+}
+
+// Earlier settings to go back to, one step per completed change (a whole
+// slider drag, a preset load, an API call)
+pub struct ParamUndo {
+    applied: serde_json::Value,
+    stack: Vec<serde_json::Value>,
+}
+
+impl ParamUndo {
+    pub fn new(params: &SimulationParams) -> Self {
+        Self { applied: serde_json::to_value(params).unwrap_or_default(), stack: Vec::new() }
+    }
+
+    // Call once a change is complete (not mid-drag)
+    pub fn record(&mut self, params: &SimulationParams) {
+        let current = serde_json::to_value(params).unwrap_or_default();
+        if current != self.applied {
+            self.stack.push(std::mem::replace(&mut self.applied, current));
+            if self.stack.len() > UNDO_LIMIT {
+                self.stack.remove(0);
+            }
+        }
+    }
+
+    // Go back to the settings before the last change
+    pub fn undo(&mut self, params: &mut SimulationParams) {
+        let Some(previous) = self.stack.pop() else { return };
+        match serde_json::from_value(previous.clone()) {
+            Ok(restored) => {
+                *params = restored;
+                self.applied = previous;
+            }
+            Err(e) => warn!("Couldn't undo: {}", e),
+        }
+    }
+}
@@ -0,0 +1,104 @@
+use macroquad::prelude::*;
+use std::collections::VecDeque;
+use std::time::Instant;
+use tracing::debug;
+
+use crate::logging::PERF;
+
+const HISTORY: usize = 120;
+const ROW_HEIGHT: f32 = 18.0;
+const SPARK_WIDTH: f32 = 120.0;
+// Frames slower than this are logged, phase by phase
+const SLOW_FRAME_MS: f32 = 50.0;
+
+struct Phase {
+    name: &'static str,
+    current: f32,
+    history: VecDeque<f32>,
+}
+
+// Per-frame timing breakdown. Phases accumulate milliseconds over a frame (the
+// sim may tick several times per frame) and are pushed into a short history
+// when the frame ends.
+pub struct Profiler {
+    pub visible: bool,
+    phases: Vec<Phase>,
+}
+
+impl Profiler {
+    pub fn new() -> Self {
+        Self {
+            visible: false,
+            phases: Vec::new(),
+        }
+    }
+
+    pub fn record(&mut self, name: &'static str, ms: f32) {
+        match self.phases.iter_mut().find(|p| p.name == name) {
+            Some(phase) => phase.current += ms,
+            None => self.phases.push(Phase { name, current: ms, history: VecDeque::with_capacity(HISTORY) }),
+        }
+    }
+
+    pub fn time<T>(&mut self, name: &'static str, f: impl FnOnce() -> T) -> T {
+        let start = Instant::now();
+        let result = f();
+        self.record(name, start.elapsed().as_secs_f32() * 1000.0);
+        result
+    }
+
+    pub fn end_frame(&mut self) {
+        let total: f32 = self.phases.iter().map(|p| p.current).sum();
+        if total > SLOW_FRAME_MS {
+            let phases: Vec<String> = self.phases.iter().map(|p| format!("{} {:.1}ms", p.name, p.current)).collect();
+            debug!(target: PERF, "Slow frame, {:.1}ms: {}", total, phases.join(", "));
+        }
+        for phase in &mut self.phases {
+            phase.history.push_back(phase.current);
+            if phase.history.len() > HISTORY {
+                phase.history.pop_front();
+            }
+            phase.current = 0.0;
+        }
+    }
+
+    pub fn draw(&self, x: f32, y: f32) {
+        if !self.visible || self.phases.is_empty() {
+            return;
+        }
+        let width = 300.0;
+        let height = (self.phases.len() + 1) as f32 * ROW_HEIGHT + 14.0;
+        draw_rectangle(x, y, width, height, Color::new(0.0, 0.0, 0.0, 0.7));
+        draw_rectangle_lines(x, y, width, height, 1.0, Color::new(0.3, 0.4, 0.5, 0.8));
+
+        let total: f32 = self.phases.iter().filter_map(|p| p.history.back()).sum();
+        draw_text(format!("FRAME {:.2} ms", total), x + 8.0, y + 18.0, 16.0, Color::new(0.4, 0.7, 1.0, 1.0));
+
+        // Shared scale so sparklines are comparable between phases
+        let max_ms = self.phases.iter()
+            .flat_map(|p| p.history.iter().copied())
+            .fold(0.1f32, f32::max);
+
+        for (i, phase) in self.phases.iter().enumerate() {
+            let row_y = y + 18.0 + (i + 1) as f32 * ROW_HEIGHT;
+            let last = phase.history.back().copied().unwrap_or(0.0);
+            draw_text(phase.name, x + 8.0, row_y, 14.0, WHITE);
+            draw_text(format!("{:6.2}", last), x + 95.0, row_y, 14.0, Color::new(0.8, 1.0, 0.8, 1.0));
+
+            let spark_x = x + width - SPARK_WIDTH - 10.0;
+            let step = SPARK_WIDTH / HISTORY as f32;
+            for (j, pair) in phase.history.iter().collect::<Vec<_>>().windows(2).enumerate() {
+                let y1 = row_y - (pair[0] / max_ms) * (ROW_HEIGHT - 4.0);
+                let y2 = row_y - (pair[1] / max_ms) * (ROW_HEIGHT - 4.0);
+                let x1 = spark_x + j as f32 * step;
+                draw_line(x1, y1, x1 + step, y2, 1.0, Color::new(1.0, 0.8, 0.3, 0.9));
+            }
+        }
+    }
+}
+
+impl Default for Profiler {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,519 @@
+use macroquad::prelude::*;
+
+use crate::camera::Camera;
+use crate::coloring::{Coloring, Palette};
+use crate::field::Field;
+use crate::habitat::Shape;
+use crate::terrain::{TerrainType, TERRAIN_CELL};
+use crate::components::{Age, Body, Camouflage, Energy, Food, Genome, Lineage, Plasmids, Position, PredatorTag, Species, Target, Velocity};
+use crate::params::SimulationParams;
+use crate::world::{World, ANTIBIOTIC_BANDS};
+use crate::world3d::World3D;
+
+const DISC_RESOLUTION: u16 = 64;
+
+// Frame-time budget the LOD controller tries to stay under (60 FPS)
+const FRAME_BUDGET: f32 = 1.0 / 60.0;
+// Frames to wait after a LOD switch before judging the new level
+const LOD_COOLDOWN_FRAMES: u32 = 30;
+// Populations above these always render at least this coarsely
+const NO_GLOW_POPULATION: usize = 5_000;
+const POINTS_POPULATION: usize = 20_000;
+// On-screen radius (pixels) below which organisms are drawn as points
+const MIN_VISIBLE_RADIUS: f32 = 1.0;
+// World units around the visible rect still drawn, so big bodies don't pop at the edge
+const CULL_MARGIN: f32 = 20.0;
+// Widest energy ring with shape coding on, for a bacterium ready to reproduce
+const MAX_ENERGY_RING: f32 = 3.0;
+// Velocity vectors point this many ticks of movement ahead
+const VELOCITY_TICKS: f32 = 8.0;
+const MINIMAP_SIZE: f32 = 200.0;
+const MINIMAP_CELLS: usize = 40;
+// Side of the cells bacteria are pooled in for the resistance heatmap
+const RESISTANCE_CELL: f32 = 40.0;
+const MINIMAP_MAX_DOTS: usize = 5_000;
+// Opacity of a predator hidden in ambush
+const HIDDEN_ALPHA: f32 = 0.25;
+// Configured consumer species, in order, cycling past the end
+const SPECIES_COLORS: [Color; 4] = [
+    Color::new(0.95, 0.75, 0.2, 1.0),
+    Color::new(0.7, 0.3, 0.9, 1.0),
+    Color::new(0.2, 0.6, 0.95, 1.0),
+    Color::new(0.95, 0.45, 0.7, 1.0),
+];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Lod {
+    Full,
+    NoGlow,
+    Points,
+}
+
+impl Lod {
+    pub fn label(self) -> &'static str {
+        match self {
+            Lod::Full => "full",
+            Lod::NoGlow => "no glow",
+            Lod::Points => "points",
+        }
+    }
+
+    fn coarser(self) -> Self {
+        match self {
+            Lod::Full => Lod::NoGlow,
+            _ => Lod::Points,
+        }
+    }
+
+    fn finer(self) -> Self {
+        match self {
+            Lod::Points => Lod::NoGlow,
+            _ => Lod::Full,
+        }
+    }
+}
+
+// Picks the level of detail each frame: drops detail when the smoothed frame
+// time goes over budget and brings it back once there is plenty of headroom.
+pub struct LodController {
+    level: Lod,
+    avg_frame_time: f32,
+    cooldown: u32,
+}
+
+impl LodController {
+    pub fn new() -> Self {
+        Self {
+            level: Lod::Full,
+            avg_frame_time: FRAME_BUDGET,
+            cooldown: 0,
+        }
+    }
+
+    pub fn level(&self) -> Lod {
+        self.level
+    }
+
+    // `scale` is on-screen pixels per world unit
+    pub fn update(&mut self, frame_time: f32, population: usize, scale: f32) -> Lod {
+        self.avg_frame_time = self.avg_frame_time * 0.9 + frame_time * 0.1;
+        if self.cooldown > 0 {
+            self.cooldown -= 1;
+        } else if self.avg_frame_time > FRAME_BUDGET * 1.1 && self.level != Lod::Points {
+            self.level = self.level.coarser();
+            self.cooldown = LOD_COOLDOWN_FRAMES;
+        } else if self.avg_frame_time < FRAME_BUDGET * 0.6 && self.level != Lod::Full {
+            self.level = self.level.finer();
+            self.cooldown = LOD_COOLDOWN_FRAMES;
+        }
+
+        let mut floor = if population > POINTS_POPULATION {
+            Lod::Points
+        } else if population > NO_GLOW_POPULATION {
+            Lod::NoGlow
+        } else {
+            Lod::Full
+        };
+        // Zoomed so far out that a typical bacterium is under a pixel
+        if 3.0 * scale < MIN_VISIBLE_RADIUS {
+            floor = Lod::Points;
+        }
+        self.level.max(floor)
+    }
+}
+
+impl Default for LodController {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// Every circle in the world is a tinted quad of the same disc texture, so
+// macroquad batches the whole scene into a handful of draw calls instead of
+// tessellating a triangle fan per circle.
+pub struct Sprites {
+    disc: Texture2D,
+}
+
+impl Sprites {
+    pub fn new() -> Self {
+        let size = DISC_RESOLUTION;
+        let mut image = Image::gen_image_color(size, size, Color::new(1.0, 1.0, 1.0, 0.0));
+        let center = size as f32 / 2.0;
+        for y in 0..size as u32 {
+            for x in 0..size as u32 {
+                let d = vec2(x as f32 + 0.5, y as f32 + 0.5).distance(vec2(center, center));
+                // One pixel of antialiasing at the rim
+                let alpha = (center - d).clamp(0.0, 1.0);
+                image.set_pixel(x, y, Color::new(1.0, 1.0, 1.0, alpha));
+            }
+        }
+        let disc = Texture2D::from_image(&image);
+        disc.set_filter(FilterMode::Linear);
+        Self { disc }
+    }
+
+    pub fn draw_disc(&self, x: f32, y: f32, radius: f32, color: Color) {
+        draw_texture_ex(&self.disc, x - radius, y - radius, color, DrawTextureParams {
+            dest_size: Some(vec2(radius * 2.0, radius * 2.0)),
+            ..Default::default()
+        });
+    }
+}
+
+impl Default for Sprites {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// Draws the entities inside `view` (world units), bacteria coloured by
+// `coloring`; call with the world camera set. With shape coding, `shapes`
+// holds the energy at which a bacterium's energy ring is widest.
+pub fn draw_world(world: &World, sprites: &Sprites, lod: Lod, view: Rect, coloring: &Coloring, shapes: Option<f32>) {
+    let view = Rect::new(view.x - CULL_MARGIN, view.y - CULL_MARGIN, view.w + CULL_MARGIN * 2.0, view.h + CULL_MARGIN * 2.0);
+
+    // World border
+    draw_rectangle_lines(0.0, 0.0, world.width, world.height, 2.0, Color::new(0.3, 0.4, 0.5, 0.5));
+    draw_ph(world, view);
+    if !world.habitat.is_open() {
+        draw_habitat(world);
+    }
+    draw_terrain(world, view);
+
+    if lod == Lod::Points {
+        draw_world_points(world, view, coloring);
+        return;
+    }
+    let glow = lod == Lod::Full;
+
+    // Draw food
+    for pos in world.ecs.query::<&Position>().with::<&Food>().iter() {
+        if !view.contains(pos.0) {
+            continue;
+        }
+        if glow {
+            sprites.draw_disc(pos.0.x, pos.0.y, 2.5, Color::new(0.2f32, 1.0f32, 0.6f32, 0.7f32));
+        }
+        sprites.draw_disc(pos.0.x, pos.0.y, 1.5, Color::new(0.5f32, 1.0f32, 0.8f32, 0.9f32));
+    }
+
+    // Draw bacteria
+    for (id, pos, body, genome, energy, age, lineage) in world.ecs.query::<(hecs::Entity, &Position, &Body, &Genome, &Energy, &Age, &Lineage)>().iter() {
+        if !view.contains(pos.0) {
+            continue;
+        }
+        let color = coloring.color(id, genome, energy.0, age.0, lineage);
+        // Glow effect
+        if glow {
+            sprites.draw_disc(pos.0.x, pos.0.y, body.size + 2.0, Color::new(color.r, color.g, color.b, 0.2));
+        }
+        sprites.draw_disc(pos.0.x, pos.0.y, body.size, color);
+        if let Some(full) = shapes {
+            let width = 0.5 + (energy.0 / full).clamp(0.0, 1.0) * (MAX_ENERGY_RING - 0.5);
+            draw_circle_lines(pos.0.x, pos.0.y, body.size + width / 2.0 + 0.5, width, Color::new(1.0, 1.0, 1.0, 0.8));
+        }
+    }
+
+    // Draw predators
+    for (pos, vel, body, species, camouflage) in world.ecs.query::<(&Position, &Velocity, &Body, Option<&Species>, Option<&Camouflage>)>().with::<&PredatorTag>().iter() {
+        if !view.contains(pos.0) {
+            continue;
+        }
+        let mut predator = species.map_or(coloring.palette.predator(), |s| SPECIES_COLORS[s.0 % SPECIES_COLORS.len()]);
+        // Hidden ambushers are faint and don't glow
+        let hidden = camouflage.is_some_and(|c| c.hidden);
+        if hidden {
+            predator.a *= HIDDEN_ALPHA;
+        }
+        let glow = glow && !hidden;
+        let p = pos.0;
+        // Shape coding: a triangle pointing where the predator is heading
+        if shapes.is_some() {
+            let dir = vel.0.try_normalize().unwrap_or(Vec2::X);
+            let side = dir.perp() * body.size * 0.9;
+            let tip = p + dir * body.size * 1.4;
+            let back = p - dir * body.size * 0.8;
+            draw_triangle(tip, back + side, back - side, predator);
+            draw_triangle_lines(tip, back + side, back - side, 1.5, Color::new(0.0, 0.0, 0.0, if hidden { 0.8 * HIDDEN_ALPHA } else { 0.8 }));
+            continue;
+        }
+        // Glow effect
+        if glow {
+            sprites.draw_disc(p.x, p.y, body.size + 3.0, Color::new(predator.r, predator.g, predator.b, 0.3));
+        }
+        sprites.draw_disc(p.x, p.y, body.size, predator);
+        if glow {
+            let eye = if coloring.palette == Palette::Standard {
+                sprites.draw_disc(p.x, p.y, body.size * 0.6, Color::new(1.0, 0.5, 0.3, 0.8));
+                Color::new(1.0, 1.0, 0.0, 0.9)
+            } else {
+                Color::new(0.0, 0.0, 0.0, 0.9)
+            };
+            // Eyes
+            sprites.draw_disc(p.x - 3.0, p.y - 2.0, 2.0, eye);
+            sprites.draw_disc(p.x + 3.0, p.y - 2.0, 2.0, eye);
+        }
+    }
+}
+
+// Waste as a brown haze, orange once it reaches the toxic threshold
+pub fn draw_pollution(world: &World, view: Rect, threshold: f32) {
+    draw_field(&world.waste, view, |waste| {
+        let level = (waste / threshold).min(1.0);
+        if level >= 1.0 { Color::new(0.8, 0.3, 0.1, 0.6) } else { Color::new(0.55, 0.4, 0.15, 0.5 * level) }
+    });
+}
+
+// Oxygen as a blue tint, clear where the water is anoxic
+pub fn draw_oxygen(world: &World, view: Rect) {
+    draw_field(&world.oxygen, view, |oxygen| Color::new(0.2, 0.5, 1.0, 0.3 * oxygen));
+}
+
+// Antibiotic as a magenta tint, deeper in each band of the gradient, under a
+// heatmap of the bacteria's average resistance in each cell: red where
+// they're sensitive, green where they're resistant
+pub fn draw_resistance(world: &World, params: &SimulationParams, view: Rect) {
+    let top = world.antibiotic_at(params, vec2(world.width, 0.0));
+    if top > 0.0 {
+        let band_width = world.width / ANTIBIOTIC_BANDS as f32;
+        for band in 0..ANTIBIOTIC_BANDS {
+            let x = band as f32 * band_width;
+            let level = world.antibiotic_at(params, vec2(x, 0.0)) / top;
+            draw_rectangle(x, 0.0, band_width, world.height, Color::new(0.9, 0.2, 0.8, 0.15 * level));
+        }
+    }
+
+    let mut count = Field::new(world.width, world.height, RESISTANCE_CELL);
+    let mut resistance = Field::new(world.width, world.height, RESISTANCE_CELL);
+    for (pos, plasmids) in world.ecs.query::<(&Position, &Plasmids)>().iter() {
+        count.add(pos.0, 1.0);
+        resistance.add(pos.0, plasmids.resistance());
+    }
+    let (cols, rows) = count.cells_in(view);
+    for row in rows {
+        for col in cols.clone() {
+            let n = count.get(col, row);
+            if n > 0.0 {
+                let share = resistance.get(col, row) / n;
+                draw_rectangle(col as f32 * RESISTANCE_CELL, row as f32 * RESISTANCE_CELL, RESISTANCE_CELL, RESISTANCE_CELL, Color::new(1.0 - share, share, 0.2, 0.35));
+            }
+        }
+    }
+}
+
+// Faint background tint: warm for acid, violet for alkaline, clear when neutral
+fn draw_ph(world: &World, view: Rect) {
+    draw_field(&world.ph, view, |ph| {
+        let shift = (ph - 7.0) / 7.0;
+        if shift < 0.0 { Color::new(1.0, 0.5, 0.1, -0.12 * shift) } else { Color::new(0.6, 0.3, 1.0, 0.12 * shift) }
+    });
+}
+
+// One rectangle per visible cell, skipping cells that come out (nearly) clear
+fn draw_field(field: &Field, view: Rect, color: impl Fn(f32) -> Color) {
+    let (cols, rows) = field.cells_in(view);
+    for row in rows {
+        for col in cols.clone() {
+            let c = color(field.get(col, row));
+            if c.a > 0.005 {
+                draw_rectangle(col as f32 * field.cell, row as f32 * field.cell, field.cell, field.cell, c);
+            }
+        }
+    }
+}
+
+// Tint the painted terrain cells inside `view`
+fn draw_terrain(world: &World, view: Rect) {
+    let terrain = &world.terrain;
+    let first_col = (view.x / TERRAIN_CELL).max(0.0) as usize;
+    let first_row = (view.y / TERRAIN_CELL).max(0.0) as usize;
+    let last_col = ((view.right() / TERRAIN_CELL).ceil().max(0.0) as usize).min(terrain.cols);
+    let last_row = ((view.bottom() / TERRAIN_CELL).ceil().max(0.0) as usize).min(terrain.rows);
+    for row in first_row..last_row {
+        for col in first_col..last_col {
+            let kind = terrain.get(col, row);
+            if kind != TerrainType::Normal {
+                draw_rectangle(col as f32 * TERRAIN_CELL, row as f32 * TERRAIN_CELL, TERRAIN_CELL, TERRAIN_CELL, kind.color());
+            }
+        }
+    }
+}
+
+// Region floors tinted by region, corridors in grey
+fn draw_habitat(world: &World) {
+    for corridor in &world.habitat.corridors {
+        draw_rectangle(corridor.x, corridor.y, corridor.w, corridor.h, Color::new(0.5, 0.5, 0.6, 0.12));
+    }
+    for region in &world.habitat.regions {
+        let c = region.color;
+        let (fill, rim) = (Color::new(c.r, c.g, c.b, 0.04), Color::new(c.r, c.g, c.b, 0.5));
+        match region.shape {
+            Shape::Rect(b) => {
+                draw_rectangle(b.x, b.y, b.w, b.h, fill);
+                draw_rectangle_lines(b.x, b.y, b.w, b.h, 2.0, rim);
+            }
+            Shape::Disc { center, radius } => {
+                draw_poly(center.x, center.y, 128, radius, 0.0, fill);
+                draw_poly_lines(center.x, center.y, 128, radius, 0.0, 2.0, rim);
+            }
+        }
+    }
+}
+
+// Cheapest level: one small square per entity, no glow or detail. Sizes are in
+// world units, so they are scaled up by the camera zoom like everything else.
+fn draw_world_points(world: &World, view: Rect, coloring: &Coloring) {
+    for pos in world.ecs.query::<&Position>().with::<&Food>().iter() {
+        if view.contains(pos.0) {
+            draw_rectangle(pos.0.x, pos.0.y, 1.0, 1.0, Color::new(0.2, 1.0, 0.6, 0.7));
+        }
+    }
+    for (id, pos, genome, energy, age, lineage) in world.ecs.query::<(hecs::Entity, &Position, &Genome, &Energy, &Age, &Lineage)>().iter() {
+        if view.contains(pos.0) {
+            draw_rectangle(pos.0.x - 1.0, pos.0.y - 1.0, 2.0, 2.0, coloring.color(id, genome, energy.0, age.0, lineage));
+        }
+    }
+    for pos in world.ecs.query::<&Position>().with::<&PredatorTag>().iter() {
+        if view.contains(pos.0) {
+            draw_rectangle(pos.0.x - 1.5, pos.0.y - 1.5, 3.0, 3.0, coloring.palette.predator());
+        }
+    }
+}
+
+// Where the minimap sits: top-right corner of the view, shaped like the world
+pub fn minimap_rect(world: &World, camera: &Camera) -> Rect {
+    let aspect = world.height / world.width;
+    let (w, h) = if aspect <= 1.0 { (MINIMAP_SIZE, MINIMAP_SIZE * aspect) } else { (MINIMAP_SIZE / aspect, MINIMAP_SIZE) };
+    Rect::new(camera.view.right() - w - 10.0, camera.view.y + 10.0, w, h)
+}
+
+// World position under a point of the minimap
+pub fn minimap_to_world(world: &World, camera: &Camera, p: Vec2) -> Vec2 {
+    let rect = minimap_rect(world, camera);
+    vec2((p.x - rect.x) / rect.w * world.width, (p.y - rect.y) / rect.h * world.height)
+}
+
+// Overview of the whole world: food density as a heatmap, organisms as dots and
+// the camera viewport as a rectangle
+pub fn draw_minimap(world: &World, camera: &Camera, palette: Palette) {
+    let rect = minimap_rect(world, camera);
+    let (x, y, w, h) = (rect.x, rect.y, rect.w, rect.h);
+    let to_map = |p: Vec2| vec2(x + p.x / world.width * w, y + p.y / world.height * h);
+
+    let cols = MINIMAP_CELLS;
+    let rows = ((MINIMAP_CELLS as f32 * h / w).round() as usize).max(1);
+    let mut food = vec![0u32; cols * rows];
+    for pos in world.ecs.query::<&Position>().with::<&Food>().iter() {
+        let cx = ((pos.0.x / world.width * cols as f32) as usize).min(cols - 1);
+        let cy = ((pos.0.y / world.height * rows as f32) as usize).min(rows - 1);
+        food[cy * cols + cx] += 1;
+    }
+    let max = food.iter().copied().max().unwrap_or(0).max(1) as f32;
+
+    draw_rectangle(x, y, w, h, Color::new(0.02, 0.02, 0.03, 0.85));
+    let (cell_w, cell_h) = (w / cols as f32, h / rows as f32);
+    for (i, &count) in food.iter().enumerate() {
+        if count == 0 {
+            continue;
+        }
+        let intensity = (count as f32 / max).sqrt();
+        let (cx, cy) = ((i % cols) as f32, (i / cols) as f32);
+        draw_rectangle(x + cx * cell_w, y + cy * cell_h, cell_w, cell_h, Color::new(0.1, 0.5, 0.3, 0.6 * intensity));
+    }
+
+    // Thin out huge populations so the minimap stays cheap
+    let bacteria = world.bacteria_count();
+    let stride = (bacteria / MINIMAP_MAX_DOTS).max(1);
+    for (pos, genome) in world.ecs.query::<(&Position, &Genome)>().iter().step_by(stride) {
+        let p = to_map(pos.0);
+        draw_rectangle(p.x - 0.5, p.y - 0.5, 1.5, 1.5, palette.dna(genome.0.color()));
+    }
+    for pos in world.ecs.query::<&Position>().with::<&PredatorTag>().iter() {
+        let p = to_map(pos.0);
+        draw_rectangle(p.x - 1.0, p.y - 1.0, 2.5, 2.5, palette.predator());
+    }
+
+    // Current viewport, clipped to the world
+    let view = camera.visible_rect();
+    let top_left = to_map(view.point().max(vec2(0.0, 0.0)));
+    let bottom_right = to_map(vec2(view.right(), view.bottom()).min(vec2(world.width, world.height)));
+    let size = bottom_right - top_left;
+    draw_rectangle_lines(top_left.x, top_left.y, size.x, size.y, 1.5, Color::new(1.0, 1.0, 1.0, 0.9));
+
+    draw_rectangle_lines(x, y, w, h, 1.0, Color::new(0.3, 0.4, 0.5, 0.8));
+}
+
+// Debug overlays, each toggled with its own key
+#[derive(Clone, Copy, Debug, Default)]
+pub struct DebugOverlays {
+    // Circle each organism can see food or prey within
+    pub sense: bool,
+    // Heading scaled by speed
+    pub velocity: bool,
+    // Line to the food or prey being steered towards
+    pub targets: bool,
+}
+
+impl DebugOverlays {
+    pub fn any(&self) -> bool {
+        self.sense || self.velocity || self.targets
+    }
+}
+
+// Draws the enabled overlays for the organisms inside `view`; call with the
+// world camera set
+pub fn draw_debug_overlays(world: &World, view: Rect, overlays: DebugOverlays) {
+    if !overlays.any() {
+        return;
+    }
+    let view = Rect::new(view.x - CULL_MARGIN, view.y - CULL_MARGIN, view.w + CULL_MARGIN * 2.0, view.h + CULL_MARGIN * 2.0);
+    for (pos, vel, body, target, predator) in world.ecs.query::<(&Position, &Velocity, &Body, &Target, Option<&PredatorTag>)>().iter() {
+        if !view.contains(pos.0) {
+            continue;
+        }
+        let p = pos.0;
+        let (sense_color, target_color) = if predator.is_some() {
+            (Color::new(1.0, 0.3, 0.2, 0.35), Color::new(1.0, 0.3, 0.2, 0.8))
+        } else {
+            (Color::new(0.6, 0.8, 1.0, 0.25), Color::new(0.4, 1.0, 0.6, 0.7))
+        };
+        if overlays.sense {
+            draw_circle_lines(p.x, p.y, body.sense_radius, 1.0, sense_color);
+        }
+        if overlays.targets && let Some(t) = target.0 {
+            draw_line(p.x, p.y, t.x, t.y, 1.0, target_color);
+            draw_circle_lines(t.x, t.y, 3.0, 1.0, target_color);
+        }
+        if overlays.velocity {
+            let tip = p + vel.0 * body.speed * VELOCITY_TICKS;
+            draw_line(p.x, p.y, tip.x, tip.y, 1.5, Color::new(1.0, 1.0, 0.3, 0.9));
+            draw_circle(tip.x, tip.y, 1.5, Color::new(1.0, 1.0, 0.3, 0.9));
+        }
+    }
+}
+
+// Ring around the organism shown in the inspector
+pub fn draw_selection(world: &World, id: hecs::Entity) {
+    let mut query = world.ecs.query_one::<(&Position, &Body)>(id);
+    if let Ok((pos, body)) = query.get() {
+        let pulse = (get_time() as f32 * 4.0).sin() * 1.5;
+        draw_circle_lines(pos.0.x, pos.0.y, body.size + 6.0 + pulse, 2.0, Color::new(1.0, 1.0, 1.0, 0.9));
+    }
+}
+
+// The 3D world with the 3D camera set: the box as wireframe, organisms as spheres
+pub fn draw_world3d(world: &World3D, palette: Palette) {
+    let size = world.extent();
+    draw_cube_wires(size / 2.0, size, Color::new(0.3, 0.4, 0.5, 0.6));
+
+    for pos in world.ecs.query::<&Position<Vec3>>().with::<&Food>().iter() {
+        draw_cube(pos.0, vec3(2.5, 2.5, 2.5), None, Color::new(0.3, 1.0, 0.7, 0.9));
+    }
+    for (pos, body, genome) in world.ecs.query::<(&Position<Vec3>, &Body, &Genome)>().iter() {
+        draw_sphere(pos.0, body.size, None, palette.dna(genome.0.color()));
+    }
+    for (pos, body) in world.ecs.query::<(&Position<Vec3>, &Body)>().with::<&PredatorTag>().iter() {
+        draw_sphere(pos.0, body.size, None, palette.predator());
+    }
+}
@@ -0,0 +1,283 @@
+use macroquad::prelude::Color;
+use std::fmt::Write;
+use std::path::Path;
+
+use crate::chart_export::{format_value, nice_step};
+use crate::params::SimulationParams;
+use crate::stats::{Marker, Metric, Stats};
+use crate::stats_csv::columns;
+use crate::world::World;
+
+// Points kept per graph; past that every other one is dropped, so a run of
+// any length fits
+const MAX_POINTS: usize = 1000;
+// Size of each embedded graph
+const GRAPH_WIDTH: f32 = 720.0;
+const GRAPH_HEIGHT: f32 = 180.0;
+
+// Lowest and highest value of a metric over the run, with their ticks
+#[derive(Clone, Copy, Debug)]
+struct Extremes {
+    min: (f32, u64),
+    max: (f32, u64),
+}
+
+// Everything a run's summary report needs, gathered tick by tick: the graphed
+// series thinned evenly to `MAX_POINTS`, each metric's peak and trough (which
+// thinning could miss), and every notable event
+pub struct RunReport {
+    seed: u64,
+    // Ticks between kept points
+    stride: u64,
+    ticks: Vec<u64>,
+    // Indexed like `columns()`
+    values: Vec<Vec<f32>>,
+    extremes: Vec<Option<Extremes>>,
+    // Values at the last tick observed
+    latest: Vec<f32>,
+    events: Vec<Marker>,
+    last_tick: Option<u64>,
+}
+
+impl RunReport {
+    pub fn new(seed: u64) -> Self {
+        let metrics = columns().count();
+        Self {
+            seed,
+            stride: 1,
+            ticks: Vec::new(),
+            values: vec![Vec::new(); metrics],
+            extremes: vec![None; metrics],
+            latest: Vec::new(),
+            events: Vec::new(),
+            last_tick: None,
+        }
+    }
+
+    // Take in the latest entry of `stats` (once per tick) and any new markers
+    pub fn observe(&mut self, stats: &Stats) {
+        let Some(&tick) = stats.tick_history.last() else { return };
+        let since = self.events.last().map_or(0, |m| m.tick);
+        for marker in stats.markers.iter().filter(|m| m.tick >= since) {
+            if !self.events.iter().rev().take_while(|e| e.tick == marker.tick).any(|e| e.label == marker.label) {
+                self.events.push(marker.clone());
+            }
+        }
+        if self.last_tick == Some(tick) {
+            return;
+        }
+        self.last_tick = Some(tick);
+
+        let latest: Vec<f32> = columns().map(|m| m.history(stats).and_then(|h| h.last()).copied().unwrap_or(0.0)).collect();
+        for (extremes, &value) in self.extremes.iter_mut().zip(&latest) {
+            let e = extremes.get_or_insert(Extremes { min: (value, tick), max: (value, tick) });
+            if value < e.min.0 {
+                e.min = (value, tick);
+            }
+            if value > e.max.0 {
+                e.max = (value, tick);
+            }
+        }
+        self.latest = latest;
+        if self.ticks.last().is_some_and(|&t| tick < t + self.stride) {
+            return;
+        }
+        self.ticks.push(tick);
+        for (series, &value) in self.values.iter_mut().zip(&self.latest) {
+            series.push(value);
+        }
+        if self.ticks.len() > MAX_POINTS {
+            self.stride *= 2;
+            halve(&mut self.ticks);
+            for series in &mut self.values {
+                halve(series);
+            }
+        }
+    }
+
+    // Write the report to `path`: Markdown for a `.md` file, with the graphs
+    // saved as SVG files next to it, or a single HTML page otherwise
+    pub fn write(&self, path: &str, params: &SimulationParams, world: &World) -> Result<(), String> {
+        let path = Path::new(path);
+        if let Some(dir) = path.parent().filter(|d| !d.as_os_str().is_empty()) {
+            std::fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+        }
+        let text = if path.extension().is_some_and(|e| e == "md") {
+            let stem = path.file_stem().map_or("report".into(), |s| s.to_string_lossy());
+            let graph_dir = path.with_file_name(format!("{}_graphs", stem));
+            std::fs::create_dir_all(&graph_dir).map_err(|e| e.to_string())?;
+            let mut graphs = Vec::new();
+            for (i, metric) in columns().enumerate() {
+                let file = format!("{}.svg", metric.file_name());
+                std::fs::write(graph_dir.join(&file), self.svg(i, metric)).map_err(|e| e.to_string())?;
+                graphs.push(format!("![{}]({}_graphs/{})", metric.title(), stem, file));
+            }
+            self.markdown(params, world, &graphs)
+        } else {
+            self.html(params, world)
+        };
+        std::fs::write(path, text).map_err(|e| e.to_string())
+    }
+
+    fn final_rows(&self, world: &World) -> Vec<(String, String)> {
+        let mut rows = vec![
+            ("Seed".to_string(), self.seed.to_string()),
+            ("Ticks".to_string(), world.tick.to_string()),
+            ("World".to_string(), format!("{:.0} x {:.0}", world.width, world.height)),
+        ];
+        for (value, metric) in self.latest.iter().zip(columns()) {
+            rows.push((metric.title().to_string(), number(*value)));
+        }
+        rows
+    }
+
+    fn extreme_rows(&self) -> Vec<[String; 5]> {
+        self.extremes.iter().zip(columns())
+            .filter_map(|(e, metric)| e.map(|e| [
+                metric.title().to_string(),
+                number(e.max.0),
+                e.max.1.to_string(),
+                number(e.min.0),
+                e.min.1.to_string(),
+            ]))
+            .collect()
+    }
+
+    fn param_rows(params: &SimulationParams) -> Vec<(String, String)> {
+        let value = serde_json::to_value(params).unwrap_or_default();
+        value.as_object().map_or(Vec::new(), |o| o.iter().map(|(k, v)| (k.clone(), v.to_string())).collect())
+    }
+
+    fn markdown(&self, params: &SimulationParams, world: &World, graphs: &[String]) -> String {
+        let mut md = format!("# Bact-Sim run report\n\nSeed {}, {} ticks.\n\n## Final state\n\n| | |\n|---|---|\n", self.seed, world.tick);
+        for (name, value) in self.final_rows(world) {
+            let _ = writeln!(md, "| {} | {} |", name, value);
+        }
+        md += "\n## Peaks and troughs\n\n| Metric | Peak | at tick | Trough | at tick |\n|---|---|---|---|---|\n";
+        for row in self.extreme_rows() {
+            let _ = writeln!(md, "| {} |", row.join(" | "));
+        }
+        md += "\n## Notable events\n\n";
+        if self.events.is_empty() {
+            md += "None.\n";
+        }
+        for event in &self.events {
+            let _ = writeln!(md, "- tick {}: {}", event.tick, event.label);
+        }
+        md += "\n## Graphs\n\n";
+        for graph in graphs {
+            let _ = writeln!(md, "{}\n", graph);
+        }
+        md += "## Parameters\n\n| Parameter | Value |\n|---|---|\n";
+        for (name, value) in Self::param_rows(params) {
+            let _ = writeln!(md, "| {} | {} |", name, value);
+        }
+        md
+    }
+
+    fn html(&self, params: &SimulationParams, world: &World) -> String {
+        let mut html = String::from(concat!(
+            "<!DOCTYPE html>\n<html><head><meta charset=\"utf-8\"><title>Bact-Sim run report</title>\n",
+            "<style>body{background:#0f0f14;color:#ddd;font-family:sans-serif;margin:2em}",
+            "table{border-collapse:collapse;margin-bottom:1.5em}td,th{border:1px solid #333;padding:3px 10px;text-align:left}",
+            "h1,h2{color:#6ab0ff}svg{display:block;margin-bottom:1em}</style></head><body>\n",
+        ));
+        let _ = writeln!(html, "<h1>Bact-Sim run report</h1><p>Seed {}, {} ticks.</p>", self.seed, world.tick);
+        html += "<h2>Final state</h2><table>\n";
+        for (name, value) in self.final_rows(world) {
+            let _ = writeln!(html, "<tr><th>{}</th><td>{}</td></tr>", escape(&name), value);
+        }
+        html += "</table><h2>Peaks and troughs</h2><table>\n<tr><th>Metric</th><th>Peak</th><th>at tick</th><th>Trough</th><th>at tick</th></tr>\n";
+        for row in self.extreme_rows() {
+            let cells: Vec<String> = row.iter().map(|c| format!("<td>{}</td>", escape(c))).collect();
+            let _ = writeln!(html, "<tr>{}</tr>", cells.concat());
+        }
+        html += "</table><h2>Notable events</h2><ul>\n";
+        if self.events.is_empty() {
+            html += "<li>None</li>\n";
+        }
+        for event in &self.events {
+            let _ = writeln!(html, "<li>tick {}: {}</li>", event.tick, escape(&event.label));
+        }
+        html += "</ul><h2>Graphs</h2>\n";
+        for (i, metric) in columns().enumerate() {
+            html += &self.svg(i, metric);
+            html += "\n";
+        }
+        html += "<h2>Parameters</h2><table>\n";
+        for (name, value) in Self::param_rows(params) {
+            let _ = writeln!(html, "<tr><th>{}</th><td>{}</td></tr>", name, escape(&value));
+        }
+        html += "</table></body></html>\n";
+        html
+    }
+
+    // One metric over the run as an SVG line graph, with its range, the tick
+    // span and a dashed line per event
+    fn svg(&self, index: usize, metric: Metric) -> String {
+        let data = &self.values[index];
+        let (w, h) = (GRAPH_WIDTH, GRAPH_HEIGHT);
+        let (left, top, bottom) = (60.0, 24.0, 22.0);
+        let plot_w = w - left - 10.0;
+        let plot_h = h - top - bottom;
+        let mut svg = format!("<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{w}\" height=\"{h}\" viewBox=\"0 0 {w} {h}\">");
+        let _ = write!(svg, "<rect width=\"{w}\" height=\"{h}\" fill=\"#15151c\"/>");
+        let _ = write!(svg, "<text x=\"{left}\" y=\"16\" fill=\"#ccc\" font-size=\"13\" font-family=\"sans-serif\">{}</text>", escape(metric.title()));
+        let (Some(&first), Some(&last)) = (self.ticks.first(), self.ticks.last()) else {
+            svg += "</svg>";
+            return svg;
+        };
+        let lo = data.iter().copied().fold(f32::INFINITY, f32::min);
+        let hi = data.iter().copied().fold(f32::NEG_INFINITY, f32::max);
+        let span = (hi - lo).max(f32::EPSILON);
+        let tick_span = (last - first).max(1) as f32;
+        let x_of = |tick: u64| left + (tick - first) as f32 / tick_span * plot_w;
+        let y_of = |v: f32| top + plot_h - (v - lo) / span * plot_h;
+
+        let _ = write!(svg, "<rect x=\"{left}\" y=\"{top}\" width=\"{plot_w}\" height=\"{plot_h}\" fill=\"none\" stroke=\"#444\"/>");
+        for event in self.events.iter().filter(|e| e.tick >= first && e.tick <= last) {
+            let x = x_of(event.tick);
+            let _ = write!(
+                svg,
+                "<line x1=\"{x:.1}\" y1=\"{top}\" x2=\"{x:.1}\" y2=\"{}\" stroke=\"{}\" stroke-dasharray=\"4 3\" opacity=\"0.6\"><title>{}</title></line>",
+                top + plot_h, hex(event.color), escape(&event.label),
+            );
+        }
+        let points: Vec<String> = self.ticks.iter().zip(data).map(|(&t, &v)| format!("{:.1},{:.1}", x_of(t), y_of(v))).collect();
+        let _ = write!(svg, "<polyline fill=\"none\" stroke=\"{}\" stroke-width=\"1.5\" points=\"{}\"/>", hex(metric.color()), points.join(" "));
+
+        let step = nice_step(span, 4);
+        let label = |x: f32, y: f32, anchor: &str, text: String| {
+            format!("<text x=\"{x:.1}\" y=\"{y:.1}\" fill=\"#999\" font-size=\"11\" font-family=\"sans-serif\" text-anchor=\"{anchor}\">{text}</text>")
+        };
+        svg += &label(left - 6.0, top + 10.0, "end", format_value(hi, step));
+        svg += &label(left - 6.0, top + plot_h, "end", format_value(lo, step));
+        svg += &label(left, h - 6.0, "start", format!("tick {}", first));
+        svg += &label(left + plot_w, h - 6.0, "end", format!("tick {}", last));
+        svg += "</svg>";
+        svg
+    }
+}
+
+// Counts without decimals, averages with three
+fn number(value: f32) -> String {
+    if value.fract() == 0.0 { format!("{}", value) } else { format!("{:.3}", value) }
+}
+
+// Every other entry, from the first
+fn halve<T>(values: &mut Vec<T>) {
+    let mut keep = false;
+    values.retain(|_| {
+        keep = !keep;
+        keep
+    });
+}
+
+fn hex(color: Color) -> String {
+    let [r, g, b, _]: [u8; 4] = color.into();
+    format!("#{:02x}{:02x}{:02x}", r, g, b)
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
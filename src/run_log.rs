@@ -0,0 +1,127 @@
+use rusqlite::{params, Connection};
+use tracing::warn;
+
+use crate::cadence::Cadence;
+use crate::components::{Age, Body, Energy, Genome, Lineage, Position, PredatorTag};
+use crate::dna::{AEROBIC, PH_OPTIMUM, PH_TOLERANCE};
+use crate::stats::averages;
+use crate::world::World;
+
+// Ticks between commits; a crash loses at most this many ticks of log
+const COMMIT_INTERVAL: u64 = 100;
+
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS ticks (
+        run INTEGER NOT NULL,
+        tick INTEGER NOT NULL,
+        bacteria INTEGER NOT NULL,
+        predators INTEGER NOT NULL,
+        food INTEGER NOT NULL,
+        avg_speed REAL NOT NULL,
+        avg_size REAL NOT NULL,
+        PRIMARY KEY (run, tick)
+    );
+    CREATE TABLE IF NOT EXISTS organisms (
+        run INTEGER NOT NULL,
+        tick INTEGER NOT NULL,
+        id INTEGER NOT NULL,
+        kind TEXT NOT NULL,
+        x REAL NOT NULL,
+        y REAL NOT NULL,
+        energy REAL NOT NULL,
+        age REAL NOT NULL,
+        speed REAL NOT NULL,
+        size REAL NOT NULL,
+        sense_radius REAL NOT NULL,
+        aerobic REAL,
+        ph_optimum REAL,
+        ph_tolerance REAL,
+        generation INTEGER,
+        PRIMARY KEY (run, tick, id)
+    );
+    CREATE TABLE IF NOT EXISTS runs (
+        run INTEGER PRIMARY KEY,
+        seed INTEGER NOT NULL,
+        started TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+    );
+";
+
+// Logs a run into SQLite: one `ticks` row per tick and, every `snapshots`
+// ticks, one `organisms` row per bacterium and predator. Several runs can share
+// a file; each gets its own `runs` row and id.
+pub struct RunLog {
+    db: Connection,
+    run: i64,
+    pub snapshots: Cadence,
+    commits: Cadence,
+}
+
+impl RunLog {
+    pub fn open(path: &str, seed: u64) -> rusqlite::Result<Self> {
+        let db = Connection::open(path)?;
+        db.execute_batch(SCHEMA)?;
+        db.execute("INSERT INTO runs (seed) VALUES (?1)", params![seed as i64])?;
+        let run = db.last_insert_rowid();
+        db.execute_batch("BEGIN")?;
+        Ok(Self { db, run, snapshots: Cadence::new(100), commits: Cadence::new(COMMIT_INTERVAL) })
+    }
+
+    pub fn record(&mut self, world: &World) -> rusqlite::Result<()> {
+        let avg = averages(&world.ecs);
+        self.db.prepare_cached("INSERT OR REPLACE INTO ticks VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)")?.execute(params![
+            self.run,
+            world.tick as i64,
+            world.bacteria_count() as i64,
+            world.predator_count() as i64,
+            world.food_count() as i64,
+            avg.speed,
+            avg.size,
+        ])?;
+        if self.snapshots.due(world.tick) {
+            self.snapshot(world)?;
+        }
+        if self.commits.due(world.tick) {
+            self.db.execute_batch("COMMIT; BEGIN")?;
+        }
+        Ok(())
+    }
+
+    fn snapshot(&mut self, world: &World) -> rusqlite::Result<()> {
+        let mut insert = self.db.prepare_cached(
+            "INSERT OR REPLACE INTO organisms VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15)",
+        )?;
+        let mut query = world.ecs.query::<(hecs::Entity, &Position, &Energy, &Age, &Body, Option<&Genome>, Option<&Lineage>, Option<&PredatorTag>)>();
+        for (id, pos, energy, age, body, genome, lineage, predator) in query.iter() {
+            if genome.is_none() && predator.is_none() {
+                continue;
+            }
+            let dna = genome.map(|g| &g.0);
+            insert.execute(params![
+                self.run,
+                world.tick as i64,
+                id.to_bits().get() as i64,
+                if predator.is_some() { "predator" } else { "bacterium" },
+                pos.0.x,
+                pos.0.y,
+                energy.0,
+                age.0,
+                body.speed,
+                body.size,
+                body.sense_radius,
+                dna.map(|d| d.gene(AEROBIC)),
+                dna.map(|d| d.gene(PH_OPTIMUM)),
+                dna.map(|d| d.gene(PH_TOLERANCE)),
+                lineage.map(|l| l.generation),
+            ])?;
+        }
+        Ok(())
+    }
+}
+
+impl Drop for RunLog {
+    fn drop(&mut self) {
+        if let Err(e) = self.db.execute_batch("COMMIT") {
+            warn!("Couldn't finish the run log: {}", e);
+        }
+    }
+}
@@ -0,0 +1,226 @@
+use macroquad::prelude::*;
+use serde::Deserialize;
+
+use crate::stats::{Metric, Stats};
+
+// Seconds a completion or failure banner stays up
+pub const BANNER_SECONDS: f64 = 4.0;
+
+// A challenge, read from a JSON file (`--scenario speed.json`):
+//
+//     {
+//         "name": "Speed demons",
+//         "description": "Breed the fastest bacteria you can",
+//         "params": { "mutation_rate": 0.2 },
+//         "objectives": [
+//             { "goal": "Average speed above 4", "metric": "speed", "above": 4.0, "within": 10000 },
+//             { "goal": "Keep predators alive", "metric": "predators", "above": 0, "for": 20000 }
+//         ]
+//     }
+//
+// `params` is optional and applied over the config and flags.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Scenario {
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    #[serde(default)]
+    pub params: serde_json::Map<String, serde_json::Value>,
+    pub objectives: Vec<Objective>,
+}
+
+// A graphed metric (by its file name, see `Metric::file_name`) to push
+// `above` or `below` a value. Met once the condition holds, or once it's held
+// `for` ticks in a row; `within` fails it if that hasn't happened by the
+// given tick. A `for` objective without `within` also fails as soon as its
+// streak breaks.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Objective {
+    pub goal: String,
+    pub metric: String,
+    pub above: Option<f32>,
+    pub below: Option<f32>,
+    #[serde(rename = "for")]
+    pub hold: Option<u64>,
+    pub within: Option<u64>,
+}
+
+impl Objective {
+    fn metric(&self) -> Option<Metric> {
+        // The marker and region graphs have no single value to reach
+        Metric::ALL.into_iter().find(|&m| m.file_name() == self.metric && m.single())
+    }
+
+    fn check(&self) -> Result<(), String> {
+        if self.metric().is_none() {
+            return Err(format!("unknown metric '{}'", self.metric));
+        }
+        if self.above.is_none() && self.below.is_none() {
+            return Err("give `above` or `below`".to_string());
+        }
+        if self.hold == Some(0) {
+            return Err("`for` must be at least one tick".to_string());
+        }
+        Ok(())
+    }
+
+    fn met(&self, value: f32) -> bool {
+        self.above.is_none_or(|a| value > a) && self.below.is_none_or(|b| value < b)
+    }
+
+    // The threshold being worked towards, for the progress bar
+    fn target(&self) -> f32 {
+        self.above.or(self.below).unwrap_or(0.0)
+    }
+}
+
+impl Scenario {
+    pub fn load(path: &str) -> Result<Self, String> {
+        let text = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
+        let scenario: Scenario = serde_json::from_str(&text).map_err(|e| e.to_string())?;
+        for (i, objective) in scenario.objectives.iter().enumerate() {
+            objective.check().map_err(|e| format!("objective {}: {}", i + 1, e))?;
+        }
+        Ok(scenario)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Status {
+    Active,
+    // Tick it was completed or failed at
+    Complete(u64),
+    Failed(u64),
+}
+
+// How far along an objective is
+#[derive(Clone, Copy, Debug)]
+pub struct Progress {
+    pub status: Status,
+    // Latest value of the metric, and 0 to 1 towards the goal
+    pub value: f32,
+    pub fraction: f32,
+    // Value when the scenario started, for threshold progress
+    start: Option<f32>,
+    // Tick the current run of the condition holding began
+    streak: Option<u64>,
+}
+
+impl Progress {
+    fn new() -> Self {
+        Self { status: Status::Active, value: 0.0, fraction: 0.0, start: None, streak: None }
+    }
+}
+
+// An objective finished this tick
+#[derive(Clone, Copy, Debug)]
+pub struct Outcome {
+    pub objective: usize,
+    pub status: Status,
+}
+
+// A scenario being played: each objective's progress, and the banner for
+// the latest outcome
+pub struct Challenge {
+    pub scenario: Scenario,
+    pub progress: Vec<Progress>,
+    // Text, colour and time shown
+    pub banner: Option<(String, Color, f64)>,
+    last_tick: Option<u64>,
+}
+
+impl Challenge {
+    pub fn new(scenario: Scenario) -> Self {
+        let progress = vec![Progress::new(); scenario.objectives.len()];
+        Self { scenario, progress, banner: None, last_tick: None }
+    }
+
+    // Start over in a new world
+    pub fn restart(&mut self) {
+        self.progress = vec![Progress::new(); self.scenario.objectives.len()];
+        self.banner = None;
+        self.last_tick = None;
+    }
+
+    pub fn complete(&self) -> bool {
+        self.progress.iter().all(|p| matches!(p.status, Status::Complete(_)))
+    }
+
+    // Check the objectives against the latest recorded stats, once per tick
+    pub fn update(&mut self, stats: &Stats) -> Vec<Outcome> {
+        let Some(&tick) = stats.tick_history.last() else { return Vec::new() };
+        if self.last_tick == Some(tick) {
+            return Vec::new();
+        }
+        self.last_tick = Some(tick);
+
+        let mut outcomes = Vec::new();
+        for (i, (objective, progress)) in self.scenario.objectives.iter().zip(&mut self.progress).enumerate() {
+            if progress.status != Status::Active {
+                continue;
+            }
+            let Some(metric) = objective.metric() else { continue };
+            let value = metric.history(stats).and_then(|h| h.last().copied()).unwrap_or(0.0);
+            progress.value = value;
+            let start = *progress.start.get_or_insert(value);
+            let met = objective.met(value);
+
+            match objective.hold {
+                Some(hold) => {
+                    if met {
+                        let since = *progress.streak.get_or_insert(tick);
+                        progress.fraction = ((tick - since) as f32 / hold as f32).min(1.0);
+                        if tick - since >= hold {
+                            progress.status = Status::Complete(tick);
+                        }
+                    } else {
+                        if progress.streak.is_some() && objective.within.is_none() {
+                            progress.status = Status::Failed(tick);
+                        }
+                        progress.streak = None;
+                        progress.fraction = 0.0;
+                    }
+                }
+                None => {
+                    let target = objective.target();
+                    progress.fraction = if met {
+                        1.0
+                    } else if (target - start).abs() > f32::EPSILON {
+                        ((value - start) / (target - start)).clamp(0.0, 1.0)
+                    } else {
+                        0.0
+                    };
+                    if met {
+                        progress.status = Status::Complete(tick);
+                    }
+                }
+            }
+            if progress.status == Status::Active && objective.within.is_some_and(|w| tick >= w) {
+                progress.status = Status::Failed(tick);
+            }
+            if progress.status != Status::Active {
+                outcomes.push(Outcome { objective: i, status: progress.status });
+            }
+        }
+
+        outcomes
+    }
+
+    // What an outcome means, and the colour to show it in
+    pub fn describe(&self, outcome: &Outcome) -> (String, Color) {
+        let goal = &self.scenario.objectives[outcome.objective].goal;
+        match outcome.status {
+            Status::Complete(_) if self.complete() => (format!("🏆 SCENARIO COMPLETE: {}", self.scenario.name), Color::new(1.0, 0.85, 0.3, 1.0)),
+            Status::Complete(_) => (format!("✔ OBJECTIVE COMPLETE: {}", goal), Color::new(0.4, 1.0, 0.5, 1.0)),
+            _ => (format!("✖ OBJECTIVE FAILED: {}", goal), Color::new(1.0, 0.4, 0.3, 1.0)),
+        }
+    }
+
+    // Show the outcome as a banner from `now` (see `get_time`)
+    pub fn announce(&mut self, outcome: &Outcome, now: f64) {
+        let (text, color) = self.describe(outcome);
+        self.banner = Some((text, color, now));
+    }
+}
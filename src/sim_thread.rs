@@ -0,0 +1,173 @@
+use std::sync::mpsc::{channel, Receiver, Sender};
+
+use crate::events::Event;
+use crate::params::SimulationParams;
+use crate::stats::TickStats;
+use crate::world::World;
+
+// Ticks the worker ran: what happened in them, how long each system took and
+// the stats of each, tick after tick
+pub struct Batch {
+    pub ticks: u32,
+    pub events: Vec<Event>,
+    pub timings: Vec<(&'static str, f32)>,
+    pub stats: Vec<TickStats>,
+}
+
+enum Job {
+    // Step the worker's world, or `world` in its place if it was changed
+    // here; the region panel's food multipliers, set on the copy shown,
+    // carry over
+    Run { world: Option<World>, params: Box<SimulationParams>, ticks: u32, food_multipliers: Vec<f32> },
+    // A copy done with, to make the next one over
+    Recycle(World),
+    // Send the world back, to change it
+    Return,
+}
+
+enum Reply {
+    // A copy of the world after the batch
+    Done(World, Batch),
+    Returned(World),
+}
+
+// Steps the world on a worker thread, so slow ticks don't hold up the window.
+// The worker keeps the world and, after each batch, makes a copy of it to
+// draw; the copies are double-buffered, the one replaced going back to be
+// made over. To change the world, `wait` for it to come back; the next
+// batch hands it over again, with a copy made here that once.
+pub struct SimThread {
+    jobs: Sender<Job>,
+    replies: Receiver<Reply>,
+    running: bool,
+    // A batch waited for, not yet handed out by `poll`
+    done: Option<Batch>,
+    // Whether the world here is the real one rather than a copy
+    holding: bool,
+}
+
+impl SimThread {
+    pub fn start() -> Self {
+        let (jobs, job_rx) = channel();
+        let (reply_tx, replies) = channel();
+        std::thread::spawn(move || {
+            let mut live: Option<World> = None;
+            let mut spare: Option<World> = None;
+            for job in job_rx {
+                let reply = match job {
+                    Job::Run { world, params, ticks, food_multipliers } => {
+                        if world.is_some() {
+                            live = world;
+                        }
+                        let Some(live) = &mut live else { continue };
+                        for (region, &multiplier) in live.habitat.regions.iter_mut().zip(&food_multipliers) {
+                            region.food_multiplier = multiplier;
+                        }
+                        let mut batch = Batch { ticks, events: Vec::new(), timings: Vec::new(), stats: Vec::new() };
+                        // The whole batch's births, for trackers that only see
+                        // the world at its end
+                        let mut born = Vec::new();
+                        for _ in 0..ticks {
+                            batch.events.extend(live.step(&params));
+                            batch.timings.extend(live.timings.iter().copied());
+                            batch.stats.push(TickStats::of(live));
+                            born.append(&mut live.born);
+                        }
+                        live.born = born;
+                        let copy = match spare.take() {
+                            Some(mut copy) => {
+                                live.snapshot_into(&mut copy);
+                                copy
+                            }
+                            None => live.snapshot(),
+                        };
+                        Reply::Done(copy, batch)
+                    }
+                    Job::Recycle(copy) => {
+                        spare = Some(copy);
+                        continue;
+                    }
+                    Job::Return => match live.take() {
+                        Some(world) => Reply::Returned(world),
+                        None => continue,
+                    },
+                };
+                if reply_tx.send(reply).is_err() {
+                    break;
+                }
+            }
+        });
+        Self { jobs, replies, running: false, done: None, holding: true }
+    }
+
+    // Neither running a batch nor holding one back
+    pub fn idle(&self) -> bool {
+        !self.running && self.done.is_none()
+    }
+
+    // Step the world `ticks` times on the worker; `world` stays a copy to
+    // draw until the batch is done
+    pub fn run(&mut self, world: &mut World, params: &SimulationParams, ticks: u32) {
+        let live = self.holding.then(|| {
+            let copy = world.snapshot();
+            std::mem::replace(world, copy)
+        });
+        let food_multipliers = world.habitat.regions.iter().map(|r| r.food_multiplier).collect();
+        self.running = self.jobs.send(Job::Run { world: live, params: Box::new(params.clone()), ticks, food_multipliers }).is_ok();
+        self.holding = false;
+    }
+
+    // The finished batch, if there is one, with a copy of its world in place
+    // of the last
+    pub fn poll(&mut self, world: &mut World) -> Option<Batch> {
+        if let Some(batch) = self.done.take() {
+            return Some(batch);
+        }
+        let reply = self.replies.try_recv().ok()?;
+        self.take(world, reply)
+    }
+
+    // Block until the batch running is done and the world itself is back,
+    // before changing it; `poll` hands the batch out after
+    pub fn wait(&mut self, world: &mut World) {
+        if self.running && let Ok(reply) = self.replies.recv() {
+            self.done = self.take(world, reply);
+        }
+        if !self.holding
+            && self.jobs.send(Job::Return).is_ok()
+            && let Ok(reply) = self.replies.recv()
+        {
+            self.take(world, reply);
+        }
+    }
+
+    // Drop the batch running, for a world about to be replaced
+    pub fn cancel(&mut self) {
+        if self.running && let Ok(Reply::Done(copy, _)) = self.replies.recv() {
+            let _ = self.jobs.send(Job::Recycle(copy));
+        }
+        self.running = false;
+        self.done = None;
+        self.holding = true;
+    }
+
+    // Put the world that came back in place of the copy shown, which goes
+    // back to the worker. Region food multipliers set on the copy meanwhile
+    // (the region panel's sliders) carry over.
+    fn take(&mut self, world: &mut World, reply: Reply) -> Option<Batch> {
+        let (mut next, batch) = match reply {
+            Reply::Done(copy, batch) => (copy, Some(batch)),
+            Reply::Returned(live) => (live, None),
+        };
+        for (region, shown) in next.habitat.regions.iter_mut().zip(&world.habitat.regions) {
+            region.food_multiplier = shown.food_multiplier;
+        }
+        let shown = std::mem::replace(world, next);
+        let _ = self.jobs.send(Job::Recycle(shown));
+        match batch {
+            Some(_) => self.running = false,
+            None => self.holding = true,
+        }
+        batch
+    }
+}
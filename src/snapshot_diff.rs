@@ -0,0 +1,153 @@
+use hecs::Entity;
+use std::collections::{HashMap, HashSet};
+
+use crate::autosave::WorldSave;
+use crate::compare::{cohens_d, mann_whitney};
+use crate::components::{Genome, Lineage, Plasmids};
+use crate::stats::{Spread, CORRELATED_GENES};
+use crate::world::World;
+
+// Lineages listed, the biggest in either save first
+const TOP_LINEAGES: usize = 8;
+
+// What a saved world holds, for comparing with another
+struct Census {
+    tick: u64,
+    bacteria: HashSet<Entity>,
+    predators: usize,
+    food: usize,
+    // Living bacteria per founder
+    lineages: HashMap<Entity, usize>,
+    generations: Vec<u32>,
+    carriers: usize,
+    // Each gene's values, in `CORRELATED_GENES` order
+    genes: Vec<Vec<f32>>,
+}
+
+impl Census {
+    fn of(world: &World) -> Self {
+        let mut census = Self {
+            tick: world.tick,
+            bacteria: HashSet::new(),
+            predators: world.predator_count(),
+            food: world.food_count(),
+            lineages: HashMap::new(),
+            generations: Vec::new(),
+            carriers: 0,
+            genes: vec![Vec::new(); CORRELATED_GENES.len()],
+        };
+        for (id, genome, lineage, plasmids) in world.ecs.query::<(Entity, &Genome, &Lineage, Option<&Plasmids>)>().iter() {
+            census.bacteria.insert(id);
+            *census.lineages.entry(lineage.founder.unwrap_or(id)).or_insert(0) += 1;
+            census.generations.push(lineage.generation);
+            census.carriers += plasmids.is_some_and(|p| !p.0.is_empty()) as usize;
+            for (values, (_, tag)) in census.genes.iter_mut().zip(CORRELATED_GENES) {
+                values.push(genome.0.gene(tag));
+            }
+        }
+        census
+    }
+
+    fn share(&self, count: usize) -> f32 {
+        count as f32 / self.bacteria.len().max(1) as f32 * 100.0
+    }
+
+    fn mean_generation(&self) -> f32 {
+        self.generations.iter().sum::<u32>() as f32 / self.generations.len().max(1) as f32
+    }
+}
+
+fn mean(values: &[f32]) -> f32 {
+    values.iter().sum::<f32>() / values.len().max(1) as f32
+}
+
+// Relative change from `a` to `b`, or a dash from nothing
+fn change(a: f32, b: f32) -> String {
+    if a != 0.0 { format!("{:+.1}%", (b - a) / a.abs() * 100.0) } else { "-".to_string() }
+}
+
+// A parameter's value as JSON, with floats back at the f32 they were saved
+// from rather than their widened digits
+fn param_text(value: &serde_json::Value) -> String {
+    match value.as_f64() {
+        Some(v) if value.is_f64() => (v as f32).to_string(),
+        _ => value.to_string(),
+    }
+}
+
+// What changed from save `a` to save `b`: the population's size and make-up,
+// how each gene is distributed, and every parameter set differently
+pub fn report(a: &WorldSave, b: &WorldSave, names: (&str, &str)) -> Result<String, String> {
+    let (census_a, census_b) = (Census::of(&a.restore(0)?), Census::of(&b.restore(0)?));
+    let (ca, cb) = (&census_a, &census_b);
+    let mut text = format!("A: {} (tick {})\nB: {} (tick {}, {:+} ticks)\n\n", names.0, ca.tick, names.1, cb.tick, cb.tick as i64 - ca.tick as i64);
+
+    text += &format!("{:<20} {:>10} {:>10} {:>9}\n", "population", "A", "B", "change");
+    let counts = [
+        ("bacteria", ca.bacteria.len() as f32, cb.bacteria.len() as f32),
+        ("predators", ca.predators as f32, cb.predators as f32),
+        ("food", ca.food as f32, cb.food as f32),
+        ("lineages", ca.lineages.len() as f32, cb.lineages.len() as f32),
+        ("mean generation", ca.mean_generation(), cb.mean_generation()),
+        ("deepest generation", ca.generations.iter().max().copied().unwrap_or(0) as f32, cb.generations.iter().max().copied().unwrap_or(0) as f32),
+        ("plasmid carriers %", ca.share(ca.carriers), cb.share(cb.carriers)),
+    ];
+    for (name, va, vb) in counts {
+        text += &format!("{:<20} {:>10.1} {:>10.1} {:>9}\n", name, va, vb, change(va, vb));
+    }
+    let lost = ca.lineages.keys().filter(|f| !cb.lineages.contains_key(f)).count();
+    let new = cb.lineages.keys().filter(|f| !ca.lineages.contains_key(f)).count();
+    let survivors = ca.bacteria.intersection(&cb.bacteria).count();
+    text += &format!("\nLineages lost: {}, new: {}. Bacteria alive in both: {}\n\n", lost, new, survivors);
+
+    // The biggest lineages on either side, by their share in B
+    let mut top: Vec<Entity> = Vec::new();
+    for census in [cb, ca] {
+        let mut sizes: Vec<(&Entity, &usize)> = census.lineages.iter().collect();
+        sizes.sort_by(|x, y| y.1.cmp(x.1).then(x.0.to_bits().cmp(&y.0.to_bits())));
+        for &(&founder, _) in sizes.iter().take(TOP_LINEAGES) {
+            if !top.contains(&founder) {
+                top.push(founder);
+            }
+        }
+    }
+    let share = |census: &Census, founder: &Entity| census.share(census.lineages.get(founder).copied().unwrap_or(0));
+    top.sort_by(|x, y| share(cb, y).total_cmp(&share(cb, x)).then(share(ca, y).total_cmp(&share(ca, x))));
+    text += &format!("{:<20} {:>10} {:>10}\n", "lineage (founder)", "A %", "B %");
+    for founder in &top {
+        text += &format!("{:<20} {:>10.1} {:>10.1}\n", format!("{:?}", founder), share(ca, founder), share(cb, founder));
+    }
+
+    text += &format!("\n{:<11} {:>9} {:>9} {:>9} {:>19} {:>19} {:>7} {:>8}\n", "gene", "A mean", "B mean", "change", "A p10-p90", "B p10-p90", "d", "p");
+    for (gene, (name, _)) in CORRELATED_GENES.iter().enumerate() {
+        let (xs, ys) = (&ca.genes[gene], &cb.genes[gene]);
+        if xs.is_empty() || ys.is_empty() {
+            continue;
+        }
+        let (sa, sb) = (Spread::of(xs.clone()), Spread::of(ys.clone()));
+        let (ma, mb) = (mean(xs), mean(ys));
+        let (_, p) = mann_whitney(xs, ys);
+        let stars = if p < 0.01 { " **" } else if p < 0.05 { " *" } else { "" };
+        text += &format!(
+            "{:<11} {:>9.3} {:>9.3} {:>9} {:>19} {:>19} {:>7.2} {:>8.4}{}\n",
+            name, ma, mb, change(ma, mb), format!("{:.3}-{:.3}", sa.p10, sa.p90), format!("{:.3}-{:.3}", sb.p10, sb.p90), cohens_d(xs, ys), p, stars,
+        );
+    }
+    text += "d: Cohen's d (B - A over the pooled SD); p: two-sided Mann-Whitney test, * p < 0.05, ** p < 0.01\n\n";
+
+    let params = |save: &WorldSave| serde_json::to_value(&save.params).map_err(|e| e.to_string());
+    let (pa, pb) = (params(a)?, params(b)?);
+    let (Some(pa), Some(pb)) = (pa.as_object(), pb.as_object()) else {
+        return Err("parameters aren't an object".to_string());
+    };
+    let changed: Vec<String> = pa.iter()
+        .filter(|&(key, value)| pb.get(key) != Some(value))
+        .map(|(key, value)| format!("{}: {} → {}", key, param_text(value), pb.get(key).map_or("-".to_string(), param_text)))
+        .collect();
+    if changed.is_empty() {
+        text += "Parameters: unchanged";
+    } else {
+        text += &format!("Parameters changed:\n  {}", changed.join("\n  "));
+    }
+    Ok(text)
+}
@@ -0,0 +1,175 @@
+use macroquad::prelude::*;
+use ::rand::Rng;
+use std::fmt::Debug;
+use std::iter::Sum;
+use std::ops::{Add, AddAssign, Div, Index, IndexMut, Mul, Neg, Sub};
+
+// The space organisms move through: the plane (`Vec2`) or the 3D box
+// (`Vec3`). The world and its systems are written over this, so both run
+// the same engine. The habitat, terrain and environmental fields are laid
+// out over the plane; in the box they hold through its whole depth, read at
+// the point's `xy`.
+pub trait Space:
+    Copy + Debug + Default + PartialEq + Send + Sync + 'static
+    + Add<Output = Self> + Sub<Output = Self> + Mul<f32, Output = Self> + Div<f32, Output = Self> + Neg<Output = Self>
+    + AddAssign + Sum + Index<usize, Output = f32> + IndexMut<usize>
+{
+    const AXES: usize;
+    const ZERO: Self;
+
+    fn distance(self, other: Self) -> f32;
+    fn length(self) -> f32;
+    fn normalize(self) -> Self;
+    fn normalize_or_zero(self) -> Self;
+    fn try_normalize(self) -> Option<Self>;
+    fn lerp(self, other: Self, s: f32) -> Self;
+
+    // Where it is on the plane, and how deep (always 0 on the plane itself)
+    fn xy(self) -> Vec2;
+    fn depth(self) -> f32;
+    fn from_xy(xy: Vec2, depth: f32) -> Self;
+
+    // Moved to `xy` on the plane at the same depth
+    fn with_xy(self, xy: Vec2) -> Self {
+        Self::from_xy(xy, self.depth())
+    }
+
+    // A random heading of unit length
+    fn random_dir(rng: &mut impl Rng) -> Self;
+
+    // This heading turned by a random angle of up to `spread` radians
+    fn wander(self, spread: f32, rng: &mut impl Rng) -> Self;
+
+    // A random point within `radius` of the origin, evenly spread
+    fn random_within(radius: f32, rng: &mut impl Rng) -> Self;
+
+    // The exact coordinates, to look positions up by
+    fn key(self) -> [u32; 3];
+}
+
+impl Space for Vec2 {
+    const AXES: usize = 2;
+    const ZERO: Self = Vec2::ZERO;
+
+    fn distance(self, other: Self) -> f32 {
+        Vec2::distance(self, other)
+    }
+
+    fn length(self) -> f32 {
+        Vec2::length(self)
+    }
+
+    fn normalize(self) -> Self {
+        Vec2::normalize(self)
+    }
+
+    fn normalize_or_zero(self) -> Self {
+        Vec2::normalize_or_zero(self)
+    }
+
+    fn try_normalize(self) -> Option<Self> {
+        Vec2::try_normalize(self)
+    }
+
+    fn lerp(self, other: Self, s: f32) -> Self {
+        Vec2::lerp(self, other, s)
+    }
+
+    fn xy(self) -> Vec2 {
+        self
+    }
+
+    fn depth(self) -> f32 {
+        0.0
+    }
+
+    fn from_xy(xy: Vec2, _: f32) -> Self {
+        xy
+    }
+
+    fn random_dir(rng: &mut impl Rng) -> Self {
+        let angle = rng.gen_range(0.0f32..std::f32::consts::TAU);
+        vec2(angle.cos(), angle.sin())
+    }
+
+    fn wander(self, spread: f32, rng: &mut impl Rng) -> Self {
+        let angle = self.y.atan2(self.x) + rng.gen_range(-spread..spread);
+        vec2(angle.cos(), angle.sin())
+    }
+
+    fn random_within(radius: f32, rng: &mut impl Rng) -> Self {
+        let radius = radius * rng.gen_range(0.0f32..1.0).sqrt();
+        Vec2::from_angle(rng.gen_range(0.0..std::f32::consts::TAU)) * radius
+    }
+
+    fn key(self) -> [u32; 3] {
+        [self.x.to_bits(), self.y.to_bits(), 0]
+    }
+}
+
+impl Space for Vec3 {
+    const AXES: usize = 3;
+    const ZERO: Self = Vec3::ZERO;
+
+    fn distance(self, other: Self) -> f32 {
+        Vec3::distance(self, other)
+    }
+
+    fn length(self) -> f32 {
+        Vec3::length(self)
+    }
+
+    fn normalize(self) -> Self {
+        Vec3::normalize(self)
+    }
+
+    fn normalize_or_zero(self) -> Self {
+        Vec3::normalize_or_zero(self)
+    }
+
+    fn try_normalize(self) -> Option<Self> {
+        Vec3::try_normalize(self)
+    }
+
+    fn lerp(self, other: Self, s: f32) -> Self {
+        Vec3::lerp(self, other, s)
+    }
+
+    fn xy(self) -> Vec2 {
+        self.truncate()
+    }
+
+    fn depth(self) -> f32 {
+        self.z
+    }
+
+    fn from_xy(xy: Vec2, depth: f32) -> Self {
+        xy.extend(depth)
+    }
+
+    fn random_dir(rng: &mut impl Rng) -> Self {
+        let z = rng.gen_range(-1.0f32..1.0);
+        let angle = rng.gen_range(0.0f32..std::f32::consts::TAU);
+        let r = (1.0 - z * z).sqrt();
+        vec3(r * angle.cos(), r * angle.sin(), z)
+    }
+
+    // Tilted off the heading towards a random side; one standing still
+    // starts off along x, as on the plane
+    fn wander(self, spread: f32, rng: &mut impl Rng) -> Self {
+        let heading = self.try_normalize().unwrap_or(Vec3::X);
+        let (side, up) = heading.any_orthonormal_pair();
+        let (angle, roll) = (rng.gen_range(-spread..spread), rng.gen_range(0.0f32..std::f32::consts::TAU));
+        let towards = side * roll.cos() + up * roll.sin();
+        heading * angle.cos() + towards * angle.sin()
+    }
+
+    fn random_within(radius: f32, rng: &mut impl Rng) -> Self {
+        let radius = radius * rng.gen_range(0.0f32..1.0).cbrt();
+        Self::random_dir(rng) * radius
+    }
+
+    fn key(self) -> [u32; 3] {
+        [self.x.to_bits(), self.y.to_bits(), self.z.to_bits()]
+    }
+}
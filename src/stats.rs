@@ -0,0 +1,1215 @@
+use macroquad::prelude::Color;
+use std::collections::HashMap;
+use tracing::info;
+
+use crate::components::{Expression, Genome, Lineage, Plasmids, Position};
+use crate::dna::{AEROBIC, AGGRESSION, ARMOR, BURST, COLOR_B, COLOR_G, COLOR_R, COOLDOWN, CROWDING, DISPERSAL, DNA, FLEE_ENERGY, INVESTMENT, MATURATION, MAX_MARKER_ALLELES, MUTATION_RATE, PH_OPTIMUM, PH_TOLERANCE, SENSE_RADIUS, SIZE, SPEED, TOXIN, VIGILANCE};
+use crate::logging::SIM;
+use crate::params::SimulationParams;
+use crate::space::Space;
+use crate::world::World;
+use crate::MAX_HISTORY;
+
+// Ticks over which a population or diversity has to halve to count as a crash
+const EVENT_WINDOW: u64 = 100;
+
+// Older entries kept before the history is thinned out (see `downsample`)
+const ARCHIVE_SAMPLES: usize = MAX_HISTORY;
+
+// Ticks between recomputing the gene correlations
+const CORRELATION_EVERY: u64 = 100;
+
+// Ticks per selection window, and windows kept
+pub const SELECTION_EVERY: u64 = 200;
+const MAX_SELECTION_WINDOWS: usize = 40;
+
+// Genes compared in the correlation matrix, with a short label each
+pub const CORRELATED_GENES: [(&str, &str); 18] = [
+    ("Spd", SPEED),
+    ("Size", SIZE),
+    ("Sense", SENSE_RADIUS),
+    ("Aer", AEROBIC),
+    ("pH", PH_OPTIMUM),
+    ("Tol", PH_TOLERANCE),
+    ("Burst", BURST),
+    ("Vig", VIGILANCE),
+    ("Aggr", AGGRESSION),
+    ("Armor", ARMOR),
+    ("Toxin", TOXIN),
+    ("Investment", INVESTMENT),
+    ("Maturation", MATURATION),
+    ("Cooldown", COOLDOWN),
+    ("Dispersal", DISPERSAL),
+    ("Flee", FLEE_ENERGY),
+    ("Crowd", CROWDING),
+    ("Mut", MUTATION_RATE),
+];
+
+// Everything the bottom panel can graph
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Metric {
+    Population,
+    // Effective population size, from the latest selection window's
+    // offspring numbers
+    EffectiveSize,
+    Predators,
+    Food,
+    Speed,
+    Size,
+    Sense,
+    // Average colour genes, one channel each
+    Red,
+    Green,
+    Blue,
+    // Average mutation rate gene
+    Mutation,
+    Diversity,
+    // Founders with living descendants (themselves included)
+    Lineages,
+    Births,
+    Deaths,
+    // Share of bacteria carrying a plasmid, in percent
+    Plasmids,
+    // Chance two bacteria differ at a neutral marker locus, averaged over
+    // the loci
+    Heterozygosity,
+    // One line per marker allele; only shown with neutral markers on
+    Markers,
+    // One line per habitat region; only shown for layouts with several
+    Regions,
+}
+
+impl Metric {
+    pub const ALL: [Metric; 19] = [
+        Metric::Population,
+        Metric::EffectiveSize,
+        Metric::Predators,
+        Metric::Food,
+        Metric::Speed,
+        Metric::Size,
+        Metric::Sense,
+        Metric::Red,
+        Metric::Green,
+        Metric::Blue,
+        Metric::Mutation,
+        Metric::Diversity,
+        Metric::Lineages,
+        Metric::Births,
+        Metric::Deaths,
+        Metric::Plasmids,
+        Metric::Heterozygosity,
+        Metric::Markers,
+        Metric::Regions,
+    ];
+
+    // Per-bacterium genes, graphed with their spread (see `Spread`)
+    pub const TRAITS: [Metric; 7] = [Metric::Speed, Metric::Size, Metric::Sense, Metric::Red, Metric::Green, Metric::Blue, Metric::Mutation];
+
+    // Shown by default, matching the original panel
+    pub const DEFAULT: [Metric; 5] = [Metric::Population, Metric::Predators, Metric::Speed, Metric::Size, Metric::Regions];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Metric::Population => "🦠 Bacteria",
+            Metric::EffectiveSize => "👥 Ne",
+            Metric::Predators => "🦖 Predators",
+            Metric::Food => "🍃 Food",
+            Metric::Speed => "⚡ Speed",
+            Metric::Size => "📏 Size",
+            Metric::Sense => "👁 Sense",
+            Metric::Red => "🔴 Red",
+            Metric::Green => "🟢 Green",
+            Metric::Blue => "🔵 Blue",
+            Metric::Mutation => "🎲 Mutation",
+            Metric::Diversity => "🧬 Diversity",
+            Metric::Lineages => "🌳 Lineages",
+            Metric::Births => "🐣 Births",
+            Metric::Deaths => "💀 Deaths",
+            Metric::Plasmids => "🧫 Plasmids",
+            Metric::Heterozygosity => "🏷 Heterozygosity",
+            Metric::Markers => "🏷 Markers",
+            Metric::Regions => "🏝 Islands",
+        }
+    }
+
+    // Plain title for exported charts and file names
+    pub fn title(self) -> &'static str {
+        match self {
+            Metric::Population => "Bacteria",
+            Metric::EffectiveSize => "Effective population size (Ne)",
+            Metric::Predators => "Predators",
+            Metric::Food => "Food",
+            Metric::Speed => "Average speed",
+            Metric::Size => "Average size",
+            Metric::Sense => "Average sense radius",
+            Metric::Red => "Average red",
+            Metric::Green => "Average green",
+            Metric::Blue => "Average blue",
+            Metric::Mutation => "Average mutation rate",
+            Metric::Diversity => "Trait diversity (%)",
+            Metric::Lineages => "Surviving founder lineages",
+            Metric::Births => "Births per tick",
+            Metric::Deaths => "Deaths per tick",
+            Metric::Plasmids => "Plasmid carriers (%)",
+            Metric::Heterozygosity => "Marker heterozygosity",
+            Metric::Markers => "Marker allele frequencies (%)",
+            Metric::Regions => "Bacteria per region",
+        }
+    }
+
+    pub fn file_name(self) -> &'static str {
+        match self {
+            Metric::Population => "population",
+            Metric::EffectiveSize => "effective_size",
+            Metric::Predators => "predators",
+            Metric::Food => "food",
+            Metric::Speed => "speed",
+            Metric::Size => "size",
+            Metric::Sense => "sense",
+            Metric::Red => "red",
+            Metric::Green => "green",
+            Metric::Blue => "blue",
+            Metric::Mutation => "mutation_rate",
+            Metric::Diversity => "diversity",
+            Metric::Lineages => "lineages",
+            Metric::Births => "births",
+            Metric::Deaths => "deaths",
+            Metric::Plasmids => "plasmids",
+            Metric::Heterozygosity => "heterozygosity",
+            Metric::Markers => "markers",
+            Metric::Regions => "regions",
+        }
+    }
+
+    pub fn color(self) -> Color {
+        match self {
+            Metric::Population => Color::new(0.3, 1.0, 0.3, 1.0),
+            Metric::EffectiveSize => Color::new(0.6, 0.9, 0.6, 1.0),
+            Metric::Predators => Color::new(1.0, 0.4, 0.3, 1.0),
+            Metric::Food => Color::new(0.3, 0.9, 0.5, 1.0),
+            Metric::Speed => Color::new(0.9, 0.7, 0.2, 1.0),
+            Metric::Size => Color::new(0.5, 0.5, 1.0, 1.0),
+            Metric::Sense => Color::new(0.4, 0.9, 0.9, 1.0),
+            Metric::Red => Color::new(1.0, 0.35, 0.35, 1.0),
+            Metric::Green => Color::new(0.35, 1.0, 0.35, 1.0),
+            Metric::Blue => Color::new(0.4, 0.5, 1.0, 1.0),
+            Metric::Mutation => Color::new(0.85, 0.95, 0.4, 1.0),
+            Metric::Diversity => Color::new(0.9, 0.5, 0.9, 1.0),
+            Metric::Lineages => Color::new(0.55, 0.85, 0.45, 1.0),
+            Metric::Births => Color::new(1.0, 0.85, 0.6, 1.0),
+            Metric::Deaths => Color::new(0.7, 0.7, 0.75, 1.0),
+            Metric::Plasmids => Color::new(0.95, 0.6, 0.2, 1.0),
+            Metric::Heterozygosity => Color::new(0.75, 0.6, 1.0, 1.0),
+            Metric::Markers => Color::new(0.75, 0.6, 1.0, 1.0),
+            Metric::Regions => Color::new(0.3, 0.8, 1.0, 1.0),
+        }
+    }
+
+    // Fixed top of the y-axis for traits with a known range
+    pub fn fixed_max(self) -> Option<f32> {
+        match self {
+            Metric::Speed => Some(5.0),
+            Metric::Size => Some(15.0),
+            Metric::Red | Metric::Green | Metric::Blue | Metric::Heterozygosity => Some(1.0),
+            Metric::Markers => Some(100.0),
+            _ => None,
+        }
+    }
+
+    // Index into per-metric arrays such as the graph scales
+    pub fn index(self) -> usize {
+        Metric::ALL.iter().position(|&m| m == self).unwrap()
+    }
+
+    // A single series, unlike the marker and region graphs' several
+    pub fn single(self) -> bool {
+        !matches!(self, Metric::Markers | Metric::Regions)
+    }
+
+    // Index into `Metric::TRAITS`, for the gene metrics
+    pub fn trait_index(self) -> Option<usize> {
+        Metric::TRAITS.iter().position(|&m| m == self)
+    }
+
+    // The gene's value in `dna`, for the gene metrics
+    pub fn gene(self, dna: &DNA) -> Option<f32> {
+        Some(match self {
+            Metric::Speed => dna.gene(SPEED),
+            Metric::Size => dna.gene(SIZE),
+            Metric::Sense => dna.gene(SENSE_RADIUS),
+            Metric::Red => dna.gene(COLOR_R),
+            Metric::Green => dna.gene(COLOR_G),
+            Metric::Blue => dna.gene(COLOR_B),
+            Metric::Mutation => dna.gene(MUTATION_RATE),
+            _ => return None,
+        })
+    }
+
+    // The recorded spread of a gene metric
+    pub fn spread(self, stats: &Stats) -> Option<&[Spread]> {
+        self.trait_index().map(|i| stats.spread_history[i].as_slice())
+    }
+
+    // The recorded series, or None for the marker and region graphs (see
+    // `marker_history` and `region_history`)
+    pub fn history(self, stats: &Stats) -> Option<&[f32]> {
+        Some(match self {
+            Metric::Population => &stats.population_history,
+            Metric::EffectiveSize => &stats.effective_size_history,
+            Metric::Predators => &stats.predator_history,
+            Metric::Food => &stats.food_history,
+            Metric::Speed => &stats.avg_speed_history,
+            Metric::Size => &stats.avg_size_history,
+            Metric::Sense => &stats.avg_sense_history,
+            Metric::Red => &stats.avg_color_history[0],
+            Metric::Green => &stats.avg_color_history[1],
+            Metric::Blue => &stats.avg_color_history[2],
+            Metric::Mutation => &stats.avg_mutation_history,
+            Metric::Diversity => &stats.diversity_history,
+            Metric::Lineages => &stats.lineage_history,
+            Metric::Births => &stats.births_history,
+            Metric::Deaths => &stats.deaths_history,
+            Metric::Plasmids => &stats.plasmid_history,
+            Metric::Heterozygosity => &stats.heterozygosity_history,
+            Metric::Markers | Metric::Regions => return None,
+        })
+    }
+}
+
+// How a gene is distributed across the bacteria, beyond its mean
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Spread {
+    pub median: f32,
+    pub p10: f32,
+    pub p90: f32,
+    pub variance: f32,
+}
+
+impl Spread {
+    pub fn of(mut values: Vec<f32>) -> Self {
+        if values.is_empty() {
+            return Self::default();
+        }
+        values.sort_by(f32::total_cmp);
+        let n = values.len() as f32;
+        let mean = values.iter().sum::<f32>() / n;
+        // Nearest-rank percentile
+        let at = |q: f32| values[(q * (n - 1.0)).round() as usize];
+        Self {
+            median: at(0.5),
+            p10: at(0.1),
+            p90: at(0.9),
+            variance: values.iter().map(|v| (v - mean) * (v - mean)).sum::<f32>() / n,
+        }
+    }
+}
+
+// Pearson correlation between every pair of `CORRELATED_GENES` across the
+// living bacteria, showing which genes evolve together or trade off
+#[derive(Clone, Copy, Debug)]
+pub struct Correlations {
+    pub tick: u64,
+    pub count: usize,
+    pub matrix: [[f32; CORRELATED_GENES.len()]; CORRELATED_GENES.len()],
+    pub heritability: Heritability,
+}
+
+impl Correlations {
+    pub fn of(ecs: &hecs::World, tick: u64) -> Self {
+        const N: usize = CORRELATED_GENES.len();
+        let genes: Vec<[f32; N]> = ecs.query::<&Genome>().iter()
+            .map(|g| CORRELATED_GENES.map(|(_, tag)| g.0.gene(tag)))
+            .collect();
+        let count = genes.len();
+        let mut mean = [0.0f32; N];
+        for values in &genes {
+            for (m, v) in mean.iter_mut().zip(values) {
+                *m += v / count as f32;
+            }
+        }
+        let mut covariance = [[0.0f32; N]; N];
+        for values in &genes {
+            for i in 0..N {
+                for j in 0..N {
+                    covariance[i][j] += (values[i] - mean[i]) * (values[j] - mean[j]);
+                }
+            }
+        }
+        // Genes that don't vary correlate with nothing
+        let matrix = std::array::from_fn(|i| std::array::from_fn(|j| {
+            let scale = (covariance[i][i] * covariance[j][j]).sqrt();
+            if scale > 1e-9 { covariance[i][j] / scale } else { 0.0 }
+        }));
+        Self { tick, count, matrix, heritability: Heritability::of(ecs) }
+    }
+}
+
+// Realized heritability of speed, size and sense radius: the slope of the
+// offspring's expressed trait on its parent's, over every living bacterium
+// whose parent is alive too. Near 1 when offspring take after their parents,
+// lower the more of the variation is developmental noise.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Heritability {
+    pub pairs: usize,
+    pub slopes: [f32; 3],
+}
+
+// Speed, size and sense radius as expressed, by bacterium
+fn expressed_traits(ecs: &hecs::World) -> HashMap<hecs::Entity, [f32; 3]> {
+    ecs.query::<(hecs::Entity, &Genome, &Expression)>().iter()
+        .map(|(id, genome, e)| (id, [genome.0.gene(SPEED) * e.speed, genome.0.gene(SIZE) * e.size, genome.0.gene(SENSE_RADIUS) * e.sense_radius]))
+        .collect()
+}
+
+impl Heritability {
+    pub fn of(ecs: &hecs::World) -> Self {
+        let traits = expressed_traits(ecs);
+        let pairs: Vec<([f32; 3], [f32; 3])> = ecs.query::<(hecs::Entity, &Lineage)>().iter()
+            .filter_map(|(id, lineage)| Some((*traits.get(&lineage.parent?)?, *traits.get(&id)?)))
+            .collect();
+        let n = pairs.len() as f32;
+        let slopes = std::array::from_fn(|i| {
+            let mean_parent = pairs.iter().map(|(p, _)| p[i]).sum::<f32>() / n;
+            let mean_child = pairs.iter().map(|(_, c)| c[i]).sum::<f32>() / n;
+            let covariance: f32 = pairs.iter().map(|(p, c)| (p[i] - mean_parent) * (c[i] - mean_child)).sum();
+            let variance: f32 = pairs.iter().map(|(p, _)| (p[i] - mean_parent) * (p[i] - mean_parent)).sum();
+            if variance > 1e-9 { covariance / variance } else { 0.0 }
+        });
+        Self { pairs: pairs.len(), slopes }
+    }
+}
+
+// Selection on speed, size and sense radius over one window, and the drift
+// its offspring numbers imply
+#[derive(Clone, Copy, Debug)]
+pub struct SelectionWindow {
+    // Tick the window ended on
+    pub tick: u64,
+    // Bacteria alive when the window began
+    pub census: usize,
+    // Mean and variance of what each of those left at its end: itself if it
+    // survived, plus its living descendants born in the window
+    pub mean_offspring: f32,
+    pub offspring_variance: f32,
+    // Ne = (N k̄ - 1) / (k̄ - 1 + Vk / k̄) for a haploid population of N with
+    // k̄ and Vk above; none when nothing varied or nothing was left
+    pub effective_size: Option<f32>,
+    // Offspring born in the window and still alive at its end
+    pub offspring: usize,
+    // S: the mean of their parents, each counted once per offspring, minus
+    // the mean of everyone alive when the window began
+    pub differential: [f32; 3],
+    // R: the offspring's own mean minus that same mean
+    pub response: [f32; 3],
+}
+
+// Selection differentials and responses, measured over windows of
+// `SELECTION_EVERY` ticks since generations overlap. The breeder's equation
+// predicts R = h²S.
+#[derive(Clone, Debug, Default)]
+pub struct Selection {
+    // Tick the current window began on, with everyone alive then
+    start: Option<(u64, HashMap<hecs::Entity, [f32; 3]>)>,
+    // Oldest first
+    pub windows: Vec<SelectionWindow>,
+}
+
+impl Selection {
+    fn record(&mut self, ecs: &hecs::World, tick: u64) {
+        let due = match &self.start {
+            Some((start, _)) => tick >= start + SELECTION_EVERY || tick < *start,
+            None => true,
+        };
+        if !due {
+            return;
+        }
+        let traits = expressed_traits(ecs);
+        if let Some((start, before)) = self.start.take()
+            && tick > start
+            && let Some(window) = Self::window(ecs, tick, &before, &traits)
+        {
+            info!(
+                target: SIM,
+                tick,
+                offspring = window.offspring,
+                census = window.census,
+                ne = %window.effective_size.map_or("-".to_string(), |ne| format!("{:.1}", ne)),
+                "Selection S / R: speed {:+.3} / {:+.3}, size {:+.3} / {:+.3}, sense {:+.2} / {:+.2}",
+                window.differential[0], window.response[0],
+                window.differential[1], window.response[1],
+                window.differential[2], window.response[2],
+            );
+            self.windows.push(window);
+            if self.windows.len() > MAX_SELECTION_WINDOWS {
+                self.windows.remove(0);
+            }
+        }
+        self.start = Some((tick, traits));
+    }
+
+    fn window(
+        ecs: &hecs::World,
+        tick: u64,
+        before: &HashMap<hecs::Entity, [f32; 3]>,
+        now: &HashMap<hecs::Entity, [f32; 3]>,
+    ) -> Option<SelectionWindow> {
+        let pairs: Vec<([f32; 3], [f32; 3])> = ecs.query::<(hecs::Entity, &Lineage)>().iter()
+            .filter(|(id, _)| !before.contains_key(id))
+            .filter_map(|(id, lineage)| Some((*before.get(&lineage.parent?)?, *now.get(&id)?)))
+            .collect();
+        if pairs.is_empty() {
+            return None;
+        }
+        let (n, born) = (before.len() as f32, pairs.len() as f32);
+        let mean: [f32; 3] = std::array::from_fn(|i| before.values().map(|t| t[i]).sum::<f32>() / n);
+
+        // Credit every living bacterium to its ancestor from the start,
+        // through parents born in the window that are still alive
+        let parents: HashMap<hecs::Entity, Option<hecs::Entity>> = ecs.query::<(hecs::Entity, &Lineage)>().iter()
+            .map(|(id, lineage)| (id, lineage.parent))
+            .collect();
+        let mut left: HashMap<hecs::Entity, f32> = before.keys().map(|&id| (id, 0.0)).collect();
+        for &id in parents.keys() {
+            let mut current = id;
+            while !before.contains_key(&current) {
+                match parents.get(&current) {
+                    Some(&Some(parent)) => current = parent,
+                    _ => break,
+                }
+            }
+            if let Some(count) = left.get_mut(&current) {
+                *count += 1.0;
+            }
+        }
+        let mean_offspring = left.values().sum::<f32>() / n;
+        let offspring_variance = left.values().map(|k| (k - mean_offspring) * (k - mean_offspring)).sum::<f32>() / n;
+        let denominator = mean_offspring - 1.0 + offspring_variance / mean_offspring;
+        let effective_size = (mean_offspring > 0.0 && denominator > 1e-6).then(|| ((n * mean_offspring - 1.0) / denominator).max(0.0));
+
+        Some(SelectionWindow {
+            tick,
+            census: before.len(),
+            mean_offspring,
+            offspring_variance,
+            effective_size,
+            offspring: pairs.len(),
+            differential: std::array::from_fn(|i| pairs.iter().map(|(p, _)| p[i]).sum::<f32>() / born - mean[i]),
+            response: std::array::from_fn(|i| pairs.iter().map(|(_, c)| c[i]).sum::<f32>() / born - mean[i]),
+        })
+    }
+
+    // Ne from the latest window that could tell
+    pub fn effective_size(&self) -> Option<f32> {
+        self.windows.iter().rev().find_map(|w| w.effective_size)
+    }
+
+    // Realized heritability of each trait: the slope of R on S through the
+    // origin over the kept windows
+    pub fn realized(&self) -> [Option<f32>; 3] {
+        std::array::from_fn(|i| {
+            let ss: f32 = self.windows.iter().map(|w| w.differential[i] * w.differential[i]).sum();
+            let sr: f32 = self.windows.iter().map(|w| w.differential[i] * w.response[i]).sum();
+            (ss > 1e-12).then(|| sr / ss)
+        })
+    }
+}
+
+// Labelled tick drawn as a vertical line across the graphs
+#[derive(Clone, Debug)]
+pub struct Marker {
+    pub tick: u64,
+    pub label: String,
+    pub color: Color,
+}
+
+// Notices parameter changes (from the sliders or the control API) and marks
+// each on the graphs with the old and new value
+pub struct ParamWatch {
+    applied: serde_json::Value,
+}
+
+impl ParamWatch {
+    pub fn new(params: &SimulationParams) -> Self {
+        Self { applied: serde_json::to_value(params).unwrap_or_default() }
+    }
+
+    // Call once a change is complete (not mid-drag) so a slider drag becomes
+    // a single marker
+    pub fn annotate(&mut self, params: &SimulationParams, stats: &mut Stats, tick: u64) {
+        let current = serde_json::to_value(params).unwrap_or_default();
+        if current == self.applied {
+            return;
+        }
+        if let (Some(before), Some(after)) = (self.applied.as_object(), current.as_object()) {
+            for (name, value) in after {
+                let old = before.get(name).unwrap_or(&serde_json::Value::Null);
+                if old != value {
+                    let label = format!("{} {}→{}", name, short_value(old), short_value(value));
+                    stats.add_marker(tick, label, Color::new(0.75, 0.8, 0.9, 1.0));
+                }
+            }
+        }
+        self.applied = current;
+    }
+}
+
+fn short_value(value: &serde_json::Value) -> String {
+    match value {
+        serde_json::Value::Number(n) => {
+            let text = format!("{:.3}", n.as_f64().unwrap_or(0.0));
+            text.trim_end_matches('0').trim_end_matches('.').to_string()
+        }
+        serde_json::Value::String(s) => s.clone(),
+        serde_json::Value::Array(_) | serde_json::Value::Object(_) => "…".to_string(),
+        other => other.to_string(),
+    }
+}
+
+// What `Stats` records of a single tick, taken right after it, e.g. on the
+// worker thread for each tick of a batch
+pub struct TickStats {
+    sample: Sample,
+    markers: Vec<Vec<f32>>,
+    // Bacteria in each region, if there's more than one
+    regions: Vec<f32>,
+}
+
+impl TickStats {
+    pub fn of<V: Space>(world: &World<V>) -> Self {
+        let mut regions = Vec::new();
+        if world.habitat.regions.len() > 1 {
+            regions = vec![0.0; world.habitat.regions.len()];
+            for pos in world.ecs.query::<&Position<V>>().with::<&Genome>().iter() {
+                if let Some(i) = world.habitat.region_at(pos.0.xy()) {
+                    regions[i] += 1.0;
+                }
+            }
+        }
+        Self {
+            sample: Sample {
+                births: world.births as f32,
+                deaths: world.deaths as f32,
+                ..Sample::from_ecs(&world.ecs, world.tick, world.predator_count(), world.food_count())
+            },
+            markers: marker_frequencies(&world.ecs),
+            regions,
+        }
+    }
+}
+
+// One entry of every tracked series
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Sample {
+    pub tick: u64,
+    pub population: f32,
+    pub effective_size: f32,
+    pub predators: f32,
+    pub food: f32,
+    pub avg_speed: f32,
+    pub avg_size: f32,
+    pub avg_sense: f32,
+    // Red, green and blue
+    pub avg_color: [f32; 3],
+    pub avg_mutation: f32,
+    // Indexed like `Metric::TRAITS`
+    pub spread: [Spread; 7],
+    pub diversity: f32,
+    pub lineages: f32,
+    pub births: f32,
+    pub deaths: f32,
+    pub plasmids: f32,
+    pub heterozygosity: f32,
+}
+
+// Historical stats for graphing
+pub struct Stats {
+    // World tick each entry was recorded on
+    pub tick_history: Vec<u64>,
+    pub population_history: Vec<f32>,
+    // 0 until the first selection window has closed
+    pub effective_size_history: Vec<f32>,
+    pub avg_speed_history: Vec<f32>,
+    pub avg_size_history: Vec<f32>,
+    pub predator_history: Vec<f32>,
+    pub food_history: Vec<f32>,
+    pub avg_sense_history: Vec<f32>,
+    // Red, green and blue
+    pub avg_color_history: [Vec<f32>; 3],
+    pub avg_mutation_history: Vec<f32>,
+    // Distribution of each gene, indexed like `Metric::TRAITS`
+    pub spread_history: [Vec<Spread>; 7],
+    pub diversity_history: Vec<f32>,
+    pub lineage_history: Vec<f32>,
+    pub births_history: Vec<f32>,
+    pub deaths_history: Vec<f32>,
+    pub plasmid_history: Vec<f32>,
+    pub heterozygosity_history: Vec<f32>,
+    // Share of bacteria with each marker allele in percent, by locus then
+    // allele, empty without neutral markers. Alleles that turn up late are
+    // padded with zeros to line up with the rest.
+    pub marker_history: Vec<Vec<Vec<f32>>>,
+    // Bacteria per habitat region, empty for an open world
+    pub region_history: Vec<Vec<f32>>,
+    // Latest gene correlations, refreshed every `CORRELATION_EVERY` ticks
+    pub correlations: Option<Correlations>,
+    pub selection: Selection,
+    // Notable events within the recorded history, oldest first
+    pub markers: Vec<Marker>,
+    // Every history holds the run so far: the first `archived` entries are
+    // older ones thinned to one per `archive_stride` ticks or more, the rest
+    // the latest `MAX_HISTORY` at full detail
+    archived: usize,
+    archive_stride: u64,
+}
+
+impl Stats {
+    pub fn new() -> Self {
+        Self {
+            tick_history: Vec::new(),
+            population_history: Vec::new(),
+            effective_size_history: Vec::new(),
+            avg_speed_history: Vec::new(),
+            avg_size_history: Vec::new(),
+            predator_history: Vec::new(),
+            food_history: Vec::new(),
+            avg_sense_history: Vec::new(),
+            avg_color_history: Default::default(),
+            avg_mutation_history: Vec::new(),
+            spread_history: Default::default(),
+            diversity_history: Vec::new(),
+            lineage_history: Vec::new(),
+            births_history: Vec::new(),
+            deaths_history: Vec::new(),
+            plasmid_history: Vec::new(),
+            heterozygosity_history: Vec::new(),
+            marker_history: Vec::new(),
+            region_history: Vec::new(),
+            correlations: None,
+            selection: Selection::default(),
+            markers: Vec::new(),
+            archived: 0,
+            archive_stride: 1,
+        }
+    }
+
+    // Calculate population averages for the current world state
+    pub fn record<V: Space>(&mut self, world: &World<V>) {
+        // Stats are recorded every frame, also while paused; only count births
+        // and deaths once per tick
+        let mut tick = TickStats::of(world);
+        if self.tick_history.last() == Some(&world.tick) {
+            tick.sample.births = 0.0;
+            tick.sample.deaths = 0.0;
+        }
+        self.record_ticks(world, vec![tick]);
+    }
+
+    // Every tick of a batch, taken as it ran, then what's worked out from
+    // the world after it
+    pub fn record_ticks<V: Space>(&mut self, world: &World<V>, ticks: Vec<TickStats>) {
+        self.update_correlations(&world.ecs, world.tick);
+        self.selection.record(&world.ecs, world.tick);
+        let effective_size = self.selection.effective_size().unwrap_or(0.0);
+        for tick in ticks {
+            self.push(Sample { effective_size, ..tick.sample });
+            self.push_markers(tick.markers);
+            if !tick.regions.is_empty() {
+                self.region_history.resize_with(tick.regions.len(), Vec::new);
+                for (history, count) in self.region_history.iter_mut().zip(tick.regions) {
+                    history.push(count);
+                }
+            }
+            self.downsample();
+        }
+    }
+
+    pub fn push(&mut self, sample: Sample) {
+        let new_tick = self.tick_history.last() != Some(&sample.tick);
+        self.tick_history.push(sample.tick);
+        self.population_history.push(sample.population);
+        self.effective_size_history.push(sample.effective_size);
+        self.avg_speed_history.push(sample.avg_speed);
+        self.avg_size_history.push(sample.avg_size);
+        self.predator_history.push(sample.predators);
+        self.food_history.push(sample.food);
+        self.avg_sense_history.push(sample.avg_sense);
+        for (history, channel) in self.avg_color_history.iter_mut().zip(sample.avg_color) {
+            history.push(channel);
+        }
+        self.avg_mutation_history.push(sample.avg_mutation);
+        for (history, spread) in self.spread_history.iter_mut().zip(sample.spread) {
+            history.push(spread);
+        }
+        self.diversity_history.push(sample.diversity);
+        self.lineage_history.push(sample.lineages);
+        self.births_history.push(sample.births);
+        self.deaths_history.push(sample.deaths);
+        self.plasmid_history.push(sample.plasmids);
+        self.heterozygosity_history.push(sample.heterozygosity);
+
+        if new_tick {
+            self.detect_events();
+        }
+    }
+
+    // Once there are more than `MAX_HISTORY` latest entries, the oldest of
+    // them joins the older ones if it's at least `archive_stride` ticks after
+    // the last of those, and is dropped otherwise. Past `ARCHIVE_SAMPLES`
+    // older entries every other one is dropped and the stride doubles, so a
+    // run of any length fits.
+    fn downsample(&mut self) {
+        while self.tick_history.len() > self.archived + MAX_HISTORY {
+            let tick = self.tick_history[self.archived];
+            if self.archived == 0 || tick >= self.tick_history[self.archived - 1] + self.archive_stride {
+                self.archived += 1;
+            } else {
+                let mut keep = vec![true; self.tick_history.len()];
+                keep[self.archived] = false;
+                self.retain(&keep);
+            }
+        }
+        if self.archived > ARCHIVE_SAMPLES {
+            self.archive_stride *= 2;
+            let keep: Vec<bool> = (0..self.tick_history.len()).map(|i| i >= self.archived || i % 2 == 0).collect();
+            self.archived = self.archived.div_ceil(2);
+            self.retain(&keep);
+        }
+        if let Some(&first) = self.tick_history.first() {
+            self.markers.retain(|m| m.tick >= first);
+        }
+    }
+
+    // Drop the entries whose flag in `keep` (one per tick entry) is false
+    // from every history
+    fn retain(&mut self, keep: &[bool]) {
+        retain_entries(&mut self.tick_history, keep);
+        retain_entries(&mut self.population_history, keep);
+        retain_entries(&mut self.effective_size_history, keep);
+        retain_entries(&mut self.avg_speed_history, keep);
+        retain_entries(&mut self.avg_size_history, keep);
+        retain_entries(&mut self.predator_history, keep);
+        retain_entries(&mut self.food_history, keep);
+        retain_entries(&mut self.avg_sense_history, keep);
+        for history in &mut self.avg_color_history {
+            retain_entries(history, keep);
+        }
+        retain_entries(&mut self.avg_mutation_history, keep);
+        for history in &mut self.spread_history {
+            retain_entries(history, keep);
+        }
+        retain_entries(&mut self.diversity_history, keep);
+        retain_entries(&mut self.lineage_history, keep);
+        retain_entries(&mut self.births_history, keep);
+        retain_entries(&mut self.deaths_history, keep);
+        retain_entries(&mut self.plasmid_history, keep);
+        retain_entries(&mut self.heterozygosity_history, keep);
+        for history in self.marker_history.iter_mut().flatten() {
+            retain_entries(history, keep);
+        }
+        for history in &mut self.region_history {
+            retain_entries(history, keep);
+        }
+    }
+
+    fn push_markers(&mut self, frequencies: Vec<Vec<f32>>) {
+        let len = self.population_history.len();
+        if self.marker_history.len() < frequencies.len() {
+            self.marker_history.resize_with(frequencies.len(), Vec::new);
+        }
+        for (l, locus) in self.marker_history.iter_mut().enumerate() {
+            let now = frequencies.get(l).map_or(&[][..], Vec::as_slice);
+            if locus.len() < now.len() {
+                locus.resize_with(now.len(), Vec::new);
+            }
+            for (a, history) in locus.iter_mut().enumerate() {
+                history.push(now.get(a).copied().unwrap_or(0.0));
+                if history.len() < len {
+                    history.splice(0..0, std::iter::repeat_n(0.0, len - history.len()));
+                }
+                if history.len() > len {
+                    history.drain(..history.len() - len);
+                }
+            }
+        }
+    }
+
+    // Every marker allele's series with a label like "M1 A", in
+    // `marker_history` order
+    pub fn marker_series(&self) -> Vec<(String, &[f32], Color)> {
+        self.marker_history.iter().enumerate()
+            .flat_map(|(l, locus)| locus.iter().enumerate().map(move |(a, history)| {
+                (format!("M{} {}", l + 1, ALLELE_NAMES[a]), history.as_slice(), allele_color(l, a))
+            }))
+            .collect()
+    }
+
+    pub fn add_marker(&mut self, tick: u64, label: impl Into<String>, color: Color) {
+        let label = label.into();
+        info!(target: SIM, tick, "{}", label);
+        self.markers.push(Marker { tick, label, color });
+    }
+
+    // Mark population crashes (bacteria more than halving within
+    // `EVENT_WINDOW` ticks), predator extinction and diversity collapse
+    // (halving likewise)
+    fn detect_events(&mut self) {
+        let (Some(&tick), Some(&predators)) = (self.tick_history.last(), self.predator_history.last()) else { return };
+        let last = self.tick_history.len() - 1;
+        if last > 0 && predators == 0.0 && self.predator_history[last - 1] > 0.0 {
+            self.add_marker(tick, "predators extinct", Color::new(1.0, 0.4, 0.3, 1.0));
+        }
+        // Every bacterium left descends from one founder
+        if last > 0 && self.lineage_history[last] == 1.0 && self.lineage_history[last - 1] > 1.0 {
+            self.add_marker(tick, "lineage fixed", Metric::Lineages.color());
+        }
+
+        let Some(before) = self.tick_history.iter().rposition(|&t| t + EVENT_WINDOW <= tick) else { return };
+        let halved = |history: &[f32], floor: f32| history[before] >= floor && history[last] < history[before] * 0.5;
+        let checks = [
+            ("population crash", halved(&self.population_history, 20.0), Color::new(1.0, 0.85, 0.3, 1.0)),
+            ("diversity collapse", halved(&self.diversity_history, 1.0), Color::new(0.9, 0.5, 0.9, 1.0)),
+        ];
+        for (label, triggered, color) in checks {
+            // One marker per crash, not one per tick while it lasts
+            let recent = self.markers.iter().any(|m| m.label == label && m.tick + EVENT_WINDOW > tick);
+            if triggered && !recent {
+                self.add_marker(tick, label, color);
+            }
+        }
+    }
+
+    fn update_correlations(&mut self, ecs: &hecs::World, tick: u64) {
+        let due = match self.correlations {
+            // Batches of ticks can step over the multiple itself
+            Some(c) => c.tick / CORRELATION_EVERY != tick / CORRELATION_EVERY,
+            None => true,
+        };
+        if due {
+            self.correlations = Some(Correlations::of(ecs, tick));
+        }
+    }
+}
+
+impl Sample {
+    fn from_ecs(ecs: &hecs::World, tick: u64, predators: usize, food: usize) -> Self {
+        let avg = averages(ecs);
+        Self {
+            tick,
+            population: avg.count as f32,
+            predators: predators as f32,
+            food: food as f32,
+            avg_speed: avg.speed,
+            avg_size: avg.size,
+            avg_sense: avg.sense_radius,
+            avg_color: avg.color,
+            avg_mutation: avg.mutation_rate,
+            spread: spreads(ecs),
+            diversity: diversity(ecs, &avg),
+            lineages: founder_lineages(ecs) as f32,
+            plasmids: plasmid_carriers(ecs, avg.count),
+            heterozygosity: heterozygosity(&marker_frequencies(ecs)),
+            ..Default::default()
+        }
+    }
+}
+
+// Bacteria count with their average genes
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Averages {
+    pub count: usize,
+    pub speed: f32,
+    pub size: f32,
+    pub sense_radius: f32,
+    // Red, green and blue
+    pub color: [f32; 3],
+    pub mutation_rate: f32,
+}
+
+pub fn averages(ecs: &hecs::World) -> Averages {
+    let mut avg = Averages::default();
+    for genome in ecs.query::<&Genome>().iter() {
+        avg.count += 1;
+        avg.speed += genome.0.gene(SPEED);
+        avg.size += genome.0.gene(SIZE);
+        avg.sense_radius += genome.0.gene(SENSE_RADIUS);
+        avg.mutation_rate += genome.0.gene(MUTATION_RATE);
+        let color = genome.0.color();
+        for (sum, channel) in avg.color.iter_mut().zip([color.r, color.g, color.b]) {
+            *sum += channel;
+        }
+    }
+    if avg.count > 0 {
+        let n = avg.count as f32;
+        avg.speed /= n;
+        avg.size /= n;
+        avg.sense_radius /= n;
+        avg.mutation_rate /= n;
+        for channel in &mut avg.color {
+            *channel /= n;
+        }
+    }
+    avg
+}
+
+// Spread of every gene in `Metric::TRAITS` across the bacteria
+fn spreads(ecs: &hecs::World) -> [Spread; 7] {
+    let mut values: [Vec<f32>; 7] = Default::default();
+    for genome in ecs.query::<&Genome>().iter() {
+        for (list, metric) in values.iter_mut().zip(Metric::TRAITS) {
+            list.extend(metric.gene(&genome.0));
+        }
+    }
+    values.map(Spread::of)
+}
+
+// How varied the bacteria are: the coefficient of variation (standard
+// deviation over mean) of speed, size and sense radius, averaged, in percent
+fn diversity(ecs: &hecs::World, avg: &Averages) -> f32 {
+    if avg.count < 2 {
+        return 0.0;
+    }
+    let mut variance = [0.0f32; 3];
+    for genome in ecs.query::<&Genome>().iter() {
+        let deltas = [genome.0.gene(SPEED) - avg.speed, genome.0.gene(SIZE) - avg.size, genome.0.gene(SENSE_RADIUS) - avg.sense_radius];
+        for (v, d) in variance.iter_mut().zip(deltas) {
+            *v += d * d;
+        }
+    }
+    let means = [avg.speed, avg.size, avg.sense_radius];
+    let cv: f32 = variance.iter().zip(means)
+        .map(|(v, mean)| (v / avg.count as f32).sqrt() / mean.max(1e-6))
+        .sum();
+    cv / 3.0 * 100.0
+}
+
+// Founders with at least one living bacterium descended from them, or
+// still alive themselves
+fn founder_lineages(ecs: &hecs::World) -> usize {
+    ecs.query::<(hecs::Entity, &Lineage)>().with::<&Genome>().iter()
+        .map(|(id, lineage)| lineage.founder.unwrap_or(id))
+        .collect::<std::collections::HashSet<_>>()
+        .len()
+}
+
+// Drop the entries of `history` flagged false in `keep`. A history shorter
+// than `keep` (regions that appeared later) lines up with its end.
+fn retain_entries<T>(history: &mut Vec<T>, keep: &[bool]) {
+    let mut flags = keep[keep.len().saturating_sub(history.len())..].iter();
+    history.retain(|_| flags.next().copied().unwrap_or(true));
+}
+
+// Percentage of the `count` bacteria carrying at least one plasmid
+fn plasmid_carriers(ecs: &hecs::World, count: usize) -> f32 {
+    if count == 0 {
+        return 0.0;
+    }
+    let carriers = ecs.query::<&Plasmids>().iter().filter(|p| !p.0.is_empty()).count();
+    carriers as f32 / count as f32 * 100.0
+}
+
+// Names of the marker alleles, by index
+pub const ALLELE_NAMES: [&str; MAX_MARKER_ALLELES] = ["A", "B", "C", "D", "E", "F", "G", "H"];
+
+// A hue per allele, darker for each later locus
+pub fn allele_color(locus: usize, allele: usize) -> Color {
+    const HUES: [(f32, f32, f32); MAX_MARKER_ALLELES] = [
+        (1.0, 0.4, 0.4), (0.4, 0.8, 1.0), (0.5, 1.0, 0.4), (1.0, 0.85, 0.3),
+        (0.85, 0.5, 1.0), (1.0, 0.6, 0.2), (0.4, 1.0, 0.85), (0.9, 0.9, 0.9),
+    ];
+    let (r, g, b) = HUES[allele % MAX_MARKER_ALLELES];
+    let shade = 1.0 / (1.0 + 0.35 * locus as f32);
+    Color::new(r * shade, g * shade, b * shade, 1.0)
+}
+
+// Percentage of the bacteria with each allele, by marker locus then allele
+fn marker_frequencies(ecs: &hecs::World) -> Vec<Vec<f32>> {
+    let mut counts: Vec<Vec<f32>> = Vec::new();
+    let mut total = 0.0;
+    for genome in ecs.query::<&Genome>().iter() {
+        total += 1.0;
+        for (l, &allele) in genome.0.markers.iter().enumerate() {
+            let allele = (allele as usize).min(MAX_MARKER_ALLELES - 1);
+            if counts.len() <= l {
+                counts.resize_with(l + 1, Vec::new);
+            }
+            if counts[l].len() <= allele {
+                counts[l].resize(allele + 1, 0.0);
+            }
+            counts[l][allele] += 1.0;
+        }
+    }
+    for count in counts.iter_mut().flatten() {
+        *count *= 100.0 / total;
+    }
+    counts
+}
+
+// Expected heterozygosity, 1 - Σp², averaged over the marker loci; 0 without
+// markers
+fn heterozygosity(frequencies: &[Vec<f32>]) -> f32 {
+    if frequencies.is_empty() {
+        return 0.0;
+    }
+    let per_locus = frequencies.iter().map(|locus| 1.0 - locus.iter().map(|p| (p / 100.0) * (p / 100.0)).sum::<f32>());
+    per_locus.sum::<f32>() / frequencies.len() as f32
+}
+
+impl Default for Stats {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ::rand::SeedableRng;
+
+    // A bacterium with these expressed speed, size and sense radius and
+    // otherwise the same genes as every other
+    fn bacterium(ecs: &mut hecs::World, [speed, size, sense_radius]: [f32; 3], parent: Option<hecs::Entity>) -> hecs::Entity {
+        let mut dna = DNA::random(&mut ::rand::rngs::StdRng::seed_from_u64(1));
+        dna.set(SPEED, speed);
+        dna.set(SIZE, size);
+        dna.set(SENSE_RADIUS, sense_radius);
+        let lineage = Lineage { parent, generation: 0, founder: None, offspring: 0, origin: None };
+        ecs.spawn((Genome(dna), Expression::default(), lineage))
+    }
+
+    fn close(a: f32, b: f32) -> bool {
+        (a - b).abs() < 1e-5
+    }
+
+    #[test]
+    fn spread_takes_nearest_rank_percentiles() {
+        // 1 to 11, shuffled: ranks 0 to 10 land exactly on 2, 6 and 10
+        let spread = Spread::of(vec![7.0, 1.0, 11.0, 4.0, 9.0, 2.0, 6.0, 10.0, 3.0, 8.0, 5.0]);
+        assert_eq!((spread.p10, spread.median, spread.p90), (2.0, 6.0, 10.0));
+        assert!(close(spread.variance, 10.0));
+
+        // 1 to 4: ranks 0.3, 1.5 and 2.7 round to 0, 2 and 3
+        let spread = Spread::of(vec![4.0, 3.0, 2.0, 1.0]);
+        assert_eq!((spread.p10, spread.median, spread.p90), (1.0, 3.0, 4.0));
+        assert!(close(spread.variance, 1.25));
+    }
+
+    #[test]
+    fn spread_of_one_or_no_values() {
+        let spread = Spread::of(vec![2.5]);
+        assert_eq!((spread.p10, spread.median, spread.p90, spread.variance), (2.5, 2.5, 2.5, 0.0));
+        let spread = Spread::of(Vec::new());
+        assert_eq!((spread.p10, spread.median, spread.p90, spread.variance), (0.0, 0.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn correlations_are_pearson_over_the_living() {
+        let mut ecs = hecs::World::new();
+        // Size rises with speed exactly, sense radius falls with it exactly,
+        // and nothing else varies
+        for x in [1.0, 2.0, 4.0, 7.0] {
+            bacterium(&mut ecs, [x, 2.0 * x + 1.0, 100.0 - 3.0 * x], None);
+        }
+        let correlations = Correlations::of(&ecs, 300);
+        assert_eq!((correlations.tick, correlations.count), (300, 4));
+        let m = correlations.matrix;
+        assert!(close(m[0][0], 1.0) && close(m[0][1], 1.0) && close(m[1][0], 1.0));
+        assert!(close(m[0][2], -1.0) && close(m[2][1], -1.0));
+        // Genes that don't vary correlate with nothing, themselves included
+        assert_eq!((m[0][3], m[3][3]), (0.0, 0.0));
+    }
+
+    #[test]
+    fn correlations_of_unevenly_related_genes() {
+        // Speeds 1, 2, 3 and sizes 1, 3, 2: covariance 0.5 over variances of 1
+        let mut ecs = hecs::World::new();
+        for (speed, size) in [(1.0, 1.0), (2.0, 3.0), (3.0, 2.0)] {
+            bacterium(&mut ecs, [speed, size, 40.0], None);
+        }
+        assert!(close(Correlations::of(&ecs, 0).matrix[0][1], 0.5));
+    }
+
+    #[test]
+    fn heritability_is_the_offspring_on_parent_slope() {
+        let mut ecs = hecs::World::new();
+        let p1 = bacterium(&mut ecs, [1.0, 4.0, 40.0], None);
+        let p2 = bacterium(&mut ecs, [3.0, 6.0, 40.0], None);
+        // Speed halfway back to 1, size exactly the parent's, sense radius off
+        // on its own while the parents' doesn't vary
+        bacterium(&mut ecs, [1.0, 4.0, 30.0], Some(p1));
+        bacterium(&mut ecs, [1.0, 4.0, 50.0], Some(p1));
+        bacterium(&mut ecs, [2.0, 6.0, 45.0], Some(p2));
+        // A child whose parent died isn't a pair
+        let gone = bacterium(&mut ecs, [9.0, 9.0, 9.0], None);
+        bacterium(&mut ecs, [1.0, 1.0, 1.0], Some(gone));
+        ecs.despawn(gone).unwrap();
+
+        let heritability = Heritability::of(&ecs);
+        assert_eq!(heritability.pairs, 3);
+        assert!(close(heritability.slopes[0], 0.5), "slopes {:?}", heritability.slopes);
+        assert!(close(heritability.slopes[1], 1.0));
+        assert_eq!(heritability.slopes[2], 0.0);
+    }
+
+    #[test]
+    fn heritability_uses_expressed_traits() {
+        let mut ecs = hecs::World::new();
+        let p1 = bacterium(&mut ecs, [1.0, 5.0, 40.0], None);
+        let p2 = bacterium(&mut ecs, [2.0, 5.0, 40.0], None);
+        // Same speed gene as its parent, but it came out twice as fast
+        let c1 = bacterium(&mut ecs, [1.0, 5.0, 40.0], Some(p1));
+        bacterium(&mut ecs, [2.0, 5.0, 40.0], Some(p2));
+        ecs.insert_one(c1, Expression { speed: 2.0, ..Expression::default() }).unwrap();
+        // Offspring speeds 2 and 2 don't follow the parents' 1 and 2
+        assert_eq!(Heritability::of(&ecs).slopes[0], 0.0);
+    }
+
+    #[test]
+    fn selection_window_credits_descendants_to_their_ancestor() {
+        let mut ecs = hecs::World::new();
+        let a = bacterium(&mut ecs, [1.0, 5.0, 40.0], None);
+        let b = bacterium(&mut ecs, [2.0, 5.0, 40.0], None);
+        let c = bacterium(&mut ecs, [3.0, 5.0, 40.0], None);
+        let d = bacterium(&mut ecs, [4.0, 5.0, 40.0], None);
+        let before = expressed_traits(&ecs);
+
+        // A survives with a child and a grandchild, B with a child; C dies
+        // childless and D dies leaving a child
+        let a1 = bacterium(&mut ecs, [1.5, 5.0, 40.0], Some(a));
+        bacterium(&mut ecs, [1.5, 5.0, 40.0], Some(a1));
+        bacterium(&mut ecs, [2.5, 5.0, 40.0], Some(b));
+        bacterium(&mut ecs, [3.5, 5.0, 40.0], Some(d));
+        ecs.despawn(c).unwrap();
+        ecs.despawn(d).unwrap();
+        let now = expressed_traits(&ecs);
+
+        let window = Selection::window(&ecs, 200, &before, &now).unwrap();
+        assert_eq!((window.tick, window.census, window.offspring), (200, 4, 3));
+        // Left 3, 2, 0 and 1: k̄ = 1.5, Vk = 1.25, so Ne = (4 * 1.5 - 1) / (0.5 + 1.25 / 1.5)
+        assert!(close(window.mean_offspring, 1.5));
+        assert!(close(window.offspring_variance, 1.25));
+        assert!(close(window.effective_size.unwrap(), 3.75), "Ne = {:?}", window.effective_size);
+        // Parents A, B and D average 7/3 against 2.5 for everyone; their
+        // children average 2.5
+        assert!(close(window.differential[0], 7.0 / 3.0 - 2.5));
+        assert!(close(window.response[0], 0.0));
+        assert!(close(window.differential[1], 0.0));
+    }
+
+    #[test]
+    fn selection_window_with_even_offspring() {
+        // Everyone survives and leaves one child: k̄ = 2 and Vk = 0, so
+        // Ne = (5 * 2 - 1) / (2 - 1)
+        let mut ecs = hecs::World::new();
+        let parents: Vec<_> = (0..5).map(|i| bacterium(&mut ecs, [i as f32, 5.0, 40.0], None)).collect();
+        let before = expressed_traits(&ecs);
+        for &parent in &parents {
+            bacterium(&mut ecs, [1.0, 5.0, 40.0], Some(parent));
+        }
+        let now = expressed_traits(&ecs);
+        let window = Selection::window(&ecs, 200, &before, &now).unwrap();
+        assert!(close(window.effective_size.unwrap(), 9.0), "Ne = {:?}", window.effective_size);
+
+        // Nobody born in the window: nothing to measure
+        assert!(Selection::window(&ecs, 400, &now, &now).is_none());
+    }
+}
@@ -0,0 +1,117 @@
+use std::fs::File;
+use std::io::{BufWriter, Write};
+
+use crate::stats::{Metric, Stats};
+
+// Every metric with a single series, in column order
+pub fn columns() -> impl Iterator<Item = Metric> {
+    Metric::ALL.into_iter().filter(|m| m.single())
+}
+
+// Streams the stats to CSV, one row per tick: a `tick` column, one column per
+// metric named after `Metric::file_name`, then `<gene>_median`, `_p10`, `_p90`
+// and `_variance` for every gene metric
+pub struct StatsCsv {
+    out: BufWriter<File>,
+    last_tick: Option<u64>,
+}
+
+impl StatsCsv {
+    pub fn create(path: &str) -> std::io::Result<Self> {
+        let mut out = BufWriter::new(File::create(path)?);
+        let mut header: Vec<String> = std::iter::once("tick").chain(columns().map(Metric::file_name)).map(String::from).collect();
+        for metric in Metric::TRAITS {
+            for stat in ["median", "p10", "p90", "variance"] {
+                header.push(format!("{}_{}", metric.file_name(), stat));
+            }
+        }
+        writeln!(out, "{}", header.join(","))?;
+        Ok(Self { out, last_tick: None })
+    }
+
+    // Write the entries of `stats` since the last call, once per tick. A
+    // frame can bring a whole batch of ticks; if the last tick written is
+    // gone, e.g. after a restart, only the latest is written.
+    pub fn record(&mut self, stats: &Stats) -> std::io::Result<()> {
+        let ticks = &stats.tick_history;
+        let Some(&latest) = ticks.last() else { return Ok(()) };
+        if self.last_tick == Some(latest) {
+            return Ok(());
+        }
+        let start = ticks.iter().rposition(|&t| Some(t) == self.last_tick).map_or(ticks.len() - 1, |i| i + 1);
+        for i in start..ticks.len() {
+            if i > start && ticks[i] == ticks[i - 1] {
+                continue;
+            }
+            let mut row = ticks[i].to_string();
+            for metric in columns() {
+                let value = metric.history(stats).and_then(|h| h.get(i)).copied().unwrap_or(0.0);
+                row.push_str(&format!(",{}", value));
+            }
+            for metric in Metric::TRAITS {
+                let s = metric.spread(stats).and_then(|h| h.get(i)).copied().unwrap_or_default();
+                row.push_str(&format!(",{},{},{},{}", s.median, s.p10, s.p90, s.variance));
+            }
+            writeln!(self.out, "{}", row)?;
+        }
+        self.last_tick = Some(latest);
+        Ok(())
+    }
+}
+
+// A previous run's stats CSV, drawn behind the live graphs for comparison
+pub struct Baseline {
+    ticks: Vec<u64>,
+    // Indexed by `Metric::index`; None for metrics the file lacks
+    series: Vec<Option<Vec<f32>>>,
+}
+
+impl Baseline {
+    pub fn load(path: &str) -> Result<Self, String> {
+        let text = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
+        Self::parse(&text)
+    }
+
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut lines = text.lines();
+        let header: Vec<&str> = lines.next().ok_or("empty stats file")?.split(',').map(str::trim).collect();
+        let tick_column = header.iter().position(|&h| h == "tick").ok_or("no tick column")?;
+        let metric_columns: Vec<Option<Metric>> = header.iter()
+            .map(|h| Metric::ALL.into_iter().find(|m| m.file_name() == *h))
+            .collect();
+
+        let mut ticks = Vec::new();
+        let mut series = vec![None; Metric::ALL.len()];
+        for (row, line) in lines.enumerate().filter(|(_, l)| !l.trim().is_empty()) {
+            let values: Vec<&str> = line.split(',').map(str::trim).collect();
+            if values.len() != header.len() {
+                return Err(format!("row {} has {} columns, expected {}", row + 1, values.len(), header.len()));
+            }
+            ticks.push(values[tick_column].parse().map_err(|_| format!("bad tick on row {}", row + 1))?);
+            for (value, metric) in values.iter().zip(&metric_columns) {
+                if let Some(metric) = metric {
+                    let value = value.parse().map_err(|_| format!("bad {} on row {}", metric.file_name(), row + 1))?;
+                    series[metric.index()].get_or_insert_with(Vec::new).push(value);
+                }
+            }
+        }
+        Ok(Self { ticks, series })
+    }
+
+    // Every recorded value of `metric`, or None if the file doesn't have it
+    pub fn series(&self, metric: Metric) -> Option<&[f32]> {
+        self.series[metric.index()].as_deref()
+    }
+
+    // The baseline's value of `metric` at each of `ticks` (the last value
+    // recorded at or before it), or None if the file doesn't have the metric
+    pub fn aligned(&self, metric: Metric, ticks: &[u64]) -> Option<Vec<f32>> {
+        let series = self.series[metric.index()].as_ref()?;
+        Some(ticks.iter()
+            .map(|t| {
+                let i = self.ticks.partition_point(|bt| bt <= t);
+                series[i.saturating_sub(1).min(series.len() - 1)]
+            })
+            .collect())
+    }
+}
@@ -0,0 +1,180 @@
+use serde::Deserialize;
+use serde_json::{Map, Value};
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::sync::Mutex;
+use tracing::info;
+
+use crate::components::Lineage;
+use crate::logging::SIM;
+use crate::params::SimulationParams;
+use crate::stats::averages;
+use crate::world::World;
+
+fn default_seeds() -> Vec<u64> {
+    vec![1]
+}
+
+fn default_ticks() -> u64 {
+    1000
+}
+
+// A parameter sweep, e.g.
+// `{"params": {"mutation_rate": [0.01, 0.1], "max_food": {"from": 500, "to": 2000, "steps": 4}}, "seeds": [1, 2, 3], "ticks": 5000}`.
+// Every combination of the listed values runs once per seed.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Sweep {
+    pub params: BTreeMap<String, Values>,
+    #[serde(default = "default_seeds")]
+    pub seeds: Vec<u64>,
+    #[serde(default = "default_ticks")]
+    pub ticks: u64,
+}
+
+// Values to try for one parameter: listed, or `steps` evenly spaced from
+// `from` to `to`
+#[derive(Deserialize)]
+#[serde(untagged)]
+pub enum Values {
+    List(Vec<Value>),
+    Range { from: f64, to: f64, steps: usize },
+}
+
+impl Values {
+    fn expand(&self) -> Vec<Value> {
+        match self {
+            Values::List(values) => values.clone(),
+            Values::Range { from, to, steps } => {
+                // Whole-number ends give whole numbers, for counts like `max_food`
+                let whole = from.fract() == 0.0 && to.fract() == 0.0;
+                (0..*steps)
+                    .map(|i| {
+                        let t = if *steps > 1 { i as f64 / (*steps - 1) as f64 } else { 0.0 };
+                        let value = from + (to - from) * t;
+                        if whole { Value::from(value.round() as i64) } else { Value::from(value) }
+                    })
+                    .collect()
+            }
+        }
+    }
+}
+
+// Where a run ended up
+pub struct Outcome {
+    pub settings: Map<String, Value>,
+    pub seed: u64,
+    pub tick: u64,
+    pub bacteria: usize,
+    pub predators: usize,
+    pub food: usize,
+    pub births: usize,
+    pub deaths: usize,
+    pub max_generation: u32,
+    pub avg_speed: f32,
+    pub avg_size: f32,
+    pub avg_sense_radius: f32,
+}
+
+impl Sweep {
+    pub fn load(path: &str) -> Result<Self, String> {
+        let text = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
+        serde_json::from_str(&text).map_err(|e| e.to_string())
+    }
+
+    // Every combination of the parameters' values, in name order
+    pub fn combinations(&self) -> Vec<Map<String, Value>> {
+        let mut combinations = vec![Map::new()];
+        for (name, values) in &self.params {
+            let values = values.expand();
+            combinations = combinations.iter()
+                .flat_map(|partial| values.iter().map(move |value| {
+                    let mut next = partial.clone();
+                    next.insert(name.clone(), value.clone());
+                    next
+                }))
+                .collect();
+        }
+        combinations
+    }
+
+    // Run every combination and seed over `base`, `jobs` at a time, each in a
+    // world from `new_world`. Outcomes come back in combination then seed order.
+    pub fn run(&self, base: &SimulationParams, jobs: usize, new_world: impl Fn(&SimulationParams, u64) -> World + Sync) -> Result<Vec<Outcome>, String> {
+        let mut runs = Vec::new();
+        for settings in self.combinations() {
+            let mut params = base.clone();
+            params.merge(settings.clone())?;
+            for &seed in &self.seeds {
+                runs.push((settings.clone(), params.clone(), seed));
+            }
+        }
+        let total = runs.len();
+        let queue = Mutex::new(runs.into_iter().enumerate());
+        let outcomes = Mutex::new(Vec::with_capacity(total));
+        std::thread::scope(|scope| {
+            for _ in 0..jobs.max(1) {
+                scope.spawn(|| {
+                    loop {
+                        let Some((i, (settings, params, seed))) = queue.lock().unwrap().next() else { break };
+                        let outcome = run_one(settings, &params, seed, self.ticks, &new_world);
+                        info!(target: SIM, "Sweep run {} of {} done", i + 1, total);
+                        outcomes.lock().unwrap().push((i, outcome));
+                    }
+                });
+            }
+        });
+        let mut outcomes = outcomes.into_inner().unwrap();
+        outcomes.sort_by_key(|(i, _)| *i);
+        Ok(outcomes.into_iter().map(|(_, outcome)| outcome).collect())
+    }
+
+    // One row per outcome: the swept parameters, the seed, then the results
+    pub fn write_csv(&self, outcomes: &[Outcome], mut out: impl Write) -> std::io::Result<()> {
+        let names: Vec<&str> = self.params.keys().map(String::as_str).collect();
+        let results = ["seed", "tick", "bacteria", "predators", "food", "births", "deaths", "max_generation", "avg_speed", "avg_size", "avg_sense_radius"];
+        writeln!(out, "{}", names.iter().chain(&results).copied().collect::<Vec<_>>().join(","))?;
+        for o in outcomes {
+            let settings: Vec<String> = names.iter().map(|name| csv_field(&o.settings[*name])).collect();
+            writeln!(out, "{},{},{},{},{},{},{},{},{},{},{},{}",
+                settings.join(","), o.seed, o.tick, o.bacteria, o.predators, o.food, o.births, o.deaths,
+                o.max_generation, o.avg_speed, o.avg_size, o.avg_sense_radius)?;
+        }
+        out.flush()
+    }
+}
+
+// Strings (enum variants) without their JSON quotes; anything else as JSON,
+// quoted if it has commas
+fn csv_field(value: &Value) -> String {
+    let text = match value {
+        Value::String(s) => s.clone(),
+        other => other.to_string(),
+    };
+    if text.contains(',') || text.contains('"') { format!("\"{}\"", text.replace('"', "\"\"")) } else { text }
+}
+
+fn run_one(settings: Map<String, Value>, params: &SimulationParams, seed: u64, ticks: u64, new_world: &impl Fn(&SimulationParams, u64) -> World) -> Outcome {
+    let mut world = new_world(params, seed);
+    let (mut births, mut deaths) = (0, 0);
+    while world.tick < ticks {
+        world.step(params);
+        births += world.births;
+        deaths += world.deaths;
+    }
+    let avg = averages(&world.ecs);
+    Outcome {
+        settings,
+        seed,
+        tick: world.tick,
+        bacteria: world.bacteria_count(),
+        predators: world.predator_count(),
+        food: world.food_count(),
+        births,
+        deaths,
+        max_generation: world.ecs.query::<&Lineage>().iter().map(|l| l.generation).max().unwrap_or(0),
+        avg_speed: avg.speed,
+        avg_size: avg.size,
+        avg_sense_radius: avg.sense_radius,
+    }
+}
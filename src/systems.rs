@@ -0,0 +1,873 @@
+use hecs::Entity;
+use macroquad::prelude::*;
+use ::rand::Rng;
+use std::collections::HashSet;
+use tracing::info;
+
+use crate::components::{Age, Aversion, Body, Camouflage, Digesting, Energy, Expression, Food, Genome, Imprint, Lineage, Metabolism, PhTolerance, Plasmids, Position, PredatorTag, Refractory, Respiration, Species, Startle, Target, Velocity};
+use crate::distances::Points;
+use crate::dna::{random_markers, AGGRESSION, ARMOR, COOLDOWN, CROWDING, DISPERSAL, DNA, FLEE_ENERGY, INVESTMENT, MATURATION, Plasmid, SENSE_RADIUS, SIZE, SPEED, TOXIN, VIGILANCE};
+use crate::habitat::reflect;
+use crate::logging::SIM;
+use crate::events::{DeathCause, Event, Meal, Organism};
+use crate::food_web::{FoodWeb, Prey};
+use crate::organism::{Bacterium, Predator};
+use crate::params::{FoodContention, SimulationParams};
+use crate::space::Space;
+use crate::terrain::TerrainType;
+use crate::world::{Removals, World};
+
+pub const FLEE_RADIUS: f32 = 80.0;
+// How close a predator has to get to startle a bacterium into a burst
+const STARTLE_RADIUS: f32 = 30.0;
+// Ticks an ambusher keeps still before it's hidden, and how close prey have
+// to be to spot it then: from `AMBUSH_SPOT_RADIUS` without vigilance up to
+// `FLEE_RADIUS` with full vigilance
+const AMBUSH_SETTLE: u32 = 20;
+const AMBUSH_SPOT_RADIUS: f32 = 15.0;
+// How far a hunter hears its pack at full cooperation
+const PACK_RADIUS: f32 = 200.0;
+// How far past its body a consumer reaches a food item, as bacteria do
+const FOOD_REACH: f32 = 2.0;
+
+// Add random food. Each region gets its share of the world's food by area,
+// scaled by its own food multiplier. Fertile terrain doubles what lands on it,
+// barren terrain grows nothing and waste chokes growth the closer it gets to the
+// toxic threshold.
+pub fn spawn_food<V: Space>(world: &World<V>, params: &SimulationParams, _: &Removals) -> Vec<Event<V>> {
+    let area = world.area_factor();
+    let regions = &world.habitat.regions;
+    let mut counts = vec![0usize; regions.len()];
+    if regions.len() == 1 {
+        counts[0] = world.food_count();
+    } else {
+        for pos in world.food_positions() {
+            if let Some(i) = world.habitat.region_at(pos.xy()) {
+                counts[i] += 1;
+            }
+        }
+    }
+
+    let total_area = world.habitat.total_area();
+    let mut rng = world.rng();
+    let mut events = Vec::new();
+    for (region, count) in regions.iter().zip(counts) {
+        let share = region.area() / total_area * region.food_multiplier;
+        if count as f32 >= params.max_food as f32 * area * share {
+            continue;
+        }
+        // Round the expected count up or down at random so small shares still get food
+        let expected = params.food_growth_rate * area * share;
+        let count = expected as usize + usize::from(rng.gen_range(0.0f32..1.0) < expected.fract());
+        for _ in 0..count {
+            let pos = region.random_food_pos(&mut *rng);
+            let waste = world.waste.at(pos);
+            if waste > 0.0 && rng.gen_range(0.0f32..1.0) < waste / params.waste_threshold {
+                continue;
+            }
+            match world.terrain.at(pos) {
+                TerrainType::Barren | TerrainType::Wall => {}
+                TerrainType::Fertile => {
+                    events.push(Event::FoodSpawned { pos: world.lift(pos, &mut *rng) });
+                    let next = world.terrain.random_pos_in_cell(pos, &mut *rng);
+                    events.push(Event::FoodSpawned { pos: world.lift(next, &mut *rng) });
+                }
+                _ => events.push(Event::FoodSpawned { pos: world.lift(pos, &mut *rng) }),
+            }
+        }
+    }
+    events
+}
+
+// Where everything is, by kind (see `Prey::index`): in plain sight, and
+// hidden in ambush
+fn positions_by_kind<V: Space>(world: &World<V>, species: usize) -> (Vec<Vec<V>>, Vec<Vec<V>>) {
+    let mut seen = vec![Vec::new(); Prey::count(species)];
+    let mut hidden = vec![Vec::new(); Prey::count(species)];
+    seen[Prey::Food.index()] = world.food_positions();
+    for (pos, genome, consumer, camouflage) in world.ecs.query::<(&Position<V>, Option<&Genome>, Option<&Species>, Option<&Camouflage>)>().with::<&Body>().iter() {
+        let kind = if genome.is_some() { Prey::Bacteria } else { Prey::consumer(consumer.map(|s| s.0)) };
+        let lists = if camouflage.is_some_and(|c| c.hidden) { &mut hidden } else { &mut seen };
+        if let Some(list) = lists.get_mut(kind.index()) {
+            list.push(pos.0);
+        }
+    }
+    (seen, hidden)
+}
+
+// Threats a bacterium or consumer at `pos` notices: all those in plain sight,
+// and hidden ones within `spot`
+fn noticed<'a, V: Space>(pos: V, seen: &'a [V], hidden: &'a [V], spot: f32) -> impl Iterator<Item = V> + 'a {
+    seen.iter().copied().chain(hidden.iter().copied().filter(move |p| pos.distance(*p) < spot))
+}
+
+// Sum of the pushes away from every threat within reach
+fn flee_direction<V: Space>(pos: V, threats: impl IntoIterator<Item = V>) -> V {
+    let mut flee_dir = V::ZERO;
+    for p in threats {
+        let dist = pos.distance(p);
+        if dist < FLEE_RADIUS && dist > 0.1 {
+            let away = (pos - p).normalize();
+            flee_dir += away / dist;
+        }
+    }
+    flee_dir
+}
+
+// Pick a new heading for every organism from what it can see. Bacteria and
+// consumers alike flee whatever eats them; consumers then hunt the nearest
+// thing in their diet.
+pub fn sensing<V: Space>(world: &World<V>, params: &SimulationParams, _: &Removals) -> Vec<Event<V>> {
+    let mut rng = world.rng();
+    let web = FoodWeb::new(params);
+    let (seen, hidden) = positions_by_kind(world, params.species.len());
+    let everywhere: Vec<Vec<V>> = seen.iter().zip(&hidden).map(|(s, h)| [s.as_slice(), h].concat()).collect();
+    // Prey sheltering in a safe zone is invisible to consumers
+    let exposed: Vec<Vec<V>> = everywhere.iter().enumerate()
+        .map(|(kind, list)| if kind == Prey::Food.index() {
+            list.clone()
+        } else {
+            list.iter().copied().filter(|p| world.terrain.at(p.xy()) != TerrainType::SafeZone).collect()
+        })
+        .collect();
+    let gather = |lists: &[Vec<V>], kinds: &mut dyn Iterator<Item = Prey>| -> Vec<V> {
+        kinds.flat_map(|kind| lists.get(kind.index()).into_iter().flatten().copied()).collect()
+    };
+    let food = Points::new(&everywhere[Prey::Food.index()]);
+    let bacteria = &everywhere[Prey::Bacteria.index()];
+    let crowd = Points::new(bacteria);
+    // Bacteria consumers can see, by colour, for those that shun some
+    let bacteria_colors: Vec<(V, Color)> = world.ecs.query::<(&Position<V>, &Genome)>().iter()
+        .filter(|(pos, _)| world.terrain.at(pos.0.xy()) != TerrainType::SafeZone)
+        .map(|(pos, genome)| (pos.0, genome.0.color()))
+        .collect();
+    let threats = gather(&seen, &mut web.hunters_of(Prey::Bacteria).into_iter());
+    let ambushers = gather(&hidden, &mut web.hunters_of(Prey::Bacteria).into_iter());
+    // With a stride, each organism looks for food or prey only on its own
+    // ticks, spread out by handle, and keeps after what it last saw between
+    let stride = params.sense_stride.max(1) as u64;
+    let looks = |id: Entity| (world.tick + id.id() as u64).is_multiple_of(stride);
+    let food_left: HashSet<[u32; 3]> = if stride > 1 {
+        everywhere[Prey::Food.index()].iter().map(|p| p.key()).collect()
+    } else {
+        HashSet::new()
+    };
+
+    // A heading for every organism, and the odd escape burst
+    let mut events = Vec::with_capacity(world.ecs.query::<&Body>().iter().len());
+    for (id, pos, vel, body, energy, startle, genome, last) in world.ecs.query::<(Entity, &Position<V>, &Velocity<V>, &Body, &Energy, &Startle, &Genome, &Target<V>)>().iter() {
+        let spot = AMBUSH_SPOT_RADIUS + genome.0.gene(VIGILANCE) * (FLEE_RADIUS - AMBUSH_SPOT_RADIUS);
+        // A predator right on top of it sets off an escape burst, if it can
+        // afford one
+        if startle.ready() && energy.0 > startle.cost() && noticed(pos.0, &threats, &ambushers, spot).any(|p| pos.0.distance(p) < STARTLE_RADIUS) {
+            events.push(Event::Startled { id, cost: startle.cost() });
+        }
+        let flee_dir = flee_direction(pos.0, noticed(pos.0, &threats, &ambushers, spot));
+        // Its regulatory genes decide what it does: a hungry one ignores
+        // predators, and one in too big a crowd leaves it instead of foraging
+        let (vel, target) = if flee_dir.length() > 0.1 && energy.0 > genome.0.gene(FLEE_ENERGY) {
+            // Flee from predators
+            ((vel.0 + flee_dir.normalize() * 0.5).normalize(), None)
+        } else {
+            // Normal behavior: random jitter / steering
+            let vel = vel.0.wander(0.2, &mut *rng);
+
+            if looks(id) && crowd.count_within(pos.0, 0.1..body.sense_radius) as f32 > genome.0.gene(CROWDING) {
+                let neighbours = bacteria.iter().filter(|p| (0.1..body.sense_radius).contains(&pos.0.distance(**p)));
+                let away = neighbours.fold(V::ZERO, |sum, p| sum + (pos.0 - *p).normalize());
+                ((vel + away.normalize_or_zero() * 0.2).normalize(), None)
+            } else {
+                // Steer towards nearest food, or between looks the food it
+                // last saw while that's still there
+                let target = if looks(id) {
+                    food.nearest_within(pos.0, body.sense_radius)
+                } else {
+                    last.0.filter(|t| food_left.contains(&t.key()))
+                };
+                match target {
+                    Some(target) => ((vel + (target - pos.0).normalize() * 0.2).normalize(), Some(target)),
+                    None => (vel, None),
+                }
+            }
+        };
+        events.push(Event::Steered { id, vel, target });
+    }
+
+    // What each kind of consumer flees from (in plain sight and hidden) and
+    // hunts: the built-in predators, then each species
+    let plans: Vec<(Vec<V>, Vec<V>, Vec<V>)> = (0..=params.species.len())
+        .map(|i| {
+            let species = i.checked_sub(1);
+            let hunters = web.hunters_of(Prey::consumer(species));
+            let diet = web.diet(species).iter().map(|(kind, _)| *kind);
+            (gather(&seen, &mut hunters.clone().into_iter()), gather(&hidden, &mut hunters.into_iter()), gather(&exposed, &mut diet.into_iter()))
+        })
+        .collect();
+    let prey_points: Vec<Points<V>> = plans.iter().map(|(_, _, prey)| Points::new(prey)).collect();
+    // Consumers with nothing to flee: handle, kind, position, heading and
+    // what they're after, if anything
+    let mut hunters: Vec<(Entity, usize, V, V, Option<V>)> = Vec::new();
+    // Where the bacteria a wary consumer shuns are, and the prey it still
+    // hunts, reused from one consumer to the next
+    let mut shunned: HashSet<[u32; 3]> = HashSet::new();
+    let mut wary: Vec<V> = Vec::new();
+    let mut wary_points = Points::default();
+    for (id, pos, vel, body, species, aversion, last) in world.ecs.query::<(Entity, &Position<V>, &Velocity<V>, &Body, Option<&Species>, Option<&Aversion>, &Target<V>)>().with::<&PredatorTag>().iter() {
+        let kind = species.map_or(0, |s| s.0 + 1);
+        let Some((threats, ambushers, prey)) = plans.get(kind) else {
+            events.push(Event::Steered { id, vel: vel.0, target: None });
+            continue;
+        };
+        let flee_dir = flee_direction(pos.0, noticed(pos.0, threats, ambushers, AMBUSH_SPOT_RADIUS));
+        if flee_dir.length() > 0.1 {
+            events.push(Event::Steered { id, vel: (vel.0 + flee_dir.normalize() * 0.5).normalize(), target: None });
+            continue;
+        }
+        // Ambushers wait where they are for prey to come by
+        let ambush = web.ambushes(species.map(|s| s.0));
+        if prey.is_empty() {
+            events.push(Event::Steered { id, vel: if ambush { V::ZERO } else { vel.0 }, target: None });
+            continue;
+        }
+        // Hunt the nearest prey, passing over any that look poisonous, or
+        // between looks go after the prey where it was last seen
+        if !looks(id) {
+            hunters.push((id, kind, pos.0, if ambush && last.0.is_none() { V::ZERO } else { vel.0 }, last.0));
+            continue;
+        }
+        let prey = match aversion.filter(|a| a.active(world.tick)) {
+            Some(aversion) => {
+                shunned.clear();
+                shunned.extend(bacteria_colors.iter()
+                    .filter(|(_, color)| aversion.avoids(*color, world.tick))
+                    .map(|(p, _)| p.key()));
+                wary.clear();
+                wary.extend(prey.iter().copied().filter(|p| !shunned.contains(&p.key())));
+                wary_points.set(&wary);
+                &wary_points
+            }
+            None => &prey_points[kind],
+        };
+        let target = prey.nearest_within(pos.0, body.sense_radius);
+        let vel = match target {
+            Some(_) => vel.0,
+            None if ambush => V::ZERO,
+            // Random wandering
+            None => vel.0.wander(0.15, &mut *rng),
+        };
+        hunters.push((id, kind, pos.0, vel, target));
+    }
+
+    let chases = pack_chases(&hunters, params.pack_cooperation);
+    for ((id, _, _, vel, _), chase) in hunters.into_iter().zip(chases) {
+        let (vel, target) = match chase {
+            Some((target, heading)) => ((vel + heading * 0.3).normalize(), Some(target)),
+            None => (vel, None),
+        };
+        events.push(Event::Steered { id, vel, target });
+    }
+    events
+}
+
+// The prey each hunter chases, if any, and which way it heads for it. Alone, a hunter
+// runs straight at what it sees. With cooperation, one that sees nothing
+// takes up the chase of the nearest packmate of its kind within earshot (up
+// to `PACK_RADIUS`), and hunters closing on the same prey spread around it to
+// come at it from different sides, more so the more they cooperate. In the
+// box they spread out level with it.
+fn pack_chases<V: Space>(hunters: &[(Entity, usize, V, V, Option<V>)], cooperation: f32) -> Vec<Option<(V, V)>> {
+    let cooperation = cooperation.clamp(0.0, 1.0);
+    let direct = |pos: V, target: V| Some((target, (target - pos).try_normalize()?));
+    if cooperation == 0.0 {
+        return hunters.iter().map(|&(_, _, pos, _, target)| direct(pos, target?)).collect();
+    }
+
+    let earshot = PACK_RADIUS * cooperation;
+    let targets: Vec<Option<V>> = hunters.iter()
+        .map(|&(_, kind, pos, _, target)| target.or_else(|| {
+            hunters.iter()
+                .filter(|(_, k, p, _, t)| *k == kind && t.is_some() && p.distance(pos) < earshot)
+                .min_by(|a, b| a.2.distance(pos).total_cmp(&b.2.distance(pos)))
+                .and_then(|(_, _, _, _, t)| *t)
+        }))
+        .collect();
+
+    // Hunters of a kind after the same prey, in the order they were found
+    let mut packs: Vec<(usize, V, Vec<usize>)> = Vec::new();
+    for (i, (&(_, kind, _, _, _), target)) in hunters.iter().zip(&targets).enumerate() {
+        let Some(target) = *target else { continue };
+        match packs.iter_mut().find(|(k, t, _)| *k == kind && *t == target) {
+            Some((_, _, members)) => members.push(i),
+            None => packs.push((kind, target, vec![i])),
+        }
+    }
+
+    let mut chases: Vec<Option<(V, V)>> = hunters.iter().zip(&targets).map(|(h, t)| direct(h.2, (*t)?)).collect();
+    for (_, target, mut members) in packs {
+        if members.len() < 2 {
+            continue;
+        }
+        // Fan the pack out around the side it's coming from, each member
+        // taking the slot nearest its own bearing so no paths cross
+        let centre = members.iter().map(|&i| hunters[i].2).sum::<V>() / members.len() as f32;
+        let angle_of = |v: V| v.xy().y.atan2(v.xy().x);
+        let base = angle_of(centre - target);
+        let bearing = |i: usize| {
+            let offset = angle_of(hunters[i].2 - target) - base;
+            offset.sin().atan2(offset.cos())
+        };
+        members.sort_by(|&a, &b| bearing(a).total_cmp(&bearing(b)));
+        let spread = std::f32::consts::TAU / members.len() as f32;
+        for (slot, &i) in members.iter().enumerate() {
+            let pos = hunters[i].2;
+            let angle = base + (slot as f32 - (members.len() - 1) as f32 / 2.0) * spread;
+            // Aim for a point beside the prey that closes in on it as the
+            // hunter does
+            let waypoint = target + V::from_xy(Vec2::from_angle(angle), 0.0) * pos.distance(target) * 0.5;
+            if let (Some((_, straight)), Some((_, flank))) = (direct(pos, target), direct(pos, waypoint)) {
+                chases[i] = Some((target, straight.lerp(flank, cooperation).try_normalize().unwrap_or(straight)));
+            }
+        }
+    }
+    chases
+}
+
+// Consumers that have kept still long enough in ambush go hidden, and come
+// out of hiding once they move or stop being ambushers
+pub fn camouflage<V: Space>(world: &World<V>, params: &SimulationParams, _: &Removals) -> Vec<Event<V>> {
+    let web = FoodWeb::new(params);
+    let mut events = Vec::new();
+    for (id, camouflage, species) in world.ecs.query::<(Entity, &Camouflage, Option<&Species>)>().iter() {
+        let hidden = web.ambushes(species.map(|s| s.0)) && camouflage.still >= AMBUSH_SETTLE;
+        if hidden != camouflage.hidden {
+            events.push(Event::Camouflaged { id, hidden });
+        }
+    }
+    events
+}
+
+// Move along the current heading, bounce off the walls and pay metabolism.
+// Swamps and digesting slow movement and predators turn back at the edge of a safe zone. A
+// bacterium's metabolic cost depends on how well its respiration suits the
+// local oxygen, with asymmetric division on how often it has divided, and with
+// epigenetics on its stress mark.
+pub fn movement<V: Space>(world: &World<V>, params: &SimulationParams, _: &Removals) -> Vec<Event<V>> {
+    let speed_mult = params.speed_multiplier;
+    let habitat = &world.habitat;
+    let extent = world.extent();
+    let mut events = Vec::with_capacity(world.ecs.query::<&Body>().iter().len());
+    for (id, pos, vel, body, metabolism, respiration, startle, digesting, imprint, plasmids, lineage, predator) in world.ecs.query::<(Entity, &Position<V>, &Velocity<V>, &Body, &Metabolism, Option<&Respiration>, Option<&Startle>, Option<&Digesting>, Option<&Imprint>, Option<&Plasmids>, &Lineage, Option<&PredatorTag>)>().iter() {
+        let old = pos.0;
+        let terrain_speed = world.terrain.at(old.xy()).speed_factor();
+        let burst = startle.map_or(1.0, Startle::speed_factor);
+        let digesting = digesting.map_or(1.0, Digesting::speed_factor);
+        let imprint = imprint.filter(|_| params.epigenetics);
+        let mut pos = pos.0 + vel.0 * body.speed * speed_mult * terrain_speed * burst * digesting * imprint.map_or(1.0, Imprint::speed_factor);
+        let mut vel = vel.0;
+        for axis in 0..V::AXES {
+            if pos[axis] < 0.0 || pos[axis] > extent[axis] {
+                vel[axis] *= -1.0;
+                pos[axis] = pos[axis].clamp(0.0, extent[axis]);
+            }
+        }
+        // Walls inside the world: island edges, the petri dish rim
+        let (xy, vxy) = habitat.bounce(old.xy(), pos.xy(), vel.xy());
+        let (mut pos, mut vel) = (pos.with_xy(xy), vel.with_xy(vxy));
+        // Painted walls block like habitat walls. Only entering is blocked, so
+        // anything caught inside freshly painted wall can still walk out.
+        let terrain = &world.terrain;
+        if terrain.is_wall(pos.xy()) && !terrain.is_wall(old.xy()) {
+            let (xy, vxy) = reflect(old.xy(), pos.xy(), vel.xy(), |p| !terrain.is_wall(p));
+            (pos, vel) = (pos.with_xy(xy), vel.with_xy(vxy));
+        }
+        let safe = |p: V| terrain.at(p.xy()) == TerrainType::SafeZone;
+        if predator.is_some() && safe(pos) && !safe(old) {
+            pos = old;
+            vel = -vel;
+        }
+        let respiration = respiration.map_or(1.0, |r| r.cost_factor(world.oxygen.at(old.xy())));
+        let aging = if predator.is_none() { 1.0 + params.division_damage.max(0.0) * lineage.offspring as f32 } else { 1.0 };
+        let upkeep = plasmids.map_or(0.0, |p| p.gene_count() as f32 * params.plasmid_cost.max(0.0));
+        let energy_cost = metabolism.0 * speed_mult * respiration * aging * imprint.map_or(1.0, Imprint::cost_factor) + upkeep;
+        events.push(Event::Moved { id, pos, vel, energy_cost, waste: energy_cost * params.waste_per_energy });
+    }
+    events
+}
+
+// Every migration interval, carry a few organisms to a random other region
+pub fn migration<V: Space>(world: &World<V>, _: &SimulationParams, _: &Removals) -> Vec<Event<V>> {
+    let habitat = &world.habitat;
+    let regions = habitat.regions.len();
+    if regions < 2 || habitat.migration_interval == 0 || world.tick == 0 || !world.tick.is_multiple_of(habitat.migration_interval) {
+        return Vec::new();
+    }
+    let mut rng = world.rng();
+    let mut events = Vec::new();
+    for (id, pos) in world.ecs.query::<(Entity, &Position<V>)>().with::<&Body>().iter() {
+        if rng.gen_range(0.0f32..1.0) >= habitat.migration_rate {
+            continue;
+        }
+        let from = habitat.region_at(pos.0.xy()).unwrap_or(0);
+        let to = (from + rng.gen_range(1..regions)) % regions;
+        events.push(Event::Migrated { id, pos: pos.0.with_xy(habitat.regions[to].shape.random_pos(&mut *rng)) });
+    }
+    events
+}
+
+// Bacteria eat the food they touch. All contacts are gathered first and then
+// each contested item is resolved by the configured policy, so the outcome never
+// depends on iteration order. With a handling time, a bacterium eats one item at
+// a time and none while it's still digesting the last.
+pub fn feeding<V: Space>(world: &World<V>, params: &SimulationParams, removals: &Removals) -> Vec<Event<V>> {
+    let food: Vec<(Entity, V)> = world.ecs.query::<(Entity, &Position<V>)>().with::<&Food>().iter()
+        .filter(|(id, _)| !removals.contains(*id))
+        .map(|(id, pos)| (id, pos.0))
+        .collect();
+
+    let food_positions = Points::new(&food.iter().map(|(_, pos)| *pos).collect::<Vec<_>>());
+    let mut contacts: Vec<Vec<(Entity, f32)>> = vec![Vec::new(); food.len()];
+    for (id, pos, body, digesting) in world.ecs.query::<(Entity, &Position<V>, &Body, Option<&Digesting>)>().with::<&Genome>().iter() {
+        if digesting.is_some_and(Digesting::busy) {
+            continue;
+        }
+        food_positions.each_within(pos.0, body.size + FOOD_REACH, |i, d| contacts[i].push((id, d)));
+    }
+
+    // What a food item gives, split or not
+    let energy = params.food_energy * params.food_efficiency;
+    let handling = params.bacteria_handling_time.max(0.0).round() as u32;
+    let mut fed = HashSet::new();
+    let mut events = Vec::new();
+    for ((food_id, _), mut eaters) in food.iter().zip(contacts) {
+        if handling > 0 {
+            eaters.retain(|(eater, _)| !fed.contains(eater));
+        }
+        if eaters.is_empty() {
+            continue;
+        }
+        match params.food_contention {
+            FoodContention::ClosestWins => {
+                // Ties go to the lower handle so the result is deterministic
+                let (eater, _) = eaters.iter()
+                    .min_by(|a, b| a.1.total_cmp(&b.1).then(a.0.cmp(&b.0)))
+                    .copied()
+                    .unwrap();
+                events.push(Event::Ate { eater, meal: Meal::Food(*food_id), energy });
+                fed.insert(eater);
+            }
+            FoodContention::Split => {
+                let share = energy / eaters.len() as f32;
+                for (eater, _) in eaters {
+                    events.push(Event::Ate { eater, meal: Meal::Food(*food_id), energy: share });
+                    fed.insert(eater);
+                }
+            }
+        }
+    }
+    if handling > 0 {
+        let mut fed: Vec<Entity> = fed.into_iter().collect();
+        fed.sort();
+        events.extend(fed.into_iter().map(|id| Event::Digesting { id, ticks: handling }));
+    }
+    events
+}
+
+// Most an armoured bacterium's armour gene can cut an attack's chance of
+// success by, and what a failed attack costs the attacker
+const ARMOR_PROTECTION: f32 = 0.8;
+const FAILED_ATTACK_COST: f32 = 10.0;
+// Damage a fully toxic bacterium does to whatever eats it; one doing at least
+// `AVERSION_DAMAGE` teaches the eater to shun its colour for `AVERSION_TICKS`
+const TOXIN_DAMAGE: f32 = 60.0;
+const AVERSION_DAMAGE: f32 = 10.0;
+const AVERSION_TICKS: u64 = 500;
+
+// Something edible
+#[derive(Clone)]
+struct Edible<V> {
+    id: Entity,
+    pos: V,
+    reach: f32,
+    // Energy on offer
+    energy: f32,
+    armor: f32,
+    toxin: f32,
+    // A bacterium's, for consumers that have learned to avoid some
+    color: Option<Color>,
+}
+
+// Consumers eat everything in their diet they touch: food left over from the
+// bacteria, bacteria and each other, gaining their diet's share of its energy.
+// Each item goes to the first consumer to reach it, and a consumer eaten
+// earlier in the tick eats nothing more. An armoured bacterium may fend an
+// attack off, costing the attacker energy. A consumer with a handling time
+// stops at its first meal and then eats nothing until it has digested it.
+pub fn predation<V: Space>(world: &World<V>, params: &SimulationParams, removals: &Removals) -> Vec<Event<V>> {
+    let web = FoodWeb::new(params);
+    let mut rng = world.rng();
+    // Everything edible, by kind
+    let mut edible: Vec<Vec<Edible<V>>> = vec![Vec::new(); Prey::count(params.species.len())];
+    let wanted: Vec<Prey> = (0..=params.species.len()).flat_map(|i| web.diet(i.checked_sub(1)).iter().map(|(kind, _)| *kind)).collect();
+    if wanted.contains(&Prey::Food) {
+        edible[Prey::Food.index()] = world.ecs.query::<(Entity, &Position<V>)>().with::<&Food>().iter()
+            .filter(|(id, _)| !removals.contains(*id))
+            .map(|(id, pos)| Edible { id, pos: pos.0, reach: FOOD_REACH, energy: params.food_energy, armor: 0.0, toxin: 0.0, color: None })
+            .collect();
+    }
+    for (id, pos, body, energy, genome, species) in world.ecs.query::<(Entity, &Position<V>, &Body, &Energy, Option<&Genome>, Option<&Species>)>().iter() {
+        let kind = if genome.is_some() { Prey::Bacteria } else { Prey::consumer(species.map(|s| s.0)) };
+        if wanted.contains(&kind) && !removals.contains(id) && world.terrain.at(pos.0.xy()) != TerrainType::SafeZone
+            && let Some(list) = edible.get_mut(kind.index())
+        {
+            let dna = genome.map(|g| &g.0);
+            list.push(Edible {
+                id,
+                pos: pos.0,
+                reach: body.size,
+                energy: energy.0.max(0.0),
+                armor: dna.map_or(0.0, |d| d.gene(ARMOR)),
+                toxin: dna.map_or(0.0, |d| d.gene(TOXIN)),
+                color: dna.map(|d| d.color()),
+            });
+        }
+    }
+
+    let mut events = Vec::new();
+    let mut eaten = HashSet::new();
+    for (id, pos, body, species, aversion, digesting) in world.ecs.query::<(Entity, &Position<V>, &Body, Option<&Species>, Option<&Aversion>, Option<&Digesting>)>().with::<&PredatorTag>().iter() {
+        if removals.contains(id) || eaten.contains(&id) || digesting.is_some_and(Digesting::busy) {
+            continue;
+        }
+        let species = species.map(|s| s.0);
+        let handling = web.handling_time(species);
+        'meal: for &(kind, efficiency) in web.diet(species) {
+            for prey in &edible[kind.index()] {
+                if prey.id == id || pos.0.distance(prey.pos) >= body.size + prey.reach || eaten.contains(&prey.id) {
+                    continue;
+                }
+                // Prey that looks like something that poisoned it is left alone
+                if let (Some(aversion), Some(color)) = (aversion, prey.color)
+                    && aversion.avoids(color, world.tick)
+                {
+                    continue;
+                }
+                if prey.armor > 0.0 && rng.gen_range(0.0f32..1.0) < prey.armor * ARMOR_PROTECTION {
+                    events.push(Event::Damaged { id, amount: FAILED_ATTACK_COST });
+                    continue;
+                }
+                eaten.insert(prey.id);
+                if kind == Prey::Food {
+                    events.push(Event::Ate { eater: id, meal: Meal::Food(prey.id), energy: prey.energy * efficiency });
+                } else {
+                    events.push(Event::Ate { eater: id, meal: Meal::Prey(prey.id), energy: prey.energy * efficiency });
+                    events.push(Event::Died { id: prey.id, cause: DeathCause::Eaten });
+                }
+                let damage = prey.toxin * TOXIN_DAMAGE;
+                if damage > 0.0 {
+                    events.push(Event::Damaged { id, amount: damage });
+                }
+                if let Some(color) = prey.color
+                    && damage >= AVERSION_DAMAGE
+                {
+                    events.push(Event::Learned { id, color, until: world.tick + AVERSION_TICKS });
+                }
+                if handling > 0 {
+                    events.push(Event::Digesting { id, ticks: handling });
+                    break 'meal;
+                }
+            }
+        }
+    }
+    events
+}
+
+// Parent's and offspring's energy after dividing `energy`, giving the offspring
+// `share` of what's left after the cost
+pub fn divide(energy: f32, cost: f32, share: f32) -> (f32, f32) {
+    let left = (energy - cost).max(0.0);
+    (left * (1.0 - share), left * share)
+}
+
+// Farthest a bacterium with full dispersal throws its offspring, and the
+// energy the offspring spends per unit of distance getting there
+const MAX_DISPERSAL: f32 = 60.0;
+const DISPERSAL_COST: f32 = 0.1;
+
+// Where offspring land: somewhere within the parent's dispersal radius, or on
+// the parent itself if that spot is off its island, outside the world or in a
+// wall
+fn disperse<V: Space>(world: &World<V>, pos: V, dispersal: f32, rng: &mut impl Rng) -> V {
+    if dispersal <= 0.0 {
+        return pos;
+    }
+    let spot = pos + V::random_within(MAX_DISPERSAL * dispersal, rng);
+    let extent = world.extent();
+    let inside = (0..V::AXES).all(|axis| (0.0..=extent[axis]).contains(&spot[axis]));
+    let habitat = &world.habitat;
+    if inside && habitat.region_at(spot.xy()) == habitat.region_at(pos.xy()) && !world.terrain.is_wall(spot.xy()) {
+        spot
+    } else {
+        pos
+    }
+}
+
+// Age after which a bacterium stops growing, energy a unit of size costs, and
+// energy it keeps back rather than spend on growing
+const GROWTH_PERIOD: f32 = 300.0;
+const GROWTH_COST: f32 = 5.0;
+const GROWTH_RESERVE: f32 = 30.0;
+
+// Young bacteria smaller than their genes say grow towards that size, as
+// long as they have energy to spare. One that goes hungry while young stays
+// small for life.
+pub fn growth<V: Space>(world: &World<V>, params: &SimulationParams, removals: &Removals) -> Vec<Event<V>> {
+    world.ecs.query::<(Entity, &Body, &Genome, &Expression, &Energy, &Age)>().iter()
+        .map(|(id, body, genome, expression, energy, age)| (id, body, genome.0.gene(SIZE) * expression.size, energy, age))
+        .filter(|(id, body, full_size, energy, age)| {
+            !removals.contains(*id) && body.size < *full_size && age.0 < GROWTH_PERIOD && energy.0 > GROWTH_RESERVE
+        })
+        .map(|(id, body, full_size, energy, _)| {
+            let step = (full_size - body.size).min(params.growth_rate.max(0.0)).min((energy.0 - GROWTH_RESERVE) / GROWTH_COST);
+            Event::Grew { id, size: body.size + step, cost: step * GROWTH_COST }
+        })
+        .collect()
+}
+
+// Age an organism has to reach before first dividing, and ticks it then waits
+// between divisions: a bacterium's own when they evolve
+pub fn reproduction_timing(params: &SimulationParams, dna: Option<&DNA>) -> (f32, u32) {
+    let (maturation, cooldown) = match dna {
+        Some(dna) if params.evolve_reproduction_timing => (dna.gene(MATURATION), dna.gene(COOLDOWN)),
+        _ => (params.maturation_age, params.reproduction_cooldown),
+    };
+    (maturation, cooldown.max(0.0).round() as u32)
+}
+
+// Organisms past their threshold divide, paying `reproduction_cost` and
+// splitting the rest with their offspring, once they are old enough and
+// rested since the last time. Under Lamarckian inheritance the offspring's
+// genes start partly from what the parent became rather than was born as.
+pub fn reproduction<V: Space>(world: &World<V>, params: &SimulationParams, removals: &Removals) -> Vec<Event<V>> {
+    let mut rng = world.rng();
+    let mut events = Vec::new();
+    let cost = params.reproduction_cost.max(0.0);
+    let share = params.offspring_share.clamp(0.0, 1.0);
+    let lamarckian = params.lamarckian.then_some(params.lamarckian_share.clamp(0.0, 1.0));
+    let loss = params.plasmid_loss_rate.clamp(0.0, 1.0) as f64;
+    for (id, pos, vel, body, genome, expression, energy, age, refractory, imprint, plasmids, lineage) in world.ecs.query::<(Entity, &Position<V>, &Velocity<V>, &Body, &Genome, &Expression, &Energy, &Age, &Refractory, &Imprint, &Plasmids, &Lineage)>().iter() {
+        let (maturation, cooldown) = reproduction_timing(params, Some(&genome.0));
+        if !removals.contains(id) && energy.0 > params.reproduction_threshold && age.0 >= maturation && refractory.0 == 0 {
+            let share = if params.evolve_offspring_share { genome.0.gene(INVESTMENT) } else { share };
+            let (parent_energy, energy) = divide(energy.0, cost, share);
+            let spot = disperse(world, pos.0, genome.0.gene(DISPERSAL), &mut *rng);
+            let dna = match lamarckian {
+                Some(share) => {
+                    let dna = &genome.0;
+                    dna.acquire(dna.gene(SPEED) * expression.speed, body.size, dna.gene(SENSE_RADIUS) * expression.sense_radius, share).mutate(params, &mut *rng)
+                }
+                None => genome.0.mutate(params, &mut *rng),
+            };
+            let birth_size = params.birth_size.clamp(0.0, 1.0);
+            let imprint = if params.epigenetics { imprint.0 * params.epigenetic_inheritance.clamp(0.0, 1.0) } else { 0.0 };
+            let expression = if params.developmental_noise > 0.0 { Expression::random(params.developmental_noise, &mut *rng) } else { Expression::default() };
+            let plasmids = plasmids.0.iter().filter(|_| !rng.gen_bool(loss)).cloned().collect();
+            let offspring = Bacterium {
+                pos: spot,
+                vel: -vel.0,
+                size: (birth_size < 1.0).then_some(dna.gene(SIZE) * expression.size * birth_size),
+                expression,
+                imprint,
+                plasmids,
+                dna,
+                energy: (energy - spot.distance(pos.0) * DISPERSAL_COST).max(0.0),
+                age: 0.0,
+                lineage: Lineage { parent: Some(id), generation: lineage.generation + 1, founder: lineage.founder.or(Some(id)), offspring: 0, origin: lineage.origin },
+            };
+            events.push(Event::Reproduced { parent: id, parent_energy, cooldown, offspring: Organism::Bacterium(offspring) });
+        }
+    }
+    let (maturation, cooldown) = reproduction_timing(params, None);
+    for (id, pos, vel, body, metabolism, energy, age, refractory, lineage, species) in world.ecs.query::<(Entity, &Position<V>, &Velocity<V>, &Body, &Metabolism, &Energy, &Age, &Refractory, &Lineage, Option<&Species>)>().with::<&PredatorTag>().iter() {
+        let threshold = match species {
+            Some(s) => match params.species.get(s.0) {
+                Some(config) => config.reproduction_threshold,
+                None => continue,
+            },
+            None => params.predator_reproduction_threshold,
+        };
+        if !removals.contains(id) && energy.0 > threshold && age.0 >= maturation && refractory.0 == 0 {
+            let (parent_energy, energy) = divide(energy.0, cost, share);
+            let offspring = Predator {
+                pos: pos.0,
+                vel: -vel.0,
+                energy,
+                speed: body.speed,
+                size: body.size,
+                sense_radius: body.sense_radius,
+                metabolism: metabolism.0,
+                species: species.map(|s| s.0),
+                lineage: Lineage { parent: Some(id), generation: lineage.generation + 1, founder: lineage.founder.or(Some(id)), offspring: 0, origin: lineage.origin },
+            };
+            events.push(Event::Reproduced { parent: id, parent_energy, cooldown, offspring: Organism::Predator(offspring) });
+        }
+    }
+    events
+}
+
+// Harm from the surroundings: waste above the toxic threshold hurts whatever
+// sits in it, bacteria suffer outside their pH tolerance and the antibiotic
+// hurts those without resistance. All waste breaks down a little each tick.
+pub fn environment<V: Space>(world: &World<V>, params: &SimulationParams, removals: &Removals) -> Vec<Event<V>> {
+    let mut events: Vec<Event<V>> = world.ecs.query::<(Entity, &Position<V>, Option<&PhTolerance>, Option<&Plasmids>)>().with::<&Energy>().iter()
+        .filter(|(id, ..)| !removals.contains(*id))
+        .filter_map(|(id, pos, ph, plasmids)| {
+            let waste = (world.waste.at(pos.0.xy()) - params.waste_threshold).max(0.0) * params.waste_toxicity;
+            let acidity = ph.map_or(0.0, |ph| ph.excess(world.ph.at(pos.0.xy()))) * params.ph_damage;
+            let antibiotic = plasmids.map_or(0.0, |p| world.antibiotic_at(params, pos.0.xy()) * (1.0 - p.resistance()));
+            let amount = waste + acidity + antibiotic;
+            (amount > 0.0).then_some(Event::Damaged { id, amount })
+        })
+        .collect();
+    if params.waste_decay > 0.0 {
+        events.push(Event::WasteDecayed { factor: 1.0 - params.waste_decay });
+    }
+    events
+}
+
+// What a fight costs each side per unit of its own aggression, and what the
+// loser loses on top
+const FIGHT_COST: f32 = 5.0;
+const FIGHT_DAMAGE: f32 = 20.0;
+// How many times stronger the winner has to be to kill and eat the loser
+const KILL_RATIO: f32 = 2.0;
+
+// Unrelated bacteria that bump into each other may fight, the more likely
+// the more aggressive the keener of the two. Strength is size weighted by
+// aggression and decides the odds; both pay for the effort, and the loser
+// takes damage or, against a much stronger winner, is killed and eaten.
+// Bacteria descended from the same founder never fight, and none fights
+// twice in a tick.
+pub fn fighting<V: Space>(world: &World<V>, params: &SimulationParams, removals: &Removals) -> Vec<Event<V>> {
+    // Handle, position, size, aggression, energy and founder, by x so only
+    // neighbours along it need checking
+    let mut bacteria: Vec<(Entity, V, f32, f32, f32, Entity)> = world.ecs.query::<(Entity, &Position<V>, &Body, &Genome, &Energy, &Lineage)>().iter()
+        .filter(|(id, ..)| !removals.contains(*id))
+        .map(|(id, pos, body, genome, energy, lineage)| (id, pos.0, body.size, genome.0.gene(AGGRESSION), energy.0, lineage.founder.unwrap_or(id)))
+        .collect();
+    bacteria.sort_by(|a, b| a.1[0].total_cmp(&b.1[0]).then(a.0.cmp(&b.0)));
+    let reach = 2.0 * bacteria.iter().map(|b| b.2).fold(0.0, f32::max);
+
+    let mut rng = world.rng();
+    let mut events = Vec::new();
+    let mut fought = HashSet::new();
+    for (i, a) in bacteria.iter().enumerate() {
+        for b in bacteria[i + 1..].iter().take_while(|b| b.1[0] - a.1[0] < reach) {
+            if a.5 == b.5 || fought.contains(&a.0) || fought.contains(&b.0) || a.1.distance(b.1) >= a.2 + b.2 {
+                continue;
+            }
+            if !rng.gen_bool(a.3.max(b.3).clamp(0.0, 1.0) as f64) {
+                continue;
+            }
+            fought.insert(a.0);
+            fought.insert(b.0);
+            let (strength_a, strength_b) = (a.2 * (0.5 + a.3), b.2 * (0.5 + b.3));
+            let a_wins = rng.gen_range(0.0..strength_a + strength_b) < strength_a;
+            let (winner, loser, ratio) = if a_wins { (a, b, strength_a / strength_b) } else { (b, a, strength_b / strength_a) };
+            events.push(Event::Damaged { id: winner.0, amount: FIGHT_COST * winner.3 });
+            if ratio >= KILL_RATIO {
+                events.push(Event::Ate { eater: winner.0, meal: Meal::Prey(loser.0), energy: loser.4.max(0.0) * params.predation_efficiency });
+                events.push(Event::Died { id: loser.0, cause: DeathCause::Killed });
+            } else {
+                events.push(Event::Damaged { id: loser.0, amount: FIGHT_COST * loser.3 + FIGHT_DAMAGE });
+            }
+        }
+    }
+    events
+}
+
+// Bacteria touching a plasmid carrier pick up a copy of each of its plasmids
+// they lack with `conjugation_rate` per tick. Copies go by what was carried
+// at the start of the tick, so a plasmid spreads one contact at a time.
+pub fn conjugation<V: Space>(world: &World<V>, params: &SimulationParams, removals: &Removals) -> Vec<Event<V>> {
+    if params.conjugation_rate <= 0.0 {
+        return Vec::new();
+    }
+    // Handle, position, size and plasmids, by x so only neighbours along it
+    // need checking
+    let mut bacteria: Vec<(Entity, V, f32, Vec<Plasmid>)> = world.ecs.query::<(Entity, &Position<V>, &Body, &Plasmids)>().iter()
+        .filter(|(id, ..)| !removals.contains(*id))
+        .map(|(id, pos, body, plasmids)| (id, pos.0, body.size, plasmids.0.clone()))
+        .collect();
+    if bacteria.iter().all(|b| b.3.is_empty()) {
+        return Vec::new();
+    }
+    bacteria.sort_by(|a, b| a.1[0].total_cmp(&b.1[0]).then(a.0.cmp(&b.0)));
+    let reach = 2.0 * bacteria.iter().map(|b| b.2).fold(0.0, f32::max);
+
+    let mut rng = world.rng();
+    let rate = params.conjugation_rate.min(1.0) as f64;
+    let mut events = Vec::new();
+    for (i, a) in bacteria.iter().enumerate() {
+        for b in bacteria[i + 1..].iter().take_while(|b| b.1[0] - a.1[0] < reach) {
+            if a.1.distance(b.1) >= a.2 + b.2 {
+                continue;
+            }
+            for (donor, recipient) in [(a, b), (b, a)] {
+                for plasmid in donor.3.iter().filter(|p| !recipient.3.contains(p)) {
+                    if rng.gen_bool(rate) {
+                        events.push(Event::Conjugated { id: recipient.0, plasmid: plasmid.clone() });
+                    }
+                }
+            }
+        }
+    }
+    events
+}
+
+// Energy below which a bacterium counts as starving, and how much each tick of
+// starving or sitting in toxic waste deepens its epigenetic mark
+const STRESS_ENERGY: f32 = 30.0;
+const IMPRINT_RATE: f32 = 0.01;
+
+// With epigenetics on, bacteria going through hard times (hunger, or waste
+// above the toxic threshold) are marked by it
+pub fn epigenetics<V: Space>(world: &World<V>, params: &SimulationParams, removals: &Removals) -> Vec<Event<V>> {
+    if !params.epigenetics {
+        return Vec::new();
+    }
+    world.ecs.query::<(Entity, &Position<V>, &Energy, &Imprint)>().iter()
+        .filter(|(id, pos, energy, imprint)| {
+            !removals.contains(*id) && imprint.0 < 1.0 && (energy.0 < STRESS_ENERGY || world.waste.at(pos.0.xy()) > params.waste_threshold)
+        })
+        .map(|(id, _, _, imprint)| Event::Imprinted { id, imprint: (imprint.0 + IMPRINT_RATE).min(1.0) })
+        .collect()
+}
+
+// Remove dead bacteria and predators
+pub fn starvation<V: Space>(world: &World<V>, _: &SimulationParams, removals: &Removals) -> Vec<Event<V>> {
+    world.ecs.query::<(Entity, &Energy, &Age)>().iter()
+        .filter(|(id, energy, _)| energy.0 <= 0.0 && !removals.contains(*id))
+        .map(|(id, _, _)| Event::Died { id, cause: DeathCause::Starved })
+        .collect()
+}
+
+// Bacteria brought back when they die out
+const REPOPULATE_COUNT: usize = 10;
+
+// Fail safe if extinction
+pub fn repopulate<V: Space>(world: &World<V>, params: &SimulationParams, removals: &Removals) -> Vec<Event<V>> {
+    let alive = world.ecs.query::<Entity>().with::<&Genome>().iter()
+        .any(|id| !removals.contains(id));
+    if alive {
+        return Vec::new();
+    }
+    info!(target: SIM, tick = world.tick, "Bacteria died out, respawning {}", REPOPULATE_COUNT);
+    world.spawn_positions(REPOPULATE_COUNT).into_iter()
+        .map(|pos| {
+            let mut rng = world.rng();
+            let mut bacterium = Bacterium::new(pos, params.initial_energy, &mut *rng);
+            bacterium.dna.markers = random_markers(params, &mut *rng);
+            Event::Spawned { organism: Organism::Bacterium(bacterium) }
+        })
+        .collect()
+}
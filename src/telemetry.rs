@@ -0,0 +1,155 @@
+use serde::Serialize;
+use std::net::{TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::mpsc::{sync_channel, Receiver, SyncSender, TrySendError};
+use std::sync::{Arc, Mutex};
+use tracing::warn;
+use tungstenite::Message;
+
+use crate::cadence::Cadence;
+use crate::components::{Body, Energy, Genome, Position, PredatorTag};
+use crate::stats::averages;
+use crate::world::World;
+
+// Frames a client may fall behind by before newer ones are dropped for it
+const CLIENT_BACKLOG: usize = 16;
+
+// One JSON frame sent to every connected client
+#[derive(Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Frame {
+    Stats {
+        tick: u64,
+        bacteria: usize,
+        predators: usize,
+        food: usize,
+        avg_speed: f32,
+        avg_size: f32,
+    },
+    Snapshot {
+        tick: u64,
+        organisms: Vec<OrganismFrame>,
+    },
+}
+
+#[derive(Serialize)]
+pub struct OrganismFrame {
+    pub id: u64,
+    pub kind: &'static str,
+    pub x: f32,
+    pub y: f32,
+    pub energy: f32,
+    pub speed: f32,
+    pub size: f32,
+    pub sense_radius: f32,
+}
+
+impl Frame {
+    pub fn stats(world: &World) -> Self {
+        let avg = averages(&world.ecs);
+        Frame::Stats {
+            tick: world.tick,
+            bacteria: world.bacteria_count(),
+            predators: world.predator_count(),
+            food: world.food_count(),
+            avg_speed: avg.speed,
+            avg_size: avg.size,
+        }
+    }
+
+    // Every bacterium and predator with its position, energy and body
+    pub fn snapshot(world: &World) -> Self {
+        let mut organisms: Vec<OrganismFrame> = world.ecs
+            .query::<(hecs::Entity, &Position, &Energy, &Body, Option<&Genome>, Option<&PredatorTag>)>()
+            .iter()
+            .filter(|(_, _, _, _, genome, predator)| genome.is_some() || predator.is_some())
+            .map(|(id, pos, energy, body, _, predator)| OrganismFrame {
+                id: id.to_bits().get(),
+                kind: if predator.is_some() { "predator" } else { "bacterium" },
+                x: pos.0.x,
+                y: pos.0.y,
+                energy: energy.0,
+                speed: body.speed,
+                size: body.size,
+                sense_radius: body.sense_radius,
+            })
+            .collect();
+        organisms.sort_by_key(|o| o.id);
+        Frame::Snapshot { tick: world.tick, organisms }
+    }
+}
+
+// Streams frames to any number of WebSocket clients. Accepting and writing
+// happen on background threads so a slow or stuck client never stalls the
+// simulation; it just misses frames.
+pub struct TelemetryServer {
+    clients: Arc<Mutex<Vec<SyncSender<Arc<str>>>>>,
+    // How often stats and snapshots are published; every 0 ticks turns that
+    // frame off
+    pub stats: Cadence,
+    pub snapshots: Cadence,
+}
+
+impl TelemetryServer {
+    pub fn bind(addr: impl ToSocketAddrs) -> std::io::Result<Self> {
+        let listener = TcpListener::bind(addr)?;
+        let clients = Arc::new(Mutex::new(Vec::new()));
+        let accepted = Arc::clone(&clients);
+        std::thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                let (tx, rx) = sync_channel(CLIENT_BACKLOG);
+                std::thread::spawn(move || serve_client(stream, rx));
+                accepted.lock().unwrap().push(tx);
+            }
+        });
+        Ok(Self { clients, stats: Cadence::new(1), snapshots: Cadence::new(0) })
+    }
+
+    pub fn client_count(&self) -> usize {
+        self.clients.lock().unwrap().len()
+    }
+
+    // Send whatever frames came due since the last call. They're kept track
+    // of without clients too, so one connecting isn't sent any early.
+    pub fn publish(&mut self, world: &World) {
+        let (stats, snapshot) = (self.stats.due(world.tick), self.snapshots.due(world.tick));
+        if self.client_count() == 0 {
+            return;
+        }
+        if stats {
+            self.broadcast(&Frame::stats(world));
+        }
+        if snapshot {
+            self.broadcast(&Frame::snapshot(world));
+        }
+    }
+
+    pub fn broadcast(&self, frame: &Frame) {
+        let text: Arc<str> = match serde_json::to_string(frame) {
+            Ok(text) => text.into(),
+            Err(e) => {
+                warn!("Couldn't encode telemetry frame: {}", e);
+                return;
+            }
+        };
+        // Drop clients whose writer has hung up; skip ones that are behind
+        self.clients.lock().unwrap().retain(|tx| match tx.try_send(Arc::clone(&text)) {
+            Ok(()) | Err(TrySendError::Full(_)) => true,
+            Err(TrySendError::Disconnected(_)) => false,
+        });
+    }
+}
+
+fn serve_client(stream: TcpStream, frames: Receiver<Arc<str>>) {
+    let mut socket = match tungstenite::accept(stream) {
+        Ok(socket) => socket,
+        Err(e) => {
+            warn!("Telemetry handshake failed: {}", e);
+            return;
+        }
+    };
+    for text in frames {
+        if socket.send(Message::text(text.as_ref())).is_err() {
+            return;
+        }
+    }
+}
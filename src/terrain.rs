@@ -0,0 +1,228 @@
+use macroquad::prelude::*;
+use ::rand::Rng;
+
+// Side of a terrain cell in world units
+pub const TERRAIN_CELL: f32 = 10.0;
+// Movement speed factor inside a swamp
+const SWAMP_SPEED: f32 = 0.4;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TerrainType {
+    Normal,
+    // Food grows twice as often
+    Fertile,
+    // No food grows
+    Barren,
+    // Everything moves slower
+    Swamp,
+    // Predators won't enter or hunt here
+    SafeZone,
+    // Impassable; nothing grows
+    Wall,
+    // Where starting and respawned organisms appear, when any is painted
+    Spawn,
+}
+
+impl TerrainType {
+    pub const ALL: [TerrainType; 7] = [
+        TerrainType::Normal,
+        TerrainType::Fertile,
+        TerrainType::Barren,
+        TerrainType::Swamp,
+        TerrainType::SafeZone,
+        TerrainType::Wall,
+        TerrainType::Spawn,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            TerrainType::Normal => "Normal",
+            TerrainType::Fertile => "Fertile",
+            TerrainType::Barren => "Barren",
+            TerrainType::Swamp => "Swamp",
+            TerrainType::SafeZone => "Safe zone",
+            TerrainType::Wall => "Wall",
+            TerrainType::Spawn => "Spawn",
+        }
+    }
+
+    pub fn color(self) -> Color {
+        match self {
+            TerrainType::Normal => Color::new(0.0, 0.0, 0.0, 0.0),
+            TerrainType::Fertile => Color::new(0.2, 0.7, 0.2, 0.18),
+            TerrainType::Barren => Color::new(0.6, 0.5, 0.35, 0.18),
+            TerrainType::Swamp => Color::new(0.3, 0.35, 0.6, 0.22),
+            TerrainType::SafeZone => Color::new(0.9, 0.9, 0.9, 0.12),
+            TerrainType::Wall => Color::new(0.35, 0.38, 0.42, 0.9),
+            TerrainType::Spawn => Color::new(0.3, 0.6, 1.0, 0.15),
+        }
+    }
+
+    pub fn speed_factor(self) -> f32 {
+        match self {
+            TerrainType::Swamp => SWAMP_SPEED,
+            _ => 1.0,
+        }
+    }
+
+    // One character per cell in the text format
+    fn symbol(self) -> char {
+        match self {
+            TerrainType::Normal => '.',
+            TerrainType::Fertile => 'F',
+            TerrainType::Barren => 'B',
+            TerrainType::Swamp => 'S',
+            TerrainType::SafeZone => 'Z',
+            TerrainType::Wall => '#',
+            TerrainType::Spawn => '*',
+        }
+    }
+
+    fn from_symbol(c: char) -> Option<Self> {
+        TerrainType::ALL.into_iter().find(|t| t.symbol() == c)
+    }
+
+    // Mask colours: black walls, green fertile ground, blue spawn areas, red safe
+    // zones, brown barren ground and anything else (white, transparent) normal
+    fn from_mask_color(c: Color) -> Self {
+        if c.a < 0.5 {
+            return TerrainType::Normal;
+        }
+        let max = c.r.max(c.g).max(c.b);
+        let min = c.r.min(c.g).min(c.b);
+        if max < 0.25 {
+            TerrainType::Wall
+        } else if max - min < 0.2 {
+            TerrainType::Normal
+        } else if c.g == max {
+            TerrainType::Fertile
+        } else if c.b == max {
+            TerrainType::Spawn
+        } else if c.g > 0.5 * c.r {
+            TerrainType::Barren
+        } else {
+            TerrainType::SafeZone
+        }
+    }
+}
+
+// Terrain painted over the world in square cells. Anything outside the grid is
+// normal terrain.
+#[derive(Clone, Debug)]
+pub struct Terrain {
+    pub cols: usize,
+    pub rows: usize,
+    cells: Vec<TerrainType>,
+}
+
+impl Terrain {
+    pub fn new(width: f32, height: f32) -> Self {
+        let cols = (width / TERRAIN_CELL).ceil() as usize;
+        let rows = (height / TERRAIN_CELL).ceil() as usize;
+        Self { cols, rows, cells: vec![TerrainType::Normal; cols * rows] }
+    }
+
+    fn cell_of(&self, p: Vec2) -> Option<usize> {
+        if p.x < 0.0 || p.y < 0.0 {
+            return None;
+        }
+        let (cx, cy) = ((p.x / TERRAIN_CELL) as usize, (p.y / TERRAIN_CELL) as usize);
+        (cx < self.cols && cy < self.rows).then(|| cy * self.cols + cx)
+    }
+
+    // Stretch an image over a `width` x `height` world, one pixel sample per cell
+    pub fn from_mask(image: &Image, width: f32, height: f32) -> Self {
+        let mut terrain = Self::new(width, height);
+        for row in 0..terrain.rows {
+            for col in 0..terrain.cols {
+                let center = (vec2(col as f32, row as f32) + 0.5) * TERRAIN_CELL;
+                let x = ((center.x / width * image.width as f32) as u32).min(image.width as u32 - 1);
+                let y = ((center.y / height * image.height as f32) as u32).min(image.height as u32 - 1);
+                terrain.cells[row * terrain.cols + col] = TerrainType::from_mask_color(image.get_pixel(x, y));
+            }
+        }
+        terrain
+    }
+
+    pub fn at(&self, p: Vec2) -> TerrainType {
+        self.cell_of(p).map_or(TerrainType::Normal, |i| self.cells[i])
+    }
+
+    pub fn get(&self, col: usize, row: usize) -> TerrainType {
+        self.cells[row * self.cols + col]
+    }
+
+    // Paint every cell whose centre lies within `radius` of `center`
+    pub fn paint(&mut self, center: Vec2, radius: f32, kind: TerrainType) {
+        let min = ((center - radius) / TERRAIN_CELL).floor().max(vec2(0.0, 0.0));
+        let max = ((center + radius) / TERRAIN_CELL).ceil();
+        for row in min.y as usize..(max.y as usize).min(self.rows) {
+            for col in min.x as usize..(max.x as usize).min(self.cols) {
+                let cell_center = (vec2(col as f32, row as f32) + 0.5) * TERRAIN_CELL;
+                if cell_center.distance(center) <= radius {
+                    self.cells[row * self.cols + col] = kind;
+                }
+            }
+        }
+    }
+
+    pub fn is_uniform(&self) -> bool {
+        self.cells.iter().all(|&c| c == TerrainType::Normal)
+    }
+
+    pub fn is_wall(&self, p: Vec2) -> bool {
+        self.at(p) == TerrainType::Wall
+    }
+
+    // Indices of the painted spawn cells
+    pub fn spawn_cells(&self) -> Vec<usize> {
+        (0..self.cells.len()).filter(|&i| self.cells[i] == TerrainType::Spawn).collect()
+    }
+
+    // Random point in one of `cells`
+    pub fn random_pos_in_cells(&self, cells: &[usize], rng: &mut impl Rng) -> Vec2 {
+        let i = cells[rng.gen_range(0..cells.len())];
+        let corner = vec2((i % self.cols) as f32, (i / self.cols) as f32) * TERRAIN_CELL;
+        corner + vec2(rng.gen_range(0.0..TERRAIN_CELL), rng.gen_range(0.0..TERRAIN_CELL))
+    }
+
+    // Random point in the same cell as `p`
+    pub fn random_pos_in_cell(&self, p: Vec2, rng: &mut impl Rng) -> Vec2 {
+        let corner = (p / TERRAIN_CELL).floor() * TERRAIN_CELL;
+        corner + vec2(rng.gen_range(0.0..TERRAIN_CELL), rng.gen_range(0.0..TERRAIN_CELL))
+    }
+
+    // Text form: a `cols rows` header, then one line of cell symbols per row
+    // (`.` normal, `F` fertile, `B` barren, `S` swamp, `Z` safe zone, `#` wall,
+    // `*` spawn)
+    pub fn to_text(&self) -> String {
+        let mut text = format!("{} {}\n", self.cols, self.rows);
+        for row in self.cells.chunks(self.cols) {
+            text.extend(row.iter().map(|c| c.symbol()));
+            text.push('\n');
+        }
+        text
+    }
+
+    pub fn from_text(text: &str) -> Result<Self, String> {
+        let mut lines = text.lines();
+        let header = lines.next().ok_or("empty terrain file")?;
+        let (cols, rows) = header.split_once(' ').ok_or("bad terrain header")?;
+        let cols: usize = cols.trim().parse().map_err(|_| "bad terrain width")?;
+        let rows: usize = rows.trim().parse().map_err(|_| "bad terrain height")?;
+
+        let mut cells = Vec::with_capacity(cols * rows);
+        for (row, line) in lines.take(rows).enumerate() {
+            if line.chars().count() != cols {
+                return Err(format!("terrain row {} should have {} cells", row, cols));
+            }
+            for c in line.chars() {
+                cells.push(TerrainType::from_symbol(c).ok_or_else(|| format!("unknown terrain '{}'", c))?);
+            }
+        }
+        if cells.len() != cols * rows {
+            return Err(format!("expected {} terrain rows", rows));
+        }
+        Ok(Self { cols, rows, cells })
+    }
+}
@@ -0,0 +1,76 @@
+// Weight of each new frame in the smoothed tick and frame-work times
+const SMOOTHING: f32 = 0.1;
+// Fewest ticks per frame the throttle goes down to: one every 20 frames
+const MIN_RATE: f32 = 0.05;
+// Seconds over which the realized ticks per second are measured
+const RATE_WINDOW: f32 = 1.0;
+
+// How many ticks the window runs per frame: up to `max_rate`, or with a frame
+// budget as many as fit beside the rest of the frame's work, judged from how
+// long ticks and the rest have been taking. Below one tick per frame the
+// fraction carries over, so the simulation slows down instead of the frame
+// rate collapsing.
+pub struct TickThrottle {
+    // Seconds per frame, none to always run `max_rate`
+    budget: Option<f32>,
+    max_rate: f32,
+    // Ticks per frame currently allowed
+    pub rate: f32,
+    owed: f32,
+    // Smoothed seconds per tick, and per frame on everything else but
+    // waiting for the display
+    tick_time: f32,
+    other_time: f32,
+    // Ticks run and seconds passed since the realized rate was measured
+    window: (u32, f32),
+    pub ticks_per_second: f32,
+}
+
+impl TickThrottle {
+    pub fn new(max_rate: u32, budget_ms: Option<f32>) -> Self {
+        let max_rate = max_rate.max(1) as f32;
+        Self {
+            budget: budget_ms.map(|ms| ms / 1000.0),
+            max_rate,
+            rate: max_rate,
+            owed: 0.0,
+            tick_time: 0.0,
+            other_time: 0.0,
+            window: (0, 0.0),
+            ticks_per_second: 0.0,
+        }
+    }
+
+    // Ticks to run this frame
+    pub fn ticks(&mut self) -> u32 {
+        self.owed = (self.owed + self.rate).min(self.max_rate);
+        let ticks = self.owed.floor();
+        self.owed -= ticks;
+        ticks as u32
+    }
+
+    // After the frame's work: `ticks` ran in `tick_time` seconds out of
+    // `work_time` spent on the frame, and `frame_time` passed since the last
+    // one
+    pub fn update(&mut self, ticks: u32, tick_time: f32, work_time: f32, frame_time: f32) {
+        if ticks > 0 {
+            self.tick_time += (tick_time / ticks as f32 - self.tick_time) * SMOOTHING;
+        }
+        self.other_time += ((work_time - tick_time).max(0.0) - self.other_time) * SMOOTHING;
+        if let Some(budget) = self.budget {
+            let fit = (budget - self.other_time).max(0.0) / self.tick_time.max(1e-6);
+            self.rate = fit.clamp(MIN_RATE, self.max_rate);
+        }
+
+        self.window = (self.window.0 + ticks, self.window.1 + frame_time);
+        if self.window.1 >= RATE_WINDOW {
+            self.ticks_per_second = self.window.0 as f32 / self.window.1;
+            self.window = (0, 0.0);
+        }
+    }
+
+    // Running fewer ticks than asked for to stay within the budget
+    pub fn throttled(&self) -> bool {
+        self.rate < self.max_rate
+    }
+}
@@ -0,0 +1,83 @@
+use crate::components::{Genome, Lineage};
+use crate::params::SimulationParams;
+use crate::population::{spawn_seeds, Seed};
+use crate::world::World;
+
+// How often a trial checks whether it's already decided
+const DECIDED_CHECK_EVERY: u64 = 100;
+
+// A genome set competing in the tournament, from a population file
+pub struct Entrant {
+    pub name: String,
+    pub seeds: Vec<Seed>,
+}
+
+// Living descendants of each entrant when a trial ended
+pub struct Trial {
+    pub seed: u64,
+    pub ticks: u64,
+    pub survivors: Vec<usize>,
+}
+
+impl Trial {
+    // The entrant with the most descendants, if it's a single one
+    pub fn winner(&self) -> Option<usize> {
+        let best = *self.survivors.iter().max()?;
+        let mut leaders = self.survivors.iter().enumerate().filter(|(_, n)| **n == best);
+        match (leaders.next(), leaders.next()) {
+            (Some((i, _)), None) if best > 0 => Some(i),
+            _ => None,
+        }
+    }
+}
+
+// Living bacteria per entrant; respawned bacteria descend from none
+fn survivors(world: &World, entrants: usize) -> Vec<usize> {
+    let mut counts = vec![0; entrants];
+    for lineage in world.ecs.query::<&Lineage>().with::<&Genome>().iter() {
+        if let Some(origin) = lineage.origin {
+            counts[origin] += 1;
+        }
+    }
+    counts
+}
+
+// One trial: every entrant's bacteria in a fresh world from `new_world`,
+// run for `ticks` or until at most one entrant has descendants left
+pub fn run_trial(entrants: &[Entrant], params: &SimulationParams, ticks: u64, seed: u64, new_world: impl Fn(u64) -> World) -> Trial {
+    let mut world = new_world(seed);
+    world.remove_bacteria();
+    for (i, entrant) in entrants.iter().enumerate() {
+        spawn_seeds(&mut world, &entrant.seeds, params.initial_energy, Some(i));
+    }
+    while world.tick < ticks {
+        world.step(params);
+        if world.tick.is_multiple_of(DECIDED_CHECK_EVERY) && survivors(&world, entrants.len()).iter().filter(|n| **n > 0).count() <= 1 {
+            break;
+        }
+    }
+    Trial { seed, ticks: world.tick, survivors: survivors(&world, entrants.len()) }
+}
+
+// Wins, mean survivors, mean share of the entrants' bacteria and extinctions
+// per entrant, as a text table
+pub fn summary(entrants: &[Entrant], trials: &[Trial]) -> String {
+    let width = entrants.iter().map(|e| e.name.len()).max().unwrap_or(0).max("entrant".len());
+    let mut table = format!("{:<width$}  {:>5}  {:>9}  {:>6}  {:>7}\n", "entrant", "wins", "survivors", "share", "extinct");
+    let count = trials.len().max(1) as f32;
+    for (i, entrant) in entrants.iter().enumerate() {
+        let wins = trials.iter().filter(|t| t.winner() == Some(i)).count();
+        let survivors = trials.iter().map(|t| t.survivors[i]).sum::<usize>() as f32 / count;
+        let share = trials.iter()
+            .map(|t| {
+                let total: usize = t.survivors.iter().sum();
+                if total > 0 { t.survivors[i] as f32 / total as f32 } else { 0.0 }
+            })
+            .sum::<f32>() / count;
+        let extinct = trials.iter().filter(|t| t.survivors[i] == 0).count();
+        table += &format!("{:<width$}  {:>5}  {:>9.1}  {:>5.1}%  {:>7}\n", entrant.name, wins, survivors, share * 100.0, extinct);
+    }
+    let draws = trials.iter().filter(|t| t.winner().is_none()).count();
+    table += &format!("{} trials, {} drawn", trials.len(), draws);
+    table
+}
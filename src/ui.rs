@@ -0,0 +1,1965 @@
+use macroquad::prelude::*;
+use std::cell::RefCell;
+use tracing::{info, warn};
+
+use crate::achievements::{Achievement, Achievements};
+use crate::chart_export::{format_value, nice_step};
+use crate::coloring::{ColorMode, Coloring, Palette};
+use crate::console::Console;
+use crate::components::{Age, Body, Energy, Genome, Imprint, Lineage, Plasmids, PredatorTag};
+use crate::dna::{AEROBIC, AGGRESSION, ARMOR, BURST, COOLDOWN, CROWDING, DISPERSAL, DNA, FLEE_ENERGY, INVESTMENT, MATURATION, MUTATION_RATE, PH_OPTIMUM, PH_TOLERANCE, SENSE_RADIUS, SIZE, SPEED, TOXIN, VIGILANCE};
+use crate::fitness::{FitnessLandscape, LANDSCAPE_BINS};
+use crate::muller::{MullerPlot, MULLER_EVERY};
+use crate::habitat::Region;
+use crate::hall_of_fame::{HallOfFame, Record};
+use crate::logging::UI;
+use crate::keymap::{key_name, Action, Keymap};
+use crate::phylogeny::{Ancestor, Phylogeny};
+use crate::params::{param_info, SimulationParams, FOOD_CONTENTION_HELP};
+use crate::presets;
+use crate::render::DebugOverlays;
+use crate::scenario::{Challenge, Status, BANNER_SECONDS};
+use crate::stats::{allele_color, Marker, Metric, Spread, Stats, ALLELE_NAMES, CORRELATED_GENES, SELECTION_EVERY};
+use crate::stats_csv::Baseline;
+use crate::terrain::TerrainType;
+use crate::world::World;
+use crate::{GRAPH_HEIGHT, MAX_HISTORY, UI_PANEL_WIDTH};
+
+// Mouse and fixed-key controls, listed after the bindable shortcuts
+const CONTROLS_HELP: &[(&str, &str)] = &[
+    ("CLICK", "Inspect"),
+    ("WHEEL", "Zoom, right-drag/WASD pan"),
+    ("1-7", "Terrain brush (editor open)"),
+];
+
+// Screen geometry, recomputed from the window size every frame so the layout
+// follows resizes and fullscreen
+#[derive(Clone, Copy, Debug)]
+pub struct Layout {
+    pub screen: Vec2,
+    // Right-hand control panel (zero width when hidden)
+    pub panel: Rect,
+    // Strip of graphs along the bottom
+    pub graphs: Rect,
+    // Area the world is shown in
+    pub sim: Rect,
+}
+
+impl Layout {
+    pub fn compute(show_ui: bool) -> Self {
+        let (sw, sh) = (screen_width(), screen_height());
+        let panel_width = if show_ui { UI_PANEL_WIDTH.min(sw) } else { 0.0 };
+        let panel_left = sw - panel_width;
+        let sim_height = (sh - GRAPH_HEIGHT).max(0.0);
+        Self {
+            screen: vec2(sw, sh),
+            panel: Rect::new(panel_left, 0.0, panel_width, sh),
+            graphs: Rect::new(10.0, sh - GRAPH_HEIGHT - 10.0, (panel_left - 20.0).max(0.0), GRAPH_HEIGHT),
+            sim: Rect::new(0.0, 0.0, panel_left, sim_height),
+        }
+    }
+}
+
+pub struct UIState {
+    pub show_ui: bool,
+    pub paused: bool,
+    pub fullscreen: bool,
+    // Organism shown in the inspector, kept across ticks by its handle
+    pub selected: Option<hecs::Entity>,
+    // Terrain being painted while the terrain editor is open
+    pub terrain_brush: Option<TerrainType>,
+    pub show_pollution: bool,
+    pub show_oxygen: bool,
+    pub show_resistance: bool,
+    pub debug: DebugOverlays,
+    // What bacteria are coloured by, and whether the panel's list of modes is open
+    pub color_mode: ColorMode,
+    pub color_menu: bool,
+    // Colour palette, and whether predators are drawn as triangles and
+    // bacteria with a ring as wide as their energy
+    pub palette: Palette,
+    pub shapes: bool,
+    pub sound: SoundSettings,
+    // A clip is being recorded (toggled with R or the REC button)
+    pub recording: bool,
+    // Metrics graphed in the bottom panel, in `Metric::ALL` order
+    pub graphs: Vec<Metric>,
+    // Y scale of each metric's graph, indexed by `Metric::index`
+    pub graph_scales: [GraphScale; Metric::ALL.len()],
+    pub show_graph_settings: bool,
+    // Graphs span the whole run, its older part thinned out, rather than the
+    // latest `MAX_HISTORY` entries (toggled in the graph settings)
+    pub graph_whole_run: bool,
+    // Gene correlation matrix (toggled with K)
+    pub show_correlations: bool,
+    // Realized fitness over two genes, by index into `CORRELATED_GENES`
+    // (toggled with L)
+    pub show_landscape: bool,
+    pub landscape_genes: (usize, usize),
+    // Selection differentials against responses (toggled with E)
+    pub show_selection: bool,
+    // Clade frequencies over time (toggled with U)
+    pub show_muller: bool,
+    // Archived genomes (toggled with H or the panel button)
+    pub show_hall_of_fame: bool,
+    // Unlocked milestones (toggled with J)
+    pub show_achievements: bool,
+    // The inspector's ANCESTRY tab is open rather than its TRAITS one
+    pub inspector_ancestry: bool,
+    // Folded panel sections, indexed by `PanelSection::index`, and how far
+    // the panel is scrolled
+    pub collapsed: [bool; PanelSection::ALL.len()],
+    pub panel_scroll: f32,
+    // Shortcut keys, listed in the panel's controls section
+    pub keymap: Keymap,
+    // Name typed for the next preset, and whether it has the keyboard
+    pub preset_name: String,
+    pub naming_preset: bool,
+    // Saved presets, listed again after a save
+    pub presets: Option<Vec<String>>,
+    // The panel's undo, import population and export tree buttons were clicked
+    pub undo_requested: bool,
+    pub import_requested: bool,
+    pub export_tree_requested: bool,
+}
+
+// Volumes of the sounds played with the `audio` feature, 0 to 1
+#[derive(Clone, Copy, Debug)]
+pub struct SoundSettings {
+    pub muted: bool,
+    pub ambient: f32,
+    pub effects: f32,
+}
+
+impl Default for SoundSettings {
+    fn default() -> Self {
+        Self { muted: false, ambient: 0.3, effects: 0.6 }
+    }
+}
+
+impl SoundSettings {
+    pub fn ambient_volume(&self) -> f32 {
+        if self.muted { 0.0 } else { self.ambient }
+    }
+
+    pub fn effects_volume(&self) -> f32 {
+        if self.muted { 0.0 } else { self.effects }
+    }
+}
+
+impl Default for UIState {
+    fn default() -> Self {
+        Self {
+            show_ui: true,
+            paused: false,
+            fullscreen: false,
+            selected: None,
+            terrain_brush: None,
+            show_pollution: false,
+            show_oxygen: false,
+            show_resistance: false,
+            debug: DebugOverlays::default(),
+            color_mode: ColorMode::default(),
+            color_menu: false,
+            palette: Palette::default(),
+            shapes: false,
+            sound: SoundSettings::default(),
+            recording: false,
+            graphs: Metric::DEFAULT.to_vec(),
+            graph_scales: [GraphScale::Linear; Metric::ALL.len()],
+            show_graph_settings: false,
+            graph_whole_run: false,
+            show_correlations: false,
+            show_landscape: false,
+            landscape_genes: (0, 1),
+            show_selection: false,
+            show_muller: false,
+            show_hall_of_fame: false,
+            show_achievements: false,
+            inspector_ancestry: false,
+            collapsed: [false; PanelSection::ALL.len()],
+            panel_scroll: 0.0,
+            keymap: Keymap::default(),
+            preset_name: String::new(),
+            naming_preset: false,
+            presets: None,
+            undo_requested: false,
+            import_requested: false,
+            export_tree_requested: false,
+        }
+    }
+}
+
+// How a graph maps values to heights, cycled by the toggle in its corner
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum GraphScale {
+    #[default]
+    Linear,
+    // Logarithmic, so a population boom doesn't flatten everything else
+    Log,
+    // Stretched between the lowest and highest value shown
+    Normalized,
+}
+
+impl GraphScale {
+    pub fn label(self) -> &'static str {
+        match self {
+            GraphScale::Linear => "lin",
+            GraphScale::Log => "log",
+            GraphScale::Normalized => "0-1",
+        }
+    }
+
+    pub fn next(self) -> Self {
+        match self {
+            GraphScale::Linear => GraphScale::Log,
+            GraphScale::Log => GraphScale::Normalized,
+            GraphScale::Normalized => GraphScale::Linear,
+        }
+    }
+}
+
+// Value range of a graph under its scale
+#[derive(Clone, Copy, Debug)]
+struct Axis {
+    scale: GraphScale,
+    lo: f32,
+    hi: f32,
+}
+
+impl Axis {
+    // `max` fixes the top of linear and log graphs; otherwise it follows the
+    // data, as does the whole range of a normalized graph
+    fn new(scale: GraphScale, values: impl Iterator<Item = f32> + Clone, max: Option<f32>) -> Self {
+        let data_max = values.clone().fold(f32::NEG_INFINITY, f32::max);
+        match scale {
+            GraphScale::Normalized => {
+                let lo = values.fold(f32::INFINITY, f32::min);
+                let hi = if data_max - lo > 1e-6 { data_max } else { lo + 1.0 };
+                Self { scale, lo, hi }
+            }
+            _ => Self { scale, lo: 0.0, hi: max.unwrap_or(data_max.max(1.0)) },
+        }
+    }
+
+    fn fraction(&self, value: f32) -> f32 {
+        let f = match self.scale {
+            GraphScale::Log => value.max(0.0).ln_1p() / self.hi.ln_1p(),
+            _ => (value - self.lo) / (self.hi - self.lo),
+        };
+        f.clamp(0.0, 1.0)
+    }
+
+    fn value_at(&self, fraction: f32) -> f32 {
+        match self.scale {
+            GraphScale::Log => (fraction * self.hi.ln_1p()).exp_m1(),
+            _ => self.lo + fraction * (self.hi - self.lo),
+        }
+    }
+
+    // Screen y of `value` on a graph spanning `y..y + height`
+    fn y(&self, value: f32, y: f32, height: f32) -> f32 {
+        y + height - self.fraction(value) * height
+    }
+}
+
+// Small button in a graph's top-right corner cycling its scale
+fn draw_scale_toggle(x: f32, y: f32, width: f32, scale: &mut GraphScale) {
+    let rect = Rect::new(x + width - 36.0, y + 5.0, 30.0, 17.0);
+    let hovered = rect.contains(Vec2::from(mouse_position()));
+    let fill = if hovered { Color::new(0.3, 0.3, 0.38, 0.95) } else { Color::new(0.18, 0.18, 0.22, 0.9) };
+    draw_rectangle(rect.x, rect.y, rect.w, rect.h, fill);
+    draw_rectangle_lines(rect.x, rect.y, rect.w, rect.h, 1.0, Color::new(0.5, 0.5, 0.55, 1.0));
+    let dims = measure_text(scale.label(), None, 13, 1.0);
+    draw_text(scale.label(), rect.center().x - dims.width / 2.0, rect.y + 13.0, 13.0, WHITE);
+    if hovered && is_mouse_button_pressed(MouseButton::Left) {
+        *scale = scale.next();
+    }
+}
+
+// UI Helper Functions
+pub fn draw_section_header(x: f32, y: f32, width: f32, text: &str, color: Color) -> f32 {
+    draw_text(text, x, y, 20.0, color);
+    draw_line(x, y + 5.0, x + width, y + 5.0, 2.0, color);
+    y + 25.0
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn draw_slider(x: f32, y: f32, width: f32, label: &str, value: &mut f32, min: f32, max: f32, unit: &str) {
+    let height = 22.0;
+    let (mouse_x, mouse_y) = mouse_position();
+    let mouse_down = is_mouse_button_down(MouseButton::Left);
+
+    // Draw label with unit
+    draw_text(label, x, y - 5.0, 16.0, WHITE);
+
+    // Draw track
+    draw_rectangle(x, y, width, height, Color::new(0.15, 0.15, 0.15, 0.9));
+    draw_rectangle_lines(x, y, width, height, 1.0, Color::new(0.4, 0.4, 0.4, 0.8));
+
+    // Calculate slider position
+    let norm = ((*value - min) / (max - min)).clamp(0.0, 1.0);
+    let handle_x = x + norm * width;
+
+    // Draw fill with gradient effect
+    draw_rectangle(x, y, norm * width, height, Color::new(0.2, 0.5, 0.9, 0.9));
+
+    // Draw handle
+    draw_rectangle(handle_x - 6.0, y - 2.0, 12.0, height + 4.0, Color::new(0.9, 0.95, 1.0, 1.0));
+    draw_rectangle_lines(handle_x - 6.0, y - 2.0, 12.0, height + 4.0, 1.0, Color::new(0.3, 0.3, 0.3, 1.0));
+
+    // Draw value text with unit, or the typed text while editing it
+    let entry = Rect::new(x + width + 6.0, y, 60.0, height);
+    if !draw_slider_entry(entry, label, value, min, max, unit) {
+        let value_text = if unit == "%" {
+            format!("{:.0}{}", *value * 100.0, unit)
+        } else if unit == "x" {
+            format!("{:.1}{}", value, unit)
+        } else {
+            format!("{:.0} {}", value, unit)
+        };
+        draw_text(&value_text, entry.x + 4.0, y + 16.0, 16.0, Color::new(0.8, 1.0, 0.8, 1.0));
+    }
+
+    // Check for interaction
+    if mouse_down && mouse_x >= x && mouse_x <= x + width && mouse_y >= y - 5.0 && mouse_y <= y + height + 5.0 {
+        let new_norm = ((mouse_x - x) / width).clamp(0.0, 1.0);
+        *value = min + new_norm * (max - min);
+    }
+}
+
+// Value being typed into a slider's entry box, found by the slider's label
+struct SliderEdit {
+    label: String,
+    text: String,
+    // The last Enter didn't parse to a number
+    invalid: bool,
+}
+
+thread_local! {
+    static SLIDER_EDIT: RefCell<Option<SliderEdit>> = const { RefCell::new(None) };
+    // Text of the tooltip to draw this frame, if the mouse is over something
+    // that has one
+    static TOOLTIP: RefCell<Option<&'static str>> = const { RefCell::new(None) };
+}
+
+// Offer `text` as the tooltip while the mouse is over `rect`
+pub fn tooltip(rect: Rect, text: &'static str) {
+    if rect.contains(Vec2::from(mouse_position())) {
+        TOOLTIP.with(|tip| *tip.borrow_mut() = Some(text));
+    }
+}
+
+// Draw this frame's tooltip, if any, word-wrapped beside the mouse. Call
+// after everything it should cover.
+pub fn draw_tooltip() {
+    let Some(text) = TOOLTIP.with(|tip| tip.borrow_mut().take()) else { return };
+    let max_width = 260.0;
+    let mut lines: Vec<String> = Vec::new();
+    for word in text.split_whitespace() {
+        match lines.last_mut() {
+            Some(line) if measure_text(format!("{} {}", line, word), None, 14, 1.0).width <= max_width => {
+                line.push(' ');
+                line.push_str(word);
+            }
+            _ => lines.push(word.to_string()),
+        }
+    }
+    let width = lines.iter().map(|l| measure_text(l, None, 14, 1.0).width).fold(0.0, f32::max) + 16.0;
+    let height = lines.len() as f32 * 17.0 + 10.0;
+    let (mouse_x, mouse_y) = mouse_position();
+    // Left of the mouse when there's no room on the right
+    let x = if mouse_x + 16.0 + width > screen_width() { mouse_x - 8.0 - width } else { mouse_x + 16.0 };
+    let y = (mouse_y + 16.0).min(screen_height() - height);
+    draw_rectangle(x, y, width, height, Color::new(0.05, 0.06, 0.09, 0.97));
+    draw_rectangle_lines(x, y, width, height, 1.0, Color::new(0.4, 0.6, 0.8, 0.9));
+    for (i, line) in lines.iter().enumerate() {
+        draw_text(line, x + 8.0, y + 19.0 + i as f32 * 17.0, 14.0, WHITE);
+    }
+}
+
+// Slider for a parameter from `PARAM_INFO`, with its description as tooltip
+fn draw_param_slider(x: f32, y: f32, width: f32, params: &mut SimulationParams, key: &str) {
+    let Some(info) = param_info(key) else { return };
+    draw_slider(x, y, width, info.label, (info.value)(params), info.min, info.max, info.unit);
+    tooltip(Rect::new(x, y - 20.0, width, 42.0), info.description);
+}
+
+// Whether a slider's value is being typed, so key shortcuts should wait
+pub fn slider_entry_active() -> bool {
+    SLIDER_EDIT.with(|edit| edit.borrow().is_some())
+}
+
+// Click the value next to a slider to type an exact one (in the units shown,
+// so percentages as percent). Enter or clicking away applies it, clamped to
+// the slider's range; ESC cancels. Returns whether the box is being edited.
+fn draw_slider_entry(rect: Rect, label: &str, value: &mut f32, min: f32, max: f32, unit: &str) -> bool {
+    let factor = if unit == "%" { 100.0 } else { 1.0 };
+    let clicked = is_mouse_button_pressed(MouseButton::Left);
+    let over = rect.contains(Vec2::from(mouse_position()));
+    SLIDER_EDIT.with(|cell| {
+        let mut slot = cell.borrow_mut();
+        match slot.as_mut() {
+            Some(edit) if edit.label == label => {
+                while let Some(c) = get_char_pressed() {
+                    if c.is_ascii_digit() || c == '.' || c == '-' {
+                        edit.text.push(c);
+                        edit.invalid = false;
+                    }
+                }
+                if is_key_pressed(KeyCode::Backspace) {
+                    edit.text.pop();
+                }
+                let enter = is_key_pressed(KeyCode::Enter) || is_key_pressed(KeyCode::KpEnter);
+                if is_key_pressed(KeyCode::Escape) {
+                    *slot = None;
+                    return false;
+                }
+                if enter || (clicked && !over) {
+                    match edit.text.parse::<f32>() {
+                        Ok(typed) if typed.is_finite() => {
+                            *value = (typed / factor).clamp(min, max);
+                            *slot = None;
+                            return false;
+                        }
+                        _ if enter => edit.invalid = true,
+                        _ => {
+                            *slot = None;
+                            return false;
+                        }
+                    }
+                }
+                let border = if edit.invalid { Color::new(1.0, 0.3, 0.3, 1.0) } else { Color::new(0.4, 0.7, 1.0, 1.0) };
+                draw_rectangle(rect.x, rect.y, rect.w, rect.h, Color::new(0.05, 0.05, 0.08, 1.0));
+                draw_rectangle_lines(rect.x, rect.y, rect.w, rect.h, 1.0, border);
+                let cursor = if (get_time() * 2.0) as i64 % 2 == 0 { "|" } else { "" };
+                draw_text(format!("{}{}", edit.text, cursor), rect.x + 4.0, rect.y + 16.0, 16.0, WHITE);
+                true
+            }
+            _ => {
+                if clicked && over {
+                    // Drop keys typed before the box was opened
+                    while get_char_pressed().is_some() {}
+                    let text = format!("{:.3}", *value * factor);
+                    let text = text.trim_end_matches('0').trim_end_matches('.').to_string();
+                    *slot = Some(SliderEdit { label: label.to_string(), text, invalid: false });
+                }
+                false
+            }
+        }
+    })
+}
+
+pub fn draw_button(x: f32, y: f32, width: f32, height: f32, label: &str, color: Color) -> bool {
+    let (mouse_x, mouse_y) = mouse_position();
+    let mouse_clicked = is_mouse_button_pressed(MouseButton::Left);
+
+    let hovered = mouse_x >= x && mouse_x <= x + width && mouse_y >= y && mouse_y <= y + height;
+
+    let btn_color = if hovered {
+        Color::new((color.r * 1.3).min(1.0), (color.g * 1.3).min(1.0), (color.b * 1.3).min(1.0), color.a)
+    } else {
+        color
+    };
+
+    draw_rectangle(x, y, width, height, btn_color);
+    draw_rectangle_lines(x, y, width, height, 2.0, WHITE);
+
+    // Center text
+    let text_size = 20.0;
+    let text_dims = measure_text(label, None, text_size as u16, 1.0);
+    draw_text(label, x + (width - text_dims.width) / 2.0, y + (height + text_size) / 2.0 - 2.0, text_size, WHITE);
+
+    hovered && mouse_clicked
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn draw_line_graph(x: f32, y: f32, width: f32, height: f32, data: &[f32], band: Option<&[Spread]>, ghost: Option<&[f32]>, ticks: &[u64], color: Color, label: &str, max_val: Option<f32>, scale: &mut GraphScale) {
+    // Background with slight gradient
+    draw_rectangle(x, y, width, height, Color::new(0.08, 0.08, 0.1, 0.85));
+    draw_rectangle_lines(x, y, width, height, 2.0, Color::new(0.3, 0.3, 0.3, 0.6));
+
+    if data.is_empty() {
+        draw_scale_toggle(x, y, width, scale);
+        return;
+    }
+
+    let band = band.unwrap_or_default();
+    let band_values = band.iter().flat_map(|s| [s.p10, s.p90]);
+    let axis = Axis::new(*scale, data.iter().chain(ghost.unwrap_or_default()).copied().chain(band_values), max_val);
+    draw_graph_axes(x, y, width, height, &axis, data.len(), ticks);
+
+    // 10th to 90th percentile shaded around the mean, with the median
+    let step = graph_step(width, data.len());
+    let band_color = Color::new(color.r, color.g, color.b, 0.15);
+    for (i, pair) in band.windows(2).enumerate() {
+        let (x1, x2) = (x + (i as f32) * step, x + ((i + 1) as f32) * step);
+        let (lo1, hi1) = (vec2(x1, axis.y(pair[0].p10, y, height)), vec2(x1, axis.y(pair[0].p90, y, height)));
+        let (lo2, hi2) = (vec2(x2, axis.y(pair[1].p10, y, height)), vec2(x2, axis.y(pair[1].p90, y, height)));
+        draw_triangle(lo1, hi1, hi2, band_color);
+        draw_triangle(lo1, hi2, lo2, band_color);
+        draw_line(x1, axis.y(pair[0].median, y, height), x2, axis.y(pair[1].median, y, height), 1.0, Color::new(color.r, color.g, color.b, 0.5));
+    }
+
+    // Baseline run behind the live line, entry for entry
+    let ghost_color = Color::new(0.8, 0.8, 0.85, 0.35);
+    if let Some(ghost) = ghost {
+        for (i, pair) in ghost.windows(2).enumerate() {
+            let x1 = x + (i as f32) * step;
+            draw_line(x1, axis.y(pair[0], y, height), x1 + step, axis.y(pair[1], y, height), 1.5, ghost_color);
+        }
+    }
+
+    // Draw data points with glow effect
+    for i in 0..data.len().saturating_sub(1) {
+        let x1 = x + (i as f32) * step;
+        let y1 = axis.y(data[i], y, height);
+        let x2 = x + ((i + 1) as f32) * step;
+        let y2 = axis.y(data[i + 1], y, height);
+
+        // Glow effect
+        draw_line(x1, y1, x2, y2, 4.0, Color::new(color.r, color.g, color.b, 0.3));
+        draw_line(x1, y1, x2, y2, 2.0, color);
+    }
+
+    // Draw label and current value with background
+    draw_rectangle(x + 3.0, y + 3.0, 150.0, 42.0, Color::new(0.0, 0.0, 0.0, 0.6));
+    draw_text(label, x + 8.0, y + 20.0, 18.0, WHITE);
+    if let Some(last) = data.last() {
+        let value_text = format_value(*last, (axis.hi - axis.lo) / 100.0);
+        draw_text(&value_text, x + 8.0, y + 38.0, 22.0, color);
+    }
+
+    // Draw max value
+    let max_text = format!("max: {:.0}", axis.hi);
+    draw_text(&max_text, x + width - 110.0, y + 18.0, 15.0, Color::new(0.7, 0.7, 0.7, 1.0));
+    draw_scale_toggle(x, y, width, scale);
+
+    let mut lines = vec![(label, data, color)];
+    if let Some(ghost) = ghost {
+        lines.push(("baseline", ghost, ghost_color));
+    }
+    draw_graph_hover(x, y, width, height, &axis, ticks, &lines);
+}
+
+// Horizontal space per entry: a graph fills up over `MAX_HISTORY` entries,
+// and squeezes in more when showing the whole run
+fn graph_step(width: f32, len: usize) -> f32 {
+    width / len.max(MAX_HISTORY) as f32
+}
+
+// The entries a graph shows: the latest `span`, lined up with the end
+fn latest<T>(history: &[T], span: usize) -> &[T] {
+    &history[history.len().saturating_sub(span)..]
+}
+
+// Gridlines with their values down the right edge, and tick labels along the
+// bottom for the last `len` entries of `ticks`
+fn draw_graph_axes(x: f32, y: f32, width: f32, height: f32, axis: &Axis, len: usize, ticks: &[u64]) {
+    let label_color = Color::new(0.5, 0.5, 0.55, 1.0);
+    for i in 0..=4 {
+        let grid_y = y + (i as f32 / 4.0) * height;
+        draw_line(x, grid_y, x + width, grid_y, 1.0, Color::new(0.2, 0.2, 0.2, 0.4));
+        // The top line already has the max and the bottom one the tick labels
+        if (1..4).contains(&i) {
+            let value = axis.value_at(1.0 - i as f32 / 4.0);
+            let text = format_value(value, (axis.hi - axis.lo) / 40.0);
+            let dims = measure_text(&text, None, 12, 1.0);
+            draw_text(&text, x + width - dims.width - 4.0, grid_y - 3.0, 12.0, label_color);
+        }
+    }
+
+    let ticks = &ticks[ticks.len().saturating_sub(len)..];
+    let (Some(&first), Some(&last)) = (ticks.first(), ticks.last()) else { return };
+    let tick_step = (nice_step((last - first).max(1) as f32, 4) as u64).max(1);
+    let step = graph_step(width, len);
+    let mut label = first.div_ceil(tick_step) * tick_step;
+    while label <= last {
+        // Entries aren't evenly spaced in ticks (the clock stops while paused),
+        // so each label goes where its tick was first recorded
+        if let Some(i) = ticks.iter().position(|&t| t >= label) {
+            let tick_x = x + i as f32 * step;
+            draw_line(tick_x, y + height - 4.0, tick_x, y + height, 1.0, label_color);
+            let text = label.to_string();
+            let dims = measure_text(&text, None, 12, 1.0);
+            let text_x = (tick_x - dims.width / 2.0).clamp(x + 2.0, x + width - dims.width - 2.0);
+            draw_text(&text, text_x, y + height - 6.0, 12.0, label_color);
+        }
+        label += tick_step;
+    }
+}
+
+// Crosshair under the mouse with the tick and each line's value there
+fn draw_graph_hover(x: f32, y: f32, width: f32, height: f32, axis: &Axis, ticks: &[u64], lines: &[(&str, &[f32], Color)]) {
+    let mouse = Vec2::from(mouse_position());
+    let len = lines.iter().map(|(_, data, _)| data.len()).max().unwrap_or(0);
+    if !Rect::new(x, y, width, height).contains(mouse) || len == 0 {
+        return;
+    }
+    let step = graph_step(width, len);
+    let i = (((mouse.x - x) / step).round() as usize).min(len - 1);
+    let line_x = x + i as f32 * step;
+    draw_line(line_x, y, line_x, y + height, 1.0, Color::new(1.0, 1.0, 1.0, 0.5));
+
+    let mut rows = Vec::new();
+    if let Some(tick) = ticks.len().checked_sub(len).and_then(|offset| ticks.get(offset + i)) {
+        rows.push((format!("tick {}", tick), WHITE));
+    }
+    for (label, data, color) in lines {
+        // Lines shorter than the longest end at the same, latest entry
+        let Some(&value) = (i + data.len()).checked_sub(len).and_then(|j| data.get(j)) else { continue };
+        draw_circle(line_x, axis.y(value, y, height), 3.5, *color);
+        rows.push((format!("{}: {}", label, format_value(value, (axis.hi - axis.lo) / 400.0)), *color));
+    }
+
+    let box_width = rows.iter().map(|(text, _)| measure_text(text, None, 14, 1.0).width).fold(0.0, f32::max) + 12.0;
+    let box_height = rows.len() as f32 * 16.0 + 6.0;
+    // Keep the readout on the side of the crosshair with room for it
+    let box_x = if line_x + 8.0 + box_width > x + width { line_x - 8.0 - box_width } else { line_x + 8.0 };
+    let box_y = (mouse.y - box_height / 2.0).clamp(y, y + height - box_height);
+    draw_rectangle(box_x, box_y, box_width, box_height, Color::new(0.0, 0.0, 0.0, 0.8));
+    for (row, (text, color)) in rows.iter().enumerate() {
+        draw_text(text, box_x + 6.0, box_y + 16.0 + row as f32 * 16.0, 14.0, *color);
+    }
+}
+
+// Collapsible sections of the UI panel, top to bottom
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PanelSection {
+    Populations,
+    Display,
+    Simulation,
+    Evolution,
+    Energy,
+    Predators,
+    Sound,
+    Presets,
+    Controls,
+}
+
+impl PanelSection {
+    pub const ALL: [PanelSection; 9] = [
+        PanelSection::Populations,
+        PanelSection::Display,
+        PanelSection::Simulation,
+        PanelSection::Evolution,
+        PanelSection::Energy,
+        PanelSection::Predators,
+        PanelSection::Sound,
+        PanelSection::Presets,
+        PanelSection::Controls,
+    ];
+
+    fn title(self) -> &'static str {
+        match self {
+            PanelSection::Populations => "📊 POPULATIONS",
+            PanelSection::Display => "🎨 DISPLAY",
+            PanelSection::Simulation => "⚙️ SIMULATION",
+            PanelSection::Evolution => "🧬 EVOLUTION",
+            PanelSection::Energy => "⚡ ENERGY",
+            PanelSection::Predators => "🦖 PREDATORS",
+            PanelSection::Sound => "🔊 SOUND",
+            PanelSection::Presets => "💾 PRESETS",
+            PanelSection::Controls => "⌨ CONTROLS",
+        }
+    }
+
+    fn color(self) -> Color {
+        match self {
+            PanelSection::Populations => Color::new(0.3, 0.9, 0.3, 1.0),
+            PanelSection::Display => Color::new(0.9, 0.8, 0.4, 1.0),
+            PanelSection::Simulation => Color::new(0.5, 0.8, 1.0, 1.0),
+            PanelSection::Evolution => Color::new(0.9, 0.5, 0.9, 1.0),
+            PanelSection::Energy => Color::new(1.0, 0.9, 0.3, 1.0),
+            PanelSection::Predators => Color::new(1.0, 0.4, 0.3, 1.0),
+            PanelSection::Sound => Color::new(0.6, 0.6, 1.0, 1.0),
+            PanelSection::Presets => Color::new(0.4, 0.8, 0.7, 1.0),
+            PanelSection::Controls => Color::new(0.7, 0.7, 0.7, 1.0),
+        }
+    }
+
+    fn help(self) -> &'static str {
+        match self {
+            PanelSection::Populations => "Living bacteria, their effective population size, food items and predators right now. Click a header to fold its section.",
+            PanelSection::Display => "How the world is drawn: what bacteria are coloured by, the colour palette, and shapes that don't rely on colour.",
+            PanelSection::Simulation => "How much food grows, how fast everything runs, and how contested food is shared.",
+            PanelSection::Evolution => "How offspring genes differ from their parent's: how often a gene mutates and by how much. Also the plasmids that carry genes between bacteria, the antibiotic that makes resistance pay and the neutral markers that only drift.",
+            PanelSection::Energy => "The energy budget of bacteria: what they start with and how much they must gather to divide.",
+            PanelSection::Predators => "When predators, which hunt bacteria, can divide.",
+            PanelSection::Sound => "A quiet ambient drone, plus blips for eating, a thud for each kill, a chime for births and a sting when bacteria or predators die out.",
+            PanelSection::Presets => "Save the current settings under a name, load saved ones, and undo setting changes one at a time.",
+            PanelSection::Controls => "Keyboard and mouse shortcuts. The keyed ones can be remapped in a --config file.",
+        }
+    }
+
+    pub fn index(self) -> usize {
+        PanelSection::ALL.iter().position(|&s| s == self).unwrap()
+    }
+}
+
+// Scrolled position in the panel. Items are drawn whole or not at all, so
+// nothing spills over the title or the controls help, and hidden sliders
+// can't be dragged.
+struct PanelRows {
+    y: f32,
+    top: f32,
+    bottom: f32,
+}
+
+impl PanelRows {
+    // Whether an item reaching `above` over and `below` under the current
+    // line fits in view
+    fn shows(&self, above: f32, below: f32) -> bool {
+        self.y - above >= self.top && self.y + below <= self.bottom
+    }
+}
+
+const IMPORT_HELP: &str = "Replaces the bacteria with the ones listed in the population file (--population, or population.json), each with the genes and position it gives.";
+const EXPORT_TREE_HELP: &str = "Writes the ancestry of up to --tree-sample survivors into trees/, as Newick (.nwk, for iTOL and the like) and GraphViz (.dot). Branch lengths are ticks between births.";
+const PALETTE_HELP: &str = "Colourblind safe swaps in Okabe-Ito colours and a blue-to-yellow scale, spreads DNA colours along that scale by hue, and draws predators white.";
+const SHAPES_HELP: &str = "Draws predators as triangles pointing where they're heading, and rings bacteria with a border that widens as their energy nears the reproduction threshold.";
+
+// Pixels scrolled per mouse wheel notch
+const PANEL_SCROLL_STEP: f32 = 40.0;
+
+// Box the preset name is typed into: click it to type, ENTER saves, ESC or a
+// click elsewhere stops
+fn draw_preset_name(x: f32, y: f32, width: f32, ui_state: &mut UIState) {
+    let rect = Rect::new(x, y, width, 26.0);
+    if is_mouse_button_pressed(MouseButton::Left) {
+        let focus = rect.contains(Vec2::from(mouse_position()));
+        if focus && !ui_state.naming_preset {
+            // Drop keys typed before the box was focused
+            while get_char_pressed().is_some() {}
+        }
+        ui_state.naming_preset = focus;
+    }
+    if ui_state.naming_preset {
+        while let Some(c) = get_char_pressed() {
+            if (c.is_alphanumeric() || c == ' ' || c == '-' || c == '_') && ui_state.preset_name.len() < 32 {
+                ui_state.preset_name.push(c);
+            }
+        }
+        if is_key_pressed(KeyCode::Backspace) {
+            ui_state.preset_name.pop();
+        }
+        if is_key_pressed(KeyCode::Escape) {
+            ui_state.naming_preset = false;
+        }
+    }
+    let border = if ui_state.naming_preset { Color::new(0.4, 0.7, 1.0, 1.0) } else { Color::new(0.4, 0.4, 0.4, 0.8) };
+    draw_rectangle(rect.x, rect.y, rect.w, rect.h, Color::new(0.05, 0.05, 0.08, 1.0));
+    draw_rectangle_lines(rect.x, rect.y, rect.w, rect.h, 1.0, border);
+    let (text, color) = match (ui_state.preset_name.is_empty(), ui_state.naming_preset) {
+        (true, false) => ("preset name".to_string(), GRAY),
+        (_, true) if (get_time() * 2.0) as i64 % 2 == 0 => (format!("{}|", ui_state.preset_name), WHITE),
+        _ => (ui_state.preset_name.clone(), WHITE),
+    };
+    draw_text(&text, rect.x + 6.0, rect.y + 18.0, 16.0, color);
+}
+
+// Section header that folds the section away when clicked
+fn draw_section_toggle(x: f32, y: f32, width: f32, section: PanelSection, open: bool) -> bool {
+    let text = format!("{} {}", if open { "-" } else { "+" }, section.title());
+    draw_section_header(x, y, width, &text, section.color());
+    let rect = Rect::new(x, y - 20.0, width, 26.0);
+    tooltip(rect, section.help());
+    rect.contains(Vec2::from(mouse_position())) && is_mouse_button_pressed(MouseButton::Left)
+}
+
+// `effective_size` is the latest estimate of Ne, if any
+pub fn draw_ui_panel(params: &mut SimulationParams, ui_state: &mut UIState, layout: &Layout, bacteria_count: usize, effective_size: Option<f32>, food_count: usize, predator_count: usize) {
+    let panel = layout.panel;
+    let panel_x = panel.x;
+
+    // Dark background with border
+    draw_rectangle(panel_x, panel.y, panel.w, panel.h, Color::new(0.02, 0.02, 0.03, 0.95));
+    draw_line(panel_x, 0.0, panel_x, panel.h, 3.0, Color::new(0.3, 0.4, 0.5, 0.8));
+
+    let slider_width = panel.w - 140.0;
+    let x_offset = panel_x + 20.0;
+    let full_width = slider_width + 60.0;
+
+    // Main Title
+    draw_text("SIMULATION", x_offset, 25.0, 28.0, Color::new(0.4, 0.7, 1.0, 1.0));
+
+    // Everything below the title scrolls
+    let view = Rect::new(panel_x, 45.0, panel.w, (panel.bottom() - 10.0 - 45.0).max(0.0));
+    let (_, wheel) = mouse_wheel();
+    if view.contains(Vec2::from(mouse_position())) && wheel != 0.0 {
+        ui_state.panel_scroll -= wheel.signum() * PANEL_SCROLL_STEP;
+        // The slider being typed into might scroll out of view
+        SLIDER_EDIT.with(|edit| *edit.borrow_mut() = None);
+    }
+    ui_state.panel_scroll = ui_state.panel_scroll.max(0.0);
+    let mut rows = PanelRows { y: view.y + 20.0 - ui_state.panel_scroll, top: view.y, bottom: view.bottom() };
+
+    for section in PanelSection::ALL {
+        // Nothing to adjust without an audio backend
+        if section == PanelSection::Sound && !cfg!(feature = "audio") {
+            continue;
+        }
+        // The play and record buttons sit between the settings and the
+        // controls list
+        if section == PanelSection::Controls {
+            let btn_width = (panel.w - 50.0) / 2.0;
+            if rows.shows(0.0, 40.0) {
+                if draw_button(x_offset, rows.y, btn_width, 40.0,
+                                if ui_state.paused { "▶ PLAY" } else { "⏸ PAUSE" },
+                                Color::new(0.2, 0.5, 0.9, 0.95)) {
+                    ui_state.paused = !ui_state.paused;
+                }
+                if draw_button(x_offset + btn_width + 10.0, rows.y, btn_width, 40.0,
+                                if ui_state.recording { "⏹ STOP" } else { "⏺ REC" },
+                                Color::new(0.75, 0.2, 0.25, 0.95)) {
+                    ui_state.recording = !ui_state.recording;
+                }
+            }
+            rows.y += 50.0;
+            if rows.shows(0.0, 30.0) {
+                if draw_button(x_offset, rows.y, btn_width, 30.0, "📈 GRAPHS", Color::new(0.25, 0.35, 0.3, 0.95)) {
+                    ui_state.show_graph_settings = !ui_state.show_graph_settings;
+                }
+                if draw_button(x_offset + btn_width + 10.0, rows.y, btn_width, 30.0, "🏆 FAME", Color::new(0.4, 0.33, 0.15, 0.95)) {
+                    ui_state.show_hall_of_fame = !ui_state.show_hall_of_fame;
+                }
+            }
+            rows.y += 55.0;
+        }
+
+        let open = !ui_state.collapsed[section.index()];
+        if rows.shows(20.0, 6.0) && draw_section_toggle(x_offset, rows.y, full_width, section, open) {
+            ui_state.collapsed[section.index()] = open;
+        }
+        rows.y += 25.0;
+        if !open {
+            rows.y += 10.0;
+            continue;
+        }
+        match section {
+            PanelSection::Populations => {
+                let lines = [
+                    (format!("🦠 Bacteria: {}", bacteria_count), Color::new(0.5, 1.0, 0.5, 1.0)),
+                    (format!("👥 Effective (Ne): {}", effective_size.map_or("-".to_string(), |ne| format!("{:.0}", ne))), Metric::EffectiveSize.color()),
+                    (format!("🍃 Food: {}", food_count), Color::new(0.3, 0.9, 0.5, 1.0)),
+                    (format!("🦖 Predators: {}", predator_count), Color::new(1.0, 0.4, 0.3, 1.0)),
+                ];
+                for (text, color) in lines {
+                    if rows.shows(16.0, 6.0) {
+                        draw_text(&text, x_offset, rows.y, 18.0, color);
+                    }
+                    rows.y += 23.0;
+                }
+                rows.y += 22.0;
+            }
+            PanelSection::Display => {
+                // Drop-down of colour modes, opening in place
+                let arrow = if ui_state.color_menu { "▴" } else { "▾" };
+                let label = format!("🎨 Colour: {} {}", ui_state.color_mode.label(), arrow);
+                if rows.shows(0.0, 26.0) && draw_button(x_offset, rows.y, full_width, 26.0, &label, Color::new(0.25, 0.25, 0.4, 0.95)) {
+                    ui_state.color_menu = !ui_state.color_menu;
+                }
+                rows.y += 30.0;
+                if ui_state.color_menu {
+                    for mode in ColorMode::ALL {
+                        let color = if mode == ui_state.color_mode { Color::new(0.3, 0.3, 0.55, 0.95) } else { Color::new(0.12, 0.12, 0.2, 0.95) };
+                        if rows.shows(0.0, 22.0) && draw_button(x_offset + 20.0, rows.y, full_width - 20.0, 22.0, mode.label(), color) {
+                            ui_state.color_mode = mode;
+                            ui_state.color_menu = false;
+                        }
+                        rows.y += 24.0;
+                    }
+                }
+
+                let palette_label = format!("Palette: {}", ui_state.palette.label());
+                tooltip(Rect::new(x_offset, rows.y, full_width, 26.0), PALETTE_HELP);
+                if rows.shows(0.0, 26.0) && draw_button(x_offset, rows.y, full_width, 26.0, &palette_label, Color::new(0.25, 0.25, 0.4, 0.95)) {
+                    ui_state.palette = ui_state.palette.next();
+                }
+                rows.y += 30.0;
+
+                let shapes_label = format!("Shapes: {}", if ui_state.shapes { "on" } else { "off" });
+                tooltip(Rect::new(x_offset, rows.y, full_width, 26.0), SHAPES_HELP);
+                if rows.shows(0.0, 26.0) && draw_button(x_offset, rows.y, full_width, 26.0, &shapes_label, Color::new(0.25, 0.25, 0.4, 0.95)) {
+                    ui_state.shapes = !ui_state.shapes;
+                }
+                rows.y += 50.0;
+            }
+            PanelSection::Simulation => {
+                if rows.shows(20.0, 24.0) {
+                    draw_param_slider(x_offset, rows.y, slider_width, params, "food_growth_rate");
+                }
+                rows.y += 50.0;
+
+                if rows.shows(20.0, 24.0) {
+                    draw_param_slider(x_offset, rows.y, slider_width, params, "speed_multiplier");
+                }
+                rows.y += 35.0;
+
+                let contention_label = format!("Food contest: {}", params.food_contention.label());
+                tooltip(Rect::new(x_offset, rows.y, full_width, 26.0), FOOD_CONTENTION_HELP);
+                if rows.shows(0.0, 26.0) && draw_button(x_offset, rows.y, full_width, 26.0, &contention_label, Color::new(0.15, 0.3, 0.45, 0.95)) {
+                    params.food_contention = params.food_contention.next();
+                }
+                rows.y += 50.0;
+            }
+            PanelSection::Evolution => {
+                if rows.shows(20.0, 24.0) {
+                    draw_param_slider(x_offset, rows.y, slider_width, params, "mutation_rate");
+                }
+                rows.y += 50.0;
+
+                if rows.shows(20.0, 24.0) {
+                    draw_param_slider(x_offset, rows.y, slider_width, params, "mutation_strength");
+                }
+                rows.y += 50.0;
+
+                if rows.shows(20.0, 24.0) {
+                    draw_param_slider(x_offset, rows.y, slider_width, params, "developmental_noise");
+                }
+                rows.y += 50.0;
+
+                if rows.shows(20.0, 24.0) {
+                    draw_param_slider(x_offset, rows.y, slider_width, params, "epigenetic_inheritance");
+                }
+                rows.y += 50.0;
+
+                if rows.shows(20.0, 24.0) {
+                    draw_param_slider(x_offset, rows.y, slider_width, params, "lamarckian_share");
+                }
+                rows.y += 50.0;
+
+                if rows.shows(20.0, 24.0) {
+                    draw_param_slider(x_offset, rows.y, slider_width, params, "gene_duplication_rate");
+                }
+                rows.y += 50.0;
+
+                if rows.shows(20.0, 24.0) {
+                    draw_param_slider(x_offset, rows.y, slider_width, params, "gene_deletion_rate");
+                }
+                rows.y += 50.0;
+
+                if rows.shows(20.0, 24.0) {
+                    draw_param_slider(x_offset, rows.y, slider_width, params, "antibiotic");
+                }
+                rows.y += 50.0;
+
+                if rows.shows(20.0, 24.0) {
+                    draw_param_slider(x_offset, rows.y, slider_width, params, "antibiotic_gradient");
+                }
+                rows.y += 50.0;
+
+                if rows.shows(20.0, 24.0) {
+                    draw_param_slider(x_offset, rows.y, slider_width, params, "plasmid_cost");
+                }
+                rows.y += 50.0;
+
+                if rows.shows(20.0, 24.0) {
+                    draw_param_slider(x_offset, rows.y, slider_width, params, "conjugation_rate");
+                }
+                rows.y += 50.0;
+
+                if rows.shows(20.0, 24.0) {
+                    draw_param_slider(x_offset, rows.y, slider_width, params, "plasmid_loss_rate");
+                }
+                rows.y += 50.0;
+
+                if rows.shows(20.0, 24.0) {
+                    draw_param_slider(x_offset, rows.y, slider_width, params, "marker_mutation_rate");
+                }
+                rows.y += 55.0;
+            }
+            PanelSection::Energy => {
+                if rows.shows(20.0, 24.0) {
+                    draw_param_slider(x_offset, rows.y, slider_width, params, "initial_energy");
+                }
+                rows.y += 50.0;
+
+                if rows.shows(20.0, 24.0) {
+                    draw_param_slider(x_offset, rows.y, slider_width, params, "reproduction_threshold");
+                }
+                rows.y += 50.0;
+
+                if rows.shows(20.0, 24.0) {
+                    draw_param_slider(x_offset, rows.y, slider_width, params, "reproduction_cost");
+                }
+                rows.y += 50.0;
+
+                if rows.shows(20.0, 24.0) {
+                    draw_param_slider(x_offset, rows.y, slider_width, params, "offspring_share");
+                }
+                rows.y += 50.0;
+
+                if rows.shows(20.0, 24.0) {
+                    draw_param_slider(x_offset, rows.y, slider_width, params, "maturation_age");
+                }
+                rows.y += 50.0;
+
+                if rows.shows(20.0, 24.0) {
+                    draw_param_slider(x_offset, rows.y, slider_width, params, "reproduction_cooldown");
+                }
+                rows.y += 50.0;
+
+                if rows.shows(20.0, 24.0) {
+                    draw_param_slider(x_offset, rows.y, slider_width, params, "division_damage");
+                }
+                rows.y += 50.0;
+
+                if rows.shows(20.0, 24.0) {
+                    draw_param_slider(x_offset, rows.y, slider_width, params, "birth_size");
+                }
+                rows.y += 50.0;
+
+                if rows.shows(20.0, 24.0) {
+                    draw_param_slider(x_offset, rows.y, slider_width, params, "growth_rate");
+                }
+                rows.y += 50.0;
+
+                if rows.shows(20.0, 24.0) {
+                    draw_param_slider(x_offset, rows.y, slider_width, params, "food_energy");
+                }
+                rows.y += 50.0;
+
+                if rows.shows(20.0, 24.0) {
+                    draw_param_slider(x_offset, rows.y, slider_width, params, "food_efficiency");
+                }
+                rows.y += 50.0;
+
+                if rows.shows(20.0, 24.0) {
+                    draw_param_slider(x_offset, rows.y, slider_width, params, "bacteria_handling_time");
+                }
+                rows.y += 55.0;
+            }
+            PanelSection::Predators => {
+                if rows.shows(20.0, 24.0) {
+                    draw_param_slider(x_offset, rows.y, slider_width, params, "predator_reproduction_threshold");
+                }
+                rows.y += 50.0;
+
+                if rows.shows(20.0, 24.0) {
+                    draw_param_slider(x_offset, rows.y, slider_width, params, "predation_efficiency");
+                }
+                rows.y += 50.0;
+
+                if rows.shows(20.0, 24.0) {
+                    draw_param_slider(x_offset, rows.y, slider_width, params, "pack_cooperation");
+                }
+                rows.y += 50.0;
+
+                if rows.shows(20.0, 24.0) {
+                    draw_param_slider(x_offset, rows.y, slider_width, params, "predator_handling_time");
+                }
+                rows.y += 60.0;
+            }
+            PanelSection::Sound => {
+                if rows.shows(20.0, 24.0) {
+                    draw_slider(x_offset, rows.y, slider_width, "Ambient volume", &mut ui_state.sound.ambient, 0.0, 1.0, "%");
+                }
+                rows.y += 50.0;
+
+                if rows.shows(20.0, 24.0) {
+                    draw_slider(x_offset, rows.y, slider_width, "Effects volume", &mut ui_state.sound.effects, 0.0, 1.0, "%");
+                }
+                rows.y += 35.0;
+
+                let mute_label = format!("{} ({})", if ui_state.sound.muted { "🔇 UNMUTE" } else { "🔊 MUTE" }, key_name(ui_state.keymap.key(Action::Mute)));
+                if rows.shows(0.0, 26.0) && draw_button(x_offset, rows.y, full_width, 26.0, &mute_label, Color::new(0.25, 0.25, 0.45, 0.95)) {
+                    ui_state.sound.muted = !ui_state.sound.muted;
+                }
+                rows.y += 50.0;
+            }
+            PanelSection::Presets => {
+                if rows.shows(0.0, 26.0) {
+                    draw_preset_name(x_offset, rows.y, full_width - 70.0, ui_state);
+                    let name = ui_state.preset_name.trim().to_string();
+                    let save = draw_button(x_offset + full_width - 60.0, rows.y, 60.0, 26.0, "Save", Color::new(0.2, 0.45, 0.4, 0.95));
+                    if save || (ui_state.naming_preset && is_key_pressed(KeyCode::Enter)) {
+                        match presets::save(&name, params) {
+                            Ok(()) => {
+                                info!(target: UI, "Saved preset '{}'", name);
+                                ui_state.presets = None;
+                                ui_state.naming_preset = false;
+                            }
+                            Err(e) => warn!("Couldn't save preset: {}", e),
+                        }
+                    }
+                }
+                rows.y += 36.0;
+
+                let names = ui_state.presets.get_or_insert_with(presets::list).clone();
+                for name in names {
+                    if rows.shows(0.0, 24.0) && draw_button(x_offset, rows.y, full_width, 24.0, &name, Color::new(0.12, 0.22, 0.2, 0.95)) {
+                        match presets::load(&name, params) {
+                            Ok(()) => info!(target: UI, "Loaded preset '{}'", name),
+                            Err(e) => warn!("Couldn't load preset '{}': {}", name, e),
+                        }
+                    }
+                    rows.y += 30.0;
+                }
+
+                let undo_label = format!("↶ UNDO ({})", key_name(ui_state.keymap.key(Action::Undo)));
+                if rows.shows(0.0, 26.0) && draw_button(x_offset, rows.y, full_width, 26.0, &undo_label, Color::new(0.3, 0.3, 0.35, 0.95)) {
+                    ui_state.undo_requested = true;
+                }
+                rows.y += 30.0;
+
+                let import_label = format!("📥 IMPORT POPULATION ({})", key_name(ui_state.keymap.key(Action::ImportPopulation)));
+                tooltip(Rect::new(x_offset, rows.y, full_width, 26.0), IMPORT_HELP);
+                if rows.shows(0.0, 26.0) && draw_button(x_offset, rows.y, full_width, 26.0, &import_label, Color::new(0.3, 0.3, 0.35, 0.95)) {
+                    ui_state.import_requested = true;
+                }
+                rows.y += 30.0;
+
+                let tree_label = format!("🌳 EXPORT TREE ({})", key_name(ui_state.keymap.key(Action::ExportTree)));
+                tooltip(Rect::new(x_offset, rows.y, full_width, 26.0), EXPORT_TREE_HELP);
+                if rows.shows(0.0, 26.0) && draw_button(x_offset, rows.y, full_width, 26.0, &tree_label, Color::new(0.3, 0.3, 0.35, 0.95)) {
+                    ui_state.export_tree_requested = true;
+                }
+                rows.y += 50.0;
+            }
+            PanelSection::Controls => {
+                let bindable = Action::ALL.map(|action| (key_name(ui_state.keymap.key(action)), action.help()));
+                let fixed = CONTROLS_HELP.iter().map(|&(key, help)| (key.to_string(), help));
+                for (key, help) in bindable.into_iter().chain(fixed) {
+                    if rows.shows(12.0, 4.0) {
+                        draw_text(format!("{:<6}→  {}", key, help), x_offset, rows.y, 14.0, GRAY);
+                    }
+                    rows.y += 18.0;
+                }
+                rows.y += 10.0;
+            }
+        }
+    }
+
+    // Stop at the end of the content, and show where in it the view is
+    let max_scroll = (rows.y + ui_state.panel_scroll - view.bottom()).max(0.0);
+    ui_state.panel_scroll = ui_state.panel_scroll.min(max_scroll);
+    if max_scroll > 0.0 {
+        let thumb = view.h * view.h / (view.h + max_scroll);
+        let thumb_y = view.y + (view.h - thumb) * ui_state.panel_scroll / max_scroll;
+        draw_rectangle(panel.right() - 7.0, view.y, 4.0, view.h, Color::new(1.0, 1.0, 1.0, 0.06));
+        draw_rectangle(panel.right() - 7.0, thumb_y, 4.0, thumb, Color::new(0.4, 0.6, 0.8, 0.7));
+    }
+    draw_tooltip();
+}
+
+// The chosen graphs side by side, sharing the panel's width. The region graph
+// only shows for layouts with several regions.
+// `baseline` is a previous run's stats, drawn faintly behind each graph
+pub fn draw_graphs_panel(stats: &Stats, regions: &[Region], baseline: Option<&Baseline>, layout: &Layout, ui_state: &mut UIState) {
+    let shown: Vec<Metric> = ui_state.graphs.iter()
+        .copied()
+        .filter(|&m| m != Metric::Regions || !stats.region_history.is_empty())
+        .filter(|&m| m != Metric::Markers || !stats.marker_history.is_empty())
+        .collect();
+    if shown.is_empty() {
+        return;
+    }
+    let graphs = shown.len() as f32;
+    let graph_width = (layout.graphs.w - 10.0 * (graphs - 1.0)) / graphs;
+    let y = layout.graphs.y;
+    let span = if ui_state.graph_whole_run { usize::MAX } else { MAX_HISTORY };
+    let ticks = latest(&stats.tick_history, span);
+
+    for (i, metric) in shown.into_iter().enumerate() {
+        let x = layout.graphs.x + (graph_width + 10.0) * i as f32;
+        let scale = &mut ui_state.graph_scales[metric.index()];
+        match metric.history(stats) {
+            Some(data) => {
+                let data = latest(data, span);
+                let ticks = latest(ticks, data.len());
+                let ghost = baseline.and_then(|b| b.aligned(metric, ticks));
+                let band = metric.spread(stats).map(|band| latest(band, span));
+                draw_line_graph(x, y, graph_width, GRAPH_HEIGHT, data, band, ghost.as_deref(), ticks, metric.color(), metric.label(), metric.fixed_max(), scale)
+            }
+            None if metric == Metric::Markers => draw_marker_graph(x, y, graph_width, GRAPH_HEIGHT, stats, span, scale),
+            None => draw_region_graph(x, y, graph_width, GRAPH_HEIGHT, stats, regions, span, scale),
+        }
+        draw_graph_markers(x, y, graph_width, GRAPH_HEIGHT, &stats.markers, ticks);
+    }
+}
+
+// Dashed vertical line with a label at each marker still in view
+fn draw_graph_markers(x: f32, y: f32, width: f32, height: f32, markers: &[Marker], ticks: &[u64]) {
+    let step = graph_step(width, ticks.len());
+    let first = ticks.first().copied().unwrap_or(0);
+    for (n, marker) in markers.iter().filter(|m| m.tick >= first).enumerate() {
+        let Some(i) = ticks.iter().position(|&t| t >= marker.tick) else { continue };
+        let line_x = x + i as f32 * step;
+        let dim = Color::new(marker.color.r, marker.color.g, marker.color.b, 0.6);
+        let mut dash_y = y + 48.0;
+        while dash_y < y + height {
+            draw_line(line_x, dash_y, line_x, (dash_y + 4.0).min(y + height), 1.0, dim);
+            dash_y += 8.0;
+        }
+        let dims = measure_text(&marker.label, None, 12, 1.0);
+        let text_x = (line_x + 3.0).min(x + width - dims.width - 2.0);
+        // Stagger the labels so markers close together stay readable
+        draw_text(&marker.label, text_x, y + 58.0 + (n % 3) as f32 * 12.0, 12.0, marker.color);
+    }
+}
+
+const GRAPH_SETTINGS_ROW: f32 = 28.0;
+
+// Popup over the middle of the sim area with a checkbox per metric
+pub fn graph_settings_rect(layout: &Layout) -> Rect {
+    let (width, height) = (300.0, 80.0 + Metric::ALL.len() as f32 * GRAPH_SETTINGS_ROW);
+    Rect::new(layout.sim.center().x - width / 2.0, layout.sim.center().y - height / 2.0, width, height)
+}
+
+pub fn draw_graph_settings(ui_state: &mut UIState, layout: &Layout) {
+    let row_height = GRAPH_SETTINGS_ROW;
+    let Rect { x, y, w: width, h: height } = graph_settings_rect(layout);
+    draw_rectangle(x, y, width, height, Color::new(0.02, 0.02, 0.03, 0.95));
+    draw_rectangle_lines(x, y, width, height, 2.0, Color::new(0.3, 0.4, 0.5, 0.8));
+    draw_text("GRAPHS", x + 15.0, y + 28.0, 22.0, Color::new(0.4, 0.7, 1.0, 1.0));
+
+    let mouse = Vec2::from(mouse_position());
+    let clicked = is_mouse_button_pressed(MouseButton::Left);
+    for (i, metric) in Metric::ALL.into_iter().enumerate() {
+        let row = Rect::new(x + 10.0, y + 45.0 + i as f32 * row_height, width - 20.0, row_height - 4.0);
+        let on = ui_state.graphs.contains(&metric);
+        if row.contains(mouse) {
+            draw_rectangle(row.x, row.y, row.w, row.h, Color::new(1.0, 1.0, 1.0, 0.06));
+            if clicked {
+                ui_state.graphs = Metric::ALL.into_iter().filter(|&m| (m == metric) != ui_state.graphs.contains(&m)).collect();
+            }
+        }
+        draw_rectangle_lines(row.x + 4.0, row.y + 4.0, 16.0, 16.0, 2.0, metric.color());
+        if on {
+            draw_rectangle(row.x + 8.0, row.y + 8.0, 8.0, 8.0, metric.color());
+        }
+        draw_text(metric.title(), row.x + 30.0, row.y + 17.0, 17.0, WHITE);
+    }
+
+    // Graph the whole run or only the latest entries
+    let range = if ui_state.graph_whole_run { "Range: whole run".to_string() } else { format!("Range: last {}", MAX_HISTORY) };
+    if draw_button(x + 10.0, y + height - 34.0, 190.0, 26.0, &range, Color::new(0.25, 0.25, 0.4, 0.95)) {
+        ui_state.graph_whole_run = !ui_state.graph_whole_run;
+    }
+    if draw_button(x + width - 90.0, y + height - 34.0, 80.0, 26.0, "Close", Color::new(0.2, 0.5, 0.9, 0.95)) {
+        ui_state.show_graph_settings = false;
+    }
+}
+
+const HALL_OF_FAME_ROW: f32 = 22.0;
+const HALL_OF_FAME_SHOWN: usize = 10;
+
+pub fn hall_of_fame_rect(layout: &Layout) -> Rect {
+    let (width, height) = (600.0, 80.0 + Record::ALL.len() as f32 * (30.0 + HALL_OF_FAME_SHOWN as f32 * HALL_OF_FAME_ROW));
+    Rect::new(layout.sim.center().x - width / 2.0, layout.sim.center().y - height / 2.0, width, height)
+}
+
+// The archived genomes, best first under each record. Returns the genome
+// whose "Clone" button was clicked, to be reintroduced.
+pub fn draw_hall_of_fame(hall: &HallOfFame, ui_state: &mut UIState, layout: &Layout) -> Option<DNA> {
+    let Rect { x, y, w: width, h: height } = hall_of_fame_rect(layout);
+    draw_rectangle(x, y, width, height, Color::new(0.02, 0.02, 0.03, 0.95));
+    draw_rectangle_lines(x, y, width, height, 2.0, Color::new(0.3, 0.4, 0.5, 0.8));
+    draw_text("HALL OF FAME", x + 15.0, y + 28.0, 22.0, Color::new(1.0, 0.8, 0.3, 1.0));
+
+    let mut reintroduce = None;
+    let mut row_y = y + 50.0;
+    for record in Record::ALL {
+        draw_text(record.title(), x + 15.0, row_y + 14.0, 16.0, Color::new(0.4, 0.7, 1.0, 1.0));
+        row_y += 26.0;
+        let entries = hall.entries(record);
+        if entries.is_empty() {
+            draw_text("Nothing archived yet", x + 25.0, row_y + 15.0, 15.0, GRAY);
+        }
+        for entry in entries.iter().take(HALL_OF_FAME_SHOWN) {
+            draw_circle(x + 25.0, row_y + 10.0, 6.0, Color { a: 1.0, ..entry.dna.color() });
+            let text = format!(
+                "age {:.0}  {} offspring  gen {}   spd {:.2}  size {:.1}  sense {:.0}",
+                entry.age, entry.offspring, entry.generation, entry.dna.gene(SPEED), entry.dna.gene(SIZE), entry.dna.gene(SENSE_RADIUS),
+            );
+            draw_text(&text, x + 40.0, row_y + 15.0, 15.0, WHITE);
+            if draw_button(x + width - 80.0, row_y, 65.0, HALL_OF_FAME_ROW - 3.0, "Clone", Color::new(0.2, 0.45, 0.3, 0.95)) {
+                reintroduce = Some(entry.dna.clone());
+            }
+            row_y += HALL_OF_FAME_ROW;
+        }
+        // Keep the next record's list in place however full this one is
+        row_y += 4.0 + (HALL_OF_FAME_SHOWN - entries.len().min(HALL_OF_FAME_SHOWN)) as f32 * HALL_OF_FAME_ROW;
+    }
+
+    if draw_button(x + width - 90.0, y + height - 34.0, 80.0, 26.0, "Close", Color::new(0.2, 0.5, 0.9, 0.95)) {
+        ui_state.show_hall_of_fame = false;
+    }
+    reintroduce
+}
+
+// Every achievement down the right of the sim area, lit once unlocked
+pub fn draw_achievements(achievements: &Achievements, layout: &Layout) {
+    let width = 330.0;
+    let row = 38.0;
+    let height = 40.0 + Achievement::ALL.len() as f32 * row;
+    let x = layout.sim.right() - width - 10.0;
+    let y = layout.sim.center().y - height / 2.0;
+    draw_rectangle(x, y, width, height, Color::new(0.02, 0.02, 0.03, 0.9));
+    draw_rectangle_lines(x, y, width, height, 2.0, Color::new(0.3, 0.4, 0.5, 0.8));
+    let title = format!("ACHIEVEMENTS  {}/{}", achievements.count(), Achievement::ALL.len());
+    draw_text(&title, x + 10.0, y + 22.0, 18.0, Color::new(1.0, 0.8, 0.3, 1.0));
+
+    for (i, achievement) in Achievement::ALL.into_iter().enumerate() {
+        let row_y = y + 36.0 + i as f32 * row;
+        let (mark, color, note) = match achievements.unlocked(achievement) {
+            Some(unlock) => ("🏅", WHITE, format!(" (tick {})", unlock.tick)),
+            None => ("🔒", GRAY, String::new()),
+        };
+        draw_text(format!("{} {}{}", mark, achievement.title(), note), x + 10.0, row_y + 14.0, 16.0, color);
+        draw_text(achievement.description(), x + 32.0, row_y + 30.0, 14.0, if color == WHITE { LIGHTGRAY } else { DARKGRAY });
+    }
+}
+
+// The scenario's objectives along the top of the sim area (below the terrain
+// toolbar while it's open), each with a progress bar, and the latest
+// outcome's banner across the middle until it fades
+pub fn draw_scenario(challenge: &mut Challenge, layout: &Layout, below_toolbar: bool) {
+    let width = 420.0;
+    let row = 36.0;
+    let height = 34.0 + challenge.progress.len() as f32 * row;
+    let x = layout.sim.center().x - width / 2.0;
+    let y = if below_toolbar { 82.0 } else { 10.0 };
+    draw_rectangle(x, y, width, height, Color::new(0.02, 0.02, 0.03, 0.85));
+    draw_rectangle_lines(x, y, width, height, 2.0, Color::new(0.3, 0.4, 0.5, 0.8));
+    draw_text(format!("SCENARIO: {}", challenge.scenario.name.to_uppercase()), x + 10.0, y + 20.0, 16.0, Color::new(1.0, 0.8, 0.3, 1.0));
+
+    for (i, (objective, progress)) in challenge.scenario.objectives.iter().zip(&challenge.progress).enumerate() {
+        let row_y = y + 30.0 + i as f32 * row;
+        let (mark, color) = match progress.status {
+            Status::Active => ("○", LIGHTGRAY),
+            Status::Complete(_) => ("✔", Color::new(0.4, 1.0, 0.5, 1.0)),
+            Status::Failed(_) => ("✖", Color::new(1.0, 0.4, 0.3, 1.0)),
+        };
+        draw_text(format!("{} {}", mark, objective.goal), x + 10.0, row_y + 14.0, 15.0, color);
+        let value = format!("{:.2}", progress.value);
+        let value_width = measure_text(&value, None, 14, 1.0).width;
+        draw_text(&value, x + width - 10.0 - value_width, row_y + 14.0, 14.0, GRAY);
+        let bar_width = width - 20.0;
+        draw_rectangle(x + 10.0, row_y + 20.0, bar_width, 6.0, Color::new(0.15, 0.15, 0.2, 1.0));
+        draw_rectangle(x + 10.0, row_y + 20.0, bar_width * progress.fraction, 6.0, color);
+    }
+
+    let Some((text, color, shown)) = &challenge.banner else { return };
+    let age = get_time() - shown;
+    if age > BANNER_SECONDS {
+        challenge.banner = None;
+        return;
+    }
+    // Fade out over the last second
+    let alpha = (BANNER_SECONDS - age).min(1.0) as f32;
+    let size = measure_text(text, None, 32, 1.0);
+    let (cx, cy) = (layout.sim.center().x, layout.sim.y + layout.sim.h * 0.3);
+    draw_rectangle(cx - size.width / 2.0 - 20.0, cy - 34.0, size.width + 40.0, 52.0, Color::new(0.02, 0.02, 0.03, 0.85 * alpha));
+    draw_text(text, cx - size.width / 2.0, cy, 32.0, Color::new(color.r, color.g, color.b, alpha));
+}
+
+// Asks whether to resume the crashed run's autosave, from `tick`, in the
+// middle of the sim area: Some(true) to resume, Some(false) to start fresh
+pub fn recovery_rect(layout: &Layout) -> Rect {
+    let (width, height) = (420.0, 120.0);
+    Rect::new(layout.sim.center().x - width / 2.0, layout.sim.center().y - height / 2.0, width, height)
+}
+
+pub fn draw_recovery(tick: u64, layout: &Layout) -> Option<bool> {
+    let Rect { x, y, w: width, h: height } = recovery_rect(layout);
+    draw_rectangle(x, y, width, height, Color::new(0.02, 0.02, 0.03, 0.95));
+    draw_rectangle_lines(x, y, width, height, 2.0, Color::new(1.0, 0.8, 0.3, 0.9));
+    draw_text("The last run didn't close cleanly.", x + 15.0, y + 30.0, 20.0, Color::new(1.0, 0.8, 0.3, 1.0));
+    draw_text(format!("Resume from its autosave at tick {}?", tick), x + 15.0, y + 56.0, 17.0, WHITE);
+    let half = (width - 40.0) / 2.0;
+    if draw_button(x + 15.0, y + height - 40.0, half, 28.0, "RESUME", Color::new(0.2, 0.55, 0.3, 0.95)) {
+        return Some(true);
+    }
+    if draw_button(x + 25.0 + half, y + height - 40.0, half, 28.0, "START FRESH", Color::new(0.3, 0.3, 0.35, 0.95)) {
+        return Some(false);
+    }
+    None
+}
+
+// The console drops down over the top of the sim area
+pub fn console_rect(layout: &Layout) -> Rect {
+    Rect::new(layout.sim.x, layout.sim.y, layout.sim.w, (layout.sim.h * 0.4).min(320.0))
+}
+
+// Typing goes to the console while it's open: ENTER runs the line (returns
+// true), UP and DOWN recall earlier ones, TAB completes and ESC closes
+pub fn draw_console(console: &mut Console, params: &SimulationParams, layout: &Layout) -> bool {
+    while let Some(c) = get_char_pressed() {
+        if !c.is_control() && console.input.len() < 200 {
+            console.input.push(c);
+        }
+    }
+    if is_key_pressed(KeyCode::Backspace) {
+        console.input.pop();
+    }
+    if is_key_pressed(KeyCode::Up) {
+        console.recall(true);
+    }
+    if is_key_pressed(KeyCode::Down) {
+        console.recall(false);
+    }
+    if is_key_pressed(KeyCode::Tab) {
+        console.complete(params);
+    }
+    if is_key_pressed(KeyCode::Escape) {
+        console.open = false;
+    }
+    let submit = is_key_pressed(KeyCode::Enter) || is_key_pressed(KeyCode::KpEnter);
+
+    let Rect { x, y, w: width, h: height } = console_rect(layout);
+    draw_rectangle(x, y, width, height, Color::new(0.02, 0.02, 0.03, 0.92));
+    draw_line(x, y + height, x + width, y + height, 2.0, Color::new(0.4, 0.7, 1.0, 0.9));
+    let line_height = 17.0;
+    let input_y = y + height - 10.0;
+    let cursor = if (get_time() * 2.0) as i64 % 2 == 0 { "|" } else { "" };
+    draw_text(format!("> {}{}", console.input, cursor), x + 10.0, input_y, 16.0, WHITE);
+    let rows = ((height - 30.0) / line_height) as usize;
+    for (i, (line, error)) in console.output().iter().rev().take(rows).enumerate() {
+        let color = if *error { Color::new(1.0, 0.45, 0.45, 1.0) } else { Color::new(0.75, 0.8, 0.85, 1.0) };
+        draw_text(line, x + 10.0, input_y - line_height * (i + 1) as f32 - 4.0, 15.0, color);
+    }
+    submit
+}
+
+// One line per habitat region on a shared scale, with a colour legend
+#[allow(clippy::too_many_arguments)]
+fn draw_region_graph(x: f32, y: f32, width: f32, height: f32, stats: &Stats, regions: &[Region], span: usize, scale: &mut GraphScale) {
+    draw_rectangle(x, y, width, height, Color::new(0.08, 0.08, 0.1, 0.85));
+    draw_rectangle_lines(x, y, width, height, 2.0, Color::new(0.3, 0.3, 0.3, 0.6));
+
+    let histories: Vec<&[f32]> = stats.region_history.iter().map(|history| latest(history, span)).collect();
+    let axis = Axis::new(*scale, histories.iter().copied().flatten().copied(), None);
+    let len = histories.iter().map(|history| history.len()).max().unwrap_or(0);
+    draw_graph_axes(x, y, width, height, &axis, len, &stats.tick_history);
+    let step = graph_step(width, len);
+    for (history, region) in histories.iter().zip(regions) {
+        for (i, pair) in history.windows(2).enumerate() {
+            let (y1, y2) = (axis.y(pair[0], y, height), axis.y(pair[1], y, height));
+            draw_line(x + i as f32 * step, y1, x + (i + 1) as f32 * step, y2, 2.0, region.color);
+        }
+    }
+
+    draw_rectangle(x + 3.0, y + 3.0, 150.0, 24.0 + 16.0 * regions.len() as f32, Color::new(0.0, 0.0, 0.0, 0.6));
+    draw_text("🏝 Islands", x + 8.0, y + 20.0, 18.0, WHITE);
+    for (i, (history, region)) in stats.region_history.iter().zip(regions).enumerate() {
+        let last = history.last().copied().unwrap_or(0.0);
+        draw_text(format!("{}: {:.0}", region.name, last), x + 8.0, y + 38.0 + i as f32 * 16.0, 15.0, region.color);
+    }
+
+    let lines: Vec<(&str, &[f32], Color)> = histories.iter().zip(regions)
+        .map(|(history, region)| (region.name, *history, region.color))
+        .collect();
+    draw_scale_toggle(x, y, width, scale);
+    draw_graph_hover(x, y, width, height, &axis, &stats.tick_history, &lines);
+}
+
+// One line per marker allele, coloured by allele and darker for later loci,
+// with each locus's current frequencies and the heterozygosity as legend
+fn draw_marker_graph(x: f32, y: f32, width: f32, height: f32, stats: &Stats, span: usize, scale: &mut GraphScale) {
+    draw_rectangle(x, y, width, height, Color::new(0.08, 0.08, 0.1, 0.85));
+    draw_rectangle_lines(x, y, width, height, 2.0, Color::new(0.3, 0.3, 0.3, 0.6));
+
+    let series: Vec<(String, &[f32], Color)> = stats.marker_series().into_iter()
+        .map(|(label, history, color)| (label, latest(history, span), color))
+        .collect();
+    let axis = Axis::new(*scale, series.iter().flat_map(|(_, history, _)| history.iter().copied()), Metric::Markers.fixed_max());
+    let len = series.iter().map(|(_, history, _)| history.len()).max().unwrap_or(0);
+    draw_graph_axes(x, y, width, height, &axis, len, &stats.tick_history);
+    let step = graph_step(width, len);
+    for (_, history, color) in &series {
+        for (i, pair) in history.windows(2).enumerate() {
+            let (y1, y2) = (axis.y(pair[0], y, height), axis.y(pair[1], y, height));
+            draw_line(x + i as f32 * step, y1, x + (i + 1) as f32 * step, y2, 1.5, *color);
+        }
+    }
+
+    // Only the first few loci fit in the legend
+    let loci = stats.marker_history.len().min(4);
+    draw_rectangle(x + 3.0, y + 3.0, 190.0, 40.0 + 16.0 * loci as f32, Color::new(0.0, 0.0, 0.0, 0.6));
+    draw_text("🏷 Markers", x + 8.0, y + 20.0, 18.0, WHITE);
+    let heterozygosity = stats.heterozygosity_history.last().copied().unwrap_or(0.0);
+    draw_text(format!("H = {:.3}", heterozygosity), x + 100.0, y + 20.0, 15.0, Metric::Heterozygosity.color());
+    for (l, locus) in stats.marker_history.iter().take(loci).enumerate() {
+        let row_y = y + 38.0 + l as f32 * 16.0;
+        draw_text(format!("M{}", l + 1), x + 8.0, row_y, 15.0, LIGHTGRAY);
+        let mut text_x = x + 34.0;
+        for (a, history) in locus.iter().enumerate() {
+            let label = format!("{}{:.0}", ALLELE_NAMES[a], history.last().copied().unwrap_or(0.0));
+            draw_text(&label, text_x, row_y, 14.0, allele_color(l, a));
+            text_x += measure_text(&label, None, 14, 1.0).width + 6.0;
+        }
+    }
+    if stats.marker_history.len() > loci {
+        draw_text(format!("+{} more loci", stats.marker_history.len() - loci), x + 8.0, y + 38.0 + 16.0 * loci as f32, 13.0, GRAY);
+    }
+
+    let lines: Vec<(&str, &[f32], Color)> = series.iter().map(|(label, history, color)| (label.as_str(), *history, *color)).collect();
+    draw_scale_toggle(x, y, width, scale);
+    draw_graph_hover(x, y, width, height, &axis, &stats.tick_history, &lines);
+}
+
+// Per-region food multipliers, shown over the bottom-right of the sim area for
+// any layout other than the open world
+pub fn draw_region_panel(world: &mut World, layout: &Layout) {
+    let regions = world.habitat.regions.len();
+    let width = 290.0;
+    let height = 30.0 + regions as f32 * 42.0;
+    let x = layout.sim.right() - width - 10.0;
+    let y = layout.graphs.y - height - 10.0;
+    draw_rectangle(x, y, width, height, Color::new(0.02, 0.02, 0.03, 0.9));
+    draw_rectangle_lines(x, y, width, height, 2.0, Color::new(0.3, 0.4, 0.5, 0.8));
+    draw_text("HABITAT FOOD", x + 10.0, y + 20.0, 18.0, Color::new(0.4, 0.7, 1.0, 1.0));
+
+    for (i, region) in world.habitat.regions.iter_mut().enumerate() {
+        let row_y = y + 52.0 + i as f32 * 42.0;
+        draw_rectangle(x + 10.0, row_y - 16.0, 8.0, 8.0, region.color);
+        draw_slider(x + 24.0, row_y, width - 90.0, region.name, &mut region.food_multiplier, 0.0, 3.0, "x");
+    }
+}
+
+// Matrix of the latest gene correlations in the sim area's top-left corner:
+// orange cells for genes rising together, blue for trade-offs
+pub fn draw_correlation_panel(stats: &Stats, layout: &Layout) {
+    let Some(correlations) = &stats.correlations else { return };
+    let cell = 44.0;
+    let genes = CORRELATED_GENES.len() as f32;
+    let (width, height) = (60.0 + genes * cell, 80.0 + genes * cell);
+    let (x, y) = (layout.sim.x + 10.0, layout.sim.y + 40.0);
+    draw_rectangle(x, y, width, height, Color::new(0.02, 0.02, 0.03, 0.9));
+    draw_rectangle_lines(x, y, width, height, 2.0, Color::new(0.3, 0.4, 0.5, 0.8));
+    let title = format!("GENE CORRELATIONS  (tick {}, n={})", correlations.tick, correlations.count);
+    draw_text(&title, x + 10.0, y + 20.0, 16.0, Color::new(0.4, 0.7, 1.0, 1.0));
+
+    let (grid_x, grid_y) = (x + 50.0, y + 48.0);
+    for (i, (label, _)) in CORRELATED_GENES.iter().enumerate() {
+        let offset = i as f32 * cell;
+        draw_text(label, grid_x + offset + 6.0, grid_y - 6.0, 14.0, LIGHTGRAY);
+        draw_text(label, x + 8.0, grid_y + offset + cell / 2.0 + 5.0, 14.0, LIGHTGRAY);
+        for (j, &r) in correlations.matrix[i].iter().enumerate() {
+            let (cx, cy) = (grid_x + j as f32 * cell, grid_y + offset);
+            let tint = if r >= 0.0 { Color::new(1.0, 0.55, 0.2, r.abs()) } else { Color::new(0.25, 0.5, 1.0, r.abs()) };
+            draw_rectangle(cx + 1.0, cy + 1.0, cell - 2.0, cell - 2.0, tint);
+            draw_text(format!("{:.2}", r), cx + 5.0, cy + cell / 2.0 + 5.0, 14.0, WHITE);
+        }
+    }
+    let [speed, size, sense] = correlations.heritability.slopes;
+    let heritability = format!("HERITABILITY  speed {:.2}  size {:.2}  sense {:.2}  ({} parent-offspring pairs)", speed, size, sense, correlations.heritability.pairs);
+    draw_text(&heritability, x + 10.0, grid_y + genes * cell + 20.0, 16.0, LIGHTGRAY);
+}
+
+// Side of a bin in the fitness landscape
+const LANDSCAPE_CELL: f32 = 24.0;
+
+pub fn fitness_landscape_rect(layout: &Layout) -> Rect {
+    let side = LANDSCAPE_BINS as f32 * LANDSCAPE_CELL;
+    let (width, height) = (side + 70.0, side + 120.0);
+    Rect::new(layout.sim.right() - width - 10.0, layout.sim.y + 40.0, width, height)
+}
+
+// Realized fitness over two genes in the sim area's top-right corner: each
+// bin brighter the more offspring the bacteria that lived there had, dark
+// where none has died yet, with the living bacteria as dots on top. The
+// buttons below step through the genes on each axis.
+pub fn draw_fitness_landscape(landscape: &FitnessLandscape, ui_state: &mut UIState, layout: &Layout) {
+    let Rect { x, y, w: width, h: height } = fitness_landscape_rect(layout);
+    draw_rectangle(x, y, width, height, Color::new(0.02, 0.02, 0.03, 0.9));
+    draw_rectangle_lines(x, y, width, height, 2.0, Color::new(0.3, 0.4, 0.5, 0.8));
+    let Some(estimate) = &landscape.estimate else { return };
+    let title = format!("FITNESS LANDSCAPE  (tick {}, n={})", estimate.tick, estimate.lifetimes);
+    draw_text(&title, x + 10.0, y + 20.0, 16.0, Color::new(0.4, 0.7, 1.0, 1.0));
+
+    let side = LANDSCAPE_BINS as f32 * LANDSCAPE_CELL;
+    let (grid_x, grid_y) = (x + 55.0, y + 36.0);
+    let best = estimate.fitness.iter().flatten().flatten().fold(0.0f32, |a, &b| a.max(b));
+    for (row, bins) in estimate.fitness.iter().enumerate() {
+        let cy = grid_y + (LANDSCAPE_BINS - 1 - row) as f32 * LANDSCAPE_CELL;
+        for (col, fitness) in bins.iter().enumerate() {
+            let color = match fitness {
+                Some(f) => ui_state.palette.ramp(if best > 0.0 { f / best } else { 0.0 }),
+                None => Color::new(0.08, 0.08, 0.1, 1.0),
+            };
+            draw_rectangle(grid_x + col as f32 * LANDSCAPE_CELL, cy, LANDSCAPE_CELL, LANDSCAPE_CELL, color);
+        }
+    }
+    let ((x_lo, x_hi), (y_lo, y_hi)) = (estimate.x_range, estimate.y_range);
+    for (gx, gy) in landscape.population(estimate.genes) {
+        let px = grid_x + ((gx - x_lo) / (x_hi - x_lo)).clamp(0.0, 1.0) * side;
+        let py = grid_y + side - ((gy - y_lo) / (y_hi - y_lo)).clamp(0.0, 1.0) * side;
+        draw_circle(px, py, 2.0, Color::new(1.0, 1.0, 1.0, 0.8));
+    }
+
+    let (x_step, y_step) = (nice_step(x_hi - x_lo, 10), nice_step(y_hi - y_lo, 10));
+    draw_text(format_value(y_hi, y_step), x + 6.0, grid_y + 10.0, 13.0, LIGHTGRAY);
+    draw_text(format_value(y_lo, y_step), x + 6.0, grid_y + side, 13.0, LIGHTGRAY);
+    draw_text(format_value(x_lo, x_step), grid_x, grid_y + side + 14.0, 13.0, LIGHTGRAY);
+    let hi = format_value(x_hi, x_step);
+    draw_text(&hi, grid_x + side - measure_text(&hi, None, 13, 1.0).width, grid_y + side + 14.0, 13.0, LIGHTGRAY);
+    draw_text(format!("best bin: {:.2} offspring per lifetime", best), x + 10.0, grid_y + side + 34.0, 14.0, LIGHTGRAY);
+
+    // Each axis steps to the next gene, skipping the other axis's
+    let next = |gene: usize, other: usize| {
+        let step = |g: usize| (g + 1) % CORRELATED_GENES.len();
+        if step(gene) == other { step(other) } else { step(gene) }
+    };
+    let (gene_x, gene_y) = ui_state.landscape_genes;
+    let button_y = grid_y + side + 46.0;
+    let button_width = (width - 30.0) / 2.0;
+    let label = format!("X: {} ▸", CORRELATED_GENES[gene_x].0);
+    if draw_button(x + 10.0, button_y, button_width, 24.0, &label, Color::new(0.25, 0.25, 0.4, 0.95)) {
+        ui_state.landscape_genes.0 = next(gene_x, gene_y);
+    }
+    let label = format!("Y: {} ▸", CORRELATED_GENES[gene_y].0);
+    if draw_button(x + 20.0 + button_width, button_y, button_width, 24.0, &label, Color::new(0.25, 0.25, 0.4, 0.95)) {
+        ui_state.landscape_genes.1 = next(gene_y, gene_x);
+    }
+}
+
+// Side of each trait's plot in the selection panel
+const SELECTION_PLOT: f32 = 150.0;
+
+// The breeder's equation at the top of the sim area: per trait, each
+// window's response R against its selection differential S, newer windows
+// brighter, with R = h²S for the parent-offspring heritability (orange) and
+// the slope the windows actually follow (white)
+pub fn draw_selection_panel(stats: &Stats, layout: &Layout) {
+    let selection = &stats.selection;
+    let (width, height) = (3.0 * (SELECTION_PLOT + 20.0) + 20.0, SELECTION_PLOT + 110.0);
+    let (x, y) = (layout.sim.center().x - width / 2.0, layout.sim.y + 40.0);
+    draw_rectangle(x, y, width, height, Color::new(0.02, 0.02, 0.03, 0.9));
+    draw_rectangle_lines(x, y, width, height, 2.0, Color::new(0.3, 0.4, 0.5, 0.8));
+    let title = format!("SELECTION & RESPONSE  ({} windows of {} ticks)", selection.windows.len(), SELECTION_EVERY);
+    draw_text(&title, x + 10.0, y + 20.0, 16.0, Color::new(0.4, 0.7, 1.0, 1.0));
+    let Some(latest) = selection.windows.last() else {
+        draw_text("Waiting for a window to finish...", x + 10.0, y + 44.0, 15.0, LIGHTGRAY);
+        return;
+    };
+
+    let realized = selection.realized();
+    let heritability = stats.correlations.as_ref().map(|c| c.heritability.slopes);
+    for (i, name) in ["Speed", "Size", "Sense"].into_iter().enumerate() {
+        let (px, py) = (x + 20.0 + i as f32 * (SELECTION_PLOT + 20.0), y + 52.0);
+        draw_text(name, px, py - 8.0, 15.0, LIGHTGRAY);
+        draw_rectangle(px, py, SELECTION_PLOT, SELECTION_PLOT, Color::new(0.06, 0.06, 0.08, 1.0));
+        // Both axes share a scale symmetric around zero
+        let extent = selection.windows.iter()
+            .fold(0.0f32, |m, w| m.max(w.differential[i].abs()).max(w.response[i].abs()))
+            .max(1e-4) * 1.1;
+        let half = SELECTION_PLOT / 2.0;
+        let (cx, cy) = (px + half, py + half);
+        let to_screen = |s: f32, r: f32| (cx + s / extent * half, cy - r / extent * half);
+        draw_line(px, cy, px + SELECTION_PLOT, cy, 1.0, DARKGRAY);
+        draw_line(cx, py, cx, py + SELECTION_PLOT, 1.0, DARKGRAY);
+        // A slope is drawn through the origin and clipped to the plot
+        let draw_slope = |slope: f32, color: Color| {
+            let s = if slope.abs() > 1.0 { extent / slope.abs() } else { extent };
+            let (x1, y1) = to_screen(-s, -s * slope);
+            let (x2, y2) = to_screen(s, s * slope);
+            draw_line(x1, y1, x2, y2, 1.5, color);
+        };
+        if let Some(h) = heritability {
+            draw_slope(h[i], Color::new(1.0, 0.55, 0.2, 0.9));
+        }
+        if let Some(slope) = realized[i] {
+            draw_slope(slope, Color::new(1.0, 1.0, 1.0, 0.7));
+        }
+        let count = selection.windows.len() as f32;
+        for (age, window) in selection.windows.iter().rev().enumerate() {
+            let (sx, sy) = to_screen(window.differential[i], window.response[i]);
+            draw_circle(sx, sy, 3.0, Color::new(0.4, 0.8, 1.0, 1.0 - 0.8 * age as f32 / count));
+        }
+        let step = nice_step(extent, 4);
+        draw_text(format!("±{}", format_value(extent, step)), px + 2.0, py + 12.0, 12.0, GRAY);
+
+        let lines = [
+            format!("S {}  R {}", format_value(latest.differential[i], step), format_value(latest.response[i], step)),
+            format!("h² {}  R/S {}",
+                heritability.map_or("-".to_string(), |h| format!("{:.2}", h[i])),
+                realized[i].map_or("-".to_string(), |r| format!("{:.2}", r))),
+        ];
+        for (j, line) in lines.iter().enumerate() {
+            draw_text(line, px, py + SELECTION_PLOT + 18.0 + j as f32 * 16.0, 14.0, LIGHTGRAY);
+        }
+    }
+    let ne = latest.effective_size.map_or("-".to_string(), |ne| format!("{:.0}", ne));
+    let footer = format!("Latest window: {} surviving offspring of {} bacteria, Ne {}, tick {}", latest.offspring, latest.census, ne, latest.tick);
+    draw_text(&footer, x + 10.0, y + height - 10.0, 14.0, GRAY);
+}
+
+pub fn muller_plot_rect(layout: &Layout) -> Rect {
+    let (width, height) = (layout.sim.w - 40.0, 340.0);
+    Rect::new(layout.sim.center().x - width / 2.0, layout.sim.center().y - height / 2.0, width, height)
+}
+
+// A founder's clade in its lineage colour, and every nested clade a lighter
+// or darker shade of its parent's
+fn clade_colors(muller: &MullerPlot, palette: Palette) -> Vec<Color> {
+    let mut colors: Vec<Color> = Vec::with_capacity(muller.clades.len());
+    for clade in &muller.clades {
+        let color = match clade.parent {
+            None => palette.lineage(clade.founder),
+            Some(parent) => {
+                let base = colors[parent];
+                let hash = clade.founder.id().wrapping_mul(2_654_435_761) >> 8;
+                let toward = if hash % 2 == 0 { 1.0 } else { 0.0 };
+                let t = 0.25 + 0.1 * (hash % 3) as f32;
+                Color::new(base.r + (toward - base.r) * t, base.g + (toward - base.g) * t, base.b + (toward - base.b) * t, 1.0)
+            }
+        };
+        colors.push(color);
+    }
+    colors
+}
+
+// Muller plot over the middle of the sim area: each clade's share of the
+// bacteria over time as a band nested inside the clade it split off from.
+// Hover for a clade's origin and share.
+pub fn draw_muller_plot(muller: &MullerPlot, palette: Palette, layout: &Layout) {
+    let Rect { x, y, w: width, h: height } = muller_plot_rect(layout);
+    draw_rectangle(x, y, width, height, Color::new(0.02, 0.02, 0.03, 0.95));
+    draw_rectangle_lines(x, y, width, height, 2.0, Color::new(0.3, 0.4, 0.5, 0.8));
+    let bands = muller.bands();
+    let ticks: Vec<u64> = muller.ticks().collect();
+    let shown = bands.iter().flatten().map(|b| b.clade).collect::<std::collections::HashSet<_>>().len();
+    let title = format!("MULLER PLOT  ({} clades shown, sampled every {} ticks)", shown, MULLER_EVERY);
+    draw_text(&title, x + 10.0, y + 20.0, 16.0, Color::new(0.4, 0.7, 1.0, 1.0));
+    let (Some(&first), Some(&last)) = (ticks.first(), ticks.last()) else { return };
+
+    let plot = Rect::new(x + 44.0, y + 32.0, width - 54.0, height - 62.0);
+    draw_rectangle(plot.x, plot.y, plot.w, plot.h, Color::new(0.06, 0.06, 0.08, 1.0));
+    let colors = clade_colors(muller, palette);
+    let column = plot.w / bands.len() as f32;
+    for (i, sample) in bands.iter().enumerate() {
+        for band in sample {
+            let top = plot.bottom() - band.hi * plot.h;
+            draw_rectangle(plot.x + i as f32 * column, top, column + 0.5, (band.hi - band.lo) * plot.h, colors[band.clade]);
+        }
+    }
+    draw_text("100%", x + 6.0, plot.y + 10.0, 13.0, LIGHTGRAY);
+    draw_text("0%", x + 18.0, plot.bottom(), 13.0, LIGHTGRAY);
+    draw_text(format!("tick {}", first), plot.x, plot.bottom() + 16.0, 13.0, LIGHTGRAY);
+    let end = format!("tick {}", last);
+    draw_text(&end, plot.right() - measure_text(&end, None, 13, 1.0).width, plot.bottom() + 16.0, 13.0, LIGHTGRAY);
+
+    // The innermost band under the mouse is the clade there
+    let mouse = Vec2::from(mouse_position());
+    if plot.contains(mouse) {
+        let i = (((mouse.x - plot.x) / column) as usize).min(bands.len() - 1);
+        let share = (plot.bottom() - mouse.y) / plot.h;
+        if let Some(band) = bands[i].iter().rev().find(|b| b.lo <= share && share < b.hi) {
+            let clade = &muller.clades[band.clade];
+            let origin = match clade.parent {
+                Some(parent) => format!("split from #{}", parent),
+                None => "founder".to_string(),
+            };
+            let text = format!("Clade #{} ({}, tick {}): {:.1}% at tick {}", band.clade, origin, clade.born, (band.hi - band.lo) * 100.0, ticks[i]);
+            draw_line(mouse.x, plot.y, mouse.x, plot.bottom(), 1.0, Color::new(1.0, 1.0, 1.0, 0.5));
+            draw_text(&text, x + 10.0, y + height - 8.0, 14.0, WHITE);
+        }
+    }
+}
+
+// Key to the bacteria colours over the bottom-left of the sim area: a
+// colour bar for continuous values, swatches for species and lineages
+pub fn draw_color_legend(coloring: &Coloring, layout: &Layout) {
+    if coloring.mode == ColorMode::Dna {
+        return;
+    }
+    let width = 240.0;
+    let height = if coloring.legend.is_empty() { 62.0 } else { 34.0 + coloring.legend.len() as f32 * 20.0 };
+    let x = layout.sim.x + 10.0;
+    let y = layout.graphs.y - height - 10.0;
+    draw_rectangle(x, y, width, height, Color::new(0.02, 0.02, 0.03, 0.9));
+    draw_rectangle_lines(x, y, width, height, 2.0, Color::new(0.3, 0.4, 0.5, 0.8));
+    draw_text(format!("COLOUR: {}", coloring.mode.label().to_uppercase()), x + 10.0, y + 20.0, 16.0, Color::new(0.4, 0.7, 1.0, 1.0));
+
+    if coloring.legend.is_empty() {
+        let (bar_x, bar_w) = (x + 10.0, width - 20.0);
+        let steps = 40;
+        for i in 0..steps {
+            let t = i as f32 / (steps - 1) as f32;
+            draw_rectangle(bar_x + bar_w * i as f32 / steps as f32, y + 28.0, bar_w / steps as f32 + 0.5, 12.0, coloring.palette.ramp(t));
+        }
+        let (lo, hi) = coloring.range;
+        let step = nice_step((hi - lo).max(f32::EPSILON), 10);
+        draw_text(format_value(lo, step), bar_x, y + 54.0, 14.0, LIGHTGRAY);
+        let hi = format_value(hi, step);
+        let hi_width = measure_text(&hi, None, 14, 1.0).width;
+        draw_text(&hi, bar_x + bar_w - hi_width, y + 54.0, 14.0, LIGHTGRAY);
+    } else {
+        for (i, (color, label)) in coloring.legend.iter().enumerate() {
+            let row = y + 38.0 + i as f32 * 20.0;
+            draw_rectangle(x + 10.0, row - 10.0, 12.0, 12.0, *color);
+            draw_text(label, x + 30.0, row, 14.0, LIGHTGRAY);
+        }
+    }
+}
+
+// Brush picker along the top of the sim area while the terrain editor is open
+pub fn terrain_toolbar_rect(layout: &Layout) -> Rect {
+    let width = TerrainType::ALL.len() as f32 * 120.0 + 10.0;
+    Rect::new(layout.sim.center().x - width / 2.0, 10.0, width, 62.0)
+}
+
+pub fn draw_terrain_toolbar(ui_state: &mut UIState, layout: &Layout) {
+    let rect = terrain_toolbar_rect(layout);
+    draw_rectangle(rect.x, rect.y, rect.w, rect.h, Color::new(0.02, 0.02, 0.03, 0.9));
+    draw_rectangle_lines(rect.x, rect.y, rect.w, rect.h, 2.0, Color::new(0.3, 0.4, 0.5, 0.8));
+
+    for (i, kind) in TerrainType::ALL.into_iter().enumerate() {
+        let x = rect.x + 10.0 + i as f32 * 120.0;
+        let selected = ui_state.terrain_brush == Some(kind);
+        let c = kind.color();
+        let color = if selected { Color::new(0.2, 0.5, 0.9, 0.95) } else { Color::new(c.r * 0.6, c.g * 0.6, c.b * 0.6, 0.95) };
+        if draw_button(x, rect.y + 8.0, 110.0, 26.0, &format!("{} {}", i + 1, kind.label()), color) {
+            ui_state.terrain_brush = Some(kind);
+        }
+    }
+    draw_text("Left-drag paints · CTRL+S saves terrain.txt · T closes", rect.x + 10.0, rect.y + 53.0, 15.0, LIGHTGRAY);
+}
+
+pub fn inspector_rect(layout: &Layout) -> Rect {
+    let (width, height) = (230.0, 380.0);
+    Rect::new(10.0, layout.graphs.y - 10.0 - height, width, height)
+}
+
+pub fn draw_inspector(world: &World, id: hecs::Entity, phylogeny: &Phylogeny, ui_state: &mut UIState, layout: &Layout) {
+    let mut query = world.ecs.query_one::<(&Energy, &Age, &Body, &Lineage, Option<&Genome>, Option<&Imprint>, Option<&Plasmids>, Option<&PredatorTag>)>(id);
+    let Ok((energy, age, body, lineage, genome, imprint, plasmids, predator)) = query.get() else {
+        return;
+    };
+
+    let Rect { x, y, w: width, h: height } = inspector_rect(layout);
+    draw_rectangle(x, y, width, height, Color::new(0.02, 0.02, 0.03, 0.9));
+    draw_rectangle_lines(x, y, width, height, 2.0, Color::new(0.3, 0.4, 0.5, 0.8));
+
+    let title = if predator.is_some() { "🦖 PREDATOR" } else { "🦠 BACTERIUM" };
+    let title_color = genome.map_or(Color::new(1.0, 0.4, 0.3, 1.0), |g| g.0.color());
+    draw_text(format!("{} {:?}", title, id), x + 10.0, y + 22.0, 18.0, title_color);
+
+    let tab_width = (width - 30.0) / 2.0;
+    for (i, (label, ancestry)) in [("TRAITS", false), ("ANCESTRY", true)].into_iter().enumerate() {
+        let color = if ui_state.inspector_ancestry == ancestry { Color::new(0.3, 0.4, 0.6, 0.95) } else { Color::new(0.15, 0.15, 0.2, 0.95) };
+        if draw_button(x + 10.0 + i as f32 * (tab_width + 10.0), y + 32.0, tab_width, 22.0, label, color) {
+            ui_state.inspector_ancestry = ancestry;
+        }
+    }
+    if ui_state.inspector_ancestry {
+        if predator.is_some() {
+            draw_text("Only bacteria's ancestry is recorded", x + 10.0, y + 76.0, 16.0, GRAY);
+        } else {
+            draw_ancestry(&phylogeny.ancestry(id), Rect::new(x, y + 60.0, width, height - 60.0));
+        }
+        return;
+    }
+
+    let parent = match lineage.parent {
+        Some(parent) if world.ecs.contains(parent) => format!("{:?} (alive)", parent),
+        Some(parent) => format!("{:?} (dead)", parent),
+        None => "founder".to_string(),
+    };
+    let lines = [
+        format!("Energy: {:.1}", energy.0),
+        format!("Age: {:.0} ticks", age.0),
+        format!("Speed: {:.2}   Size: {:.2}", body.speed, body.size),
+        format!("Sense radius: {:.1}", body.sense_radius),
+        match genome {
+            Some(g) => format!("Aerobic: {:.2}", g.0.gene(AEROBIC)),
+            None => "Aerobic: n/a".to_string(),
+        },
+        match genome {
+            Some(g) => format!("pH: {:.1} ± {:.1}", g.0.gene(PH_OPTIMUM), g.0.gene(PH_TOLERANCE)),
+            None => "pH: n/a".to_string(),
+        },
+        match genome {
+            Some(g) => format!("Burst: {:.2}   Vigilance: {:.2}", g.0.gene(BURST), g.0.gene(VIGILANCE)),
+            None => "Burst: n/a   Vigilance: n/a".to_string(),
+        },
+        match genome {
+            Some(g) => format!("Aggression: {:.2}   Armor: {:.2}", g.0.gene(AGGRESSION), g.0.gene(ARMOR)),
+            None => "Aggression: n/a   Armor: n/a".to_string(),
+        },
+        match genome {
+            Some(g) => format!("Toxin: {:.2}   Investment: {:.2}", g.0.gene(TOXIN), g.0.gene(INVESTMENT)),
+            None => "Toxin: n/a   Investment: n/a".to_string(),
+        },
+        match genome {
+            Some(g) => format!("Maturation: {:.0}   Cooldown: {:.0}", g.0.gene(MATURATION), g.0.gene(COOLDOWN)),
+            None => "Maturation: n/a   Cooldown: n/a".to_string(),
+        },
+        match genome {
+            Some(g) => format!("Dispersal: {:.2}   Imprint: {:.2}", g.0.gene(DISPERSAL), imprint.map_or(0.0, |i| i.0)),
+            None => "Dispersal: n/a   Imprint: n/a".to_string(),
+        },
+        match genome {
+            Some(g) => format!("Flee below: {:.0}   Crowd: {:.0}", g.0.gene(FLEE_ENERGY), g.0.gene(CROWDING)),
+            None => "Flee below: n/a   Crowd: n/a".to_string(),
+        },
+        match genome {
+            Some(g) => format!("Genome: {} genes   Mut.: {:.1}%", g.0.genes.len(), g.0.gene(MUTATION_RATE) * 100.0),
+            None => "Genome: n/a".to_string(),
+        },
+        match plasmids {
+            Some(p) if !p.0.is_empty() => format!("Plasmids: {}   Resistance: {:.0}%", p.0.len(), p.resistance() * 100.0),
+            Some(_) => "Plasmids: none".to_string(),
+            None => "Plasmids: n/a".to_string(),
+        },
+        format!("Generation: {}   Offspring: {}", lineage.generation, lineage.offspring),
+        format!("Parent: {}", parent),
+        format!("Lineage: {:?}", lineage.founder.unwrap_or(id)),
+    ];
+    for (i, line) in lines.iter().enumerate() {
+        draw_text(line, x + 10.0, y + 76.0 + i as f32 * 18.0, 16.0, WHITE);
+    }
+}
+
+// The inspector's ANCESTRY tab: speed, size and sense radius at birth along
+// the line of descent, oldest ancestor on the left, then every mutation on
+// the way, the latest first
+fn draw_ancestry(line: &[Ancestor], area: Rect) {
+    let (x, y) = (area.x, area.y);
+    let (Some(oldest), Some(latest)) = (line.first(), line.last()) else {
+        draw_text("No ancestry recorded", x + 10.0, y + 16.0, 16.0, GRAY);
+        return;
+    };
+    let since = if oldest.generation == 0 { "founder".to_string() } else { format!("gen {}", oldest.generation) };
+    draw_text(format!("{} ancestors, back to {:?} ({})", line.len() - 1, oldest.id, since), x + 10.0, y + 16.0, 14.0, LIGHTGRAY);
+    draw_text(format!("Born ticks {} to {}", oldest.born, latest.born), x + 10.0, y + 32.0, 14.0, LIGHTGRAY);
+
+    // Each trait scaled to its own range along the line
+    let chart = Rect::new(x + 10.0, y + 42.0, area.w - 20.0, 80.0);
+    draw_rectangle(chart.x, chart.y, chart.w, chart.h, Color::new(0.08, 0.08, 0.1, 0.85));
+    let traits = [(0, Metric::Speed.color()), (1, Metric::Size.color()), (2, Metric::Sense.color())];
+    for (row, &(gene, color)) in traits.iter().enumerate() {
+        let (lo, hi) = line.iter().fold((f32::MAX, f32::MIN), |(lo, hi), a| (lo.min(a.genes[gene]), hi.max(a.genes[gene])));
+        let point = |i: usize| {
+            let t = if line.len() > 1 { i as f32 / (line.len() - 1) as f32 } else { 0.5 };
+            let v = if hi > lo { (line[i].genes[gene] - lo) / (hi - lo) } else { 0.5 };
+            vec2(chart.x + t * chart.w, chart.bottom() - 4.0 - v * (chart.h - 8.0))
+        };
+        for i in 1..line.len() {
+            let (a, b) = (point(i - 1), point(i));
+            draw_line(a.x, a.y, b.x, b.y, 1.5, color);
+        }
+        let step = nice_step((hi - lo).max(oldest.genes[gene].abs() * 0.01).max(1e-3), 10);
+        let text = format!("{} {} → {}", CORRELATED_GENES[gene].0, format_value(oldest.genes[gene], step), format_value(latest.genes[gene], step));
+        draw_text(&text, x + 10.0, chart.bottom() + 16.0 + row as f32 * 16.0, 14.0, color);
+    }
+
+    // Genes that changed from parent to offspring, the latest first
+    let mutations: Vec<(u32, usize, f32, f32)> = line.windows(2).rev()
+        .flat_map(|pair| (0..CORRELATED_GENES.len())
+            .filter(|&g| pair[0].genes[g] != pair[1].genes[g])
+            .map(|g| (pair[1].generation, g, pair[0].genes[g], pair[1].genes[g])))
+        .collect();
+    let list_y = chart.bottom() + 70.0;
+    draw_text(format!("Mutations: {} over {} births", mutations.len(), line.len() - 1), x + 10.0, list_y, 16.0, WHITE);
+    let rows = ((area.bottom() - list_y - 6.0) / 16.0) as usize;
+    for (i, &(generation, gene, old, new)) in mutations.iter().take(rows).enumerate() {
+        let step = nice_step((new - old).abs().max(1e-4), 1);
+        let color = if new > old { Color::new(0.5, 0.9, 0.5, 1.0) } else { Color::new(0.95, 0.55, 0.45, 1.0) };
+        let text = format!("gen {:<5} {} {} → {}", generation, CORRELATED_GENES[gene].0, format_value(old, step), format_value(new, step));
+        draw_text(&text, x + 10.0, list_y + 16.0 + i as f32 * 16.0, 14.0, color);
+    }
+}
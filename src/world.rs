@@ -0,0 +1,640 @@
+use hecs::Entity;
+use macroquad::prelude::*;
+use ::rand::rngs::StdRng;
+use ::rand::{Rng, SeedableRng};
+use std::cell::{RefCell, RefMut};
+use std::collections::BTreeSet;
+use std::marker::PhantomData;
+use std::time::Instant;
+use tracing::trace;
+
+use crate::components::{Age, Aversion, Body, Camouflage, Digesting, Energy, Expression, Food, Genome, Imprint, Lineage, Metabolism, PhTolerance, Plasmids, Position, PredatorTag, Refractory, Respiration, Species, Startle, Target, Velocity};
+use crate::dna::{random_markers, Plasmid, DNA};
+use crate::food_web::SpeciesConfig;
+use crate::habitat::Habitat;
+use crate::field::Field;
+use crate::logging::{PERF, SIM};
+use crate::events::{Event, Meal, Organism};
+use crate::organism::{Bacterium, Predator};
+use crate::params::{PhZone, SimulationParams, DEFAULT_WORLD_HEIGHT, DEFAULT_WORLD_WIDTH};
+use crate::phylogeny::Genes;
+use crate::space::Space;
+use crate::systems;
+use crate::stats::CORRELATED_GENES;
+use crate::terrain::Terrain;
+
+const INITIAL_BACTERIA: usize = 50;
+const INITIAL_FOOD: usize = 200;
+// Side of a waste or oxygen grid cell in world units
+const WASTE_CELL: f32 = 20.0;
+const OXYGEN_CELL: f32 = 20.0;
+const PH_CELL: f32 = 20.0;
+const NEUTRAL_PH: f32 = 7.0;
+// Oxygen falls off to nothing this far (as a share of the shorter world side)
+// from the edges, and within `VENT_RADIUS` of each vent
+const OXYGEN_EDGE_REACH: f32 = 0.35;
+const OXYGEN_VENTS: usize = 3;
+const VENT_RADIUS: f32 = 80.0;
+
+// A system reads the world and emits the events for one step of the tick
+type System<V> = fn(&World<V>, &SimulationParams, &Removals) -> Vec<Event<V>>;
+
+// The tick order. Each system sees the events of the previous ones already applied.
+fn systems<V: Space>() -> [(&'static str, System<V>); 15] {
+    [
+        ("spawn food", systems::spawn_food),
+        ("sensing", systems::sensing),
+        ("movement", systems::movement),
+        ("camouflage", systems::camouflage),
+        ("migration", systems::migration),
+        ("feeding", systems::feeding),
+        ("predation", systems::predation),
+        ("fighting", systems::fighting),
+        ("conjugation", systems::conjugation),
+        ("growth", systems::growth),
+        ("reproduction", systems::reproduction),
+        ("environment", systems::environment),
+        ("epigenetics", systems::epigenetics),
+        ("starvation", systems::starvation),
+        ("repopulate", systems::repopulate),
+    ]
+}
+
+// Bands the antibiotic gradient steps up in across the world
+pub const ANTIBIOTIC_BANDS: usize = 5;
+
+// Entities marked for removal during the current tick. Despawning is deferred to
+// the end of the tick so the handles in events stay valid while they are applied,
+// and done in handle order so a replay reuses freed slots identically.
+#[derive(Default)]
+pub struct Removals(BTreeSet<Entity>);
+
+impl Removals {
+    pub fn contains(&self, id: Entity) -> bool {
+        self.0.contains(&id)
+    }
+}
+
+// A bacterium as it came into the world
+#[derive(Clone, Debug)]
+pub struct Birth {
+    pub id: Entity,
+    pub parent: Option<Entity>,
+    pub generation: u32,
+    // The world's tick once the step it was added in is over, as trackers
+    // looking after that step see it
+    pub tick: u64,
+    pub genes: Genes,
+}
+
+// The simulation, on the plane or (as `World3D`) in a box
+pub struct World<V = Vec2> {
+    pub width: f32,
+    pub height: f32,
+    // How deep the box is; nothing on the plane
+    pub depth: f32,
+    pub habitat: Habitat,
+    pub terrain: Terrain,
+    // Waste left behind by metabolism
+    pub waste: Field,
+    // Oxygen level (0..1), fixed when the world is created
+    pub oxygen: Field,
+    // pH (0..14), fixed when the world is created
+    pub ph: Field,
+    pub ecs: hecs::World,
+    pub tick: u64,
+    // Milliseconds each system took during the last `step`
+    pub timings: Vec<(&'static str, f32)>,
+    // Bacteria born and died during the last tick
+    pub births: usize,
+    pub deaths: usize,
+    // Bacteria added during the last tick (or the last batch of them on the
+    // worker thread), for trackers that might otherwise miss some that
+    // already died again
+    pub born: Vec<Birth>,
+    // Events in the last tick, to size the next one's log up front
+    events_per_tick: usize,
+    // `max_food` the food storage was last reserved for
+    food_reserved: usize,
+    // Every random draw in the simulation comes from here, so a seed fully
+    // determines a run
+    rng: RefCell<StdRng>,
+    space: PhantomData<V>,
+}
+
+impl World {
+    pub fn new(params: &SimulationParams, seed: u64) -> Self {
+        Self::with_terrain(params, seed, Terrain::new(params.world_width, params.world_height))
+    }
+
+    // New world on a prepared terrain, populated from its spawn areas if it has any
+    pub fn with_terrain(params: &SimulationParams, seed: u64, terrain: Terrain) -> Self {
+        Self::populated(params, seed, 0.0, terrain)
+    }
+
+    pub fn empty(width: f32, height: f32, seed: u64) -> Self {
+        Self::blank(width, height, 0.0, seed)
+    }
+
+    // Advance the simulation by one tick and return the events that happened.
+    // Stepping goes through a plain function for the plane and one for the
+    // box, so the engine is compiled here rather than into every crate that
+    // steps a world, where it came out noticeably slower.
+    pub fn step(&mut self, params: &SimulationParams) -> Vec<Event> {
+        self.advance(params)
+    }
+}
+
+impl World<Vec3> {
+    pub fn step(&mut self, params: &SimulationParams) -> Vec<Event<Vec3>> {
+        self.advance(params)
+    }
+}
+
+impl<V: Space> World<V> {
+    // New world `depth` deep (none on the plane) on a prepared terrain
+    pub fn populated(params: &SimulationParams, seed: u64, depth: f32, terrain: Terrain) -> Self {
+        let mut world = Self::blank(params.world_width, params.world_height, depth, seed);
+        world.habitat = Habitat::new(params.world_layout, world.width, world.height);
+        world.terrain = terrain;
+        let vents: Vec<Vec2> = (0..OXYGEN_VENTS).map(|_| world.random_pos().xy()).collect();
+        world.oxygen = oxygen_field(world.width, world.height, &vents);
+        world.ph = ph_field(world.width, world.height, &params.ph_zones);
+        let area = world.area_factor();
+        world.spawn_bacteria((INITIAL_BACTERIA as f32 * area) as usize, params.initial_energy);
+        if params.plasmid_share > 0.0 {
+            world.seed_plasmids(params.plasmid_share);
+        }
+        if params.neutral_markers > 0 {
+            world.seed_markers(params);
+        }
+        world.spawn_predators((params.predator_count * area) as usize);
+        for (index, species) in params.species.iter().enumerate() {
+            world.spawn_species(index, species, (species.count * area) as usize);
+        }
+        world.reserve_food(params.max_food);
+        world.spawn_food((INITIAL_FOOD as f32 * area) as usize);
+        world
+    }
+
+    fn blank(width: f32, height: f32, depth: f32, seed: u64) -> Self {
+        Self {
+            width,
+            height,
+            depth,
+            habitat: Habitat::open(width, height),
+            terrain: Terrain::new(width, height),
+            waste: Field::new(width, height, WASTE_CELL),
+            oxygen: oxygen_field(width, height, &[]),
+            ph: ph_field(width, height, &[]),
+            ecs: hecs::World::new(),
+            tick: 0,
+            timings: Vec::new(),
+            births: 0,
+            deaths: 0,
+            born: Vec::new(),
+            events_per_tick: 0,
+            food_reserved: 0,
+            rng: RefCell::new(StdRng::seed_from_u64(seed)),
+            space: PhantomData,
+        }
+    }
+
+    // A copy with every organism under the same handle, e.g. to draw while
+    // this world is stepped on another thread. Handles for newborns are
+    // handed out differently, so the copy doesn't run on exactly like the
+    // original.
+    pub fn snapshot(&self) -> Self {
+        let mut copy = Self::blank(self.width, self.height, self.depth, 0);
+        self.snapshot_into(&mut copy);
+        copy
+    }
+
+    // The same copy made over an old one, reusing its storage
+    pub fn snapshot_into(&self, copy: &mut Self) {
+        copy.ecs.clear();
+        let mut builder = hecs::EntityBuilder::new();
+        for entity in self.ecs.iter() {
+            copy_component::<Position<V>>(&entity, &mut builder);
+            copy_component::<Velocity<V>>(&entity, &mut builder);
+            copy_component::<Target<V>>(&entity, &mut builder);
+            copy_component::<Energy>(&entity, &mut builder);
+            copy_component::<Age>(&entity, &mut builder);
+            copy_component::<Body>(&entity, &mut builder);
+            copy_component::<Expression>(&entity, &mut builder);
+            copy_component::<Metabolism>(&entity, &mut builder);
+            copy_component::<Respiration>(&entity, &mut builder);
+            copy_component::<PhTolerance>(&entity, &mut builder);
+            copy_component::<Startle>(&entity, &mut builder);
+            copy_component::<Digesting>(&entity, &mut builder);
+            copy_component::<Imprint>(&entity, &mut builder);
+            copy_component::<Plasmids>(&entity, &mut builder);
+            copy_component::<Refractory>(&entity, &mut builder);
+            copy_component::<Genome>(&entity, &mut builder);
+            copy_component::<Lineage>(&entity, &mut builder);
+            copy_component::<PredatorTag>(&entity, &mut builder);
+            copy_component::<Aversion>(&entity, &mut builder);
+            copy_component::<Camouflage>(&entity, &mut builder);
+            copy_component::<Species>(&entity, &mut builder);
+            copy_component::<Food>(&entity, &mut builder);
+            copy.ecs.spawn_at(entity.entity(), builder.build());
+        }
+        copy.width = self.width;
+        copy.height = self.height;
+        copy.depth = self.depth;
+        copy.habitat.clone_from(&self.habitat);
+        copy.terrain.clone_from(&self.terrain);
+        copy.waste.clone_from(&self.waste);
+        copy.oxygen.clone_from(&self.oxygen);
+        copy.ph.clone_from(&self.ph);
+        copy.tick = self.tick;
+        copy.timings.clone_from(&self.timings);
+        copy.births = self.births;
+        copy.deaths = self.deaths;
+        copy.born.clone_from(&self.born);
+        copy.events_per_tick = self.events_per_tick;
+        copy.food_reserved = self.food_reserved;
+        copy.rng.clone_from(&self.rng);
+    }
+
+    pub fn spawn_bacteria(&mut self, count: usize, energy: f32) {
+        for pos in self.spawn_positions(count) {
+            Bacterium::new(pos, energy, self.rng.get_mut()).spawn(&mut self.ecs);
+        }
+    }
+
+    // Copies of `dna` as new founders, e.g. a genome brought back from the
+    // hall of fame
+    pub fn spawn_clones(&mut self, dna: &DNA, count: usize, energy: f32) {
+        for pos in self.spawn_positions(count) {
+            let mut bacterium = Bacterium::new(pos, energy, self.rng.get_mut());
+            bacterium.dna = dna.clone();
+            bacterium.spawn(&mut self.ecs);
+        }
+    }
+
+    // Give about `share` of the bacteria a resistance plasmid
+    pub fn seed_plasmids(&mut self, share: f32) {
+        let rng = self.rng.get_mut();
+        for plasmids in self.ecs.query_mut::<&mut Plasmids>() {
+            if rng.gen_bool(share.clamp(0.0, 1.0) as f64) {
+                plasmids.0.push(Plasmid::resistance());
+            }
+        }
+    }
+
+    // Give every bacterium without a full set of `neutral_markers` random
+    // marker alleles
+    pub fn seed_markers(&mut self, params: &SimulationParams) {
+        let rng = self.rng.get_mut();
+        for genome in self.ecs.query_mut::<&mut Genome>() {
+            if genome.0.markers.len() != params.neutral_markers {
+                genome.0.markers = random_markers(params, rng);
+            }
+        }
+    }
+
+    pub fn remove_bacteria(&mut self) {
+        let bacteria: Vec<Entity> = self.ecs.query::<Entity>().with::<&Genome>().iter().collect();
+        for id in bacteria {
+            let _ = self.ecs.despawn(id);
+        }
+    }
+
+    pub fn spawn_predators(&mut self, count: usize) {
+        for pos in self.spawn_positions(count) {
+            Predator::new(pos, self.rng.get_mut()).spawn(&mut self.ecs);
+        }
+    }
+
+    // Founders of the configured species at `index`
+    pub fn spawn_species(&mut self, index: usize, config: &SpeciesConfig, count: usize) {
+        for pos in self.spawn_positions(count) {
+            Predator::of_species(index, config, pos, self.rng.get_mut()).spawn(&mut self.ecs);
+        }
+    }
+
+    // Food that would land in a wall is dropped
+    pub fn spawn_food(&mut self, count: usize) {
+        for _ in 0..count {
+            let pos = self.random_pos();
+            if !self.terrain.is_wall(pos.xy()) {
+                self.ecs.spawn((Position(pos), Food));
+            }
+        }
+    }
+
+    // Where new organisms appear: the painted spawn areas if there are any,
+    // otherwise anywhere in the habitat
+    pub fn spawn_positions(&self, count: usize) -> Vec<V> {
+        let cells = self.terrain.spawn_cells();
+        (0..count)
+            .map(|_| if cells.is_empty() {
+                self.random_pos()
+            } else {
+                let mut rng = self.rng();
+                let xy = self.terrain.random_pos_in_cells(&cells, &mut *rng);
+                self.lift(xy, &mut *rng)
+            })
+            .collect()
+    }
+
+    // Organism whose body is under (or within `slack` of) `pos`, nearest first
+    pub fn organism_at(&self, pos: V, slack: f32) -> Option<Entity> {
+        self.ecs.query::<(Entity, &Position<V>, &Body)>().iter()
+            .map(|(id, p, body)| (id, p.0.distance(pos) - body.size))
+            .filter(|(_, d)| *d <= slack)
+            .min_by(|a, b| a.1.total_cmp(&b.1))
+            .map(|(id, _)| id)
+    }
+
+    pub fn rng(&self) -> RefMut<'_, StdRng> {
+        self.rng.borrow_mut()
+    }
+
+    // Random point inside the habitat
+    pub fn random_pos(&self) -> V {
+        let mut rng = self.rng();
+        let xy = self.habitat.random_pos(&mut *rng);
+        self.lift(xy, &mut *rng)
+    }
+
+    // A point above `xy` on the plane, at a random depth in the box
+    pub fn lift(&self, xy: Vec2, rng: &mut impl Rng) -> V {
+        let depth = if V::AXES > 2 { rng.gen_range(0.0..self.depth) } else { 0.0 };
+        V::from_xy(xy, depth)
+    }
+
+    // The far corner of the world
+    pub fn extent(&self) -> V {
+        V::from_xy(vec2(self.width, self.height), self.depth)
+    }
+
+    // Antibiotic at `p`: the background level plus the gradient's band
+    pub fn antibiotic_at(&self, params: &SimulationParams, p: Vec2) -> f32 {
+        let band = ((p.x / self.width * ANTIBIOTIC_BANDS as f32).max(0.0) as usize).min(ANTIBIOTIC_BANDS - 1);
+        params.antibiotic.max(0.0) + params.antibiotic_gradient.max(0.0) * band as f32 / (ANTIBIOTIC_BANDS - 1) as f32
+    }
+
+    // World area relative to the default sim area. The box counts by volume,
+    // one default height deep counting as the default area, so food and
+    // populations keep the same density as on the plane when seen from the
+    // front.
+    pub fn area_factor(&self) -> f32 {
+        let depth = if V::AXES > 2 { self.depth / DEFAULT_WORLD_HEIGHT } else { 1.0 };
+        (self.width * self.height) / (DEFAULT_WORLD_WIDTH * DEFAULT_WORLD_HEIGHT) * depth
+    }
+
+    pub fn bacteria_count(&self) -> usize {
+        self.ecs.query::<&Genome>().iter().count()
+    }
+
+    pub fn predator_count(&self) -> usize {
+        self.ecs.query::<&PredatorTag>().iter().count()
+    }
+
+    pub fn food_count(&self) -> usize {
+        self.ecs.query::<&Food>().iter().count()
+    }
+
+    pub fn food_positions(&self) -> Vec<V> {
+        self.ecs.query::<&Position<V>>().with::<&Food>().iter().map(|p| p.0).collect()
+    }
+
+    pub fn bacteria_positions(&self) -> Vec<V> {
+        self.ecs.query::<&Position<V>>().with::<&Genome>().iter().map(|p| p.0).collect()
+    }
+
+    pub fn predator_positions(&self) -> Vec<V> {
+        self.ecs.query::<&Position<V>>().with::<&PredatorTag>().iter().map(|p| p.0).collect()
+    }
+
+    fn advance(&mut self, params: &SimulationParams) -> Vec<Event<V>> {
+        let mut removals = Removals::default();
+        let mut log = Vec::with_capacity(self.events_per_tick);
+        if params.max_food != self.food_reserved {
+            self.reserve_food(params.max_food);
+        }
+        self.timings.clear();
+        self.born.clear();
+        (self.births, self.deaths) = (0, 0);
+        for (name, system) in systems() {
+            let start = Instant::now();
+            let events = system(self, params, &removals);
+            for event in &events {
+                self.apply(event, &mut removals);
+            }
+            self.timings.push((name, start.elapsed().as_secs_f32() * 1000.0));
+            log.extend(events);
+        }
+        self.finish_tick(removals);
+        self.events_per_tick = log.len();
+        trace!(target: PERF, tick = self.tick, timings = ?self.timings, "systems");
+        trace!(target: SIM, tick = self.tick, bacteria = self.bacteria_count(), predators = self.predator_count(), births = self.births, deaths = self.deaths, "tick");
+        log
+    }
+
+    // Re-apply a tick previously returned by `step`
+    pub fn apply_tick(&mut self, events: &[Event<V>]) {
+        let mut removals = Removals::default();
+        self.born.clear();
+        (self.births, self.deaths) = (0, 0);
+        for event in events {
+            self.apply(event, &mut removals);
+        }
+        self.finish_tick(removals);
+    }
+
+    fn apply(&mut self, event: &Event<V>, removals: &mut Removals) {
+        match event {
+            Event::FoodSpawned { pos } => {
+                self.ecs.spawn((Position(*pos), Food));
+            }
+            Event::Steered { id, vel, target } => {
+                if let Ok((v, t)) = self.ecs.query_one_mut::<(&mut Velocity<V>, &mut Target<V>)>(*id) {
+                    v.0 = *vel;
+                    t.0 = *target;
+                }
+            }
+            Event::Startled { id, cost } => {
+                if let Ok((e, startle)) = self.ecs.query_one_mut::<(&mut Energy, &mut Startle)>(*id) {
+                    e.0 -= cost;
+                    startle.start();
+                }
+            }
+            Event::Moved { id, pos, vel, energy_cost, waste } => {
+                if let Ok((p, v, e, age, startle, camouflage, digesting, refractory)) = self.ecs.query_one_mut::<(&mut Position<V>, &mut Velocity<V>, &mut Energy, &mut Age, Option<&mut Startle>, Option<&mut Camouflage>, Option<&mut Digesting>, Option<&mut Refractory>)>(*id) {
+                    if let Some(camouflage) = camouflage {
+                        camouflage.still = if p.0 == *pos { camouflage.still + 1 } else { 0 };
+                    }
+                    p.0 = *pos;
+                    v.0 = *vel;
+                    e.0 -= energy_cost;
+                    age.0 += 1.0;
+                    if let Some(startle) = startle {
+                        startle.tick();
+                    }
+                    if let Some(digesting) = digesting {
+                        digesting.0 = digesting.0.saturating_sub(1);
+                    }
+                    if let Some(refractory) = refractory {
+                        refractory.0 = refractory.0.saturating_sub(1);
+                    }
+                }
+                self.waste.add(pos.xy(), *waste);
+            }
+            Event::Migrated { id, pos } => {
+                if let Ok(p) = self.ecs.query_one_mut::<&mut Position<V>>(*id) {
+                    p.0 = *pos;
+                }
+            }
+            Event::Camouflaged { id, hidden } => {
+                if let Ok(camouflage) = self.ecs.query_one_mut::<&mut Camouflage>(*id) {
+                    camouflage.hidden = *hidden;
+                }
+            }
+            Event::Ate { eater, meal, energy } => {
+                if let Ok(e) = self.ecs.query_one_mut::<&mut Energy>(*eater) {
+                    e.0 += energy;
+                }
+                if let Meal::Food(food) = meal {
+                    removals.0.insert(*food);
+                }
+            }
+            Event::Digesting { id, ticks } => {
+                if let Ok(digesting) = self.ecs.query_one_mut::<&mut Digesting>(*id) {
+                    digesting.0 = *ticks;
+                }
+            }
+            Event::Imprinted { id, imprint } => {
+                if let Ok(mark) = self.ecs.query_one_mut::<&mut Imprint>(*id) {
+                    mark.0 = *imprint;
+                }
+            }
+            Event::Conjugated { id, plasmid } => {
+                if let Ok(plasmids) = self.ecs.query_one_mut::<&mut Plasmids>(*id)
+                    && !plasmids.0.contains(plasmid)
+                {
+                    plasmids.0.push(plasmid.clone());
+                }
+            }
+            Event::Grew { id, size, cost } => {
+                if let Ok((body, metabolism, e, genome)) = self.ecs.query_one_mut::<(&mut Body, &mut Metabolism, &mut Energy, &Genome)>(*id) {
+                    body.size = *size;
+                    *metabolism = Metabolism::for_bacterium(body, &genome.0);
+                    e.0 -= cost;
+                }
+            }
+            Event::Reproduced { parent, parent_energy, cooldown, offspring } => {
+                if let Ok((e, lineage, refractory)) = self.ecs.query_one_mut::<(&mut Energy, &mut Lineage, Option<&mut Refractory>)>(*parent) {
+                    e.0 = *parent_energy;
+                    lineage.offspring += 1;
+                    if let Some(refractory) = refractory {
+                        refractory.0 = *cooldown;
+                    }
+                }
+                if matches!(offspring, Organism::Bacterium(_)) {
+                    self.births += 1;
+                }
+                self.add(offspring.clone());
+            }
+            Event::Damaged { id, amount } => {
+                if let Ok(e) = self.ecs.query_one_mut::<&mut Energy>(*id) {
+                    e.0 -= amount;
+                }
+            }
+            Event::Learned { id, color, until } => {
+                if let Ok(aversion) = self.ecs.query_one_mut::<&mut Aversion>(*id) {
+                    aversion.learn(*color, *until, self.tick);
+                }
+            }
+            Event::Died { id, .. } => {
+                if self.ecs.satisfies::<&Genome>(*id) && !removals.0.contains(id) {
+                    self.deaths += 1;
+                }
+                removals.0.insert(*id);
+            }
+            Event::Spawned { organism } => {
+                self.add(organism.clone());
+            }
+            Event::WasteDecayed { factor } => {
+                self.waste.scale(*factor);
+            }
+        }
+    }
+
+    // Room in storage for all the food `max_food` allows in this world, so it
+    // isn't regrown as food fills up; done for a new world and whenever
+    // `max_food` changes. hecs already hands a despawned entity's handle and
+    // storage slot to the next one spawned, so eaten food and dead organisms
+    // make room for the new without allocating.
+    fn reserve_food(&mut self, max_food: usize) {
+        self.food_reserved = max_food;
+        let room = (max_food as f32 * self.area_factor()) as usize;
+        self.ecs.reserve::<(Position<V>, Food)>(room.saturating_sub(self.food_count()) as u32);
+    }
+
+    fn add(&mut self, organism: Organism<V>) -> Entity {
+        match organism {
+            Organism::Bacterium(b) => {
+                let (lineage, genes) = (b.lineage, CORRELATED_GENES.map(|(_, tag)| b.dna.gene(tag)));
+                let id = b.spawn(&mut self.ecs);
+                self.born.push(Birth { id, parent: lineage.parent, generation: lineage.generation, tick: self.tick + 1, genes });
+                id
+            }
+            Organism::Predator(p) => p.spawn(&mut self.ecs),
+        }
+    }
+
+    fn finish_tick(&mut self, removals: Removals) {
+        for id in removals.0 {
+            let _ = self.ecs.despawn(id);
+        }
+        self.tick += 1;
+    }
+}
+
+// Oxygen diffusing in from the world edges and from a few vents, leaving the
+// middle anoxic
+fn oxygen_field(width: f32, height: f32, vents: &[Vec2]) -> Field {
+    let mut field = Field::new(width, height, OXYGEN_CELL);
+    let reach = OXYGEN_EDGE_REACH * width.min(height);
+    for row in 0..field.rows {
+        for col in 0..field.cols {
+            let p = (vec2(col as f32, row as f32) + 0.5) * OXYGEN_CELL;
+            let edge_dist = p.x.min(p.y).min(width - p.x).min(height - p.y);
+            let mut level = 1.0 - (edge_dist / reach).clamp(0.0, 1.0);
+            for vent in vents {
+                level = level.max(1.0 - (p.distance(*vent) / VENT_RADIUS).min(1.0));
+            }
+            field.add(p, level);
+        }
+    }
+    field
+}
+
+// Neutral water shifted towards each zone's pH, fully at its centre and not at
+// all past its rim
+fn ph_field(width: f32, height: f32, zones: &[PhZone]) -> Field {
+    let mut field = Field::new(width, height, PH_CELL);
+    let scale = width.min(height);
+    for row in 0..field.rows {
+        for col in 0..field.cols {
+            let p = (vec2(col as f32, row as f32) + 0.5) * PH_CELL;
+            let mut ph = NEUTRAL_PH;
+            for zone in zones {
+                let center = vec2(zone.x * width, zone.y * height);
+                let weight = 1.0 - (p.distance(center) / (zone.radius * scale)).min(1.0);
+                ph += (zone.ph - NEUTRAL_PH) * weight;
+            }
+            field.add(p, ph.clamp(0.0, 14.0));
+        }
+    }
+    field
+}
+
+fn copy_component<T: hecs::Component + Clone>(entity: &hecs::EntityRef, builder: &mut hecs::EntityBuilder) {
+    if let Some(component) = entity.get::<&T>() {
+        builder.add((*component).clone());
+    }
+}
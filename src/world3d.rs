@@ -0,0 +1,17 @@
+use macroquad::prelude::*;
+
+use crate::params::SimulationParams;
+use crate::terrain::Terrain;
+use crate::world::World;
+
+// Optional 3D mode: the same world and systems as the 2D one, in a box. The
+// habitat, terrain and environmental fields hold through its whole depth.
+pub type World3D = World<Vec3>;
+
+impl World3D {
+    // A box as wide and tall as the 2D world and as deep as its shorter side
+    pub fn in_box(params: &SimulationParams, seed: u64) -> Self {
+        let depth = params.world_width.min(params.world_height);
+        Self::populated(params, seed, depth, Terrain::new(params.world_width, params.world_height))
+    }
+}
@@ -0,0 +1,46 @@
+use rand::SeedableRng;
+use rand::rngs::StdRng;
+
+use bact_sim::dna::{Gene, DNA, CROWDING, INVESTMENT, MAX_MUTATION_RATE, MIN_MUTATION_RATE, MUTATION_RATE, PH_OPTIMUM, SPEED};
+use bact_sim::params::SimulationParams;
+
+#[test]
+fn a_paralog_takes_over_when_the_first_copy_is_lost() {
+    let mut dna = DNA::random(&mut StdRng::seed_from_u64(1));
+    dna.set(SPEED, 2.0);
+    dna.genes.push(Gene::new(SPEED, 5.0));
+    assert_eq!(dna.gene(SPEED), 2.0);
+
+    dna.genes.retain(|gene| gene.value != 2.0 || gene.tag != SPEED);
+    assert_eq!(dna.gene(SPEED), 5.0);
+
+    // With no copy left the locus reads as knocked out
+    dna.genes.retain(|gene| gene.tag != SPEED && gene.tag != CROWDING);
+    assert_eq!((dna.gene(SPEED), dna.gene(CROWDING)), (0.1, 50.0));
+}
+
+#[test]
+fn mutation_keeps_core_genes_within_their_bounds() {
+    let params = SimulationParams { mutation_rate: 1.0, mutation_strength: 5.0, ..SimulationParams::default() };
+    let mut rng = StdRng::seed_from_u64(2);
+    let mut dna = DNA::random(&mut rng);
+    for _ in 0..200 {
+        dna = dna.mutate(&params, &mut rng);
+        assert!((0.05..=0.95).contains(&dna.gene(INVESTMENT)));
+        assert!((0.0..=14.0).contains(&dna.gene(PH_OPTIMUM)));
+        assert!((MIN_MUTATION_RATE..=MAX_MUTATION_RATE).contains(&dna.gene(MUTATION_RATE)));
+        assert!(dna.gene(SPEED) >= 0.1);
+    }
+}
+
+#[test]
+fn duplication_and_deletion_change_the_genome_length() {
+    let mut rng = StdRng::seed_from_u64(3);
+    let founder = DNA::random(&mut rng);
+    let grow = SimulationParams { gene_duplication_rate: 1.0, ..SimulationParams::default() };
+    let grown = founder.mutate(&grow, &mut rng);
+    assert_eq!(grown.genes.len(), founder.genes.len() + 1);
+
+    let shrink = SimulationParams { gene_deletion_rate: 1.0, ..SimulationParams::default() };
+    assert_eq!(grown.mutate(&shrink, &mut rng).genes.len(), founder.genes.len());
+}
@@ -0,0 +1,81 @@
+use std::collections::BTreeMap;
+
+use bact_sim::components::Lineage;
+use bact_sim::habitat::WorldLayout;
+use bact_sim::params::SimulationParams;
+use bact_sim::stats::averages;
+use bact_sim::world::World;
+
+// Golden summaries, one per run below. After a change that's meant to alter
+// the simulation, regenerate them with `UPDATE_GOLDEN=1 cargo test --test golden`.
+const GOLDEN_PATH: &str = "tests/golden.json";
+const TICKS: u64 = 1000;
+// Relative difference allowed per value, for float rounding that differs
+// between platforms or compiler versions
+const TOLERANCE: f64 = 0.01;
+
+// Name, seed, layout and predators per default-sized area
+const RUNS: [(&str, u64, WorldLayout, f32); 4] = [
+    ("open_seed_1", 1, WorldLayout::Open, 5.0),
+    ("open_no_predators_seed_3", 3, WorldLayout::Open, 0.0),
+    ("islands_seed_42", 42, WorldLayout::Islands, 5.0),
+    ("petri_dish_seed_7", 7, WorldLayout::PetriDish { rich_center: true }, 5.0),
+];
+
+// Where a fixed-seed run ends up after `TICKS` ticks
+fn summary(seed: u64, layout: WorldLayout, predators: f32) -> BTreeMap<String, f64> {
+    let params = SimulationParams { world_layout: layout, predator_count: predators, ..SimulationParams::default() };
+    let mut world = World::new(&params, seed);
+    let (mut births, mut deaths) = (0, 0);
+    for _ in 0..TICKS {
+        world.step(&params);
+        births += world.births;
+        deaths += world.deaths;
+    }
+    let avg = averages(&world.ecs);
+    let generation = world.ecs.query::<&Lineage>().iter().map(|l| l.generation).max().unwrap_or(0);
+    BTreeMap::from([
+        ("bacteria", world.bacteria_count() as f64),
+        ("predators", world.predator_count() as f64),
+        ("food", world.food_count() as f64),
+        ("births", births as f64),
+        ("deaths", deaths as f64),
+        ("max_generation", generation as f64),
+        ("avg_speed", avg.speed as f64),
+        ("avg_size", avg.size as f64),
+        ("avg_sense_radius", avg.sense_radius as f64),
+    ])
+    .into_iter()
+    .map(|(name, value)| (name.to_string(), value))
+    .collect()
+}
+
+#[test]
+fn fixed_seed_runs_match_golden_values() {
+    let actual: BTreeMap<String, BTreeMap<String, f64>> = RUNS.iter()
+        .map(|&(name, seed, layout, predators)| (name.to_string(), summary(seed, layout, predators)))
+        .collect();
+
+    if std::env::var_os("UPDATE_GOLDEN").is_some() {
+        std::fs::write(GOLDEN_PATH, serde_json::to_string_pretty(&actual).unwrap() + "\n").unwrap();
+        return;
+    }
+    let text = std::fs::read_to_string(GOLDEN_PATH).expect("no golden file; create it with UPDATE_GOLDEN=1");
+    let golden: BTreeMap<String, BTreeMap<String, f64>> = serde_json::from_str(&text).unwrap();
+
+    let mut mismatches = Vec::new();
+    for (run, values) in &actual {
+        let Some(expected) = golden.get(run) else {
+            mismatches.push(format!("{}: no golden values", run));
+            continue;
+        };
+        for (name, &value) in values {
+            match expected.get(name) {
+                Some(&want) if (value - want).abs() <= TOLERANCE * value.abs().max(want.abs()).max(1.0) => {}
+                Some(&want) => mismatches.push(format!("{} {}: {} (golden {})", run, name, value, want)),
+                None => mismatches.push(format!("{} {}: no golden value", run, name)),
+            }
+        }
+    }
+    assert!(mismatches.is_empty(), "simulation changed:\n{}", mismatches.join("\n"));
+}
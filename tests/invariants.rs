@@ -0,0 +1,147 @@
+use proptest::prelude::*;
+use std::collections::HashMap;
+
+use bact_sim::components::{Energy, Position};
+use bact_sim::events::{Event, Meal};
+use bact_sim::habitat::WorldLayout;
+use bact_sim::params::{FoodContention, SimulationParams};
+use bact_sim::world::World;
+
+// Ticks run per parameter set; long enough for feeding, reproduction and
+// predation to happen, short enough for a few dozen sets
+const TICKS: u64 = 150;
+
+fn layout() -> impl Strategy<Value = WorldLayout> {
+    prop_oneof![
+        Just(WorldLayout::Open),
+        Just(WorldLayout::Islands),
+        any::<bool>().prop_map(|rich_center| WorldLayout::PetriDish { rich_center }),
+    ]
+}
+
+fn contention() -> impl Strategy<Value = FoodContention> {
+    prop_oneof![Just(FoodContention::ClosestWins), Just(FoodContention::Split)]
+}
+
+// Parameters across the panel's slider ranges and beyond, in worlds small
+// enough to step quickly
+prop_compose! {
+    fn params()(
+        food_growth_rate in 0.0f32..10.0,
+        max_food in 0usize..1500,
+        mutation_rate in 0.0f32..0.5,
+        mutation_strength in 0.0f32..0.5,
+        reproduction_threshold in 50.0f32..300.0,
+        initial_energy in 50.0f32..200.0,
+        speed_multiplier in 0.1f32..3.0,
+        predator_count in 0.0f32..20.0,
+        predator_reproduction_threshold in 100.0f32..400.0,
+        food_contention in contention(),
+        waste_per_energy in 0.0f32..1.0,
+        waste_decay in 0.0f32..0.2,
+        waste_threshold in 1.0f32..50.0,
+        ph_damage in 0.0f32..2.0,
+        world_width in 200.0f32..900.0,
+        world_height in 200.0f32..700.0,
+        world_layout in layout(),
+    ) -> SimulationParams {
+        SimulationParams {
+            food_growth_rate,
+            max_food,
+            mutation_rate,
+            mutation_strength,
+            reproduction_threshold,
+            initial_energy,
+            speed_multiplier,
+            predator_count,
+            predator_reproduction_threshold,
+            food_contention,
+            waste_per_energy,
+            waste_decay,
+            waste_threshold,
+            ph_damage,
+            world_width,
+            world_height,
+            world_layout,
+            ..SimulationParams::default()
+        }
+    }
+}
+
+// Most food a world may gain in one tick: every region's growth, rounded up,
+// doubled where it lands on fertile ground
+fn growth_per_tick(world: &World, params: &SimulationParams) -> usize {
+    let total = world.habitat.total_area();
+    world.habitat.regions.iter()
+        .map(|r| 2 * ((params.food_growth_rate * world.area_factor() * r.area() / total * r.food_multiplier) as usize + 1))
+        .sum()
+}
+
+fn food_cap(world: &World, params: &SimulationParams) -> f32 {
+    let total = world.habitat.total_area();
+    world.habitat.regions.iter()
+        .map(|r| params.max_food as f32 * world.area_factor() * r.area() / total * r.food_multiplier)
+        .sum()
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(32))]
+
+    #[test]
+    fn energy_never_goes_nan(params in params(), seed in any::<u64>()) {
+        let mut world = World::new(&params, seed);
+        for _ in 0..TICKS {
+            world.step(&params);
+            for energy in world.ecs.query::<&Energy>().iter() {
+                prop_assert!(!energy.0.is_nan(), "NaN energy at tick {}", world.tick);
+            }
+        }
+    }
+
+    #[test]
+    fn food_stays_under_its_cap(params in params(), seed in any::<u64>()) {
+        let mut world = World::new(&params, seed);
+        let (cap, growth) = (food_cap(&world, &params), growth_per_tick(&world, &params));
+        for _ in 0..TICKS {
+            let before = world.food_count();
+            world.step(&params);
+            // Food only grows while under the cap, by at most one tick's growth
+            let limit = (before as f32).max(cap) as usize + growth;
+            prop_assert!(world.food_count() <= limit, "{} food at tick {}, limit {}", world.food_count(), world.tick, limit);
+        }
+    }
+
+    #[test]
+    fn food_is_eaten_at_most_once(params in params(), seed in any::<u64>()) {
+        let mut world = World::new(&params, seed);
+        for _ in 0..TICKS {
+            let events = world.step(&params);
+            let mut eaten: HashMap<_, (usize, f32)> = HashMap::new();
+            for event in &events {
+                if let Event::Ate { meal: Meal::Food(food), energy, .. } = event {
+                    let entry = eaten.entry(*food).or_default();
+                    *entry = (entry.0 + 1, entry.1 + energy);
+                }
+            }
+            for (food, (eaters, energy)) in eaten {
+                prop_assert!(energy <= params.food_energy * params.food_efficiency * 1.0001, "food handed out {} energy", energy);
+                if params.food_contention == FoodContention::ClosestWins {
+                    prop_assert_eq!(eaters, 1);
+                }
+                prop_assert!(!world.ecs.contains(food), "eaten food still in the world");
+            }
+        }
+    }
+
+    #[test]
+    fn organisms_stay_in_the_world(params in params(), seed in any::<u64>()) {
+        let mut world = World::new(&params, seed);
+        for _ in 0..TICKS {
+            world.step(&params);
+            for pos in world.ecs.query::<&Position>().iter() {
+                let inside = (0.0..=world.width).contains(&pos.0.x) && (0.0..=world.height).contains(&pos.0.y);
+                prop_assert!(inside, "{:?} outside the {}x{} world at tick {}", pos.0, world.width, world.height, world.tick);
+            }
+        }
+    }
+}
@@ -0,0 +1,37 @@
+use bact_sim::params::SimulationParams;
+use serde_json::json;
+
+fn merge(changes: serde_json::Value) -> Result<SimulationParams, String> {
+    let mut params = SimulationParams::default();
+    params.merge(changes.as_object().unwrap().clone()).map(|_| params)
+}
+
+#[test]
+fn merge_rejects_values_out_of_range() {
+    for changes in [
+        json!({ "world_width": 0.0 }),
+        json!({ "world_height": -500.0 }),
+        json!({ "world_width": 1e9 }),
+        json!({ "max_food": 1_000_000_000u64 }),
+        json!({ "neutral_markers": 10_000 }),
+        json!({ "sense_stride": 0 }),
+        json!({ "mutation_rate": 2.0 }),
+        json!({ "plasmid_share": 1.5 }),
+    ] {
+        assert!(merge(changes.clone()).is_err(), "{} was accepted", changes);
+    }
+}
+
+#[test]
+fn merge_keeps_everything_when_one_value_is_rejected() {
+    let mut params = SimulationParams::default();
+    let changes = json!({ "max_food": 500, "world_width": 0.0 });
+    assert!(params.merge(changes.as_object().unwrap().clone()).is_err());
+    assert_eq!(params.max_food, SimulationParams::default().max_food);
+}
+
+#[test]
+fn merge_accepts_values_in_range() {
+    let params = merge(json!({ "world_width": 5000.0, "max_food": 2000, "neutral_markers": 4, "sense_stride": 4 })).unwrap();
+    assert_eq!((params.world_width, params.max_food, params.neutral_markers, params.sense_stride), (5000.0, 2000, 4, 4));
+}
@@ -0,0 +1,66 @@
+use macroquad::color::Color;
+
+use bact_sim::autosave::WorldSave;
+use bact_sim::components::{Aversion, Camouflage, Digesting, Refractory};
+use bact_sim::dna::{COLOR_G, DNA, INVESTMENT, SPEED};
+use bact_sim::params::SimulationParams;
+use bact_sim::world::World;
+
+// The parameters saves held when autosaves were added; everything since has
+// to load from a save without it
+const FIRST_SAVED: [&str; 19] = [
+    "food_growth_rate", "max_food", "mutation_rate", "mutation_strength", "reproduction_threshold", "initial_energy", "speed_multiplier",
+    "predator_count", "predator_reproduction_threshold", "food_contention", "waste_per_energy", "waste_decay", "waste_threshold",
+    "waste_toxicity", "ph_zones", "ph_damage", "world_width", "world_height", "world_layout",
+];
+
+#[test]
+fn older_saves_load_with_defaults() {
+    let defaults = serde_json::to_value(SimulationParams::default()).unwrap();
+    let mut old = defaults.clone();
+    old.as_object_mut().unwrap().retain(|key, _| FIRST_SAVED.contains(&key.as_str()));
+    let loaded: SimulationParams = serde_json::from_value(old).expect("parameters added since should default");
+    assert_eq!(serde_json::to_value(loaded).unwrap(), defaults);
+}
+
+#[test]
+fn saves_keep_cooldowns_digestion_and_what_predators_learned() {
+    let mut world = World::empty(800.0, 600.0, 7);
+    world.spawn_bacteria(3, 100.0);
+    world.spawn_predators(2);
+    for (refractory, digesting) in world.ecs.query_mut::<(&mut Refractory, &mut Digesting)>() {
+        *refractory = Refractory(12);
+        *digesting = Digesting(3);
+    }
+    for (camouflage, aversion) in world.ecs.query_mut::<(&mut Camouflage, &mut Aversion)>() {
+        *camouflage = Camouflage { still: 25, hidden: true };
+        aversion.learn(Color::new(1.0, 0.0, 0.0, 1.0), 500, 0);
+    }
+
+    let text = serde_json::to_string(&WorldSave::of(&world, &SimulationParams::default())).unwrap();
+    let restored = serde_json::from_str::<WorldSave>(&text).unwrap().restore(7).unwrap();
+    let organisms = restored.ecs.query::<(&Refractory, &Digesting)>().iter()
+        .map(|(refractory, digesting)| (refractory.0, digesting.0))
+        .collect::<Vec<_>>();
+    assert_eq!(organisms, vec![(12, 3); 5]);
+    for (camouflage, aversion) in restored.ecs.query::<(&Camouflage, &Aversion)>().iter() {
+        assert_eq!((camouflage.still, camouflage.hidden), (25, true));
+        assert!(aversion.avoids(Color::new(1.0, 0.0, 0.0, 1.0), 499) && !aversion.active(500));
+    }
+}
+
+#[test]
+fn genomes_saved_as_loci_load_as_gene_lists() {
+    let old = serde_json::json!({
+        "speed": 2.0, "size": 5.0, "sense_radius": 40.0, "color": [0.5, 0.6, 0.7, 0.9], "aerobic": 1.0, "ph_optimum": 7.0,
+        "ph_tolerance": 1.0, "extra_genes": [{ "tag": "speed", "value": 3.0 }, { "tag": "unheard_of", "value": 4.0 }],
+    });
+    let dna: DNA = serde_json::from_value(old).unwrap();
+    assert_eq!(dna.genes.len(), 23);
+    assert_eq!((dna.gene(SPEED), dna.gene(COLOR_G), dna.gene(INVESTMENT), dna.gene("unheard_of")), (2.0, 0.6, 0.5, 4.0));
+
+    // Written back as a list, paralogs and unknown tags included
+    let text = serde_json::to_string(&dna).unwrap();
+    let reloaded: DNA = serde_json::from_str(&text).unwrap();
+    assert_eq!(reloaded.genes, dna.genes);
+}
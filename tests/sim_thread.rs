@@ -0,0 +1,43 @@
+use bact_sim::params::SimulationParams;
+use bact_sim::sim_thread::SimThread;
+use bact_sim::stats::Stats;
+use bact_sim::world::World;
+
+// Run batches of `ticks` on the worker until `batches` are done, recording
+// the stats as the window does
+fn run(sim: &mut SimThread, world: &mut World, stats: &mut Stats, params: &SimulationParams, ticks: u32, batches: usize) {
+    for _ in 0..batches {
+        sim.run(world, params, ticks);
+        sim.wait(world);
+        let batch = sim.poll(world).unwrap();
+        stats.record_ticks(world, batch.stats);
+    }
+}
+
+#[test]
+fn batches_record_every_tick_and_hand_the_world_back() {
+    let params = SimulationParams::default();
+    let mut world = World::new(&params, 7);
+    let mut stats = Stats::new();
+    let mut sim = SimThread::start();
+    run(&mut sim, &mut world, &mut stats, &params, 5, 3);
+    assert_eq!(world.tick, 15);
+    assert_eq!(stats.tick_history, (1..=15).collect::<Vec<u64>>());
+
+    // The same as stepping the world here, tick by tick
+    let mut direct = World::new(&params, 7);
+    let mut expected = Stats::new();
+    for _ in 0..15 {
+        direct.step(&params);
+        expected.record(&direct);
+    }
+    assert_eq!(stats.population_history, expected.population_history);
+    assert_eq!(stats.births_history, expected.births_history);
+
+    // Stepped right here, the world back from the worker runs on from where
+    // it was, and the next batch takes it over again
+    world.step(&params);
+    run(&mut sim, &mut world, &mut stats, &params, 4, 1);
+    assert_eq!(world.tick, 20);
+    assert_eq!(stats.tick_history.last(), Some(&20));
+}
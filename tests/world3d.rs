@@ -0,0 +1,39 @@
+use macroquad::prelude::Vec3;
+
+use bact_sim::components::{Genome, Plasmids, Position};
+use bact_sim::habitat::WorldLayout;
+use bact_sim::params::SimulationParams;
+use bact_sim::stats::Stats;
+use bact_sim::world3d::World3D;
+
+#[test]
+fn the_box_runs_the_shared_systems() {
+    let params = SimulationParams {
+        world_layout: WorldLayout::Islands,
+        plasmid_share: 0.5,
+        neutral_markers: 4,
+        epigenetics: true,
+        ..SimulationParams::default()
+    };
+    let mut world = World3D::in_box(&params, 3);
+    assert!(world.ecs.query::<&Genome>().iter().all(|g| g.0.markers.len() == 4));
+    assert!(world.ecs.query::<&Plasmids>().iter().any(|p| !p.0.is_empty()));
+
+    let mut stats = Stats::new();
+    let (mut births, mut deaths) = (0, 0);
+    for _ in 0..150 {
+        world.step(&params);
+        births += world.births;
+        deaths += world.deaths;
+        stats.record(&world);
+    }
+    assert!(births > 0 && deaths > 0);
+    assert_eq!(stats.births_history.iter().sum::<f32>(), births as f32);
+    assert_eq!(stats.deaths_history.iter().sum::<f32>(), deaths as f32);
+
+    // Everything keeps inside the box, and makes use of its depth
+    let extent = world.extent();
+    let positions: Vec<Vec3> = world.ecs.query::<&Position<Vec3>>().iter().map(|p| p.0).collect();
+    assert!(positions.iter().all(|p| p.cmpge(Vec3::ZERO).all() && p.cmple(extent).all()));
+    assert!(positions.iter().any(|p| p.z > extent.z / 2.0));
+}